@@ -1,7 +1,7 @@
 import { createContext, createElement, useContext, useEffect, useCallback, useRef, useState, type ReactNode } from 'react';
 
 // Lazy imports for Tauri plugins (only available in Tauri environment)
-let check: typeof import('@tauri-apps/plugin-updater').check | null = null;
+let invoke: typeof import('@tauri-apps/api/core').invoke | null = null;
 let relaunch: typeof import('@tauri-apps/plugin-process').relaunch | null = null;
 let listen: typeof import('@tauri-apps/api/event').listen | null = null;
 let getVersion: typeof import('@tauri-apps/api/app').getVersion | null = null;
@@ -23,13 +23,13 @@ async function initTauriImports() {
   if (typeof window === 'undefined') return false;
   if (!isTauriRuntime()) return false;
   try {
-    const [updaterModule, processModule, eventModule, appModule] = await Promise.all([
-      import('@tauri-apps/plugin-updater'),
+    const [coreModule, processModule, eventModule, appModule] = await Promise.all([
+      import('@tauri-apps/api/core'),
       import('@tauri-apps/plugin-process'),
       import('@tauri-apps/api/event'),
       import('@tauri-apps/api/app'),
     ]);
-    check = updaterModule.check;
+    invoke = coreModule.invoke;
     relaunch = processModule.relaunch;
     listen = eventModule.listen;
     getVersion = appModule.getVersion;
@@ -39,6 +39,22 @@ async function initTauriImports() {
   }
 }
 
+// What the shell's `check_for_updates` returns.
+interface UpdateCheck {
+  available: boolean;
+  version: string | null;
+}
+
+// The shell's update commands reject with `{ kind, message? }`.
+function errorMessage(e: unknown, fallback: string): string {
+  if (typeof e === 'string') return e;
+  if (e instanceof Error) return e.message;
+  const err = e as { kind?: string; message?: string } | null;
+  if (err?.kind === 'noUpdate') return 'There is no update to install';
+  if (err?.kind === 'offline') return 'The update server could not be reached';
+  return err?.message ?? fallback;
+}
+
 export interface UseAutoUpdateOptions {
   /** Enable update checking (default: true) */
   enabled?: boolean;
@@ -123,7 +139,7 @@ function useAutoUpdateInternal({
 
   // Download update
   const downloadUpdate = useCallback(async () => {
-    if (!check) {
+    if (!invoke || !listen) {
       setError('Update check not available');
       return;
     }
@@ -141,33 +157,42 @@ function useAutoUpdateInternal({
       setError(null);
       setProgress((prev) => (prev > 0 ? prev : 0));
 
-      const update = await check();
+      const update = await invoke<UpdateCheck>('check_for_updates');
       console.info('[AutoUpdate] Check result', {
-        available: update?.available ?? false,
-        version: update?.version ?? null,
+        available: update.available,
+        version: update.version,
       });
-      if (update?.available) {
+      if (update.available) {
         console.info('[AutoUpdate] Downloading update', { version: update.version });
-        // Simple progress indicator (indeterminate since we don't have total size)
         setProgress((prev) => Math.max(prev, 10));
-        await update.downloadAndInstall((event) => {
-          if (event.event === 'Progress') {
-            // Increment progress gradually
-            setProgress((prev) => Math.min(prev + 5, 95));
-          } else if (event.event === 'Finished') {
-            setProgress(100);
-          }
-        });
-        console.info('[AutoUpdate] Download finished', { version: update.version });
+        const unlisten = await listen<{ downloaded: number; total: number | null }>(
+          'update-download-progress',
+          (event) => {
+            const { downloaded, total } = event.payload;
+            if (total) {
+              setProgress(Math.min(Math.round((downloaded / total) * 100), 99));
+            } else {
+              // Without a size, creep towards the end.
+              setProgress((prev) => Math.min(prev + 5, 95));
+            }
+          },
+        );
+        try {
+          const version = await invoke<string>('install_update');
+          setProgress(100);
+          console.info('[AutoUpdate] Download finished', { version });
+        } finally {
+          unlisten();
+        }
         setReadyToRestart(true);
       }
     } catch (e) {
       console.error('Update download failed:', e);
       console.error('[AutoUpdate] Download attempt failed', {
         attempt: downloadAttemptRef.current,
-        error: e instanceof Error ? e.message : String(e),
+        error: errorMessage(e, String(e)),
       });
-      setError(e instanceof Error ? e.message : 'Download failed');
+      setError(errorMessage(e, 'Download failed'));
     } finally {
       setDownloading(false);
     }
@@ -183,22 +208,22 @@ function useAutoUpdateInternal({
 
   // Manual check for updates
   const checkForUpdates = useCallback(async (): Promise<string | null> => {
-    if (!check) {
+    if (!invoke) {
       setError('Update check not available');
       return null;
     }
 
     try {
       setError(null);
-      const update = await check();
-      if (update?.available) {
+      const update = await invoke<UpdateCheck>('check_for_updates');
+      if (update.available && update.version) {
         setUpdateAvailable(update.version);
         return update.version;
       }
       return null;
     } catch (e) {
       console.error('Update check failed:', e);
-      setError(e instanceof Error ? e.message : 'Check failed');
+      setError(errorMessage(e, 'Check failed'));
       return null;
     }
   }, []);
@@ -227,10 +252,10 @@ function useAutoUpdateInternal({
     let cancelled = false;
 
     const setupListener = async () => {
-      unlisten = await listenFn<string>('update-available', async (event) => {
-        const version = event.payload;
+      unlisten = await listenFn<UpdateCheck>('update-available', async (event) => {
+        const { version } = event.payload;
         console.log(`Update available: ${version}`);
-        setUpdateAvailable(version);
+        if (version) setUpdateAvailable(version);
       });
 
       // If cleanup was called while we were setting up, unlisten immediately
@@ -1,3 +1,5 @@
+import { isTauriRuntime } from './tauriRuntime';
+
 export interface AgentRunResponse {
   run_id: string;
   events_url: string;
@@ -5,14 +7,66 @@ export interface AgentRunResponse {
 
 const DEFAULT_BACKEND_URL = 'http://127.0.0.1:8123';
 
+// What the desktop shell injects as window.__PLUTODUCK__: the URL it actually launched the
+// backend on and the bearer token the backend requires. Both change when the backend respawns.
+interface ShellBackendConfig {
+  backendUrl?: string | null;
+  token?: string | null;
+}
+
+let shellConfig: ShellBackendConfig | undefined;
+let listeningForChanges = false;
+
+function injectedConfig(): ShellBackendConfig | undefined {
+  return (window as { __PLUTODUCK__?: ShellBackendConfig }).__PLUTODUCK__;
+}
+
+function readShellConfig(): ShellBackendConfig | undefined {
+  if (typeof window === 'undefined') return undefined;
+  if (!shellConfig) {
+    shellConfig = injectedConfig();
+  }
+  if (!listeningForChanges && isTauriRuntime()) {
+    listeningForChanges = true;
+    import('@tauri-apps/api/event')
+      .then(async ({ listen }) => {
+        await listen<ShellBackendConfig>('backend-config-changed', (event) => {
+          shellConfig = event.payload;
+        });
+        // A change announced before the listener was up still reached window.__PLUTODUCK__.
+        shellConfig = injectedConfig() ?? shellConfig;
+      })
+      .catch((error) => console.error('Failed to listen for backend config changes', error));
+  }
+  return shellConfig;
+}
+
 export function getBackendUrl(): string {
+  const injected = readShellConfig()?.backendUrl;
+  if (injected) {
+    return injected.replace(/\/$/, '');
+  }
   const base = process.env.NEXT_PUBLIC_BACKEND_URL?.trim();
   return base && base.length > 0 ? base.replace(/\/$/, '') : DEFAULT_BACKEND_URL;
 }
 
+export function getBackendToken(): string | undefined {
+  return readShellConfig()?.token ?? undefined;
+}
+
+/** The Authorization header the shell's backend requires, when there is a token. */
+export function authHeaders(): Record<string, string> {
+  const token = getBackendToken();
+  return token ? { Authorization: `Bearer ${token}` } : {};
+}
+
 export async function fetchBackendHealth(signal?: AbortSignal): Promise<boolean> {
   try {
-    const response = await fetch(`${getBackendUrl()}/health`, { method: 'GET', signal });
+    const response = await fetch(`${getBackendUrl()}/health`, {
+      method: 'GET',
+      headers: authHeaders(),
+      signal,
+    });
     return response.ok;
   } catch (error) {
     console.error('Health check failed', error);
@@ -25,6 +79,7 @@ export async function startAgentRun(question: string, signal?: AbortSignal): Pro
     method: 'POST',
     headers: {
       'Content-Type': 'application/json',
+      ...authHeaders(),
     },
     body: JSON.stringify({ question }),
     signal,
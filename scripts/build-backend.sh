@@ -18,6 +18,13 @@ rm -rf dist/pluto-duck-backend build/pluto-duck-backend
 
 pyinstaller pluto-duck-backend.spec --distpath dist --workpath build --clean
 
+# The shell refuses to spawn a backend that doesn't match this (see tauri-shell/src-tauri/src/backend/integrity.rs).
+for binary in dist/pluto-duck-backend/pluto-duck-backend{,.exe}; do
+  if [ -f "$binary" ]; then
+    shasum -a 256 "$binary" | cut -d' ' -f1 > dist/pluto-duck-backend/backend.sha256
+  fi
+done
+
 # # Copy .env file if it exists
 # if [ -f .env ]; then
 #   echo "Copying .env to backend distribution..."
@@ -1,3 +1,88 @@
+/// Every command the app registers. Listing them makes each one need a permission, so the
+/// capabilities can scope what a window may invoke (see `permissions/` and `capabilities/`).
+const COMMANDS: &[&str] = &[
+  "add_recent",
+  "allow_sleep",
+  "backend_status",
+  "backup_data",
+  "cancel_data_dir_usage",
+  "check_for_updates",
+  "clear_recents",
+  "copy_to_clipboard",
+  "create_profile",
+  "dismiss_palette",
+  "export_diagnostics",
+  "export_file",
+  "force_reload_window",
+  "get_audit_log",
+  "get_autostart",
+  "get_backend_error_counts",
+  "get_backend_logs",
+  "get_backend_url",
+  "get_data_dir_usage",
+  "get_log_sessions",
+  "get_logs_disk_usage",
+  "get_power_state",
+  "get_recent_backend_output",
+  "get_recents",
+  "get_release_notes",
+  "get_shell_config",
+  "get_storage_info",
+  "get_update_channel",
+  "install_update",
+  "install_update_from_file",
+  "list_crash_reports",
+  "list_profiles",
+  "notify",
+  "open_external",
+  "open_logs_folder",
+  "open_new_window",
+  "open_utility_window",
+  "pick_directory",
+  "prevent_sleep",
+  "quit_app",
+  "reload_window",
+  "reset_app_data",
+  "restart_backend",
+  "restore_data",
+  "reveal_exported_file",
+  "secret_delete",
+  "secret_get",
+  "secret_set",
+  "set_always_on_top",
+  "set_autostart",
+  "set_badge_count",
+  "set_close_behavior",
+  "set_data_root",
+  "set_global_shortcut",
+  "set_palette_shortcut",
+  "set_progress",
+  "set_shell_config",
+  "set_theme_override",
+  "set_titlebar_height",
+  "set_update_channel",
+  "show_log_viewer",
+  "skip_update_version",
+  "snooze_update",
+  "start_backend",
+  "stop_backend",
+  "submit_palette",
+  "switch_profile",
+  "test_proxy",
+  "toggle_devtools",
+  "validate_data_folder",
+  "window_close",
+  "window_minimize",
+  "window_toggle_maximize",
+  "zoom_in",
+  "zoom_out",
+  "zoom_reset",
+];
+
 fn main() {
-  tauri_build::build()
+  tauri_build::try_build(
+    tauri_build::Attributes::new()
+      .app_manifest(tauri_build::AppManifest::new().commands(COMMANDS)),
+  )
+  .expect("failed to run the tauri build script");
 }
@@ -0,0 +1,135 @@
+//! `audit.log` in the data root: one JSON line per privileged shell action (backend restarts,
+//! data backups, resets and restores, profile and data root changes, installed updates, saved
+//! and deleted secrets, settings changes), with when it happened, its parameters, and how it
+//! went. Entries are only ever
+//! appended. Commands record through `record` rather than writing the file themselves.
+
+use std::fmt::Display;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::Mutex;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::backend;
+
+pub const AUDIT_FILE: &str = "audit.log";
+const DEFAULT_LIMIT: usize = 200;
+/// How much of the end of the log `recent` reads; plenty for any sensible limit.
+const TAIL_BYTES: u64 = 1024 * 1024;
+/// Parameters whose names contain one of these are never written out.
+const SECRET_NAMES: &[&str] = &["password", "passwd", "secret", "token", "apikey", "api_key"];
+const REDACTED: &str = "[REDACTED]";
+
+/// Keeps concurrent commands from interleaving their lines.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+  /// RFC 3339, local time.
+  at: String,
+  action: String,
+  params: Value,
+  outcome: Outcome,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum Outcome {
+  Ok,
+  Failed,
+}
+
+/// Appends `action` with `params` and the outcome of `result`. Failing to write is logged and
+/// otherwise ignored; the action has happened either way.
+pub fn record<T, E: Display>(app: &AppHandle, action: &str, params: Value, result: &Result<T, E>) {
+  let entry = AuditEntry {
+    at: chrono::Local::now().to_rfc3339(),
+    action: action.to_string(),
+    params: redact(params),
+    outcome: if result.is_ok() {
+      Outcome::Ok
+    } else {
+      Outcome::Failed
+    },
+    error: result.as_ref().err().map(ToString::to_string),
+  };
+  if let Err(err) = append(app, &entry) {
+    warn!("failed to record {action} in the audit log: {err}");
+  }
+}
+
+fn append(app: &AppHandle, entry: &AuditEntry) -> std::io::Result<()> {
+  let mut line = serde_json::to_string(entry)?;
+  line.push('\n');
+  let root = backend::data_root::configured_root(app);
+  fs::create_dir_all(&root)?;
+  let _guard = WRITE_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+  OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(root.join(AUDIT_FILE))?
+    .write_all(line.as_bytes())
+}
+
+fn redact(value: Value) -> Value {
+  match value {
+    Value::Object(map) => Value::Object(
+      map
+        .into_iter()
+        .map(|(name, value)| {
+          let lower = name.to_ascii_lowercase();
+          if SECRET_NAMES.iter().any(|secret| lower.contains(secret)) {
+            (name, Value::String(REDACTED.to_string()))
+          } else {
+            (name, redact(value))
+          }
+        })
+        .collect(),
+    ),
+    Value::Array(items) => Value::Array(items.into_iter().map(redact).collect()),
+    other => other,
+  }
+}
+
+/// The last `limit` lines of the log, oldest first.
+pub fn recent_lines(app: &AppHandle, limit: usize) -> std::io::Result<Vec<String>> {
+  let path = backend::data_root::configured_root(app).join(AUDIT_FILE);
+  let mut file = match fs::File::open(&path) {
+    Ok(file) => file,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+    Err(err) => return Err(err),
+  };
+  let len = file.metadata()?.len();
+  let start = len.saturating_sub(TAIL_BYTES);
+  file.seek(SeekFrom::Start(start))?;
+  let mut bytes = Vec::new();
+  file.read_to_end(&mut bytes)?;
+  let text = String::from_utf8_lossy(&bytes);
+  let mut lines: Vec<&str> = text.lines().collect();
+  // Reading from the middle of the file starts mid-line.
+  if start > 0 && !lines.is_empty() {
+    lines.remove(0);
+  }
+  let skip = lines.len().saturating_sub(limit);
+  Ok(lines[skip..].iter().map(|line| line.to_string()).collect())
+}
+
+/// The last `limit` (default 200) audit entries, oldest first.
+#[tauri::command]
+pub fn get_audit_log(app: AppHandle, limit: Option<usize>) -> Result<Vec<AuditEntry>, String> {
+  let lines = recent_lines(&app, limit.unwrap_or(DEFAULT_LIMIT))
+    .map_err(|err| format!("failed to read the audit log: {err}"))?;
+  Ok(
+    lines
+      .iter()
+      .filter_map(|line| serde_json::from_str(line).ok())
+      .collect(),
+  )
+}
@@ -0,0 +1,107 @@
+//! Launching at login: a launch agent on macOS, a `Run` registry value on Windows, and an XDG
+//! autostart entry on Linux, all through the autostart plugin. `autostart.enabled` mirrors the
+//! registration, and is put back into effect at launch in case it was changed by hand or the
+//! app has moved since.
+//!
+//! Login launches carry `--autostart`. With `autostart.start_hidden`, such a launch builds the
+//! windows and starts the backend as usual but shows nothing other than the tray icon; on
+//! macOS it stays out of the Dock too, until a window is shown.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_autostart::ManagerExt;
+
+use crate::{audit, config};
+
+/// Passed to launches at login.
+pub const ARG: &str = "--autostart";
+
+/// Set while a hidden login launch hasn't shown a window yet.
+#[derive(Default)]
+pub struct HiddenStart(AtomicBool);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Autostart {
+  enabled: bool,
+  start_hidden: bool,
+}
+
+pub fn plugin() -> tauri::plugin::TauriPlugin<tauri::Wry> {
+  tauri_plugin_autostart::Builder::new().arg(ARG).build()
+}
+
+/// Brings the registration in line with the config, and says whether this launch should stay
+/// hidden.
+pub fn install(app: &AppHandle) -> bool {
+  let config = config::saved(app).autostart;
+  apply(app, config.enabled);
+  let hidden = config.start_hidden && std::env::args().skip(1).any(|arg| arg == ARG);
+  app.manage(HiddenStart(AtomicBool::new(hidden)));
+  if hidden {
+    info!("launched at login; starting hidden");
+    #[cfg(target_os = "macos")]
+    if let Err(err) = app.set_activation_policy(tauri::ActivationPolicy::Accessory) {
+      warn!("failed to leave the Dock: {err}");
+    }
+  }
+  hidden
+}
+
+/// Registers or unregisters the app to launch at login, if it isn't already.
+pub fn apply(app: &AppHandle, enabled: bool) {
+  if let Err(err) = register(app, enabled) {
+    warn!("{err}");
+  }
+}
+
+fn register(app: &AppHandle, enabled: bool) -> Result<(), String> {
+  let manager = app.autolaunch();
+  let registered = manager.is_enabled().unwrap_or(!enabled);
+  if registered == enabled {
+    return Ok(());
+  }
+  let result = if enabled { manager.enable() } else { manager.disable() };
+  result.map_err(|err| {
+    let action = if enabled { "register" } else { "unregister" };
+    format!("failed to {action} launch at login: {err}")
+  })
+}
+
+/// Ends a hidden start, putting the app back in the Dock on macOS. Called whenever a main
+/// window is shown.
+pub fn reveal(app: &AppHandle) {
+  let Some(state) = app.try_state::<HiddenStart>() else {
+    return;
+  };
+  if state.0.swap(false, Ordering::SeqCst) {
+    #[cfg(target_os = "macos")]
+    if let Err(err) = app.set_activation_policy(tauri::ActivationPolicy::Regular) {
+      warn!("failed to return to the Dock: {err}");
+    }
+  }
+}
+
+#[tauri::command]
+pub fn get_autostart(app: AppHandle) -> Result<Autostart, String> {
+  let enabled = app.autolaunch().is_enabled().map_err(|err| err.to_string())?;
+  let start_hidden = config::saved(&app).autostart.start_hidden;
+  Ok(Autostart { enabled, start_hidden })
+}
+
+#[tauri::command]
+pub fn set_autostart(app: AppHandle, enabled: bool, start_hidden: bool) -> Result<(), String> {
+  let result = register(&app, enabled).and_then(|()| {
+    config::update(&app, |config| {
+      config.autostart.enabled = enabled;
+      config.autostart.start_hidden = start_hidden;
+    })
+    .map_err(|err| format!("launch at login changed, but saving the setting failed: {err}"))
+  });
+  let params = serde_json::json!({ "enabled": enabled, "startHidden": start_hidden });
+  audit::record(&app, "set_autostart", params, &result);
+  result
+}
@@ -0,0 +1,1648 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use log::{error, info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult};
+
+use crate::config::ShellConfig;
+use crate::logs::LogStream;
+use crate::{audit, logs, platform, windows};
+
+pub mod backup;
+mod bind_check;
+mod binary;
+mod cloud_sync;
+mod crash;
+mod data_lock;
+pub mod data_root;
+mod file_lock;
+#[cfg(target_os = "macos")]
+mod gatekeeper;
+mod instance;
+mod integrity;
+mod launch_dialog;
+mod launch_task;
+mod migrate;
+mod output;
+mod pidfile;
+pub mod profiles;
+pub mod reset;
+mod process;
+mod severity;
+pub mod storage;
+mod termination;
+#[cfg(all(test, unix))]
+mod test_support;
+pub mod usage;
+
+pub use data_lock::DataLock;
+pub use launch_task::LaunchTask;
+pub use output::{LogLine, LogRing};
+use severity::Classifier;
+use termination::{Cause, Termination};
+
+/// Where in the data root the backend keeps downloaded artifacts it can fetch again.
+pub(crate) const ARTIFACTS_DIR: &str = "artifacts";
+/// Where in the data root the backend keeps scratch files it recreates.
+pub(crate) const RUNTIME_DIR: &str = "runtime";
+/// Data root directories that only hold what the backend can rebuild.
+pub(crate) const CACHE_DIRS: &[&str] = &[ARTIFACTS_DIR, RUNTIME_DIR];
+
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the watchdog checks the backend's logs for rotation.
+const LOG_ROTATION_INTERVAL: Duration = Duration::from_secs(30);
+/// Crashes matter less while nobody is looking, so the watchdog relaxes while the app is hidden.
+const HIDDEN_WATCHDOG_INTERVAL: Duration = Duration::from_secs(10);
+const HEALTH_POLL_INTERVAL: Duration = Duration::from_millis(250);
+const HEALTH_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const READY_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long exit waits for a launch in flight before stopping the backend anyway.
+const LAUNCH_EXIT_TIMEOUT: Duration = Duration::from_secs(10);
+const JOBS_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const STARTUP_TAIL_LINES: usize = 100;
+const FAILURE_TAIL_LINES: usize = 100;
+const CRASH_REPORT_TAIL_LINES: usize = 200;
+const DIALOG_TAIL_LINES: usize = 15;
+
+// Crash-loop protection: stop restarting after MAX_CRASHES within CRASH_WINDOW, and forget
+// past crashes once the backend has stayed up for STABLE_UPTIME.
+const MAX_CRASHES: usize = 5;
+const CRASH_WINDOW: Duration = Duration::from_secs(60);
+const STABLE_UPTIME: Duration = Duration::from_secs(120);
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Startup deadlines missed in a row, on the first launch after an update, before going back
+/// to the backend from before it.
+const ROLLBACK_AFTER_FAILURES: u32 = 2;
+
+const RETRY_BUTTON: &str = "Retry";
+const OPEN_LOGS_BUTTON: &str = "Open logs";
+
+/// The supervised backend child and the bookkeeping needed to restart it.
+pub struct Backend {
+  child: Option<Child>,
+  spec: LaunchSpec,
+  /// `None` until the first spawn.
+  spawned_at: Option<Instant>,
+  started_at: Option<SystemTime>,
+  restart_count: u32,
+  /// Whether the current child has answered a health check.
+  ready: bool,
+  /// Startup deadlines missed since the backend was last ready.
+  startup_failures: u32,
+  /// Whether `spec.binary` is the previous backend, standing in for a broken update's.
+  rollback: bool,
+  /// When recent crashes happened, oldest first.
+  crashes: VecDeque<Instant>,
+  restart_at: Option<Instant>,
+  /// Set once the crash loop limit is hit; cleared by the user choosing "Retry".
+  failed: bool,
+  shutting_down: bool,
+  shutdown_timeout: Duration,
+  last_exit: Option<Termination>,
+  /// The bearer token the current child requires; a new one for every spawn.
+  auth_token: String,
+  /// The current child's PID until it's known to have exited, so shutdown can still kill it
+  /// if `child` was lost, say to a panic while stopping it.
+  pid: Option<u32>,
+  /// Holds every child and its descendants; closing it (even by the shell crashing) kills them.
+  #[cfg(windows)]
+  job: Option<process::Job>,
+}
+
+pub type BackendState = Arc<Mutex<Backend>>;
+
+pub struct BackendProcess {
+  state: BackendState,
+  /// How the first shutdown went; `RunEvent::Exit` and the drop after it both ask.
+  shut_down: OnceLock<bool>,
+}
+
+impl BackendProcess {
+  /// Stops the backend if it is still running, and returns whether its process is gone for
+  /// sure. Only the first call does anything; later ones report how it went.
+  pub fn shutdown(&self) -> bool {
+    *self.shut_down.get_or_init(|| self.stop())
+  }
+
+  fn stop(&self) -> bool {
+    // A thread that panicked holding the lock mustn't leave the backend running past the app.
+    let mut backend = self
+      .state
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner());
+    backend.shutting_down = true;
+    let Some(exit) = backend.stop() else {
+      return backend.kill_lost_child();
+    };
+    info!("backend process {} at app exit: {}", exit.pid, exit.reason);
+    // `stop` reaps the child, so its PID now names something else, if anything.
+    let still_running = process::executable_path(exit.pid)
+      .is_some_and(|running| pidfile::same_file(&running, &backend.spec.binary));
+    if still_running {
+      error!("backend process {} is still running after being stopped", exit.pid);
+    }
+    !still_running
+  }
+}
+
+impl Drop for BackendProcess {
+  fn drop(&mut self) {
+    info!("BackendProcess dropping - stopping backend");
+    self.shutdown();
+  }
+}
+
+/// Everything needed to (re)start the backend the same way it was first launched.
+struct LaunchSpec {
+  binary: PathBuf,
+  data_root: PathBuf,
+  log_dir: PathBuf,
+  log_rotation: logs::Rotation,
+  output: output::Forwarder,
+  port: u16,
+  /// Proxy variables for the backend's own requests.
+  proxy_env: Vec<(String, String)>,
+}
+
+impl LaunchSpec {
+  fn log_files(&self) -> [PathBuf; 2] {
+    [
+      self.log_dir.join(logs::STDOUT_LOG),
+      self.log_dir.join(logs::STDERR_LOG),
+    ]
+  }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendStatus {
+  running: bool,
+  ready: bool,
+  pid: Option<u32>,
+  port: u16,
+  /// Milliseconds since the Unix epoch at which the current (or last) child was spawned.
+  started_at: Option<u64>,
+  restart_count: u32,
+  data_root: PathBuf,
+  last_exit: Option<Termination>,
+  /// The executable running, or last run.
+  binary: PathBuf,
+  /// Whether that's the previous backend, after the updated one failed to start.
+  rollback: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LaunchProgress {
+  message: &'static str,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BackendSpawned {
+  pid: u32,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BackendRestarted {
+  pid: u32,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BackendReady {
+  pid: u32,
+  url: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BackendRollback {
+  /// The previous backend, now running.
+  binary: PathBuf,
+  /// The version whose backend didn't start.
+  version: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BackendStartTimeout {
+  pid: u32,
+  url: String,
+  error: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BackendCrashReport {
+  path: PathBuf,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BackendFailed {
+  crashes: usize,
+  last_exit: String,
+}
+
+impl Backend {
+  fn new(spec: LaunchSpec, shutdown_timeout: Duration) -> Self {
+    Self {
+      child: None,
+      spec,
+      spawned_at: None,
+      started_at: None,
+      restart_count: 0,
+      ready: false,
+      startup_failures: 0,
+      rollback: false,
+      crashes: VecDeque::new(),
+      restart_at: None,
+      failed: false,
+      shutting_down: false,
+      shutdown_timeout,
+      last_exit: None,
+      auth_token: String::new(),
+      pid: None,
+      #[cfg(windows)]
+      job: process::Job::new()
+        .map_err(|err| warn!("failed to create backend job object: {err}"))
+        .ok(),
+    }
+  }
+
+  /// Records a crash and either schedules a restart with backoff or gives up.
+  /// Returns `true` when the crash loop limit has been reached.
+  fn record_crash(&mut self) -> bool {
+    let now = Instant::now();
+    self.crashes.push_back(now);
+    while self
+      .crashes
+      .front()
+      .is_some_and(|at| now.duration_since(*at) > CRASH_WINDOW)
+    {
+      self.crashes.pop_front();
+    }
+
+    if self.crashes.len() >= MAX_CRASHES {
+      self.failed = true;
+      self.restart_at = None;
+      return true;
+    }
+
+    let delay = backoff(self.crashes.len());
+    warn!(
+      "restarting backend in {delay:?} ({} crash(es) in the last {CRASH_WINDOW:?})",
+      self.crashes.len()
+    );
+    self.restart_at = Some(now + delay);
+    false
+  }
+
+  fn forget_crashes_if_stable(&mut self) {
+    let stable = self
+      .spawned_at
+      .is_some_and(|at| at.elapsed() >= STABLE_UPTIME);
+    if !self.crashes.is_empty() && stable {
+      info!("backend stable for {STABLE_UPTIME:?}; resetting crash counter");
+      self.crashes.clear();
+    }
+  }
+
+  /// PID of the child if it is actually alive. Uses try_wait rather than trusting `child`,
+  /// which may hold a process that already died.
+  fn running_pid(&mut self) -> Option<u32> {
+    let child = self.child.as_mut()?;
+    match child.try_wait() {
+      Ok(None) => Some(child.id()),
+      Ok(Some(_)) => None,
+      Err(err) => {
+        warn!("failed to poll backend process {}: {err}", child.id());
+        None
+      }
+    }
+  }
+
+  fn status(&mut self) -> BackendStatus {
+    let pid = self.running_pid();
+    BackendStatus {
+      running: pid.is_some(),
+      ready: pid.is_some() && self.ready,
+      pid,
+      port: self.spec.port,
+      started_at: self.started_at.and_then(|at| {
+        at.duration_since(UNIX_EPOCH)
+          .ok()
+          .map(|elapsed| elapsed.as_millis() as u64)
+      }),
+      restart_count: self.restart_count,
+      data_root: self.spec.data_root.clone(),
+      last_exit: self.last_exit.clone(),
+      binary: self.spec.binary.clone(),
+      rollback: self.rollback,
+    }
+  }
+
+  fn base_url(&self) -> String {
+    format!("http://127.0.0.1:{}", self.spec.port)
+  }
+
+  fn spawn_now(&mut self) -> Result<u32> {
+    let port = choose_port(self.spec.port);
+    if port != self.spec.port {
+      warn!("port {} is already in use; starting backend on {port}", self.spec.port);
+      self.spec.port = port;
+    }
+    let token = generate_auth_token()?;
+    let child = spawn_child(&self.spec, &token)?;
+    self.auth_token = token;
+    let pid = child.id();
+    #[cfg(windows)]
+    if let Some(job) = &self.job {
+      if let Err(err) = job.assign(&child) {
+        warn!("failed to add backend process {pid} to job object: {err}");
+      }
+    }
+    self.child = Some(child);
+    self.pid = Some(pid);
+    pidfile::write(&self.spec.data_root, pid, self.spec.port, &self.spec.binary);
+    self.spawned_at = Some(Instant::now());
+    self.started_at = Some(SystemTime::now());
+    self.restart_at = None;
+    self.ready = false;
+    Ok(pid)
+  }
+
+  /// Starts a child after launch or an explicit stop.
+  fn start(&mut self) -> Result<u32> {
+    if self.shutting_down {
+      anyhow::bail!("app is shutting down");
+    }
+    self.failed = false;
+    self.crashes.clear();
+    self.spawn_now()
+  }
+
+  /// Spawns a replacement for a child that is no longer running.
+  fn respawn(&mut self) -> Result<u32> {
+    let pid = self.spawn_now()?;
+    self.restart_count += 1;
+    Ok(pid)
+  }
+
+  /// Gracefully stops the current child, leaving nothing scheduled to replace it.
+  /// Returns how the child ended if there was one.
+  fn stop(&mut self) -> Option<Termination> {
+    self.restart_at = None;
+    let exit = self
+      .child
+      .take()
+      .map(|mut child| stop_child(&mut child, self.shutdown_timeout));
+    pidfile::remove(&self.spec.data_root);
+    if let Some(exit) = &exit {
+      self.pid = None;
+      self.kill_tree(exit.pid);
+      self.last_exit = Some(exit.clone());
+    }
+    exit
+  }
+
+  /// Terminates a child whose handle is gone without it being seen to exit, by the PID it was
+  /// spawned with, unless that PID now runs something else. Returns whether it's gone.
+  fn kill_lost_child(&mut self) -> bool {
+    let Some(pid) = self.pid.take() else {
+      return true;
+    };
+    let ours = process::executable_path(pid)
+      .is_some_and(|running| pidfile::same_file(&running, &self.spec.binary));
+    if !ours {
+      return true;
+    }
+    warn!("backend process {pid} is still running without a handle; terminating it by PID");
+    let gone = process::terminate(pid, self.shutdown_timeout);
+    self.kill_tree(pid);
+    pidfile::remove(&self.spec.data_root);
+    if !gone {
+      error!("backend process {pid} is still running after being terminated");
+    }
+    gone
+  }
+
+  /// Kills any processes the exited child `pid` left behind.
+  fn kill_tree(&self, pid: u32) {
+    // The child leads its own process group (see spawn_child).
+    #[cfg(unix)]
+    process::kill_group(pid);
+    #[cfg(windows)]
+    {
+      let _ = pid;
+      if let Some(job) = &self.job {
+        job.terminate();
+      }
+    }
+  }
+
+  /// Gracefully stops the current child (if any) and starts a fresh one.
+  /// Returns the new PID and how the old child ended.
+  fn restart(&mut self) -> Result<(u32, Option<Termination>)> {
+    if self.shutting_down {
+      anyhow::bail!("app is shutting down");
+    }
+    let exit = self.stop();
+    let pid = self.respawn()?;
+    // A manual restart is a fresh start; it shouldn't count toward crash-loop detection.
+    self.failed = false;
+    self.crashes.clear();
+    Ok((pid, exit))
+  }
+
+  fn restart_due(&self) -> bool {
+    self.child.is_none()
+      && !self.failed
+      && self.restart_at.is_some_and(|at| Instant::now() >= at)
+  }
+}
+
+/// Returns `preferred` if it can be bound on loopback, otherwise a free ephemeral port.
+fn choose_port(preferred: u16) -> u16 {
+  if TcpListener::bind((Ipv4Addr::LOCALHOST, preferred)).is_ok() {
+    return preferred;
+  }
+  match TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).and_then(|listener| listener.local_addr()) {
+    Ok(addr) => addr.port(),
+    Err(err) => {
+      warn!("failed to find a free port, keeping {preferred}: {err}");
+      preferred
+    }
+  }
+}
+
+fn backoff(crashes: usize) -> Duration {
+  let exponent = crashes.saturating_sub(1).min(16) as u32;
+  INITIAL_BACKOFF.saturating_mul(1 << exponent).min(MAX_BACKOFF)
+}
+
+/// Asks the backend to exit so DuckDB can flush and close cleanly, escalating to a
+/// hard kill if it is still alive after `timeout`.
+fn stop_child(child: &mut Child, timeout: Duration) -> Termination {
+  let pid = child.id();
+  if let Ok(Some(status)) = child.try_wait() {
+    info!("backend process {pid} already exited with {status}");
+    return Termination::new(pid, Cause::Exited, Some(status));
+  }
+
+  if request_terminate(child) {
+    info!("sent SIGTERM to backend process {pid}; waiting up to {timeout:?}");
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+      match child.try_wait() {
+        Ok(Some(status)) => {
+          info!("backend process {pid} exited gracefully with {status}");
+          return Termination::new(pid, Cause::Stopped, Some(status));
+        }
+        Ok(None) => thread::sleep(EXIT_POLL_INTERVAL),
+        Err(err) => {
+          warn!("failed to poll backend process {pid}: {err}");
+          break;
+        }
+      }
+    }
+    warn!("backend process {pid} did not exit within {timeout:?}; killing it");
+  }
+
+  // Take the whole group down while the leader is unreaped and its PID can't be reused.
+  #[cfg(unix)]
+  process::kill_group(pid);
+  let _ = child.kill();
+  let status = child.wait().ok();
+  info!("backend process {pid} killed");
+  Termination::new(pid, Cause::Killed, status)
+}
+
+#[cfg(unix)]
+fn request_terminate(child: &Child) -> bool {
+  // SAFETY: plain kill(2) on the process group of a child we still own and have not reaped.
+  let result = unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGTERM) };
+  if result != 0 {
+    warn!(
+      "failed to send SIGTERM to backend: {}",
+      std::io::Error::last_os_error()
+    );
+  }
+  result == 0
+}
+
+// No console-ctrl equivalent yet for a windowless child; kill immediately.
+#[cfg(not(unix))]
+fn request_terminate(_child: &Child) -> bool {
+  false
+}
+
+/// Carries the auth token to the backend, or from whoever started an external one. The
+/// backend refuses requests without it as a bearer token, so other local processes can't read
+/// the user's data. It's never logged or put in a URL.
+const AUTH_TOKEN_ENV: &str = "PLUTODUCK_AUTH_TOKEN";
+
+/// 256 random bits, hex-encoded.
+fn generate_auth_token() -> Result<String> {
+  let mut bytes = [0u8; 32];
+  getrandom::fill(&mut bytes)
+    .map_err(|err| anyhow::anyhow!("failed to generate a backend auth token: {err}"))?;
+  Ok(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// The token requests to the backend must carry, if it wants one.
+pub fn auth_token(app: &AppHandle) -> Option<String> {
+  if let Some(external) = app.try_state::<ExternalBackend>() {
+    return external.token.clone();
+  }
+  let state = app.try_state::<BackendState>()?;
+  let backend = state.lock().ok()?;
+  Some(backend.auth_token.clone()).filter(|token| !token.is_empty())
+}
+
+/// Adds the backend's auth token to a request the shell makes to it.
+pub fn authorize(app: &AppHandle, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+  match auth_token(app) {
+    Some(token) => request.bearer_auth(token),
+    None => request,
+  }
+}
+
+/// A backend the shell did not start and must not manage, e.g. one run by hand during development.
+pub struct ExternalBackend {
+  url: String,
+  token: Option<String>,
+}
+
+/// Attaches to `external_url`, or starts launching the managed backend in the background.
+/// Only a bad external URL fails here; anything else is reported by `launch_failed` later.
+pub fn launch(app: &AppHandle, external_url: Option<String>) -> Result<()> {
+  if let Some(url) = external_url {
+    let url = url.trim().trim_end_matches('/').to_string();
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+      anyhow::bail!("external backend URL {url:?} must start with http:// or https://");
+    }
+    info!("using externally managed backend at {url}; not spawning one");
+    // Whoever runs it decides on a token, if any.
+    let token = std::env::var(AUTH_TOKEN_ENV)
+      .ok()
+      .filter(|token| !token.trim().is_empty());
+    app.manage(ExternalBackend { url, token });
+    return Ok(());
+  }
+
+  app.manage(LaunchTask::default());
+  launch_in_background(app);
+  Ok(())
+}
+
+/// Launches the managed backend on a blocking task, so setup can build the windows meanwhile:
+/// resolving the data root, checking the binary and creating the log files can take a while
+/// on a cold disk or a network home. A failure is reported once setup is done, the same way
+/// as one there.
+fn launch_in_background(app: &AppHandle) {
+  let Some(launch) = app.state::<LaunchTask>().begin() else {
+    info!("not launching the backend; the app is exiting");
+    return;
+  };
+  let app = app.clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    let result = launch_managed(&app);
+    let cancelled = launch.is_cancelled();
+    drop(launch);
+    match result {
+      Ok(()) => {}
+      Err(err) if cancelled => info!("backend launch stopped for exit: {err:#}"),
+      Err(err) => {
+        // Queued behind setup, so the windows exist for the dialog to belong to.
+        let handle = app.clone();
+        let _ = app.run_on_main_thread(move || launch_failed(&handle, &err));
+      }
+    }
+  });
+}
+
+fn launch_managed(app: &AppHandle) -> Result<()> {
+  progress(app, "Checking your data…");
+  let data_root = resolve_data_root(app);
+  storage::check_free_space(app, &data_root);
+  cloud_sync::check(app, &data_root);
+  match migrate::check(&data_root)? {
+    migrate::Plan::Current => {}
+    migrate::Plan::Newer(version) => anyhow::bail!(
+      "The data in {} was written by a newer version of Pluto Duck (data version {version}; \
+       this version understands up to {}). Update Pluto Duck to open it; this version could \
+       damage it.",
+      data_root.display(),
+      migrate::DATA_VERSION
+    ),
+    migrate::Plan::Upgrade { from } => migrate::run(app, &data_root, from)?,
+  }
+  start(app, data_root)
+}
+
+fn progress(app: &AppHandle, message: &'static str) {
+  let _ = app.emit("backend-launch-progress", LaunchProgress { message });
+}
+
+/// Whether exit has begun, after which a launch still running must not spawn anything.
+fn exiting(app: &AppHandle) -> bool {
+  app
+    .try_state::<LaunchTask>()
+    .is_some_and(|task| task.is_cancelled())
+}
+
+/// Keeps a launch in flight from spawning a child once exit has begun, waiting a while for it
+/// to get out of the way. Run before the backend is stopped at exit.
+pub fn cancel_launch(app: &AppHandle) {
+  if let Some(task) = app.try_state::<LaunchTask>() {
+    if !task.cancel_and_wait(LAUNCH_EXIT_TIMEOUT) {
+      error!("a backend launch was still running {LAUNCH_EXIT_TIMEOUT:?} into exit");
+    }
+  }
+}
+
+/// Reports a backend that couldn't be launched: on the splash if it's up, else in a dialog
+/// offering a retry.
+pub fn launch_failed(app: &AppHandle, err: &anyhow::Error) {
+  error!("backend launch failed: {err:?}");
+  eprintln!("backend launch failed: {err:?}");
+  if let Some(damaged) = err.downcast_ref::<integrity::Damaged>() {
+    integrity::report(app, damaged);
+    return;
+  }
+  #[cfg(target_os = "macos")]
+  {
+    if let Some(blocked) = err.downcast_ref::<gatekeeper::Blocked>() {
+      gatekeeper::report(app, blocked);
+      return;
+    }
+    if let Some(blocked) = gatekeeper::diagnose(app, err) {
+      gatekeeper::report(app, &blocked);
+      return;
+    }
+  }
+  if windows::splash::is_open(app) {
+    windows::splash::fail(app, "The backend could not be launched.", &format!("{err:#}"));
+    return;
+  }
+  launch_dialog::show(app, err);
+}
+
+/// Spawns the backend against a data root in the current layout.
+fn start(app: &AppHandle, data_root: PathBuf) -> Result<()> {
+  let app_handle = app.clone();
+  progress(app, "Checking the backend…");
+  let binary = binary::resolve(app)?;
+  #[cfg(target_os = "macos")]
+  gatekeeper::check(app, &binary)?;
+  integrity::verify(app, &binary)?;
+  let rollback = binary::is_previous(app, &binary);
+  if rollback {
+    warn!("this version's backend failed to start before; using the previous one");
+  }
+  // Before anything touches the data root, including reaping a backend that may be another
+  // shell's.
+  // A retried launch still holds it from the attempt before.
+  if app.try_state::<DataLock>().is_none() {
+    app.manage(data_lock::acquire(&data_root)?);
+  }
+  migrate::remove_backups_when_ready(app, &data_root);
+
+  info!(
+    "launching backend binary {:?} with data root {:?}",
+    binary,
+    data_root
+  );
+
+  // Each run of the app logs into its own timestamped directory under `logs/`.
+  progress(app, "Preparing logs…");
+  let log_config = &app.state::<ShellConfig>().logs;
+  let session_started = chrono::Local::now();
+  let logs_root = data_root.join("logs");
+  let log_dir = logs::create_session_dir(&logs_root, session_started)
+    .context("failed to create log directory")?;
+  logs::prune_sessions(&logs_root, log_config.kept_sessions.max(1));
+  logs::enforce_size_cap(&logs_root, log_config.max_total_bytes(), &log_dir);
+  logs::combined::open(&log_dir);
+  pidfile::reap_orphan(&data_root, &binary);
+
+  let ring = Arc::new(LogRing::new(output::RING_CAPACITY));
+  app.manage(ring.clone());
+  let classifier = Arc::new(Classifier::new(&log_config.severity_patterns));
+  app.manage(classifier.clone());
+  let spec = LaunchSpec {
+    binary,
+    data_root,
+    log_dir,
+    log_rotation: logs::Rotation {
+      max_bytes: log_config.max_file_bytes(),
+      keep: log_config.rotated_files,
+    },
+    output: output::Forwarder::new(app_handle.clone(), ring, classifier),
+    port: app.state::<ShellConfig>().backend.port,
+    proxy_env: crate::proxy::settings(app).env(),
+  };
+  let shutdown_timeout = Duration::from_secs(
+    app.state::<ShellConfig>().backend.shutdown_timeout_secs,
+  );
+  let mut backend = Backend::new(spec, shutdown_timeout);
+  backend.rollback = rollback;
+  let state: BackendState = Arc::new(Mutex::new(backend));
+
+  // Manage the state before spawning so start_backend can retry a failed first spawn.
+  app.manage(state.clone());
+  app.manage(BackendProcess {
+    state: state.clone(),
+    shut_down: OnceLock::new(),
+  });
+  spawn_watchdog(app_handle.clone(), state.clone());
+
+  // Exit waits for this launch, then stops the backend once; a child spawned after that
+  // would be left running.
+  if exiting(app) {
+    anyhow::bail!("the app is exiting");
+  }
+  respawn(app, &state)?;
+  let backend = state
+    .lock()
+    .map_err(|_| anyhow::anyhow!("backend state is poisoned"))?;
+  let session = logs::SessionInfo {
+    started_at: session_started,
+    app_version: app.package_info().version.to_string(),
+    binary: backend.spec.binary.clone(),
+    port: backend.spec.port,
+  };
+  if let Err(err) = logs::write_session_info(&backend.spec.log_dir, &session) {
+    warn!("failed to write log session info: {err}");
+  }
+
+  Ok(())
+}
+
+/// Spawns the managed backend and watches its startup in the background. Also what a retry
+/// runs when the first spawn failed after the state was set up.
+fn respawn(app: &AppHandle, state: &BackendState) -> Result<()> {
+  let startup_timeout = Duration::from_secs(
+    app.state::<ShellConfig>().backend.startup_timeout_secs,
+  );
+  let pid = spawn(state, app)?;
+  let url = state
+    .lock()
+    .map_err(|_| anyhow::anyhow!("backend state is poisoned"))?
+    .base_url();
+  spawn_startup_check(app.clone(), state.clone(), pid, url, startup_timeout);
+  Ok(())
+}
+
+/// Starts the backend unless a live child already exists, returning its PID either way.
+pub fn spawn(state: &BackendState, app_handle: &AppHandle) -> Result<u32> {
+  let mut backend = state
+    .lock()
+    .map_err(|_| anyhow::anyhow!("backend state is poisoned"))?;
+  if let Some(pid) = backend.running_pid() {
+    return Ok(pid);
+  }
+
+  let pid = backend.start()?;
+  info!(
+    "backend process {pid} spawned on {} with data root {:?}",
+    backend.base_url(),
+    backend.spec.data_root
+  );
+  let _ = app_handle.emit("backend-spawned", BackendSpawned { pid });
+  Ok(pid)
+}
+
+/// Base URL of the backend, if it has been launched or attached to.
+pub fn backend_url(app: &AppHandle) -> Option<String> {
+  if let Some(external) = app.try_state::<ExternalBackend>() {
+    return Some(external.url.clone());
+  }
+  let state = app.try_state::<BackendState>()?;
+  let backend = state.lock().ok()?;
+  Some(backend.base_url())
+}
+
+/// Whether the shell's backend child is running; `None` if the shell doesn't manage one.
+pub fn is_running(app: &AppHandle) -> Option<bool> {
+  let state = managed_state(app).ok()?;
+  let mut backend = state.lock().ok()?;
+  Some(backend.running_pid().is_some())
+}
+
+#[tauri::command]
+pub fn get_backend_url(app: AppHandle) -> Result<String, String> {
+  backend_url(&app).ok_or_else(|| "backend was not launched".to_string())
+}
+
+/// The last lines of combined backend stdout/stderr, oldest first.
+#[tauri::command]
+pub fn get_recent_backend_output(app: AppHandle) -> Vec<LogLine> {
+  app
+    .try_state::<Arc<LogRing>>()
+    .map(|ring| ring.snapshot())
+    .unwrap_or_default()
+}
+
+/// Crash reports written for abnormal backend exits, newest first.
+#[tauri::command]
+pub fn list_crash_reports(app: AppHandle) -> Result<Vec<crash::CrashReport>, String> {
+  crash::list_reports(&resolve_data_root(&app))
+    .map_err(|err| format!("failed to list crash reports: {err}"))
+}
+
+/// How many backend output lines matched each configured severity this session.
+#[tauri::command]
+pub fn get_backend_error_counts(app: AppHandle) -> BTreeMap<String, u64> {
+  app
+    .try_state::<Arc<Classifier>>()
+    .map(|classifier| classifier.counts())
+    .unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn backend_status(app: AppHandle) -> Result<BackendStatus, String> {
+  let state = managed_state(&app)?;
+  let mut backend = state.lock().map_err(|_| "backend state is poisoned".to_string())?;
+  Ok(backend.status())
+}
+
+/// Restarts the backend on demand (e.g. when it is wedged) and waits until it is healthy.
+#[tauri::command]
+pub async fn restart_backend(app: AppHandle) -> Result<u32, String> {
+  let result = restart_managed(app.clone()).await;
+  audit::record(&app, "restart_backend", serde_json::json!({}), &result);
+  result
+}
+
+async fn restart_managed(app: AppHandle) -> Result<u32, String> {
+  let state = managed_state(&app)?;
+  let restart_state = state.clone();
+
+  let _ = app.emit("backend-restarting", ());
+  info!("restarting backend on request");
+
+  // Stopping can block for the whole shutdown timeout, so keep it off the async runtime.
+  // The lock is held across stop + spawn, which keeps the watchdog and exit cleanup out.
+  let (pid, exit, url) = tauri::async_runtime::spawn_blocking(move || {
+    let mut backend = restart_state
+      .lock()
+      .map_err(|_| anyhow::anyhow!("backend state is poisoned"))?;
+    let (pid, exit) = backend.restart()?;
+    anyhow::Ok((pid, exit, backend.base_url()))
+  })
+  .await
+  .map_err(|err| err.to_string())?
+  .map_err(|err| {
+    error!("backend restart failed: {err:?}");
+    format!("{err:#}")
+  })?;
+  if let Some(exit) = exit {
+    let _ = app.emit("backend-exited", exit);
+  }
+
+  await_ready(&app, &state, pid, url, READY_TIMEOUT)
+    .await
+    .map_err(|err| format!("{err:#}"))?;
+  Ok(pid)
+}
+
+/// Stops the backend and keeps it stopped until `start_backend` is called.
+#[tauri::command]
+pub async fn stop_backend(app: AppHandle) -> Result<(), String> {
+  let result = stop_managed(app.clone()).await;
+  audit::record(&app, "stop_backend", serde_json::json!({}), &result);
+  result
+}
+
+async fn stop_managed(app: AppHandle) -> Result<(), String> {
+  let state = managed_state(&app)?;
+  let exit = tauri::async_runtime::spawn_blocking(move || {
+    let mut backend = state.lock().map_err(|_| "backend state is poisoned".to_string())?;
+    info!("stopping backend on request");
+    Ok::<_, String>(backend.stop())
+  })
+  .await
+  .map_err(|err| err.to_string())??;
+  if let Some(exit) = exit {
+    let _ = app.emit("backend-exited", exit);
+  }
+  let _ = app.emit("backend-stopped", ());
+  Ok(())
+}
+
+/// Starts the backend if it is not running and waits until it is healthy.
+#[tauri::command]
+pub async fn start_backend(app: AppHandle) -> Result<u32, String> {
+  let result = start_managed(app.clone()).await;
+  audit::record(&app, "start_backend", serde_json::json!({}), &result);
+  result
+}
+
+async fn start_managed(app: AppHandle) -> Result<u32, String> {
+  let state = managed_state(&app)?;
+  let spawn_handle = app.clone();
+  let spawn_state = state.clone();
+  let (pid, url) = tauri::async_runtime::spawn_blocking(move || {
+    let pid = spawn(&spawn_state, &spawn_handle)?;
+    let url = spawn_state
+      .lock()
+      .map_err(|_| anyhow::anyhow!("backend state is poisoned"))?
+      .base_url();
+    anyhow::Ok((pid, url))
+  })
+  .await
+  .map_err(|err| err.to_string())?
+  .map_err(|err| {
+    error!("backend start failed: {err:?}");
+    format!("{err:#}")
+  })?;
+
+  await_ready(&app, &state, pid, url, READY_TIMEOUT)
+    .await
+    .map_err(|err| format!("{err:#}"))?;
+  Ok(pid)
+}
+
+/// The last `count` lines the backend printed, across restarts.
+fn recent_output(app: &AppHandle, count: usize) -> Vec<String> {
+  app
+    .try_state::<Arc<LogRing>>()
+    .map(|ring| ring.recent(count))
+    .unwrap_or_default()
+}
+
+fn managed_state(app: &AppHandle) -> Result<BackendState, String> {
+  if app.try_state::<ExternalBackend>().is_some() {
+    return Err("backend is externally managed".to_string());
+  }
+  app
+    .try_state::<BackendState>()
+    .map(|state| state.inner().clone())
+    .ok_or_else(|| "backend was not launched".to_string())
+}
+
+/// Waits for `pid` to pass a health check in the background and announces the outcome.
+fn spawn_readiness_check(app_handle: AppHandle, state: BackendState, pid: u32, url: String) {
+  tauri::async_runtime::spawn(async move {
+    let _ = await_ready(&app_handle, &state, pid, url, READY_TIMEOUT).await;
+  });
+}
+
+/// Like `spawn_readiness_check`, but tells the user (with the stderr tail) when the backend
+/// misses the startup deadline. Nothing is shown once the health check has succeeded.
+fn spawn_startup_check(
+  app_handle: AppHandle,
+  state: BackendState,
+  pid: u32,
+  url: String,
+  timeout: Duration,
+) {
+  tauri::async_runtime::spawn(async move {
+    let Err(err) = await_ready(&app_handle, &state, pid, url, timeout).await else {
+      return;
+    };
+    // Stopped on purpose, and the user has been told.
+    if err.is::<bind_check::Exposed>() {
+      return;
+    }
+    let Ok((log_dir, failures, rollback)) = state.lock().map(|mut backend| {
+      backend.startup_failures += 1;
+      (backend.spec.log_dir.clone(), backend.startup_failures, backend.rollback)
+    }) else {
+      return;
+    };
+    if crate::updates::updated_from(&app_handle).is_some() && !rollback {
+      if failures < ROLLBACK_AFTER_FAILURES {
+        warn!("updated backend missed its startup deadline ({failures} time(s)); retrying");
+        retry_startup(app_handle, state, timeout);
+        return;
+      }
+      if let Some(previous) = binary::roll_back(&app_handle) {
+        roll_back(app_handle, state, previous, timeout);
+        return;
+      }
+    }
+
+    let tail = recent_output(&app_handle, STARTUP_TAIL_LINES);
+    error!(
+      "backend missed the {timeout:?} startup deadline; last {} output line(s):\n{}",
+      tail.len(),
+      tail.join("\n")
+    );
+
+    let mut message = format!(
+      "The Pluto Duck backend did not start within {} seconds.\n\n{err:#}",
+      timeout.as_secs()
+    );
+    let mut title = "Backend failed to start";
+    if let Some(from) = crate::updates::updated_from(&app_handle) {
+      // The first launch of a new version; its backend may be what's broken.
+      title = "The update may have broken the backend";
+      let binary = state
+        .lock()
+        .map(|backend| backend.spec.binary.display().to_string())
+        .unwrap_or_default();
+      message.push_str(&format!(
+        "\n\nPluto Duck was just updated from version {from}, and the new backend ({binary}) \
+         isn't starting. Reinstalling version {from} from the releases page undoes the update; \
+         your data is kept."
+      ));
+    }
+    let recent = &tail[tail.len().saturating_sub(DIALOG_TAIL_LINES)..];
+    if !recent.is_empty() {
+      message.push_str("\n\nRecent output:\n");
+      message.push_str(&recent.join("\n"));
+    }
+
+    let retry_handle = app_handle.clone();
+    show_backend_dialog(&app_handle, title, message, log_dir, move || {
+      retry_startup(retry_handle, state, timeout);
+    });
+  });
+}
+
+/// Replaces an updated backend that won't start with the one from before the update, and
+/// announces it as `backend-rollback`.
+fn roll_back(app_handle: AppHandle, state: BackendState, previous: PathBuf, timeout: Duration) {
+  warn!(
+    "updated backend failed to start {ROLLBACK_AFTER_FAILURES} times; rolling back to {:?}",
+    previous
+  );
+  if let Ok(mut backend) = state.lock() {
+    backend.spec.binary = previous.clone();
+    backend.rollback = true;
+    backend.startup_failures = 0;
+  }
+  let _ = app_handle.emit(
+    "backend-rollback",
+    BackendRollback {
+      binary: previous,
+      version: app_handle.package_info().version.to_string(),
+    },
+  );
+  retry_startup(app_handle, state, timeout);
+}
+
+fn retry_startup(app_handle: AppHandle, state: BackendState, timeout: Duration) {
+  info!("user requested backend retry after startup timeout");
+  tauri::async_runtime::spawn(async move {
+    let restart_state = state.clone();
+    let restarted = tauri::async_runtime::spawn_blocking(move || {
+      let mut backend = restart_state
+        .lock()
+        .map_err(|_| anyhow::anyhow!("backend state is poisoned"))?;
+      let (pid, exit) = backend.restart()?;
+      anyhow::Ok((pid, exit, backend.base_url()))
+    })
+    .await;
+    match restarted {
+      Ok(Ok((pid, exit, url))) => {
+        if let Some(exit) = exit {
+          let _ = app_handle.emit("backend-exited", exit);
+        }
+        spawn_startup_check(app_handle, state, pid, url, timeout);
+      }
+      Ok(Err(err)) => error!("backend retry failed: {err:?}"),
+      Err(err) => error!("backend retry task failed: {err}"),
+    }
+  });
+}
+
+/// Emits `backend-waiting-health` and polls the backend until it is healthy, then marks it
+/// ready and emits `backend-ready`; emits `backend-start-timeout` with the last error if it
+/// never becomes healthy.
+async fn await_ready(
+  app_handle: &AppHandle,
+  state: &BackendState,
+  pid: u32,
+  url: String,
+  timeout: Duration,
+) -> Result<()> {
+  let _ = app_handle.emit("backend-waiting-health", BackendSpawned { pid });
+  let token = state
+    .lock()
+    .map(|backend| backend.auth_token.clone())
+    .unwrap_or_default();
+  if let Err(err) = wait_for_health(&url, &token, timeout).await {
+    error!("backend process {pid} did not become ready: {err:#}");
+    let _ = app_handle.emit(
+      "backend-start-timeout",
+      BackendStartTimeout {
+        pid,
+        url,
+        error: format!("{err:#}"),
+      },
+    );
+    return Err(err);
+  }
+  if let Err(exposed) = bind_check::verify(app_handle, &url, &token).await {
+    bind_check::refuse(app_handle, state, pid, &exposed);
+    return Err(exposed.into());
+  }
+
+  if let Ok(mut backend) = state.lock() {
+    // The child may have been replaced while we were polling; only mark our own.
+    if backend.child.as_ref().is_some_and(|child| child.id() == pid) {
+      backend.ready = true;
+      backend.startup_failures = 0;
+    }
+  }
+  info!("backend process {pid} is ready at {url}");
+  let _ = app_handle.emit("backend-ready", BackendReady { pid, url });
+  windows::bootstrap::announce(app_handle);
+  Ok(())
+}
+
+/// Polls `{base_url}/health` until it answers with a success status or `timeout` elapses.
+async fn wait_for_health(base_url: &str, token: &str, timeout: Duration) -> Result<()> {
+  // The backend is on loopback; a configured proxy would only get in the way.
+  let client = reqwest::Client::builder()
+    .no_proxy()
+    .timeout(HEALTH_REQUEST_TIMEOUT)
+    .build()
+    .context("failed to build health check client")?;
+  let health_url = format!("{base_url}/health");
+  let deadline = Instant::now() + timeout;
+
+  loop {
+    let last_error = match client.get(&health_url).bearer_auth(token).send().await {
+      Ok(response) if response.status().is_success() => return Ok(()),
+      Ok(response) => format!("status {}", response.status()),
+      Err(err) => err.to_string(),
+    };
+    if Instant::now() >= deadline {
+      anyhow::bail!("backend did not become healthy within {timeout:?}: {last_error}");
+    }
+    tokio::time::sleep(HEALTH_POLL_INTERVAL).await;
+  }
+}
+
+/// How many jobs the backend reports as running, from `GET /api/jobs/active`. `None` if the
+/// backend isn't reachable or doesn't answer in time, in which case nothing is known to be
+/// at risk.
+pub async fn active_jobs(app: &AppHandle) -> Option<usize> {
+  fetch_active_jobs(app)
+    .await
+    .map_err(|err| warn!("active jobs check failed: {err}"))
+    .ok()
+}
+
+/// Like `active_jobs`, but leaves logging the failure to the caller, for callers that ask
+/// repeatedly.
+pub async fn fetch_active_jobs(app: &AppHandle) -> Result<usize, String> {
+  let base = backend_url(app).ok_or_else(|| "backend address unknown".to_string())?;
+  let url = format!("{base}/api/jobs/active");
+  let client = reqwest::Client::builder()
+    .no_proxy()
+    .timeout(JOBS_REQUEST_TIMEOUT)
+    .build()
+    .map_err(|err| err.to_string())?;
+  let response = authorize(app, client.get(&url))
+    .send()
+    .await
+    .map_err(|err| err.to_string())?;
+  if !response.status().is_success() {
+    return Err(format!("returned status {}", response.status()));
+  }
+  let bytes = response.bytes().await.map_err(|err| err.to_string())?;
+  let body: serde_json::Value = serde_json::from_slice(&bytes).map_err(|err| err.to_string())?;
+  // Either a list of jobs, `{"jobs": [...]}`, or a bare count.
+  let jobs = body.get("jobs").unwrap_or(&body);
+  jobs
+    .as_array()
+    .map(Vec::len)
+    .or_else(|| {
+      body
+        .get("active")
+        .or_else(|| body.get("count"))
+        .and_then(|count| count.as_u64())
+        .map(|count| count as usize)
+    })
+    .ok_or_else(|| "unrecognized response".to_string())
+}
+
+fn spawn_child(spec: &LaunchSpec, auth_token: &str) -> Result<Child> {
+  let [stdout_path, stderr_path] = spec.log_files();
+  // Always append: respawns keep the crashed process's output, and size-based rotation
+  // truncates the file underneath the child.
+  let stdout_log = open_log(&stdout_path).context("failed to create stdout log")?;
+  let stderr_log = open_log(&stderr_path).context("failed to create stderr log")?;
+
+  let mut command = Command::new(&spec.binary);
+  if let Some(parent) = spec.binary.parent() {
+    command.current_dir(parent);
+  }
+  command
+    .env("PLUTODUCK_DATA_DIR__ROOT", &spec.data_root)
+    .env(AUTH_TOKEN_ENV, auth_token)
+    .envs(spec.proxy_env.iter().map(|(name, value)| (name, value)))
+    .args([
+      "--port",
+      &spec.port.to_string(),
+      "--data-root",
+      spec.data_root.to_string_lossy().as_ref(),
+    ])
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped());
+  // Own process group, so stopping the backend also stops any workers it forks.
+  #[cfg(unix)]
+  {
+    use std::os::unix::process::CommandExt;
+    command.process_group(0);
+  }
+  // The backend is a console program; don't flash a console window for it. Output still goes
+  // to the log files because the handles are redirected above.
+  #[cfg(windows)]
+  {
+    use std::os::windows::process::CommandExt;
+    command.creation_flags(windows_sys::Win32::System::Threading::CREATE_NO_WINDOW);
+  }
+
+  let mut child = command.spawn().context("failed to spawn backend process")?;
+  if let Some(stdout) = child.stdout.take() {
+    spec.output.forward(LogStream::Stdout, stdout, stdout_log);
+  }
+  if let Some(stderr) = child.stderr.take() {
+    spec.output.forward(LogStream::Stderr, stderr, stderr_log);
+  }
+  Ok(child)
+}
+
+fn open_log(path: &Path) -> std::io::Result<File> {
+  OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn rotate_oversized_logs(files: &[PathBuf], rotation: logs::Rotation) {
+  for path in files {
+    match logs::rotate_if_oversized(path, rotation) {
+      Ok(true) => info!("rotated {:?}", path),
+      Ok(false) => {}
+      Err(err) => warn!("failed to rotate {:?}: {err}", path),
+    }
+  }
+}
+
+/// Polls the child and restarts it with exponential backoff if it exits while the app is
+/// still running, giving up (and telling the user) when it keeps crashing.
+fn spawn_watchdog(app_handle: AppHandle, state: BackendState) {
+  let watchdog_state = state.clone();
+  let result = thread::Builder::new()
+    .name("backend-watchdog".into())
+    .spawn(move || {
+      let mut rotated_at: Option<Instant> = None;
+      loop {
+        thread::sleep(if windows::visibility::is_visible(&app_handle) {
+          WATCHDOG_INTERVAL
+        } else {
+          HIDDEN_WATCHDOG_INTERVAL
+        });
+        // Rotation touches the disk, so it runs with the backend unlocked.
+        if rotated_at.map_or(true, |at| at.elapsed() >= LOG_ROTATION_INTERVAL) {
+          rotated_at = Some(Instant::now());
+          let Ok(backend) = watchdog_state.lock() else {
+            break;
+          };
+          let (files, rotation) = (backend.spec.log_files(), backend.spec.log_rotation);
+          drop(backend);
+          rotate_oversized_logs(&files, rotation);
+        }
+        let Ok(mut backend) = watchdog_state.lock() else {
+          break;
+        };
+        // Checked under the lock so we never respawn after shutdown took the child.
+        if backend.shutting_down {
+          break;
+        }
+
+        if let Some(child) = backend.child.as_mut() {
+          match child.try_wait() {
+            Ok(None) => {
+              backend.forget_crashes_if_stable();
+              continue;
+            }
+            Ok(Some(status)) => {
+              let exit = Termination::new(child.id(), Cause::Exited, Some(status));
+              error!("backend process {} {}", exit.pid, exit.reason);
+              backend.child = None;
+              backend.pid = None;
+              backend.kill_tree(exit.pid);
+              backend.last_exit = Some(exit.clone());
+              let _ = app_handle.emit("backend-exited", exit.clone());
+              let crash =
+                (exit.code != Some(0)).then(|| crash_context(&app_handle, &backend, &exit));
+              if backend.record_crash() {
+                give_up(&app_handle, &watchdog_state, &backend, &exit.reason);
+              }
+              // Hashing the binary for the report can take a while; not with the backend locked.
+              drop(backend);
+              if let Some(crash) = crash {
+                write_crash_report(&app_handle, &crash);
+              }
+              continue;
+            }
+            Err(err) => {
+              warn!("failed to poll backend process {}: {err}", child.id());
+            }
+          }
+          continue;
+        }
+
+        if backend.restart_due() {
+          match backend.respawn() {
+            Ok(pid) => {
+              info!("backend respawned with pid {pid}");
+              let _ = app_handle.emit("backend-restarted", BackendRestarted { pid });
+              spawn_readiness_check(
+                app_handle.clone(),
+                watchdog_state.clone(),
+                pid,
+                backend.base_url(),
+              );
+            }
+            Err(err) => {
+              error!("failed to respawn backend: {err:?}");
+              if backend.record_crash() {
+                give_up(&app_handle, &watchdog_state, &backend, &format!("{err:#}"));
+              }
+            }
+          }
+        }
+      }
+    });
+  if let Err(err) = result {
+    error!("failed to start backend watchdog: {err}");
+  }
+}
+
+fn crash_context(
+  app_handle: &AppHandle,
+  backend: &Backend,
+  exit: &Termination,
+) -> crash::CrashContext {
+  let stderr_tail = app_handle
+    .try_state::<Arc<LogRing>>()
+    .map(|ring| ring.recent_from(LogStream::Stderr, CRASH_REPORT_TAIL_LINES))
+    .unwrap_or_default();
+  crash::CrashContext {
+    data_root: backend.spec.data_root.clone(),
+    exit: exit.clone(),
+    uptime: backend.spawned_at.map(|at| at.elapsed()),
+    app_version: app_handle.package_info().version.to_string(),
+    binary: backend.spec.binary.clone(),
+    stderr_tail,
+  }
+}
+
+/// Writes the report with the binary's hash, cached by integrity checks unless it changed.
+fn write_crash_report(app_handle: &AppHandle, context: &crash::CrashContext) {
+  let binary_sha256 = integrity::hash(app_handle, &context.binary);
+  match crash::write_report(context, binary_sha256) {
+    Ok(path) => {
+      info!("wrote backend crash report {:?}", path);
+      let _ = app_handle.emit("backend-crash-report", BackendCrashReport { path });
+    }
+    Err(err) => warn!("failed to write backend crash report: {err}"),
+  }
+}
+
+fn give_up(app_handle: &AppHandle, state: &BackendState, backend: &Backend, last_exit: &str) {
+  error!(
+    "backend crashed {} times within {CRASH_WINDOW:?}; not restarting (last exit: {last_exit})",
+    backend.crashes.len()
+  );
+  let _ = app_handle.emit(
+    "backend-failed",
+    BackendFailed {
+      crashes: backend.crashes.len(),
+      last_exit: last_exit.to_string(),
+    },
+  );
+  let tail = recent_output(app_handle, FAILURE_TAIL_LINES);
+  error!(
+    "last {} backend output line(s) before giving up:\n{}",
+    tail.len(),
+    tail.join("\n")
+  );
+  let mut message = format!(
+    "The Pluto Duck backend keeps crashing and will not be restarted automatically.\n\nLast exit: {last_exit}"
+  );
+  let recent = &tail[tail.len().saturating_sub(DIALOG_TAIL_LINES)..];
+  if !recent.is_empty() {
+    message.push_str("\n\nRecent output:\n");
+    message.push_str(&recent.join("\n"));
+  }
+  let state = state.clone();
+  show_backend_dialog(
+    app_handle,
+    "Backend stopped",
+    message,
+    backend.spec.log_dir.clone(),
+    move || {
+      if let Ok(mut backend) = state.lock() {
+        info!("user requested backend retry after crash loop");
+        backend.failed = false;
+        backend.crashes.clear();
+        backend.restart_at = Some(Instant::now());
+      }
+    },
+  );
+}
+
+/// Shows a non-blocking error dialog offering "Retry" and "Open logs".
+fn show_backend_dialog(
+  app_handle: &AppHandle,
+  title: &str,
+  message: String,
+  log_dir: PathBuf,
+  on_retry: impl FnOnce() + Send + 'static,
+) {
+  // During startup the splash shows the failure with the same actions.
+  if windows::splash::is_open(app_handle) {
+    return;
+  }
+  app_handle
+    .dialog()
+    .message(message)
+    .title(title)
+    .kind(MessageDialogKind::Error)
+    .buttons(MessageDialogButtons::OkCancelCustom(
+      RETRY_BUTTON.into(),
+      OPEN_LOGS_BUTTON.into(),
+    ))
+    .show_with_result(move |result| match result {
+      MessageDialogResult::Custom(label) if label == RETRY_BUTTON => on_retry(),
+      MessageDialogResult::Custom(label) if label == OPEN_LOGS_BUTTON => {
+        if let Err(err) = platform::open_path(&log_dir) {
+          error!("failed to open log directory {:?}: {err}", log_dir);
+        }
+      }
+      _ => {}
+    });
+}
+
+/// Keeps a copy of the current backend to roll back to, before an update replaces it.
+pub fn preserve_binary(app: &AppHandle) {
+  if let Err(err) = binary::preserve(app) {
+    warn!("failed to keep the current backend for a rollback: {err:#}");
+  }
+}
+
+/// Makes this the only shell for its data root. Returns `false` if another instance already
+/// owns it, in which case this one should exit without launching anything.
+pub fn claim_single_instance(app: &AppHandle) -> bool {
+  match instance::claim(app, &resolve_data_root(app)) {
+    Ok(instance::Claim::Primary(lock)) => {
+      app.manage(lock);
+      true
+    }
+    Ok(instance::Claim::Forwarded) => {
+      info!("another instance owns this data root; handed over to it");
+      false
+    }
+    Ok(instance::Claim::Unreachable(err)) => {
+      error!("{err:#}");
+      eprintln!("Pluto Duck is already running but not responding: {err:#}");
+      false
+    }
+    Err(err) => {
+      warn!("single-instance check failed, continuing without it: {err:#}");
+      true
+    }
+  }
+}
+
+/// This session's log directory, if the shell launched the backend.
+pub fn current_log_dir(app: &AppHandle) -> Option<PathBuf> {
+  let state = app.try_state::<BackendState>()?;
+  let backend = state.lock().ok()?;
+  Some(backend.spec.log_dir.clone())
+}
+
+/// Root of the backend's data (DuckDB files, logs, pid file).
+pub fn data_root(app: &AppHandle) -> PathBuf {
+  resolve_data_root(app)
+}
+
+/// `path`, resolved, if it is a file the backend could have written for the frontend to hand
+/// over, i.e. inside the temp directory or the data root. Commands that take such a path would
+/// otherwise reach any file the app can read.
+pub fn scratch_file(app: &AppHandle, path: &Path) -> Result<PathBuf, String> {
+  let resolved = path
+    .canonicalize()
+    .map_err(|err| format!("failed to open {}: {err}", path.display()))?;
+  let inside = [std::env::temp_dir(), data_root(app)]
+    .iter()
+    .filter_map(|root| root.canonicalize().ok())
+    .any(|root| resolved.starts_with(root));
+  if !inside {
+    return Err(format!(
+      "{} is outside the temp directory and the data root",
+      path.display()
+    ));
+  }
+  Ok(resolved)
+}
+
+/// Directory holding one log directory per session.
+pub fn log_root(app: &AppHandle) -> PathBuf {
+  resolve_data_root(app).join("logs")
+}
+
+/// On first run, asks where to keep data (then relaunches) and returns `true`; the caller must
+/// then not claim the instance or launch the backend.
+pub fn ask_for_data_root(app: &AppHandle) -> bool {
+  if !data_root::needs_choice(app) {
+    return false;
+  }
+  data_root::ask(app);
+  true
+}
+
+fn resolve_data_root(app: &AppHandle) -> PathBuf {
+  let root = data_root::configured_root(app);
+  let logs = root.join("logs");
+  if let Err(err) = std::fs::create_dir_all(&logs) {
+    error!("failed to create backend data directories: {err}");
+  }
+  cloud_sync::exclude(&root);
+  root
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn shutdown_stops_the_backend_behind_a_poisoned_lock_only_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut backend = test_support::backend(dir.path(), "exec sleep 300\n");
+    let pid = test_support::spawn(&mut backend);
+    let state: BackendState = Arc::new(Mutex::new(backend));
+    let poisoner = state.clone();
+    let _ = thread::spawn(move || {
+      let _backend = poisoner.lock().unwrap();
+      panic!("poisoning the backend lock");
+    })
+    .join();
+    assert!(state.is_poisoned());
+
+    let process = BackendProcess {
+      state: state.clone(),
+      shut_down: OnceLock::new(),
+    };
+    assert!(process.shutdown());
+    assert!(test_support::gone(pid), "backend process {pid} is still running");
+
+    // Only the first shutdown stops anything, so a child spawned since is left alone.
+    let mut backend = state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let respawned = test_support::spawn(&mut backend);
+    drop(backend);
+    assert!(process.shutdown());
+    assert!(test_support::alive(respawned));
+
+    let mut backend = state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    backend.stop();
+    assert!(test_support::gone(respawned));
+  }
+
+  #[test]
+  fn shutdown_kills_a_child_whose_handle_was_lost() {
+    let dir = tempfile::tempdir().unwrap();
+    let sleeper = test_support::sleeper(dir.path());
+    let mut backend =
+      test_support::backend(dir.path(), "exec \"$PLUTODUCK_DATA_DIR__ROOT/sleeper\" 300\n");
+    let pid = test_support::spawn(&mut backend);
+    test_support::wait_for_exec(pid, &sleeper);
+    // Only a PID still running the backend's binary gets killed, and the script is now sleep.
+    backend.spec.binary = sleeper;
+    // Something still has to reap the child for it to count as gone once it's killed.
+    let mut child = backend.child.take().unwrap();
+    let reaper = thread::spawn(move || child.wait());
+    assert_eq!(backend.pid, Some(pid));
+
+    let process = BackendProcess {
+      state: Arc::new(Mutex::new(backend)),
+      shut_down: OnceLock::new(),
+    };
+    assert!(process.shutdown());
+    assert!(test_support::gone(pid), "backend process {pid} is still running");
+    assert!(!reaper.join().unwrap().unwrap().success());
+    assert!(!dir.path().join("backend.pid").exists());
+  }
+}
@@ -0,0 +1,570 @@
+//! `backup_data`: the data root as one zip, e.g. before an update. The backend holds DuckDB
+//! file locks and may be mid-write, so it's stopped for the copy and started again afterwards,
+//! whether or not the backup worked. The archive is written under a temporary name and only
+//! renamed into place once complete; the data itself is only ever read.
+//!
+//! `restore_data` is the reverse. The current data is moved aside to
+//! `<root>.pre-restore-<timestamp>` rather than deleted, and only removed once the backend has
+//! come up healthy on the restored data; any failure before that puts it back.
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::DialogExt;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use super::{migrate, RUNTIME_DIR};
+use crate::{audit, walk};
+
+/// Names the archive as one of ours; read back by restore.
+pub(super) const MANIFEST: &str = "pluto-duck-backup.json";
+/// Bumped when the archive changes shape.
+pub(super) const BACKUP_FORMAT: u32 = 1;
+/// Left out on top of what the shell itself keeps in the root: scratch files the backend
+/// recreates.
+const EXCLUDED: &[&str] = &[RUNTIME_DIR];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Manifest {
+  pub format: u32,
+  pub data_version: u32,
+  pub app_version: String,
+  pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+  path: PathBuf,
+  size_bytes: u64,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupProgress {
+  copied_bytes: u64,
+  total_bytes: u64,
+  percent: u8,
+}
+
+/// Writes a backup of the data root to `target`, or asks where to save it when no path is
+/// given. Returns `None` if the user cancelled the save dialog.
+#[tauri::command]
+pub async fn backup_data(
+  app: AppHandle,
+  target: Option<String>,
+) -> Result<Option<BackupInfo>, String> {
+  let result = backup(app.clone(), target.clone()).await;
+  let path = result.as_ref().ok().and_then(|info| info.as_ref()).map(|info| &info.path);
+  let params = serde_json::json!({ "target": target, "path": path });
+  audit::record(&app, "backup_data", params, &result);
+  result
+}
+
+async fn backup(app: AppHandle, target: Option<String>) -> Result<Option<BackupInfo>, String> {
+  let dialog_app = app.clone();
+  let target = tauri::async_runtime::spawn_blocking(move || match target {
+    Some(path) => Ok(Some(PathBuf::from(path))),
+    None => ask_for_target(&dialog_app),
+  })
+  .await
+  .map_err(|err| err.to_string())?
+  .map_err(|err| format!("{err:#}"))?;
+  let Some(target) = target else {
+    return Ok(None);
+  };
+
+  let was_running = super::is_running(&app).ok_or("backend is not managed by the shell")?;
+  if was_running {
+    super::stop_backend(app.clone()).await?;
+  }
+  let write_app = app.clone();
+  let written = tauri::async_runtime::spawn_blocking(move || {
+    write_backup(&write_app, &super::data_root(&write_app), &target).map(|size| (target, size))
+  })
+  .await;
+  if was_running {
+    if let Err(err) = super::start_backend(app.clone()).await {
+      error!("backend failed to start again after backup: {err}");
+    }
+  }
+
+  let (path, size_bytes) = written
+    .map_err(|err| err.to_string())?
+    .map_err(|err| {
+      error!("backup failed: {err:?}");
+      format!("{err:#}")
+    })?;
+  info!("data backed up to {:?} ({size_bytes} bytes)", path);
+  Ok(Some(BackupInfo { path, size_bytes }))
+}
+
+fn ask_for_target(app: &AppHandle) -> Result<Option<PathBuf>> {
+  let file_name = format!(
+    "pluto-duck-backup-{}.zip",
+    chrono::Local::now().format("%Y%m%d-%H%M%S")
+  );
+  let mut dialog = app
+    .dialog()
+    .file()
+    .set_title("Back Up Data")
+    .set_file_name(file_name)
+    .add_filter("Zip archive", &["zip"]);
+  if let Ok(documents) = app.path().document_dir() {
+    dialog = dialog.set_directory(documents);
+  }
+  match dialog.blocking_save_file() {
+    Some(path) => Ok(Some(
+      path
+        .into_path()
+        .context("save dialog returned an unusable path")?,
+    )),
+    None => Ok(None),
+  }
+}
+
+/// Zips `root` into `target`, returning the archive's size.
+fn write_backup(app: &AppHandle, root: &Path, target: &Path) -> Result<u64> {
+  let mut files = Vec::new();
+  let entries =
+    std::fs::read_dir(root).with_context(|| format!("failed to read {}", root.display()))?;
+  for entry in entries {
+    let entry = entry.with_context(|| format!("failed to read {}", root.display()))?;
+    let name = entry.file_name().to_string_lossy().into_owned();
+    if migrate::SKIPPED.contains(&name.as_str()) || EXCLUDED.contains(&name.as_str()) {
+      continue;
+    }
+    // A file that can't be read fails the backup rather than going missing from it.
+    files.extend(walk::all_files(&entry.path())?);
+  }
+  let total: u64 = files.iter().map(|(_, len)| len).sum();
+
+  let partial = target.with_extension("zip.partial");
+  let result = write_archive(app, root, &files, total, &partial).and_then(|()| {
+    std::fs::rename(&partial, target)
+      .with_context(|| format!("failed to move the backup to {}", target.display()))
+  });
+  if let Err(err) = result {
+    if let Err(remove_err) = std::fs::remove_file(&partial) {
+      if remove_err.kind() != std::io::ErrorKind::NotFound {
+        warn!("failed to remove partial backup {:?}: {remove_err}", partial);
+      }
+    }
+    return Err(err);
+  }
+  Ok(std::fs::metadata(target).map_or(0, |metadata| metadata.len()))
+}
+
+fn write_archive(
+  app: &AppHandle,
+  root: &Path,
+  files: &[(PathBuf, u64)],
+  total: u64,
+  target: &Path,
+) -> Result<()> {
+  let file =
+    File::create(target).with_context(|| format!("failed to create {}", target.display()))?;
+  let mut zip = ZipWriter::new(BufWriter::new(file));
+  let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+  let manifest = Manifest {
+    format: BACKUP_FORMAT,
+    data_version: migrate::DATA_VERSION,
+    app_version: app.package_info().version.to_string(),
+    created_at: chrono::Local::now().to_rfc3339(),
+  };
+  zip.start_file(MANIFEST, options)?;
+  zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+
+  let mut copied = 0u64;
+  let mut reported = None;
+  let mut buffer = vec![0u8; 1 << 20];
+  for (path, len) in files {
+    let name = entry_name(root, path)?;
+    let mut source =
+      File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    zip.start_file(name, options.large_file(*len >= u64::from(u32::MAX)))?;
+    loop {
+      let read = source
+        .read(&mut buffer)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+      if read == 0 {
+        break;
+      }
+      zip.write_all(&buffer[..read])?;
+      copied += read as u64;
+      let percent = (copied.saturating_mul(100) / total.max(1)).min(100) as u8;
+      if reported != Some(percent) {
+        reported = Some(percent);
+        let _ = app.emit(
+          "data-backup-progress",
+          BackupProgress {
+            copied_bytes: copied,
+            total_bytes: total,
+            percent,
+          },
+        );
+      }
+    }
+  }
+  zip.finish()?.flush()?;
+  Ok(())
+}
+
+/// `path` relative to `root`, with `/` separators as zip expects.
+fn entry_name(root: &Path, path: &Path) -> Result<String> {
+  let relative = path
+    .strip_prefix(root)
+    .with_context(|| format!("{} is outside the data root", path.display()))?;
+  let parts: Vec<_> = relative
+    .components()
+    .map(|part| part.as_os_str().to_string_lossy())
+    .collect();
+  Ok(parts.join("/"))
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RestoreProgress {
+  phase: &'static str,
+  percent: u8,
+}
+
+/// Replaces the data root with the contents of a `backup_data` archive and restarts the
+/// backend on it. Backups from a newer data version are refused; older ones are upgraded.
+#[tauri::command]
+pub async fn restore_data(app: AppHandle, archive_path: String) -> Result<(), String> {
+  let result = restore(app.clone(), archive_path.clone()).await;
+  audit::record(&app, "restore_data", serde_json::json!({ "archivePath": archive_path }), &result);
+  result
+}
+
+async fn restore(app: AppHandle, archive_path: String) -> Result<(), String> {
+  let archive = PathBuf::from(archive_path);
+  let check_archive = archive.clone();
+  let manifest = tauri::async_runtime::spawn_blocking(move || read_manifest(&check_archive))
+    .await
+    .map_err(|err| err.to_string())?
+    .map_err(|err| format!("{err:#}"))?;
+  if manifest.data_version > migrate::DATA_VERSION {
+    return Err(format!(
+      "this backup was made by a newer version of Pluto Duck (data version {}; this version \
+       understands up to {}); update Pluto Duck to restore it",
+      manifest.data_version,
+      migrate::DATA_VERSION
+    ));
+  }
+
+  if super::is_running(&app).ok_or("backend is not managed by the shell")? {
+    super::stop_backend(app.clone()).await?;
+  }
+  let root = super::data_root(&app);
+  let aside = root.with_file_name(format!(
+    "{}.pre-restore-{}",
+    root.file_name().unwrap_or_default().to_string_lossy(),
+    chrono::Local::now().format("%Y%m%d-%H%M%S")
+  ));
+  info!("restoring data from {:?}; current data moves to {:?}", archive, aside);
+
+  let restore_app = app.clone();
+  let (restore_root, restore_aside) = (root.clone(), aside.clone());
+  let restored = tauri::async_runtime::spawn_blocking(move || {
+    restore_into(&restore_app, &archive, &manifest, &restore_root, &restore_aside)
+  })
+  .await
+  .map_err(|err| anyhow::anyhow!("restore task failed: {err}"))
+  .and_then(|result| result);
+  if let Err(err) = restored {
+    error!("restore failed: {err:?}");
+    if let Err(start_err) = super::start_backend(app.clone()).await {
+      error!("backend failed to start again after a failed restore: {start_err}");
+    }
+    return Err(format!("{err:#}"));
+  }
+
+  progress(&app, "Starting the backend", 100);
+  match super::start_backend(app.clone()).await {
+    Ok(_) => {
+      info!("restore finished; removing the previous data in {:?}", aside);
+      if let Err(err) = std::fs::remove_dir_all(&aside) {
+        warn!("failed to remove {:?}: {err}", aside);
+      }
+      Ok(())
+    }
+    Err(err) => {
+      error!("backend did not come up on the restored data: {err}; rolling back");
+      let _ = super::stop_backend(app.clone()).await;
+      let rolled_back = put_back(&root, &aside);
+      if let Err(start_err) = super::start_backend(app.clone()).await {
+        error!("backend failed to start again after rolling back: {start_err}");
+      }
+      match rolled_back {
+        Ok(()) => Err(format!(
+          "the backend did not start on the restored data ({err}); the previous data was put back"
+        )),
+        Err(rollback_err) => Err(format!(
+          "the backend did not start on the restored data ({err}), and the previous data could \
+           not be put back ({rollback_err:#}); it is in {}",
+          aside.display()
+        )),
+      }
+    }
+  }
+}
+
+/// The manifest of a `backup_data` archive, or an error saying why this isn't one.
+fn read_manifest(archive: &Path) -> Result<Manifest> {
+  let file = File::open(archive).with_context(|| format!("failed to open {}", archive.display()))?;
+  let mut zip = ZipArchive::new(file)
+    .with_context(|| format!("{} is not a zip archive", archive.display()))?;
+  let mut entry = zip
+    .by_name(MANIFEST)
+    .with_context(|| format!("{} is not a Pluto Duck backup", archive.display()))?;
+  let mut raw = Vec::new();
+  entry.read_to_end(&mut raw)?;
+  let manifest: Manifest = serde_json::from_slice(&raw)
+    .with_context(|| format!("{} has an unreadable backup manifest", archive.display()))?;
+  if manifest.format != BACKUP_FORMAT {
+    anyhow::bail!(
+      "{} is in backup format {}, which this version can't read",
+      archive.display(),
+      manifest.format
+    );
+  }
+  Ok(manifest)
+}
+
+/// Moves the data in `root` to `aside` and extracts the archive in its place, putting the old
+/// data back if anything fails.
+fn restore_into(
+  app: &AppHandle,
+  archive: &Path,
+  manifest: &Manifest,
+  root: &Path,
+  aside: &Path,
+) -> Result<()> {
+  std::fs::create_dir_all(aside).with_context(|| format!("failed to create {}", aside.display()))?;
+  if let Err(err) = move_entries(root, aside) {
+    // Nothing was restored yet, so only what already moved needs to go back; deleting what's
+    // left in the root would lose it.
+    if let Err(move_err) = move_entries(aside, root) {
+      return Err(err.context(format!(
+        "some of the data could not be moved back ({move_err:#}); it is in {}",
+        aside.display()
+      )));
+    }
+    let _ = std::fs::remove_dir(aside);
+    return Err(err.context("failed to move the current data aside"));
+  }
+  let result = extract(app, archive, root).and_then(|()| {
+    if manifest.data_version < migrate::DATA_VERSION {
+      migrate::run(app, root, manifest.data_version)?;
+      migrate::remove_backups_when_ready(app, root);
+      Ok(())
+    } else {
+      migrate::write_version(root, manifest.data_version)
+    }
+  });
+  if let Err(err) = result {
+    if let Err(rollback_err) = put_back(root, aside) {
+      return Err(err.context(format!(
+        "restore failed and the previous data could not be put back ({rollback_err:#}); it is \
+         in {}",
+        aside.display()
+      )));
+    }
+    return Err(err.context("restore failed; the previous data was put back"));
+  }
+  Ok(())
+}
+
+fn extract(app: &AppHandle, archive: &Path, root: &Path) -> Result<()> {
+  unpack(archive, root, |percent| progress(app, "Restoring your data", percent))
+}
+
+/// Writes the archive's data entries into `root`, calling `on_progress` with each new percent.
+fn unpack(archive: &Path, root: &Path, mut on_progress: impl FnMut(u8)) -> Result<()> {
+  let file = File::open(archive).with_context(|| format!("failed to open {}", archive.display()))?;
+  let mut zip = ZipArchive::new(file)?;
+  let mut total = 0u64;
+  for index in 0..zip.len() {
+    total += zip.by_index(index)?.size();
+  }
+
+  let mut done = 0u64;
+  let mut reported = None;
+  for index in 0..zip.len() {
+    let mut entry = zip.by_index(index)?;
+    // Entries that would land outside the root, the manifest, and the shell's own files.
+    let Some(relative) = entry.enclosed_name() else {
+      warn!("skipping unsafe backup entry {:?}", entry.name());
+      continue;
+    };
+    let top = relative
+      .components()
+      .next()
+      .map(|part| part.as_os_str().to_string_lossy().into_owned())
+      .unwrap_or_default();
+    if entry.name() == MANIFEST || migrate::SKIPPED.contains(&top.as_str()) {
+      continue;
+    }
+    let target = root.join(&relative);
+    if entry.is_dir() {
+      std::fs::create_dir_all(&target)?;
+      continue;
+    }
+    if let Some(parent) = target.parent() {
+      std::fs::create_dir_all(parent)
+        .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let mut output = BufWriter::new(
+      File::create(&target).with_context(|| format!("failed to create {}", target.display()))?,
+    );
+    std::io::copy(&mut entry, &mut output)
+      .and_then(|_| output.flush())
+      .with_context(|| format!("failed to extract {}", relative.display()))?;
+
+    done += entry.size();
+    let percent = (done.saturating_mul(100) / total.max(1)).min(100) as u8;
+    if reported != Some(percent) {
+      reported = Some(percent);
+      on_progress(percent);
+    }
+  }
+  Ok(())
+}
+
+/// Undoes `restore_into`: removes whatever was restored and moves the data in `aside` back.
+fn put_back(root: &Path, aside: &Path) -> Result<()> {
+  for entry in migrate::entries(root)? {
+    let removed = if entry.is_dir() {
+      std::fs::remove_dir_all(&entry)
+    } else {
+      std::fs::remove_file(&entry)
+    };
+    removed.with_context(|| format!("failed to remove {}", entry.display()))?;
+  }
+  move_entries(aside, root)?;
+  if let Err(err) = std::fs::remove_dir(aside) {
+    warn!("failed to remove {:?}: {err}", aside);
+  }
+  Ok(())
+}
+
+/// Renames the data entries of `from` into `to`; both are on the same volume, so this is quick.
+fn move_entries(from: &Path, to: &Path) -> Result<()> {
+  for entry in migrate::entries(from)? {
+    let target = to.join(entry.file_name().unwrap_or_default());
+    std::fs::rename(&entry, &target)
+      .with_context(|| format!("failed to move {} to {}", entry.display(), target.display()))?;
+  }
+  Ok(())
+}
+
+fn progress(app: &AppHandle, phase: &'static str, percent: u8) {
+  let _ = app.emit("data-restore-progress", RestoreProgress { phase, percent });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A zip at `dir/name` holding `entries`, with a manifest in `format` unless that's `None`.
+  fn archive(dir: &Path, name: &str, format: Option<u32>, entries: &[(&str, &str)]) -> PathBuf {
+    let path = dir.join(name);
+    let mut zip = ZipWriter::new(File::create(&path).unwrap());
+    let options = SimpleFileOptions::default();
+    if let Some(format) = format {
+      let manifest = Manifest {
+        format,
+        data_version: migrate::DATA_VERSION,
+        app_version: "0.0.0".to_string(),
+        created_at: "2024-06-01T10:00:00+00:00".to_string(),
+      };
+      zip.start_file(MANIFEST, options).unwrap();
+      zip.write_all(&serde_json::to_vec(&manifest).unwrap()).unwrap();
+    }
+    for (name, contents) in entries {
+      zip.start_file(*name, options).unwrap();
+      zip.write_all(contents.as_bytes()).unwrap();
+    }
+    zip.finish().unwrap();
+    path
+  }
+
+  #[test]
+  fn read_manifest_accepts_only_our_archives_in_a_known_format() {
+    let dir = tempfile::tempdir().unwrap();
+    let ours = archive(dir.path(), "ours.zip", Some(BACKUP_FORMAT), &[]);
+    assert_eq!(read_manifest(&ours).unwrap().data_version, migrate::DATA_VERSION);
+
+    let foreign = archive(dir.path(), "foreign.zip", None, &[("data/a.duckdb", "x")]);
+    let newer = archive(dir.path(), "newer.zip", Some(BACKUP_FORMAT + 1), &[]);
+    let not_zip = dir.path().join("not.zip");
+    std::fs::write(&not_zip, "plain text").unwrap();
+    for path in [foreign, newer, not_zip] {
+      assert!(read_manifest(&path).is_err(), "{} was accepted", path.display());
+    }
+  }
+
+  #[test]
+  fn unpack_writes_the_data_and_skips_the_rest() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = archive(
+      dir.path(),
+      "backup.zip",
+      Some(BACKUP_FORMAT),
+      &[
+        ("data/warehouse.duckdb", "duck"),
+        ("artifacts/a/b.bin", "bin"),
+        ("logs/old.log", "log"),
+        ("../escaped", "nope"),
+      ],
+    );
+    let root = dir.path().join("root");
+    let mut percents = Vec::new();
+    unpack(&path, &root, |percent| percents.push(percent)).unwrap();
+
+    assert_eq!(std::fs::read_to_string(root.join("data/warehouse.duckdb")).unwrap(), "duck");
+    assert_eq!(std::fs::read_to_string(root.join("artifacts/a/b.bin")).unwrap(), "bin");
+    assert!(!root.join(MANIFEST).exists());
+    assert!(!root.join("logs").exists());
+    assert!(!dir.path().join("escaped").exists());
+    assert!(!percents.is_empty());
+  }
+
+  #[test]
+  fn entry_name_is_relative_with_forward_slashes() {
+    let root = Path::new("root");
+    assert_eq!(entry_name(root, &root.join("data").join("a.duckdb")).unwrap(), "data/a.duckdb");
+    assert!(entry_name(root, Path::new("elsewhere/a")).is_err());
+  }
+
+  #[test]
+  fn put_back_replaces_what_was_restored_with_the_data_set_aside() {
+    let dir = tempfile::tempdir().unwrap();
+    let (root, aside) = (dir.path().join("root"), dir.path().join("aside"));
+    std::fs::create_dir_all(root.join("data")).unwrap();
+    std::fs::write(root.join("data/old.duckdb"), "old").unwrap();
+    std::fs::write(root.join("shell.lock"), "").unwrap();
+    std::fs::create_dir_all(&aside).unwrap();
+    move_entries(&root, &aside).unwrap();
+    assert!(aside.join("data/old.duckdb").exists());
+    assert!(root.join("shell.lock").exists());
+
+    std::fs::create_dir_all(root.join("data")).unwrap();
+    std::fs::write(root.join("data/restored.duckdb"), "new").unwrap();
+    put_back(&root, &aside).unwrap();
+    assert_eq!(std::fs::read_to_string(root.join("data/old.duckdb")).unwrap(), "old");
+    assert!(!root.join("data/restored.duckdb").exists());
+    assert!(root.join("shell.lock").exists());
+    assert!(!aside.exists());
+  }
+}
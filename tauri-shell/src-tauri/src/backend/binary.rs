@@ -0,0 +1,265 @@
+//! Finds the backend executable. Bundle layouts differ per platform (and the `../../dist`
+//! resource is rewritten to `_up_/_up_/dist` by the bundler), so several locations are tried.
+//!
+//! Before an update replaces the bundle, the running backend is copied to
+//! `pluto-duck-backend.prev` in the app data directory. If the updated backend won't start,
+//! launch rolls back to that copy, and keeps using it until the app version changes again.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+const BINARY_OVERRIDE_ENV: &str = "PLUTODUCK_BACKEND_BIN";
+const BINARY_STEM: &str = "pluto-duck-backend";
+const DEBUG_DIR: &str = "../../dist/pluto-duck-backend";
+const PREVIOUS_DIR: &str = "pluto-duck-backend.prev";
+/// Present while the previous backend stands in for this app version's.
+const ROLLBACK_FILE: &str = "backend-rollback.json";
+/// Where the `../../dist/pluto-duck-backend` resource can end up relative to the resource dir.
+const RESOURCE_DIRS: &[&str] = &[
+  "_up_/_up_/dist/pluto-duck-backend",
+  "dist/pluto-duck-backend",
+  "pluto-duck-backend",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rollback {
+  app_version: String,
+}
+
+/// Returns the first existing backend candidate, or an error listing every path tried. A
+/// rollback to the previous backend wins over everything but the env override.
+pub fn resolve(app: &AppHandle) -> Result<PathBuf> {
+  let override_path = override_path();
+  if override_path.is_none() {
+    if let Some(previous) = rolled_back(app) {
+      return Ok(previous);
+    }
+  }
+  let resource_dir = app.path().resource_dir().ok();
+  let exe_dir = std::env::current_exe()
+    .ok()
+    .and_then(|exe| exe.parent().map(Path::to_path_buf));
+
+  let tried = candidates(override_path, resource_dir.as_deref(), exe_dir.as_deref());
+  if let Some(found) = tried.iter().find(|path| path.is_file()) {
+    return Ok(found.clone());
+  }
+  let listing = tried
+    .iter()
+    .map(|path| format!("  {}", path.display()))
+    .collect::<Vec<_>>()
+    .join("\n");
+  anyhow::bail!("backend binary not found; tried:\n{listing}")
+}
+
+/// The `PLUTODUCK_BACKEND_BIN` override, which wins over everything else.
+fn override_path() -> Option<PathBuf> {
+  std::env::var_os(BINARY_OVERRIDE_ENV).map(PathBuf::from)
+}
+
+/// Candidate paths in priority order: the env override, the dev build output (debug builds
+/// only), the bundled resource, then next to the shell executable.
+fn candidates(
+  override_path: Option<PathBuf>,
+  resource_dir: Option<&Path>,
+  exe_dir: Option<&Path>,
+) -> Vec<PathBuf> {
+  let mut paths = Vec::new();
+  paths.extend(override_path);
+
+  if cfg!(debug_assertions) {
+    let debug_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(DEBUG_DIR);
+    push_names(&mut paths, &debug_dir);
+  }
+  if let Some(resource_dir) = resource_dir {
+    for dir in RESOURCE_DIRS {
+      push_names(&mut paths, &resource_dir.join(dir));
+    }
+  }
+  if let Some(exe_dir) = exe_dir {
+    push_names(&mut paths, &exe_dir.join(BINARY_STEM));
+    push_names(&mut paths, exe_dir);
+  }
+  paths
+}
+
+/// Adds the binary in `dir` under each file name it may have on this platform.
+fn push_names(paths: &mut Vec<PathBuf>, dir: &Path) {
+  if cfg!(windows) {
+    paths.push(dir.join(format!("{BINARY_STEM}.exe")));
+  }
+  paths.push(dir.join(BINARY_STEM));
+}
+
+/// Copies the backend `resolve` finds to `pluto-duck-backend.prev`, replacing an older copy.
+/// A bundled backend directory is copied whole, since the executable needs its libraries.
+/// While rolled back, the copy already is the backend that works, so it's kept.
+pub fn preserve(app: &AppHandle) -> Result<()> {
+  let current = resolve(app)?;
+  let target = previous_dir(app)?;
+  if current.starts_with(&target) {
+    return Ok(());
+  }
+  let bundled = current
+    .parent()
+    .filter(|dir| dir.file_name().is_some_and(|name| name == BINARY_STEM));
+  let partial = target.with_extension("prev.partial");
+  let _ = fs::remove_dir_all(&partial);
+  match bundled {
+    Some(dir) => copy_tree(dir, &partial)?,
+    None => {
+      fs::create_dir_all(&partial)
+        .with_context(|| format!("failed to create {}", partial.display()))?;
+      let name = current.file_name().unwrap_or_default();
+      copy_tree(&current, &partial.join(name))?;
+    }
+  }
+  if target.exists() {
+    fs::remove_dir_all(&target)
+      .with_context(|| format!("failed to remove {}", target.display()))?;
+  }
+  fs::rename(&partial, &target)
+    .with_context(|| format!("failed to move {} into place", partial.display()))?;
+  info!("kept backend {:?} as {:?}", current, target);
+  Ok(())
+}
+
+/// Switches this app version to the preserved previous backend, returning it, or `None` if
+/// there is none.
+pub fn roll_back(app: &AppHandle) -> Option<PathBuf> {
+  let previous = previous(app)?;
+  let record = Rollback {
+    app_version: app.package_info().version.to_string(),
+  };
+  let result = previous_dir(app).and_then(|dir| {
+    let path = dir.with_file_name(ROLLBACK_FILE);
+    fs::write(&path, serde_json::to_vec_pretty(&record)?)
+      .with_context(|| format!("failed to write {}", path.display()))
+  });
+  if let Err(err) = result {
+    // This run still rolls back; the next one tries the new backend again.
+    warn!("{err:#}");
+  }
+  Some(previous)
+}
+
+/// Whether `binary` is the preserved previous backend.
+pub fn is_previous(app: &AppHandle, binary: &Path) -> bool {
+  previous_dir(app).is_ok_and(|dir| binary.starts_with(dir))
+}
+
+/// The previous backend, if this app version has been rolled back to it.
+fn rolled_back(app: &AppHandle) -> Option<PathBuf> {
+  let path = previous_dir(app).ok()?.with_file_name(ROLLBACK_FILE);
+  let rollback: Rollback = serde_json::from_slice(&fs::read(&path).ok()?).ok()?;
+  if rollback.app_version != app.package_info().version.to_string() {
+    // Another update since; its backend gets a chance of its own.
+    info!("clearing backend rollback made for version {}", rollback.app_version);
+    let _ = fs::remove_file(&path);
+    return None;
+  }
+  let previous = previous(app);
+  if previous.is_none() {
+    warn!("backend rollback is set but {:?} is missing", path.with_file_name(PREVIOUS_DIR));
+  }
+  previous
+}
+
+fn previous(app: &AppHandle) -> Option<PathBuf> {
+  let dir = previous_dir(app).ok()?;
+  let mut paths = Vec::new();
+  push_names(&mut paths, &dir);
+  paths.into_iter().find(|path| path.is_file())
+}
+
+fn previous_dir(app: &AppHandle) -> Result<PathBuf> {
+  let dir = app
+    .path()
+    .app_data_dir()
+    .context("app data directory unavailable")?;
+  Ok(dir.join(PREVIOUS_DIR))
+}
+
+fn copy_tree(from: &Path, to: &Path) -> Result<()> {
+  if from.is_dir() {
+    fs::create_dir_all(to).with_context(|| format!("failed to create {}", to.display()))?;
+    let read = fs::read_dir(from).with_context(|| format!("failed to read {}", from.display()))?;
+    for entry in read {
+      let entry = entry.with_context(|| format!("failed to read {}", from.display()))?;
+      copy_tree(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    return Ok(());
+  }
+  fs::copy(from, to)
+    .with_context(|| format!("failed to copy {} to {}", from.display(), to.display()))?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// The names `push_names` adds for `dir`, in order.
+  fn names(dir: &Path) -> Vec<PathBuf> {
+    if cfg!(windows) {
+      vec![dir.join("pluto-duck-backend.exe"), dir.join("pluto-duck-backend")]
+    } else {
+      vec![dir.join("pluto-duck-backend")]
+    }
+  }
+
+  #[test]
+  fn push_names_tries_the_platform_name_first() {
+    let dir = Path::new("bin");
+    let mut paths = Vec::new();
+    push_names(&mut paths, dir);
+    assert_eq!(paths, names(dir));
+    assert_eq!(paths[0].extension().is_some(), cfg!(windows));
+  }
+
+  #[test]
+  fn candidates_go_from_debug_build_to_resources_to_the_exe_dir() {
+    let resources = Path::new("resources");
+    let exe_dir = Path::new("app");
+    let mut expected = Vec::new();
+    if cfg!(debug_assertions) {
+      expected.extend(names(&Path::new(env!("CARGO_MANIFEST_DIR")).join(DEBUG_DIR)));
+    }
+    expected.extend(names(&resources.join("_up_/_up_/dist/pluto-duck-backend")));
+    expected.extend(names(&resources.join("dist/pluto-duck-backend")));
+    expected.extend(names(&resources.join("pluto-duck-backend")));
+    expected.extend(names(&exe_dir.join("pluto-duck-backend")));
+    expected.extend(names(exe_dir));
+
+    assert_eq!(candidates(None, Some(resources), Some(exe_dir)), expected);
+  }
+
+  #[test]
+  fn candidates_skip_unknown_dirs() {
+    let expected = if cfg!(debug_assertions) {
+      names(&Path::new(env!("CARGO_MANIFEST_DIR")).join(DEBUG_DIR))
+    } else {
+      Vec::new()
+    };
+    assert_eq!(candidates(None, None, None), expected);
+  }
+
+  #[test]
+  fn env_override_comes_first() {
+    let binary = Path::new("custom").join("backend");
+    let paths = candidates(
+      Some(binary.clone()),
+      Some(Path::new("resources")),
+      Some(Path::new("app")),
+    );
+
+    assert_eq!(paths[0], binary);
+    assert_eq!(paths.iter().filter(|path| **path == binary).count(), 1);
+  }
+}
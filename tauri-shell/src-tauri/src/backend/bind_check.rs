@@ -0,0 +1,162 @@
+//! Makes sure the backend only listens on loopback before it's declared ready. A backend bound
+//! to `0.0.0.0` (by a stray `--host` or environment setting, say) would serve the user's data
+//! to the whole network, so one found reachable any other way is stopped and the user warned,
+//! unless `backend.allow_remote_bind` says that's wanted.
+
+use std::fmt;
+use std::net::{IpAddr, SocketAddr, TcpStream, UdpSocket};
+use std::time::Duration;
+
+use log::{error, info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Url};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+use super::BackendState;
+use crate::{config, windows};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+/// Documentation-only addresses; connecting a UDP socket to one sends nothing, it only picks
+/// the address a route out of this machine would use.
+const ROUTE_PROBES: &[(&str, &str)] = &[
+  ("0.0.0.0:0", "192.0.2.1:9"),
+  ("[::]:0", "[2001:db8::1]:9"),
+];
+/// Fields of the health response that may carry the address the backend bound.
+const BIND_FIELDS: &[&str] = &["host", "bind", "bind_address"];
+
+/// A backend reachable at an address other than loopback.
+#[derive(Debug)]
+pub struct Exposed {
+  address: String,
+}
+
+impl fmt::Display for Exposed {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "the backend is listening on {}, beyond loopback", self.address)
+  }
+}
+
+impl std::error::Error for Exposed {}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BackendExposed {
+  pid: u32,
+  address: String,
+}
+
+/// Checks where the backend that answered at `base_url` can be reached: the bind address its
+/// health check reports, if it reports one, and whether its port answers on this machine's
+/// network address.
+pub async fn verify(app: &AppHandle, base_url: &str, token: &str) -> Result<(), Exposed> {
+  let Some(port) = Url::parse(base_url).ok().and_then(|url| url.port_or_known_default()) else {
+    return Ok(());
+  };
+  let mut found = reported_bind(base_url, token)
+    .await
+    .filter(|address| !is_loopback(address));
+  if found.is_none() {
+    found = tauri::async_runtime::spawn_blocking(move || reachable_from_network(port))
+      .await
+      .unwrap_or(None);
+  }
+  let Some(address) = found else {
+    return Ok(());
+  };
+  if config::saved(app).backend.allow_remote_bind {
+    warn!("the backend is reachable at {address}; allowed by backend.allow_remote_bind");
+    return Ok(());
+  }
+  Err(Exposed { address })
+}
+
+async fn reported_bind(base_url: &str, token: &str) -> Option<String> {
+  let client = reqwest::Client::builder()
+    .no_proxy()
+    .timeout(REQUEST_TIMEOUT)
+    .build()
+    .ok()?;
+  let response = client
+    .get(format!("{base_url}/health"))
+    .bearer_auth(token)
+    .send()
+    .await
+    .ok()
+    .filter(|response| response.status().is_success())?;
+  let bytes = response.bytes().await.ok()?;
+  let body: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+  BIND_FIELDS
+    .iter()
+    .find_map(|field| body.get(*field).and_then(|value| value.as_str()))
+    .map(str::to_string)
+}
+
+fn is_loopback(address: &str) -> bool {
+  let host = address.trim_start_matches('[').trim_end_matches(']');
+  host.eq_ignore_ascii_case("localhost")
+    || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// `ip:port` if the backend's port accepts connections on an address of this machine that
+/// other hosts could route to.
+fn reachable_from_network(port: u16) -> Option<String> {
+  ROUTE_PROBES.iter().find_map(|(bind, target)| {
+    let socket = UdpSocket::bind(bind).ok()?;
+    socket.connect(target).ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    if ip.is_loopback() || ip.is_unspecified() {
+      return None;
+    }
+    let address = SocketAddr::new(ip, port);
+    TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)
+      .ok()
+      .map(|_| address.to_string())
+  })
+}
+
+/// Stops backend `pid` for listening beyond loopback and tells the user why, on the splash if
+/// it's up, else in a dialog. The watchdog leaves it stopped.
+pub fn refuse(app: &AppHandle, state: &BackendState, pid: u32, exposed: &Exposed) {
+  error!("SECURITY: backend process {pid} is reachable at {}; stopping it", exposed.address);
+  let stop_state = state.clone();
+  let stop_app = app.clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    let Ok(mut backend) = stop_state.lock() else {
+      return;
+    };
+    if !backend.child.as_ref().is_some_and(|child| child.id() == pid) {
+      return;
+    }
+    backend.failed = true;
+    if let Some(exit) = backend.stop() {
+      info!("stopped exposed backend process {pid}");
+      let _ = stop_app.emit("backend-exited", exit);
+    }
+  });
+  let _ = app.emit(
+    "backend-exposed",
+    BackendExposed {
+      pid,
+      address: exposed.address.clone(),
+    },
+  );
+
+  let message = format!(
+    "The Pluto Duck backend was listening on {}, where other computers on the network could \
+     reach your data, so it was stopped.\n\nIf you want it reachable from the network, set \
+     allow_remote_bind = true in the [backend] section of config.toml.",
+    exposed.address
+  );
+  if windows::splash::is_open(app) {
+    windows::splash::fail(app, "The backend was stopped for security.", &message);
+    return;
+  }
+  app
+    .dialog()
+    .message(message)
+    .title("Backend stopped for security")
+    .kind(MessageDialogKind::Warning)
+    .show(|_| {});
+}
@@ -0,0 +1,119 @@
+//! Keeps backup and sync tools away from the data root: they copy (and on conflicts, rewrite)
+//! DuckDB files while the backend has them open, which can corrupt them. The root is marked for
+//! exclusion wherever the OS has a way to say so, caches get a `CACHEDIR.TAG`, and a root
+//! inside a synced folder (Dropbox, iCloud Drive, OneDrive), which no marker keeps out, gets a
+//! warning.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+use super::CACHE_DIRS;
+use crate::config::{self, ShellConfig};
+use crate::platform;
+
+/// See <https://bford.info/cachedir/>.
+const CACHEDIR_TAG: &str = "Signature: 8a477f597d28d172789f06886806bc55\n\
+  # This file marks a Pluto Duck cache directory; backup tools can skip it.\n";
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DataRootSynced {
+  data_root: PathBuf,
+  service: &'static str,
+}
+
+/// Marks `data_root` and its caches for exclusion from backups and indexing. Cheap enough to
+/// repeat, so it runs whenever the root is resolved.
+pub fn exclude(data_root: &Path) {
+  if let Err(err) = platform::exclude_from_backup(data_root) {
+    warn!("failed to exclude {:?} from backups: {err}", data_root);
+  }
+  for dir in CACHE_DIRS {
+    let tag = data_root.join(dir).join("CACHEDIR.TAG");
+    if tag.exists() {
+      continue;
+    }
+    let result = std::fs::create_dir_all(data_root.join(dir))
+      .and_then(|()| std::fs::write(&tag, CACHEDIR_TAG));
+    if let Err(err) = result {
+      warn!("failed to write {:?}: {err}", tag);
+    }
+  }
+}
+
+/// Warns when `data_root` is inside a synced folder: every launch in the log and as
+/// `data-root-in-sync-folder`, and once in a dialog, which doesn't hold up launch.
+pub fn check(app: &AppHandle, data_root: &Path) {
+  let Some(service) = sync_service(app, data_root) else {
+    return;
+  };
+  warn!("data root {:?} is inside a {service} folder", data_root);
+  let _ = app.emit(
+    "data-root-in-sync-folder",
+    DataRootSynced {
+      data_root: data_root.to_path_buf(),
+      service,
+    },
+  );
+  if !app.state::<ShellConfig>().backend.warn_sync_folder {
+    return;
+  }
+  if let Err(err) = config::update(app, |config| config.backend.warn_sync_folder = false) {
+    // Better to skip the dialog than to show it every launch.
+    warn!("not warning about the synced data root: {err:#}");
+    return;
+  }
+  app
+    .dialog()
+    .message(format!(
+      "Pluto Duck's data is in {}, which {service} syncs.\n\n{service} can copy the databases \
+       while Pluto Duck has them open and damage them. Moving the data to a folder that isn't \
+       synced (Settings → Data location) avoids this.",
+      data_root.display()
+    ))
+    .title("Data folder is synced")
+    .kind(MessageDialogKind::Warning)
+    .show(|_| {});
+}
+
+/// The sync service whose folder holds `path`, judged by the services' default locations and
+/// folder names.
+fn sync_service(app: &AppHandle, path: &Path) -> Option<&'static str> {
+  let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+  let mut roots = Vec::new();
+  if let Ok(home) = app.path().home_dir() {
+    roots.push((home.join("Library/Mobile Documents"), "iCloud Drive"));
+  }
+  for var in ["OneDrive", "OneDriveConsumer", "OneDriveCommercial"] {
+    if let Some(dir) = env::var_os(var).filter(|dir| !dir.is_empty()) {
+      roots.push((PathBuf::from(dir), "OneDrive"));
+    }
+  }
+  if let Some((_, service)) = roots.iter().find(|(root, _)| path.starts_with(root)) {
+    return Some(service);
+  }
+
+  // e.g. `~/Dropbox`, `~/Library/CloudStorage/OneDrive-Personal`, `OneDrive - Contoso`.
+  path.components().find_map(|component| {
+    let name = component.as_os_str().to_string_lossy().to_lowercase();
+    let named = |service: &str| {
+      name == service
+        || name.starts_with(&format!("{service}-"))
+        || name.starts_with(&format!("{service} "))
+    };
+    if named("dropbox") {
+      Some("Dropbox")
+    } else if named("onedrive") {
+      Some("OneDrive")
+    } else if name == "iclouddrive" || name == "icloud drive" {
+      Some("iCloud Drive")
+    } else {
+      None
+    }
+  })
+}
@@ -0,0 +1,104 @@
+//! Crash reports: a text file per abnormal backend exit under `data_root/crashes/`, with
+//! enough context to attach to a support request.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use super::termination::Termination;
+
+const CRASH_DIR: &str = "crashes";
+const MAX_REPORTS: usize = 20;
+
+/// Everything that goes into one report but the binary's hash, which may take a while to
+/// compute; gathered while the backend is locked, and written once it isn't.
+pub struct CrashContext {
+  pub data_root: PathBuf,
+  pub exit: Termination,
+  pub uptime: Option<Duration>,
+  pub app_version: String,
+  pub binary: PathBuf,
+  pub stderr_tail: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+  pub name: String,
+  pub path: PathBuf,
+  /// Milliseconds since the Unix epoch.
+  pub created_at: Option<u64>,
+  pub size: u64,
+}
+
+/// Writes `crash-<timestamp>.txt` and prunes the directory to the newest reports.
+pub fn write_report(
+  context: &CrashContext,
+  binary_sha256: std::io::Result<String>,
+) -> std::io::Result<PathBuf> {
+  let data_root = &context.data_root;
+  let dir = data_root.join(CRASH_DIR);
+  std::fs::create_dir_all(&dir)?;
+  let now = chrono::Local::now();
+  let path = dir.join(format!("crash-{}.txt", now.format("%Y-%m-%dT%H-%M-%S%.3f")));
+
+  let mut report = String::new();
+  let _ = writeln!(report, "Pluto Duck backend crash report");
+  let _ = writeln!(report, "time: {}", now.to_rfc3339());
+  let _ = writeln!(report, "app version: {}", context.app_version);
+  let _ = writeln!(report, "os: {} {}", std::env::consts::OS, std::env::consts::ARCH);
+  let _ = writeln!(report, "pid: {}", context.exit.pid);
+  let _ = writeln!(report, "exit: {}", context.exit.reason);
+  if let Some(uptime) = context.uptime {
+    let _ = writeln!(report, "uptime: {:.1}s", uptime.as_secs_f64());
+  }
+  let _ = writeln!(report, "binary: {}", context.binary.display());
+  let hash = binary_sha256.unwrap_or_else(|err| format!("unavailable ({err})"));
+  let _ = writeln!(report, "binary sha256: {hash}");
+  let _ = writeln!(report, "\n--- last {} stderr line(s) ---", context.stderr_tail.len());
+  for line in &context.stderr_tail {
+    let _ = writeln!(report, "{line}");
+  }
+
+  std::fs::write(&path, report)?;
+  if let Ok(reports) = list_reports(data_root) {
+    for old in reports.into_iter().skip(MAX_REPORTS) {
+      let _ = std::fs::remove_file(&old.path);
+    }
+  }
+  Ok(path)
+}
+
+/// Crash reports, newest first.
+pub fn list_reports(data_root: &Path) -> std::io::Result<Vec<CrashReport>> {
+  let entries = match std::fs::read_dir(data_root.join(CRASH_DIR)) {
+    Ok(entries) => entries,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+    Err(err) => return Err(err),
+  };
+  let mut reports: Vec<CrashReport> = entries
+    .flatten()
+    .filter_map(|entry| {
+      let name = entry.file_name().to_string_lossy().into_owned();
+      if !name.starts_with("crash-") || !name.ends_with(".txt") {
+        return None;
+      }
+      let metadata = entry.metadata().ok()?;
+      Some(CrashReport {
+        created_at: metadata
+          .modified()
+          .ok()
+          .and_then(|at| at.duration_since(UNIX_EPOCH).ok())
+          .map(|elapsed| elapsed.as_millis() as u64),
+        size: metadata.len(),
+        path: entry.path(),
+        name,
+      })
+    })
+    .collect();
+  // The timestamped names sort chronologically.
+  reports.sort_by(|a, b| b.name.cmp(&a.name));
+  Ok(reports)
+}
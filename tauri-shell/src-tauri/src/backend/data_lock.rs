@@ -0,0 +1,119 @@
+//! `.lock` in the data root is held for as long as this shell runs a backend on it, so no
+//! second backend can open the same DuckDB files, however it was pointed at them (a profile, an
+//! override, or a shell whose instance lock failed). The file records the holder's PID: a lock
+//! that is taken names its holder, and one whose holder is gone is reclaimed. The OS drops the
+//! lock when the holder exits, crash or not; `release` just does it sooner and tidies up.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+
+use super::file_lock::try_lock;
+use super::process;
+
+const LOCK_FILE: &str = ".lock";
+
+/// The held lock, kept managed until `release`.
+pub struct DataLock(Mutex<Option<Held>>);
+
+struct Held {
+  _file: File,
+  path: PathBuf,
+}
+
+/// Locks `data_root` for this process, or explains who holds it.
+pub fn acquire(data_root: &Path) -> Result<DataLock> {
+  let path = data_root.join(LOCK_FILE);
+  let mut file = open(&path)?;
+  if !try_lock(&file) {
+    let holder = read_pid(&mut file);
+    match holder {
+      Some(pid) if !process::is_alive(pid) => {
+        // Only a lock that outlived its process (e.g. on a network drive) gets here.
+        warn!("reclaiming data lock {:?} left by pid {pid}, which is gone", path);
+        drop(file);
+        std::fs::remove_file(&path)
+          .with_context(|| format!("failed to remove stale lock {}", path.display()))?;
+        file = open(&path)?;
+        if !try_lock(&file) {
+          anyhow::bail!(in_use(data_root, read_pid(&mut file)));
+        }
+      }
+      holder => anyhow::bail!(in_use(data_root, holder)),
+    }
+  }
+
+  let pid = std::process::id();
+  file
+    .set_len(0)
+    .and_then(|()| file.seek(SeekFrom::Start(0)))
+    .and_then(|_| writeln!(file, "{pid}"))
+    .with_context(|| format!("failed to write {}", path.display()))?;
+  info!("locked data root {:?} for pid {pid}", data_root);
+  Ok(DataLock(Mutex::new(Some(Held { _file: file, path }))))
+}
+
+impl DataLock {
+  /// Lets go of the data root, once the backend is stopped for good.
+  pub fn release(&self) {
+    let held = self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take();
+    let Some(held) = held else {
+      return;
+    };
+    // Removed while still locked, so no other process can have taken it in between.
+    if let Err(err) = std::fs::remove_file(&held.path) {
+      warn!("failed to remove data lock {:?}: {err}", held.path);
+    }
+    info!("released data lock {:?}", held.path);
+  }
+}
+
+fn in_use(data_root: &Path, holder: Option<u32>) -> String {
+  let holder = match holder {
+    Some(pid) => format!("another process (PID {pid})"),
+    None => "another process".to_string(),
+  };
+  format!(
+    "The data in {} is in use by {holder}. Opening it twice could damage it, so the backend \
+     was not started. Quit the other copy of Pluto Duck and try again.",
+    data_root.display()
+  )
+}
+
+fn open(path: &Path) -> Result<File> {
+  OpenOptions::new()
+    .read(true)
+    .write(true)
+    .create(true)
+    .truncate(false)
+    .open(path)
+    .with_context(|| format!("failed to open {}", path.display()))
+}
+
+fn read_pid(file: &mut File) -> Option<u32> {
+  let mut raw = String::new();
+  file.seek(SeekFrom::Start(0)).ok()?;
+  file.read_to_string(&mut raw).ok()?;
+  raw.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn a_held_lock_names_its_holder_until_released() {
+    let dir = tempfile::tempdir().unwrap();
+    let lock = acquire(dir.path()).unwrap();
+    let err = acquire(dir.path()).err().expect("the lock is held");
+    assert!(err.to_string().contains(&format!("PID {}", std::process::id())));
+
+    lock.release();
+    assert!(!dir.path().join(LOCK_FILE).exists());
+    acquire(dir.path()).unwrap().release();
+  }
+}
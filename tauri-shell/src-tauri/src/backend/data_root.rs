@@ -0,0 +1,198 @@
+//! Where the backend keeps its data. Users can move it (the DuckDB files can get big) with the
+//! `backend.data_root` setting, which is offered once on first run and changed later with
+//! `set_data_root`. Both save the setting and relaunch the app, so the instance lock, logs,
+//! and backend all move to the new root together. Portable mode overrides the setting, and
+//! profiles other than the default one have roots of their own.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use log::{error, info, warn};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::config::{self, ShellConfig};
+use super::profiles;
+use crate::{audit, portable};
+
+const DEFAULT_BUTTON: &str = "Default Location";
+const CHOOSE_BUTTON: &str = "Choose Folder…";
+
+/// Where data goes without a `data_root` setting.
+pub fn default_root(app: &AppHandle) -> PathBuf {
+  let base = if cfg!(debug_assertions) {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../.dev-data")
+  } else {
+    app
+      .path()
+      .app_data_dir()
+      .unwrap_or_else(|_| std::env::temp_dir().join("pluto_duck"))
+  };
+  base.join("backend")
+}
+
+/// The portable root, the active profile's, the configured one, or the default one, in that
+/// order.
+pub fn configured_root(app: &AppHandle) -> PathBuf {
+  let configured = app
+    .try_state::<ShellConfig>()
+    .and_then(|config| config.backend.data_root.clone());
+  chosen_root(portable::root(), profiles::active_root(app), configured)
+    .unwrap_or_else(|| default_root(app))
+}
+
+/// The first of the portable, profile, and configured roots that's set.
+fn chosen_root(
+  portable: Option<&Path>,
+  profile: Option<PathBuf>,
+  configured: Option<PathBuf>,
+) -> Option<PathBuf> {
+  portable.map(|root| root.join("backend")).or(profile).or(configured)
+}
+
+/// A first run of a release build: no setting and nothing at the default location yet.
+pub fn needs_choice(app: &AppHandle) -> bool {
+  let configured = app
+    .try_state::<ShellConfig>()
+    .is_some_and(|config| config.backend.data_root.is_some());
+  !cfg!(debug_assertions)
+    && portable::root().is_none()
+    && profiles::active_root(app).is_none()
+    && !configured
+    && !default_root(app).exists()
+}
+
+/// Asks where to keep data, saves the answer, and relaunches the app to use it. Shown instead
+/// of launching the backend, since the root can't change under a running one.
+pub fn ask(app: &AppHandle) {
+  let default = default_root(app);
+  let dialog_app = app.clone();
+  app
+    .dialog()
+    .message(format!(
+      "Pluto Duck keeps its databases in {}.\n\nYou can keep them there, or choose another \
+       folder, for example on a larger disk.",
+      default.display()
+    ))
+    .title("Where should Pluto Duck keep its data?")
+    .kind(MessageDialogKind::Info)
+    .buttons(MessageDialogButtons::OkCancelCustom(
+      DEFAULT_BUTTON.into(),
+      CHOOSE_BUTTON.into(),
+    ))
+    .show(move |use_default| {
+      if use_default {
+        choose(&dialog_app, default);
+        return;
+      }
+      let picker_app = dialog_app.clone();
+      dialog_app
+        .dialog()
+        .file()
+        .set_title("Choose a folder for Pluto Duck's data")
+        .pick_folder(move |folder| {
+          // Backing out of the picker keeps the default.
+          let root = folder
+            .and_then(|folder| folder.into_path().ok())
+            .unwrap_or(default);
+          choose(&picker_app, root);
+        });
+    });
+}
+
+fn choose(app: &AppHandle, root: PathBuf) {
+  if let Err(err) = check_writable(&root) {
+    error!("chosen data root {:?} is unusable: {err:#}", root);
+    let retry_app = app.clone();
+    app
+      .dialog()
+      .message(format!("{err:#}"))
+      .title("Can't use that folder")
+      .kind(MessageDialogKind::Error)
+      .show(move |_| ask(&retry_app));
+    return;
+  }
+  match config::update(app, |config| config.backend.data_root = Some(root.clone())) {
+    Ok(()) => {
+      info!("data root set to {:?}; relaunching", root);
+      app.request_restart();
+    }
+    Err(err) => {
+      // Relaunching without the setting would only ask again.
+      error!("failed to save data root: {err:#}");
+      let exit_app = app.clone();
+      app
+        .dialog()
+        .message(format!("The data location could not be saved: {err:#}"))
+        .title("Pluto Duck")
+        .kind(MessageDialogKind::Error)
+        .show(move |_| exit_app.exit(1));
+    }
+  }
+}
+
+/// Creates `root` if needed and makes sure files can be written there.
+pub(super) fn check_writable(root: &Path) -> anyhow::Result<()> {
+  std::fs::create_dir_all(root)
+    .with_context(|| format!("{} could not be created", root.display()))?;
+  let probe = root.join(".pluto-duck-write-test");
+  std::fs::write(&probe, b"")
+    .with_context(|| format!("{} isn't writable", root.display()))?;
+  if let Err(err) = std::fs::remove_file(&probe) {
+    warn!("failed to remove {:?}: {err}", probe);
+  }
+  Ok(())
+}
+
+/// Moves the active profile's data to `path` from the next launch, relaunching now. The
+/// backend is stopped gracefully on the way out; existing data is not copied over.
+#[tauri::command]
+pub async fn set_data_root(app: AppHandle, path: PathBuf) -> Result<(), String> {
+  let result = change_data_root(app.clone(), path.clone()).await;
+  audit::record(&app, "set_data_root", serde_json::json!({ "path": path }), &result);
+  result
+}
+
+async fn change_data_root(app: AppHandle, path: PathBuf) -> Result<(), String> {
+  if let Some(root) = portable::root() {
+    return Err(format!(
+      "portable mode keeps data in {}; remove portable.marker to choose another location",
+      root.display()
+    ));
+  }
+  if !path.is_absolute() {
+    return Err(format!("{} is not an absolute path", path.display()));
+  }
+  check_writable(&path).map_err(|err| format!("{err:#}"))?;
+  let saved = match profiles::set_active_root(&app, &path) {
+    Ok(true) => Ok(()),
+    Ok(false) => config::update(&app, |config| config.backend.data_root = Some(path.clone())),
+    Err(err) => Err(err),
+  };
+  saved.map_err(|err| format!("data location could not be saved: {err:#}"))?;
+  info!("data root changed to {:?}; relaunching", path);
+  app.request_restart();
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn portable_root_wins_over_the_setting() {
+    let chosen = chosen_root(
+      Some(Path::new("/usb/data")),
+      None,
+      Some(PathBuf::from("/configured")),
+    );
+    assert_eq!(chosen, Some(PathBuf::from("/usb/data/backend")));
+  }
+
+  #[test]
+  fn setting_applies_outside_portable_mode() {
+    let chosen = chosen_root(None, None, Some(PathBuf::from("/configured")));
+    assert_eq!(chosen, Some(PathBuf::from("/configured")));
+    assert_eq!(chosen_root(None, None, None), None);
+  }
+}
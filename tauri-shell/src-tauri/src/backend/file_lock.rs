@@ -0,0 +1,38 @@
+//! Exclusive, non-blocking locks on a whole file, which the OS drops when the holder exits.
+
+use std::fs::File;
+
+/// Takes the lock on `file`, or returns `false` if another process holds it.
+#[cfg(unix)]
+pub fn try_lock(file: &File) -> bool {
+  use std::os::unix::io::AsRawFd;
+
+  // SAFETY: flock on a descriptor we own for the duration of the call.
+  unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) == 0 }
+}
+
+/// Takes the lock on `file`, or returns `false` if another process holds it.
+#[cfg(windows)]
+pub fn try_lock(file: &File) -> bool {
+  use std::os::windows::io::AsRawHandle;
+  use windows_sys::Win32::Storage::FileSystem::{
+    LockFileEx, LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY,
+  };
+  use windows_sys::Win32::System::IO::OVERLAPPED;
+
+  // SAFETY: the handle is owned by `file` and the OVERLAPPED lives across the synchronous call.
+  unsafe {
+    let mut overlapped: OVERLAPPED = std::mem::zeroed();
+    // Windows locks keep others from reading the locked bytes, so lock one byte well past
+    // anything written (a holder's PID, say) rather than the contents.
+    overlapped.Anonymous.Anonymous.OffsetHigh = 1;
+    LockFileEx(
+      file.as_raw_handle(),
+      LOCKFILE_EXCLUSIVE_LOCK | LOCKFILE_FAIL_IMMEDIATELY,
+      0,
+      1,
+      0,
+      &mut overlapped,
+    ) != 0
+  }
+}
@@ -0,0 +1,206 @@
+//! Explains macOS refusing to run the backend. A bundle still carrying the
+//! `com.apple.quarantine` attribute, or run from the read-only copy App Translocation makes of
+//! an app opened straight from its download, fails to spawn with a bare "Operation not
+//! permitted". Both are checked before spawning, and a spawn that fails with `EPERM` anyway
+//! gets the same diagnosis, so the user is told how to fix it.
+
+use std::ffi::CString;
+use std::fmt;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use log::{error, info, warn};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult};
+
+use super::BackendState;
+use crate::windows;
+
+const QUARANTINE_ATTR: &str = "com.apple.quarantine";
+const TRANSLOCATION_DIR: &str = "/AppTranslocation/";
+const CLEAR_BUTTON: &str = "Clear and Relaunch";
+const MOVE_ADVICE: &str = "Move Pluto Duck to the Applications folder and open it from there.";
+
+/// Why macOS won't (or didn't) let the backend run.
+#[derive(Debug)]
+pub enum Blocked {
+  /// Running from App Translocation's randomized, read-only copy of the app.
+  Translocated,
+  /// The backend is quarantined; `clear` is what to clear it from, if the user can.
+  Quarantined { binary: PathBuf, clear: Option<PathBuf> },
+  /// Spawning failed with `EPERM` without either of the above.
+  NotPermitted { binary: PathBuf },
+}
+
+impl fmt::Display for Blocked {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Translocated => write!(f, "the app is running from App Translocation"),
+      Self::Quarantined { binary, .. } => {
+        write!(f, "the backend {} is quarantined", binary.display())
+      }
+      Self::NotPermitted { binary } => {
+        write!(f, "macOS did not permit running the backend {}", binary.display())
+      }
+    }
+  }
+}
+
+impl std::error::Error for Blocked {}
+
+/// Checks for translocation and quarantine before `binary` is spawned.
+pub fn check(app: &AppHandle, binary: &Path) -> Result<(), Blocked> {
+  if is_translocated(app) {
+    return Err(Blocked::Translocated);
+  }
+  if has_quarantine(binary) {
+    let target = bundle(binary).unwrap_or_else(|| binary.to_path_buf());
+    return Err(Blocked::Quarantined {
+      binary: binary.to_path_buf(),
+      clear: Some(target).filter(|target| is_writable(target)),
+    });
+  }
+  Ok(())
+}
+
+/// The diagnosis for a launch error caused by macOS refusing the spawn, if it was.
+pub fn diagnose(app: &AppHandle, err: &anyhow::Error) -> Option<Blocked> {
+  let refused = err.chain().any(|cause| {
+    cause
+      .downcast_ref::<std::io::Error>()
+      .is_some_and(|err| err.raw_os_error() == Some(libc::EPERM))
+  });
+  if !refused {
+    return None;
+  }
+  let state = app.try_state::<BackendState>()?;
+  let binary = state.lock().ok()?.spec.binary.clone();
+  Some(match check(app, &binary) {
+    Err(blocked) => blocked,
+    Ok(()) => Blocked::NotPermitted { binary },
+  })
+}
+
+fn is_translocated(app: &AppHandle) -> bool {
+  let resource_dir = app.path().resource_dir().ok();
+  let exe = std::env::current_exe().ok();
+  [resource_dir, exe]
+    .into_iter()
+    .flatten()
+    .any(|path| path.to_string_lossy().contains(TRANSLOCATION_DIR))
+}
+
+fn c_path(path: &Path) -> Option<CString> {
+  CString::new(path.as_os_str().as_bytes()).ok()
+}
+
+fn has_quarantine(path: &Path) -> bool {
+  let (Some(path), Ok(name)) = (c_path(path), CString::new(QUARANTINE_ATTR)) else {
+    return false;
+  };
+  // SAFETY: both strings are NUL-terminated; a null buffer only asks for the value's size.
+  let size = unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), std::ptr::null_mut(), 0, 0, 0) };
+  size >= 0
+}
+
+fn is_writable(path: &Path) -> bool {
+  let Some(path) = c_path(path) else {
+    return false;
+  };
+  // SAFETY: a NUL-terminated path.
+  unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
+
+/// The `.app` bundle `binary` is in, if it's in one.
+fn bundle(binary: &Path) -> Option<PathBuf> {
+  binary
+    .ancestors()
+    .find(|dir| dir.extension().is_some_and(|ext| ext == "app"))
+    .map(Path::to_path_buf)
+}
+
+fn clear(target: &Path) -> Result<(), String> {
+  let status = Command::new("/usr/bin/xattr")
+    .args(["-dr", QUARANTINE_ATTR])
+    .arg(target)
+    .status()
+    .map_err(|err| err.to_string())?;
+  if !status.success() {
+    return Err(format!("xattr exited with {status}"));
+  }
+  Ok(())
+}
+
+/// Explains `blocked` on the splash, if it's up, or else in a dialog. A quarantine the shell
+/// can clear gets the dialog either way, since only it offers to.
+pub fn report(app: &AppHandle, blocked: &Blocked) {
+  error!("backend blocked by macOS: {blocked}");
+  let (message, clear_target) = match blocked {
+    Blocked::Translocated => (
+      format!(
+        "macOS is running Pluto Duck from a temporary, read-only location because it was \
+         opened straight from its download, and that keeps the backend from starting.\n\n\
+         {MOVE_ADVICE}"
+      ),
+      None,
+    ),
+    Blocked::Quarantined { clear, .. } => (
+      format!(
+        "macOS has quarantined part of Pluto Duck as downloaded from the internet, and won't \
+         let the backend start.\n\n{MOVE_ADVICE}{}",
+        if clear.is_some() {
+          " Or clear the quarantine and relaunch now."
+        } else {
+          ""
+        }
+      ),
+      clear.clone(),
+    ),
+    Blocked::NotPermitted { .. } => (
+      format!(
+        "macOS did not allow the Pluto Duck backend to start.\n\n{MOVE_ADVICE} If it still \
+         won't start, allow it under System Settings > Privacy & Security."
+      ),
+      None,
+    ),
+  };
+  if windows::splash::is_open(app) {
+    windows::splash::fail(app, "macOS blocked the backend.", &message);
+    if clear_target.is_none() {
+      return;
+    }
+  }
+  let dialog = app
+    .dialog()
+    .message(message)
+    .title("Pluto Duck can't start its backend")
+    .kind(MessageDialogKind::Warning);
+  let Some(target) = clear_target else {
+    dialog.show(|_| {});
+    return;
+  };
+  let app = app.clone();
+  dialog
+    .buttons(MessageDialogButtons::OkCancelCustom(CLEAR_BUTTON.into(), "Close".into()))
+    .show_with_result(move |result| {
+      if !matches!(result, MessageDialogResult::Custom(label) if label == CLEAR_BUTTON) {
+        return;
+      }
+      match clear(&target) {
+        Ok(()) => {
+          info!("cleared quarantine from {:?}; relaunching", target);
+          app.restart();
+        }
+        Err(err) => {
+          warn!("failed to clear quarantine from {:?}: {err}", target);
+          app
+            .dialog()
+            .message(format!("The quarantine could not be cleared: {err}\n\n{MOVE_ADVICE}"))
+            .title("Pluto Duck can't start its backend")
+            .kind(MessageDialogKind::Error)
+            .show(|_| {});
+        }
+      }
+    });
+}
@@ -0,0 +1,268 @@
+//! One shell per data root. The first instance holds an exclusive lock on `shell.lock` and
+//! listens on a loopback port recorded in `shell-instance.json`; a later launch against the
+//! same data root sends its argv and cwd there and exits instead of starting a second backend
+//! that would fight over the port and the DuckDB file. The record also holds a random nonce,
+//! readable only by the user, that a forwarded launch must carry: any local process can reach
+//! the port, and the arguments it forwards are handled as deep links and files to open.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use super::file_lock::try_lock;
+use crate::windows;
+
+const LOCK_FILE: &str = "shell.lock";
+const INSTANCE_FILE: &str = "shell-instance.json";
+/// How long a second instance keeps trying to reach the first instance or take over its lock.
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(5);
+const FORWARD_RETRY_INTERVAL: Duration = Duration::from_millis(200);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+/// How long the listener waits for a connection's message, so a client that sends nothing
+/// can't hold up the launches after it.
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+/// Far more than any real argv; longer messages are cut off and so fail to parse.
+const MAX_MESSAGE_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct InstanceRecord {
+  pid: u32,
+  port: u16,
+  nonce: String,
+}
+
+/// What goes over the wire: the launch, and the nonce proving the sender could read the
+/// record.
+#[derive(Serialize, Deserialize)]
+struct Forwarded {
+  nonce: String,
+  launch: SecondInstance,
+}
+
+/// Sent by a second instance, and passed on to the frontend as `second-instance`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecondInstance {
+  args: Vec<String>,
+  cwd: Option<PathBuf>,
+}
+
+/// Keeps the instance lock for the life of the process; the OS releases it when we exit or
+/// crash.
+pub struct InstanceLock {
+  _file: File,
+}
+
+/// What happened when this process tried to become the instance for its data root.
+pub enum Claim {
+  /// We are the only instance; keep the lock managed for the rest of the run.
+  Primary(InstanceLock),
+  /// Another instance owns the data root and has been handed our arguments.
+  Forwarded,
+  /// Another instance owns the data root but didn't take our arguments (e.g. it is hung).
+  Unreachable(anyhow::Error),
+}
+
+/// Takes the instance lock for `data_root`, or forwards this launch to the instance holding it.
+pub fn claim(app: &AppHandle, data_root: &Path) -> anyhow::Result<Claim> {
+  std::fs::create_dir_all(data_root)?;
+  let file = OpenOptions::new()
+    .read(true)
+    .write(true)
+    .create(true)
+    .truncate(false)
+    .open(data_root.join(LOCK_FILE))?;
+  let message = SecondInstance {
+    args: std::env::args().skip(1).collect(),
+    cwd: std::env::current_dir().ok(),
+  };
+
+  // Keep trying both: the holder may still be starting up (not listening yet) or on its way
+  // out (e.g. relaunching itself), in which case the lock frees up shortly.
+  let started = Instant::now();
+  loop {
+    if try_lock(&file) {
+      break;
+    }
+    match forward(data_root, &message) {
+      Ok(()) => return Ok(Claim::Forwarded),
+      Err(err) if started.elapsed() >= FORWARD_TIMEOUT => {
+        return Ok(Claim::Unreachable(
+          err.context("another instance holds the data root but is not answering"),
+        ));
+      }
+      Err(_) => thread::sleep(FORWARD_RETRY_INTERVAL),
+    }
+  }
+
+  let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+  let record = InstanceRecord {
+    pid: std::process::id(),
+    port: listener.local_addr()?.port(),
+    nonce: generate_nonce()?,
+  };
+  write_record(data_root, &record)?;
+  listen(app.clone(), listener, record.nonce);
+  Ok(Claim::Primary(InstanceLock { _file: file }))
+}
+
+/// Sends `message` to the instance recorded in the data root.
+fn forward(data_root: &Path, message: &SecondInstance) -> anyhow::Result<()> {
+  let record = read_record(data_root)?;
+  let address = SocketAddr::from((Ipv4Addr::LOCALHOST, record.port));
+  let mut stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+  let forwarded = Forwarded {
+    nonce: record.nonce,
+    launch: message.clone(),
+  };
+  let mut line = serde_json::to_vec(&forwarded)?;
+  line.push(b'\n');
+  stream.write_all(&line)?;
+  info!("forwarded launch to running instance (pid {})", record.pid);
+  Ok(())
+}
+
+fn read_record(data_root: &Path) -> anyhow::Result<InstanceRecord> {
+  Ok(serde_json::from_slice(&std::fs::read(
+    data_root.join(INSTANCE_FILE),
+  )?)?)
+}
+
+/// Replaces the record with one only the user can read, since it holds the nonce.
+fn write_record(data_root: &Path, record: &InstanceRecord) -> anyhow::Result<()> {
+  let path = data_root.join(INSTANCE_FILE);
+  // A record left by another user's or an older shell may be readable by others.
+  let _ = std::fs::remove_file(&path);
+  let mut options = OpenOptions::new();
+  options.write(true).create_new(true);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600);
+  }
+  options
+    .open(&path)?
+    .write_all(&serde_json::to_vec_pretty(record)?)?;
+  Ok(())
+}
+
+/// 128 random bits, hex-encoded.
+fn generate_nonce() -> anyhow::Result<String> {
+  let mut bytes = [0u8; 16];
+  getrandom::fill(&mut bytes)
+    .map_err(|err| anyhow::anyhow!("failed to generate a single-instance nonce: {err}"))?;
+  Ok(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Reads one forwarded launch from `stream`, if it carries `nonce`.
+fn receive(stream: TcpStream, nonce: &str) -> anyhow::Result<SecondInstance> {
+  stream.set_read_timeout(Some(READ_TIMEOUT))?;
+  let mut line = String::new();
+  BufReader::new(stream.take(MAX_MESSAGE_BYTES)).read_line(&mut line)?;
+  let forwarded: Forwarded = serde_json::from_str(&line)?;
+  anyhow::ensure!(forwarded.nonce == nonce, "wrong nonce");
+  Ok(forwarded.launch)
+}
+
+/// Accepts launches forwarded by later instances: each brings the main window forward and is
+/// announced as `second-instance`.
+fn listen(app: AppHandle, listener: TcpListener, nonce: String) {
+  let result = thread::Builder::new()
+    .name("single-instance".into())
+    .spawn(move || {
+      for stream in listener.incoming() {
+        let stream = match stream {
+          Ok(stream) => stream,
+          Err(err) => {
+            warn!("failed to accept second instance connection: {err}");
+            continue;
+          }
+        };
+        let message = match receive(stream, &nonce) {
+          Ok(message) => message,
+          Err(err) => {
+            warn!("ignoring second instance message: {err:#}");
+            continue;
+          }
+        };
+        info!("second instance launched with args {:?}", message.args);
+
+        let main_app = app.clone();
+        let _ = app.run_on_main_thread(move || {
+          if let Err(err) = windows::show_main_window(&main_app) {
+            error!("failed to show main window: {err}");
+          }
+        });
+        crate::deep_link::handle_args(&app, &message.args);
+        crate::open_files::handle_args(&app, &message.args, message.cwd.as_deref());
+        let _ = app.emit("second-instance", message);
+      }
+    });
+  if let Err(err) = result {
+    error!("failed to start single-instance listener: {err}");
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// The listener's end of a connection whose client sends `message`, if anything.
+  fn connection(message: Option<&[u8]>) -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    if let Some(message) = message {
+      client.write_all(message).unwrap();
+    }
+    (listener.accept().unwrap().0, client)
+  }
+
+  fn message(nonce: &str) -> Vec<u8> {
+    let forwarded = Forwarded {
+      nonce: nonce.to_string(),
+      launch: SecondInstance {
+        args: vec!["pluto-duck://open".to_string()],
+        cwd: None,
+      },
+    };
+    let mut line = serde_json::to_vec(&forwarded).unwrap();
+    line.push(b'\n');
+    line
+  }
+
+  #[test]
+  fn receive_takes_a_launch_with_the_nonce() {
+    let (stream, _client) = connection(Some(&message("secret")));
+    let launch = receive(stream, "secret").unwrap();
+    assert_eq!(launch.args, ["pluto-duck://open"]);
+  }
+
+  #[test]
+  fn receive_refuses_a_wrong_nonce() {
+    let (stream, _client) = connection(Some(&message("guess")));
+    assert!(receive(stream, "secret").is_err());
+  }
+
+  #[test]
+  fn receive_gives_up_on_a_silent_client() {
+    let (stream, _client) = connection(None);
+    let started = Instant::now();
+    assert!(receive(stream, "secret").is_err());
+    assert!(started.elapsed() < READ_TIMEOUT * 2);
+  }
+
+  #[test]
+  fn receive_caps_the_message_length() {
+    let mut long = vec![b' '; MAX_MESSAGE_BYTES as usize];
+    long.extend(message("secret"));
+    let (stream, _client) = connection(Some(&long));
+    assert!(receive(stream, "secret").is_err());
+  }
+}
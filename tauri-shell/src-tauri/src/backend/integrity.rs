@@ -0,0 +1,181 @@
+//! Checks the bundled backend against the `backend.sha256` the build writes next to it. A
+//! download cut short or a file quarantined by antivirus leaves a backend that crashes at
+//! startup with errors that point nowhere near the cause, so a damaged one isn't spawned and
+//! the user is told to reinstall instead. Release builds only. Hashing the whole binary takes a
+//! moment, so the hash is cached with the shell's settings by file size and modification time.
+
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult};
+
+use crate::{config, platform, windows};
+
+const CHECKSUM_FILE: &str = "backend.sha256";
+const CACHE_FILE: &str = "backend-integrity.json";
+const RELEASES_URL: &str = "https://github.com/Fluxloop-AI/pluto-duck-oss/releases/latest";
+const DOWNLOAD_BUTTON: &str = "Download Pluto Duck";
+
+/// The hash of a binary as it was when last read.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Cached {
+  path: PathBuf,
+  size: u64,
+  modified_ms: u128,
+  sha256: String,
+}
+
+/// A backend binary that doesn't match its checksum.
+#[derive(Debug)]
+pub struct Damaged {
+  binary: PathBuf,
+}
+
+impl fmt::Display for Damaged {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "the backend binary {} is damaged", self.binary.display())
+  }
+}
+
+impl std::error::Error for Damaged {}
+
+/// Compares `binary` with the checksum shipped beside it. Binaries without one (a
+/// `PLUTODUCK_BACKEND_BIN` override, say) aren't checked, nor is anything in debug builds.
+pub fn verify(app: &AppHandle, binary: &Path) -> Result<(), Damaged> {
+  if cfg!(debug_assertions) {
+    return Ok(());
+  }
+  let Some(expected) = expected(binary) else {
+    warn!("no {CHECKSUM_FILE} beside {:?}; not checking it", binary);
+    return Ok(());
+  };
+  let actual = match hash(app, binary) {
+    Ok(actual) => actual,
+    Err(err) => {
+      // Unreadable counts as damaged; spawning it would fail anyway.
+      error!("failed to hash backend binary {:?}: {err}", binary);
+      return Err(Damaged {
+        binary: binary.to_path_buf(),
+      });
+    }
+  };
+  if actual != expected {
+    error!("backend binary {:?} has sha256 {actual}, expected {expected}", binary);
+    return Err(Damaged {
+      binary: binary.to_path_buf(),
+    });
+  }
+  Ok(())
+}
+
+/// The first word of `backend.sha256`, as `shasum` writes it.
+fn expected(binary: &Path) -> Option<String> {
+  let text = fs::read_to_string(binary.with_file_name(CHECKSUM_FILE)).ok()?;
+  text
+    .split_whitespace()
+    .next()
+    .map(|hash| hash.to_ascii_lowercase())
+}
+
+/// The binary's hash, from the cache while its size and modification time are unchanged.
+pub fn hash(app: &AppHandle, binary: &Path) -> std::io::Result<String> {
+  let metadata = fs::metadata(binary)?;
+  let modified_ms = metadata
+    .modified()?
+    .duration_since(UNIX_EPOCH)
+    .map(|since| since.as_millis())
+    .unwrap_or_default();
+  // The config dir rather than app data, so a portable copy leaves nothing on the machine.
+  let cache_path = config::config_dir(app).map(|dir| dir.join(CACHE_FILE));
+  let cached = cache_path
+    .as_ref()
+    .and_then(|path| fs::read(path).ok())
+    .and_then(|bytes| serde_json::from_slice::<Cached>(&bytes).ok());
+  if let Some(cached) = cached {
+    if cached.path == binary && cached.size == metadata.len() && cached.modified_ms == modified_ms
+    {
+      return Ok(cached.sha256);
+    }
+  }
+
+  info!("hashing backend binary {:?}", binary);
+  let sha256 = file_sha256(binary)?;
+  if let Some(cache_path) = cache_path {
+    let entry = Cached {
+      path: binary.to_path_buf(),
+      size: metadata.len(),
+      modified_ms,
+      sha256: sha256.clone(),
+    };
+    let result = serde_json::to_vec_pretty(&entry)
+      .map_err(std::io::Error::from)
+      .and_then(|bytes| {
+        if let Some(parent) = cache_path.parent() {
+          fs::create_dir_all(parent)?;
+        }
+        fs::write(&cache_path, bytes)
+      });
+    if let Err(err) = result {
+      warn!("failed to cache the backend hash in {:?}: {err}", cache_path);
+    }
+  }
+  Ok(sha256)
+}
+
+fn file_sha256(path: &Path) -> std::io::Result<String> {
+  let mut file = fs::File::open(path)?;
+  let mut hasher = Sha256::new();
+  let mut buffer = [0u8; 64 * 1024];
+  loop {
+    let read = file.read(&mut buffer)?;
+    if read == 0 {
+      break;
+    }
+    hasher.update(&buffer[..read]);
+  }
+  Ok(
+    hasher
+      .finalize()
+      .iter()
+      .map(|byte| format!("{byte:02x}"))
+      .collect(),
+  )
+}
+
+/// Tells the user the install is damaged and points them at a fresh download.
+pub fn report(app: &AppHandle, damaged: &Damaged) {
+  let message = format!(
+    "Part of the Pluto Duck installation is damaged or missing ({}), so it can't start. \
+     This can happen when a download is interrupted or antivirus software quarantines a \
+     file.\n\nReinstall Pluto Duck to fix it; your data is kept.",
+    damaged.binary.display()
+  );
+  if windows::splash::is_open(app) {
+    windows::splash::fail(app, "The installation is damaged.", &message);
+    return;
+  }
+  app
+    .dialog()
+    .message(message)
+    .title("Pluto Duck needs to be reinstalled")
+    .kind(MessageDialogKind::Error)
+    .buttons(MessageDialogButtons::OkCancelCustom(
+      DOWNLOAD_BUTTON.into(),
+      "Close".into(),
+    ))
+    .show_with_result(|result| {
+      if matches!(result, MessageDialogResult::Custom(label) if label == DOWNLOAD_BUTTON) {
+        if let Err(err) = platform::open_url(RELEASES_URL) {
+          error!("failed to open {RELEASES_URL}: {err}");
+        }
+      }
+    });
+}
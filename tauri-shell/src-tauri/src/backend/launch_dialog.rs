@@ -0,0 +1,93 @@
+//! The dialog for a backend that couldn't be launched and has no splash to report on.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use anyhow::Result;
+use log::{error, info, warn};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult};
+
+use crate::{logs, windows};
+
+const RETRY_BUTTON: &str = "Retry";
+const OPEN_LOGS_BUTTON: &str = "Open Logs Folder";
+const QUIT_BUTTON: &str = "Quit";
+
+/// Set while the dialog is up, so failures arriving meanwhile don't stack a second one on it.
+static OPEN: AtomicBool = AtomicBool::new(false);
+
+/// Shows the failure with its causes, offering to retry the launch, open the logs or quit.
+pub fn show(app: &AppHandle, err: &anyhow::Error) {
+  if OPEN.swap(true, Ordering::SeqCst) {
+    warn!("backend launch failed again while its dialog is open: {err:#}");
+    return;
+  }
+  present(app, describe(err));
+}
+
+fn present(app: &AppHandle, message: String) {
+  let mut dialog = app
+    .dialog()
+    .message(message.clone())
+    .title("The backend could not be launched")
+    .kind(MessageDialogKind::Error)
+    .buttons(MessageDialogButtons::YesNoCancelCustom(
+      RETRY_BUTTON.into(),
+      OPEN_LOGS_BUTTON.into(),
+      QUIT_BUTTON.into(),
+    ));
+  // A hidden window would hide the dialog with it.
+  if let Some(window) = app
+    .get_webview_window(windows::MAIN_WINDOW)
+    .filter(|window| window.is_visible().unwrap_or(false))
+  {
+    dialog = dialog.parent(&window);
+  }
+  let app = app.clone();
+  dialog.show_with_result(move |result| match result {
+    MessageDialogResult::Custom(label) if label == RETRY_BUTTON => {
+      OPEN.store(false, Ordering::SeqCst);
+      info!("user requested backend launch retry");
+      // Respawning waits on the backend lock and starts a process; not on the event loop.
+      thread::spawn(move || {
+        if let Err(err) = relaunch(&app) {
+          super::launch_failed(&app, &err);
+        }
+      });
+    }
+    MessageDialogResult::Custom(label) if label == OPEN_LOGS_BUTTON => {
+      if let Err(err) = logs::reveal_logs_folder(&app) {
+        error!("failed to open logs folder: {err}");
+      }
+      // Still nothing running; keep the choice in front of the user.
+      present(&app, message);
+    }
+    _ => {
+      info!("quitting after backend launch failure");
+      app.exit(0);
+    }
+  });
+}
+
+/// Launches again, or just spawns again when the first attempt got as far as managing the
+/// backend state.
+fn relaunch(app: &AppHandle) -> Result<()> {
+  match app.try_state::<super::BackendState>() {
+    Some(state) => super::respawn(app, state.inner()),
+    None => super::launch(app, crate::external_backend_url()),
+  }
+}
+
+/// The error and each of its causes, one per line.
+fn describe(err: &anyhow::Error) -> String {
+  let mut message = err.to_string();
+  let mut causes = err.chain().skip(1).peekable();
+  if causes.peek().is_some() {
+    message.push_str("\n\nCaused by:");
+    for cause in causes {
+      message.push_str(&format!("\n  • {cause}"));
+    }
+  }
+  message
+}
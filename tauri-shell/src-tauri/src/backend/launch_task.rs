@@ -0,0 +1,72 @@
+//! Launches running in the background, so exit can wait for one rather than race it: a child
+//! spawned after exit cleanup stopped the backend would outlive the app.
+
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+#[derive(Default)]
+struct Launches {
+  running: usize,
+  cancelled: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+  launches: Mutex<Launches>,
+  finished: Condvar,
+}
+
+impl Shared {
+  fn lock(&self) -> MutexGuard<'_, Launches> {
+    self.launches.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+}
+
+#[derive(Clone, Default)]
+pub struct LaunchTask(Arc<Shared>);
+
+/// One launch in flight; it counts as finished when dropped, including by a panic.
+pub struct Launch(Arc<Shared>);
+
+impl LaunchTask {
+  /// Records a launch about to start, or `None` once exit has cancelled launches.
+  pub fn begin(&self) -> Option<Launch> {
+    let mut launches = self.0.lock();
+    if launches.cancelled {
+      return None;
+    }
+    launches.running += 1;
+    Some(Launch(self.0.clone()))
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    self.0.lock().cancelled
+  }
+
+  /// Stops new launches and waits up to `timeout` for those in flight to finish. Returns
+  /// whether none are left.
+  pub fn cancel_and_wait(&self, timeout: Duration) -> bool {
+    let mut launches = self.0.lock();
+    launches.cancelled = true;
+    let (launches, _) = self
+      .0
+      .finished
+      .wait_timeout_while(launches, timeout, |launches| launches.running > 0)
+      .unwrap_or_else(|poisoned| poisoned.into_inner());
+    launches.running == 0
+  }
+}
+
+impl Launch {
+  pub fn is_cancelled(&self) -> bool {
+    self.0.lock().cancelled
+  }
+}
+
+impl Drop for Launch {
+  fn drop(&mut self) {
+    let mut launches = self.0.lock();
+    launches.running = launches.running.saturating_sub(1);
+    self.0.finished.notify_all();
+  }
+}
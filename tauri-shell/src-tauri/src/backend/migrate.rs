@@ -0,0 +1,434 @@
+//! Versioned layout of the data root. A `data_version` file records the layout the data was last
+//! written in. Launch upgrades older layouts before the backend sees them, and refuses newer
+//! ones, which this shell would misread.
+//!
+//! An upgrade first copies the root to a `<root>.v<N>-backup` sibling and puts it back if a step
+//! fails. The backup is kept until the backend has come up healthy once on the upgraded data,
+//! so a crash right after an upgrade still leaves the old data untouched. A root with nothing
+//! to back up is emptied again instead, so a half-finished import never passes for data.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Listener, Manager};
+
+use crate::audit;
+
+/// The layout this shell reads and writes.
+pub const DATA_VERSION: u32 = 1;
+const VERSION_FILE: &str = "data_version";
+/// Never backed up, copied, or reset: session logs and the audit log aren't data (the audit
+/// log has to outlive resets and restores to be worth anything), and the rest is held by the
+/// running shell.
+pub(super) const SKIPPED: &[&str] = &[
+  "logs",
+  audit::AUDIT_FILE,
+  VERSION_FILE,
+  "shell.lock",
+  "shell-instance.json",
+  "backend.pid",
+  ".lock",
+];
+
+/// One upgrade step, producing layout `to` from `to - 1`.
+struct Step {
+  to: u32,
+  description: &'static str,
+  run: fn(&Migration) -> Result<()>,
+}
+
+const STEPS: &[Step] = &[Step {
+  to: 1,
+  description: "Copying your data from the previous location",
+  run: import_legacy_root,
+}];
+
+/// What launch has to do before the backend may use a data root.
+pub enum Plan {
+  Current,
+  Upgrade { from: u32 },
+  /// Written by a newer shell.
+  Newer(u32),
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MigrationProgress {
+  message: String,
+  percent: u8,
+}
+
+pub fn check(root: &Path) -> Result<Plan> {
+  let plan = match read_version(root)? {
+    Some(version) if version > DATA_VERSION => Plan::Newer(version),
+    Some(version) if version == DATA_VERSION => Plan::Current,
+    Some(version) => Plan::Upgrade { from: version },
+    // Shells before the marker already wrote the current layout into their root.
+    None if root.join("data").is_dir() => {
+      write_version(root, DATA_VERSION)?;
+      Plan::Current
+    }
+    None => Plan::Upgrade { from: 0 },
+  };
+  Ok(plan)
+}
+
+/// Upgrades `root` from layout `from` to the current one, restoring it on failure.
+pub fn run(app: &AppHandle, root: &Path, from: u32) -> Result<()> {
+  let backup = backup_path(root, from);
+  if backup.is_dir() {
+    // An earlier attempt stopped partway; start over from the data it saved.
+    warn!("resuming interrupted data upgrade from {:?}", backup);
+    restore(root, &backup)?;
+  } else if has_data(root)? {
+    info!("backing up data root {:?} to {:?} before upgrading", root, backup);
+    copy_contents(app, "Backing up your data", root, &backup)?;
+  }
+
+  let migration = Migration {
+    app: app.clone(),
+    root: root.to_path_buf(),
+  };
+  for step in STEPS.iter().filter(|step| step.to > from) {
+    info!("upgrading data root to version {}: {}", step.to, step.description);
+    migration.progress(step.description, 0);
+    let result = (step.run)(&migration).and_then(|()| write_version(root, step.to));
+    if let Err(err) = result {
+      let err = err.context(format!("data upgrade to version {} failed", step.to));
+      return Err(undo(root, &backup, err));
+    }
+  }
+  Ok(())
+}
+
+/// Puts `root` back as it was before a failed upgrade: the backup's data, or nothing if there
+/// was none to back up. Returns `err` with what couldn't be undone, if anything.
+fn undo(root: &Path, backup: &Path, err: anyhow::Error) -> anyhow::Error {
+  if !backup.is_dir() {
+    // Without this, the next launch would find a partial copy with no version and take it
+    // for data written by a shell from before versioning.
+    return match clear(root) {
+      Ok(()) => err,
+      Err(clear_err) => err.context(format!(
+        "data upgrade failed and what it had copied could not be removed ({clear_err:#}); \
+         empty {} before starting again",
+        root.display()
+      )),
+    };
+  }
+  if let Err(restore_err) = restore(root, backup) {
+    return err.context(format!(
+      "data upgrade failed and the old data could not be put back ({restore_err:#}); it is \
+       still in {}",
+      backup.display()
+    ));
+  }
+  remove_backup(backup);
+  err
+}
+
+/// Deletes upgrade backups of `root` once the backend has come up healthy on it.
+pub fn remove_backups_when_ready(app: &AppHandle, root: &Path) {
+  let backups = backups(root);
+  if backups.is_empty() {
+    return;
+  }
+  app.once_any("backend-ready", move |_| {
+    for backup in &backups {
+      remove_backup(backup);
+    }
+  });
+}
+
+struct Migration {
+  app: AppHandle,
+  root: PathBuf,
+}
+
+impl Migration {
+  fn progress(&self, message: &str, percent: u8) {
+    let _ = self.app.emit(
+      "data-migration-progress",
+      MigrationProgress {
+        message: message.to_string(),
+        percent,
+      },
+    );
+  }
+}
+
+/// Version 1: the shell's own root. Before it, the backend used its built-in default
+/// (`~/Library/Application Support/PlutoDuck` on macOS, `~/.pluto-duck` elsewhere), so data
+/// found there is copied over. The old directory is left alone for the backend's own use.
+/// Development, portable, and profile roots are meant to start out empty, so they import
+/// nothing.
+fn import_legacy_root(migration: &Migration) -> Result<()> {
+  let separate = crate::portable::root().is_some()
+    || super::profiles::active_root(&migration.app).is_some();
+  if cfg!(debug_assertions) || separate {
+    return Ok(());
+  }
+  let Ok(home) = migration.app.path().home_dir() else {
+    return Ok(());
+  };
+  let legacy = if cfg!(target_os = "macos") {
+    home.join("Library/Application Support/PlutoDuck")
+  } else {
+    home.join(".pluto-duck")
+  };
+  if legacy == migration.root || !legacy.join("data").is_dir() || has_data(&migration.root)? {
+    return Ok(());
+  }
+  info!("importing data from {:?}", legacy);
+  copy_contents(
+    &migration.app,
+    "Copying your data from the previous location",
+    &legacy,
+    &migration.root,
+  )
+}
+
+fn read_version(root: &Path) -> Result<Option<u32>> {
+  let path = root.join(VERSION_FILE);
+  match fs::read_to_string(&path) {
+    Ok(raw) => raw
+      .trim()
+      .parse()
+      .map(Some)
+      .with_context(|| format!("{} is not a data version: {raw:?}", path.display())),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+    Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+  }
+}
+
+pub(super) fn write_version(root: &Path, version: u32) -> Result<()> {
+  let path = root.join(VERSION_FILE);
+  fs::write(&path, format!("{version}\n"))
+    .with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn backup_path(root: &Path, from: u32) -> PathBuf {
+  let name = root.file_name().unwrap_or_default().to_string_lossy();
+  root.with_file_name(format!("{name}.v{from}-backup"))
+}
+
+fn backups(root: &Path) -> Vec<PathBuf> {
+  let (Some(parent), Some(name)) = (root.parent(), root.file_name()) else {
+    return Vec::new();
+  };
+  let prefix = format!("{}.v", name.to_string_lossy());
+  let Ok(entries) = fs::read_dir(parent) else {
+    return Vec::new();
+  };
+  entries
+    .flatten()
+    .map(|entry| entry.path())
+    .filter(|path| {
+      let name = path.file_name().unwrap_or_default().to_string_lossy();
+      name.starts_with(&prefix) && name.ends_with("-backup") && path.is_dir()
+    })
+    .collect()
+}
+
+fn remove_backup(backup: &Path) {
+  match fs::remove_dir_all(backup) {
+    Ok(()) => info!("removed data backup {:?}", backup),
+    Err(err) => warn!("failed to remove data backup {:?}: {err}", backup),
+  }
+}
+
+/// Whether `root` holds anything besides what the shell itself keeps there.
+fn has_data(root: &Path) -> Result<bool> {
+  Ok(!entries(root)?.is_empty())
+}
+
+/// The top-level entries of `dir` that belong to the data, skipping `SKIPPED`.
+pub(super) fn entries(dir: &Path) -> Result<Vec<PathBuf>> {
+  let read = match fs::read_dir(dir) {
+    Ok(read) => read,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+    Err(err) => return Err(err).with_context(|| format!("failed to read {}", dir.display())),
+  };
+  let mut entries = Vec::new();
+  for entry in read {
+    let entry = entry.with_context(|| format!("failed to read {}", dir.display()))?;
+    if !SKIPPED.contains(&entry.file_name().to_string_lossy().as_ref()) {
+      entries.push(entry.path());
+    }
+  }
+  Ok(entries)
+}
+
+/// Replaces the data in `root` with the backup's copy.
+fn restore(root: &Path, backup: &Path) -> Result<()> {
+  clear(root)?;
+  copy_tree(backup, root, &mut |_| {})
+}
+
+/// Removes the data in `root`, leaving the shell's own files.
+fn clear(root: &Path) -> Result<()> {
+  for entry in entries(root)? {
+    // Not `is_dir`, which would follow a link to a directory and empty its target.
+    let is_dir = fs::symlink_metadata(&entry).is_ok_and(|metadata| metadata.is_dir());
+    let result = if is_dir {
+      fs::remove_dir_all(&entry)
+    } else {
+      fs::remove_file(&entry)
+    };
+    result.with_context(|| format!("failed to remove {}", entry.display()))?;
+  }
+  Ok(())
+}
+
+/// Copies the data in `from` into `to`, reporting progress by bytes copied.
+fn copy_contents(app: &AppHandle, message: &str, from: &Path, to: &Path) -> Result<()> {
+  let migration = Migration {
+    app: app.clone(),
+    root: to.to_path_buf(),
+  };
+  let mut total = 0;
+  for entry in entries(from)? {
+    total += crate::walk::dir_size(&entry);
+  }
+  let mut copied = 0u64;
+  let mut reported = 0;
+  migration.progress(message, 0);
+  fs::create_dir_all(to).with_context(|| format!("failed to create {}", to.display()))?;
+  for entry in entries(from)? {
+    let target = to.join(entry.file_name().unwrap_or_default());
+    copy_tree(&entry, &target, &mut |bytes| {
+      copied += bytes;
+      let percent = (copied.saturating_mul(100) / total.max(1)).min(100) as u8;
+      if percent != reported {
+        reported = percent;
+        migration.progress(message, percent);
+      }
+    })?;
+  }
+  Ok(())
+}
+
+/// Copies links as links rather than what they point to, which may be outside the data or
+/// loop back into it.
+fn copy_tree(from: &Path, to: &Path, on_copied: &mut dyn FnMut(u64)) -> Result<()> {
+  let metadata =
+    fs::symlink_metadata(from).with_context(|| format!("failed to read {}", from.display()))?;
+  if metadata.file_type().is_symlink() {
+    return copy_link(from, to);
+  }
+  if metadata.is_dir() {
+    fs::create_dir_all(to).with_context(|| format!("failed to create {}", to.display()))?;
+    let read = fs::read_dir(from).with_context(|| format!("failed to read {}", from.display()))?;
+    for entry in read {
+      let entry = entry.with_context(|| format!("failed to read {}", from.display()))?;
+      copy_tree(&entry.path(), &to.join(entry.file_name()), on_copied)?;
+    }
+    return Ok(());
+  }
+  let bytes = fs::copy(from, to)
+    .with_context(|| format!("failed to copy {} to {}", from.display(), to.display()))?;
+  on_copied(bytes);
+  Ok(())
+}
+
+#[cfg(unix)]
+fn copy_link(from: &Path, to: &Path) -> Result<()> {
+  let target =
+    fs::read_link(from).with_context(|| format!("failed to read link {}", from.display()))?;
+  std::os::unix::fs::symlink(&target, to)
+    .with_context(|| format!("failed to copy link {} to {}", from.display(), to.display()))
+}
+
+/// Making links takes a privilege most Windows users don't have, so they're left out.
+#[cfg(windows)]
+fn copy_link(from: &Path, _to: &Path) -> Result<()> {
+  warn!("not copying link {:?}", from);
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_reads_the_version() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    assert!(matches!(check(root).unwrap(), Plan::Upgrade { from: 0 }));
+
+    write_version(root, DATA_VERSION).unwrap();
+    assert!(matches!(check(root).unwrap(), Plan::Current));
+    write_version(root, DATA_VERSION + 1).unwrap();
+    assert!(matches!(check(root).unwrap(), Plan::Newer(version) if version == DATA_VERSION + 1));
+    write_version(root, 0).unwrap();
+    assert!(matches!(check(root).unwrap(), Plan::Upgrade { from: 0 }));
+
+    fs::write(root.join(VERSION_FILE), "garbage").unwrap();
+    assert!(check(root).is_err());
+  }
+
+  #[test]
+  fn check_takes_unversioned_data_for_the_current_layout() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("data")).unwrap();
+    assert!(matches!(check(dir.path()).unwrap(), Plan::Current));
+    assert_eq!(read_version(dir.path()).unwrap(), Some(DATA_VERSION));
+  }
+
+  #[test]
+  fn undo_without_a_backup_removes_a_partial_import() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("root");
+    fs::create_dir_all(root.join("data")).unwrap();
+    fs::write(root.join("data/half.duckdb"), "partial").unwrap();
+    fs::create_dir(root.join("logs")).unwrap();
+
+    let err = undo(&root, &backup_path(&root, 0), anyhow::anyhow!("copy failed"));
+    assert_eq!(err.to_string(), "copy failed");
+    assert!(!root.join("data").exists());
+    assert!(root.join("logs").is_dir(), "the shell's own files stay");
+    assert!(matches!(check(&root).unwrap(), Plan::Upgrade { from: 0 }));
+  }
+
+  #[test]
+  fn undo_puts_the_backup_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("root");
+    let backup = backup_path(&root, 0);
+    fs::create_dir_all(backup.join("data")).unwrap();
+    fs::write(backup.join("data/old.duckdb"), "old").unwrap();
+    fs::create_dir_all(root.join("data")).unwrap();
+    fs::write(root.join("data/new.duckdb"), "new").unwrap();
+
+    undo(&root, &backup, anyhow::anyhow!("step failed"));
+    assert_eq!(fs::read_to_string(root.join("data/old.duckdb")).unwrap(), "old");
+    assert!(!root.join("data/new.duckdb").exists());
+    assert!(!backup.exists());
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn copy_tree_copies_links_as_links() {
+    let dir = tempfile::tempdir().unwrap();
+    let outside = dir.path().join("outside");
+    fs::create_dir(&outside).unwrap();
+    fs::write(outside.join("big.parquet"), "not data").unwrap();
+    let from = dir.path().join("from");
+    fs::create_dir(&from).unwrap();
+    std::os::unix::fs::symlink(&outside, from.join("linked")).unwrap();
+    fs::write(from.join("file"), "data").unwrap();
+
+    let to = dir.path().join("to");
+    let mut copied = 0;
+    copy_tree(&from, &to, &mut |bytes| copied += bytes).unwrap();
+    assert_eq!(copied, 4);
+    assert_eq!(fs::read_link(to.join("linked")).unwrap(), outside);
+
+    // Clearing removes the link, not what it points to.
+    clear(&to).unwrap();
+    assert!(outside.join("big.parquet").exists());
+  }
+}
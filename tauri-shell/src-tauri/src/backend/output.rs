@@ -0,0 +1,217 @@
+//! Reads the backend's piped output, mirrors it to the log files, keeps the most recent lines in
+//! memory for diagnostics, and pushes each line to the webview as a `backend-log` event.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use log::{error, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::severity::Classifier;
+use crate::logs::combined;
+use crate::logs::LogStream;
+use crate::windows::{visibility, LOG_VIEWER_WINDOW};
+
+/// Lines of combined stdout/stderr kept in memory.
+pub const RING_CAPACITY: usize = 1000;
+/// How long a log viewer visibility check is trusted before asking the window again.
+const VISIBILITY_CACHE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLine {
+  stream: LogStream,
+  line: String,
+  /// Milliseconds since the Unix epoch.
+  ts: u64,
+}
+
+/// A bounded buffer of the most recent output lines; the oldest line is dropped when full.
+/// Shared by every child the shell spawns, so pre-crash output survives restarts.
+pub struct LogRing {
+  lines: Mutex<VecDeque<LogLine>>,
+  capacity: usize,
+}
+
+impl LogRing {
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      lines: Mutex::new(VecDeque::with_capacity(capacity)),
+      capacity,
+    }
+  }
+
+  fn push(&self, line: LogLine) {
+    let Ok(mut lines) = self.lines.lock() else {
+      return;
+    };
+    if lines.len() == self.capacity {
+      lines.pop_front();
+    }
+    lines.push_back(line);
+  }
+
+  /// Every buffered line, oldest first.
+  pub fn snapshot(&self) -> Vec<LogLine> {
+    self
+      .lines
+      .lock()
+      .map(|lines| lines.iter().cloned().collect())
+      .unwrap_or_default()
+  }
+
+  /// Text of the last `count` lines from `stream`, oldest first.
+  pub fn recent_from(&self, stream: LogStream, count: usize) -> Vec<String> {
+    let Ok(lines) = self.lines.lock() else {
+      return Vec::new();
+    };
+    let mut recent: Vec<String> = lines
+      .iter()
+      .rev()
+      .filter(|line| line.stream == stream)
+      .take(count)
+      .map(|line| line.line.clone())
+      .collect();
+    recent.reverse();
+    recent
+  }
+
+  /// Text of the last `count` lines, oldest first.
+  pub fn recent(&self, count: usize) -> Vec<String> {
+    let Ok(lines) = self.lines.lock() else {
+      return Vec::new();
+    };
+    let skip = lines.len().saturating_sub(count);
+    lines.iter().skip(skip).map(|line| line.line.clone()).collect()
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorLogged<'a> {
+  severity: &'a str,
+  #[serde(flatten)]
+  line: &'a LogLine,
+}
+
+#[derive(Clone)]
+pub struct Forwarder {
+  /// `None` in tests, which have no app to send events to.
+  app: Option<AppHandle>,
+  ring: Arc<LogRing>,
+  classifier: Arc<Classifier>,
+}
+
+impl Forwarder {
+  pub fn new(app: AppHandle, ring: Arc<LogRing>, classifier: Arc<Classifier>) -> Self {
+    Self {
+      app: Some(app),
+      ring,
+      classifier,
+    }
+  }
+
+  #[cfg(all(test, unix))]
+  pub fn detached(ring: Arc<LogRing>, classifier: Arc<Classifier>) -> Self {
+    Self {
+      app: None,
+      ring,
+      classifier,
+    }
+  }
+
+  /// Copies `source` line by line into `log` and the ring, sending `backend-log` for each line
+  /// to the log viewer while it is visible. Runs until the pipe closes.
+  pub fn forward(&self, stream: LogStream, source: impl Read + Send + 'static, mut log: File) {
+    let forwarder = self.clone();
+    let result = thread::Builder::new()
+      .name(format!("backend-{}", stream.name()))
+      .spawn(move || {
+        let mut reader = BufReader::new(source);
+        let mut visibility = Visibility::new();
+        let mut buffer = Vec::new();
+        loop {
+          buffer.clear();
+          match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(err) => {
+              warn!("failed to read backend {}: {err}", stream.name());
+              break;
+            }
+          }
+          // Write the raw bytes so the file matches what the backend printed.
+          if let Err(err) = log.write_all(&buffer) {
+            warn!("failed to write backend {} log: {err}", stream.name());
+          }
+
+          let text = String::from_utf8_lossy(&buffer);
+          combined::write(
+            match stream {
+              LogStream::Stdout => combined::Source::BackendOut,
+              LogStream::Stderr => combined::Source::BackendErr,
+            },
+            &text,
+          );
+          let line = LogLine {
+            stream,
+            line: text.trim_end_matches(['\r', '\n']).to_string(),
+            ts: SystemTime::now()
+              .duration_since(UNIX_EPOCH)
+              .map_or(0, |elapsed| elapsed.as_millis() as u64),
+          };
+          if let Some(app) = &forwarder.app {
+            // Only the log viewer shows live output; skip the IPC while it's hidden or closed.
+            if visibility.log_viewer_visible(app) {
+              let _ = app.emit_to(LOG_VIEWER_WINDOW, "backend-log", &line);
+            }
+            // Errors are rare and worth a badge even while hidden, so they're always sent.
+            if let Some(severity) = forwarder.classifier.classify(&line.line) {
+              let _ = app.emit("backend-error-logged", ErrorLogged { severity, line: &line });
+            }
+          }
+          forwarder.ring.push(line);
+        }
+      });
+    if let Err(err) = result {
+      error!("failed to start backend {} reader: {err}", stream.name());
+    }
+  }
+}
+
+/// Caches whether the log viewer is visible so we don't query it for every line.
+struct Visibility {
+  visible: bool,
+  checked_at: Option<Instant>,
+}
+
+impl Visibility {
+  fn new() -> Self {
+    Self {
+      visible: false,
+      checked_at: None,
+    }
+  }
+
+  fn log_viewer_visible(&mut self, app: &AppHandle) -> bool {
+    if !visibility::is_visible(app) {
+      return false;
+    }
+    let stale = self
+      .checked_at
+      .map_or(true, |at| at.elapsed() >= VISIBILITY_CACHE);
+    if stale {
+      self.visible = app
+        .get_webview_window(LOG_VIEWER_WINDOW)
+        .and_then(|window| window.is_visible().ok())
+        .unwrap_or(false);
+      self.checked_at = Some(Instant::now());
+    }
+    self.visible
+  }
+}
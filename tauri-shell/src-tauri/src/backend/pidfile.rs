@@ -0,0 +1,98 @@
+//! `backend.pid` records which backend process the shell launched, so the next launch can
+//! clean up a backend orphaned by a shell crash before it fights it for the port and the
+//! DuckDB file lock.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::process;
+
+const PID_FILE: &str = "backend.pid";
+const ORPHAN_TERMINATE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PidRecord {
+  pid: u32,
+  port: u16,
+  binary: PathBuf,
+}
+
+fn path(data_root: &Path) -> PathBuf {
+  data_root.join(PID_FILE)
+}
+
+pub fn write(data_root: &Path, pid: u32, port: u16, binary: &Path) {
+  let record = PidRecord {
+    pid,
+    port,
+    binary: binary.to_path_buf(),
+  };
+  let result = serde_json::to_vec_pretty(&record)
+    .map_err(std::io::Error::other)
+    .and_then(|bytes| std::fs::write(path(data_root), bytes));
+  if let Err(err) = result {
+    warn!("failed to write backend pid file: {err}");
+  }
+}
+
+pub fn remove(data_root: &Path) {
+  match std::fs::remove_file(path(data_root)) {
+    Ok(()) => {}
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+    Err(err) => warn!("failed to remove backend pid file: {err}"),
+  }
+}
+
+/// Terminates the backend recorded by a previous session if it is still running our binary.
+///
+/// A PID that has since been reused by an unrelated process is left alone.
+pub fn reap_orphan(data_root: &Path, binary: &Path) {
+  let file = path(data_root);
+  let record: PidRecord = match std::fs::read(&file) {
+    Ok(bytes) => match serde_json::from_slice(&bytes) {
+      Ok(record) => record,
+      Err(err) => {
+        warn!("ignoring unreadable backend pid file {:?}: {err}", file);
+        remove(data_root);
+        return;
+      }
+    },
+    Err(_) => return,
+  };
+
+  let Some(running) = process::executable_path(record.pid) else {
+    info!("stale backend pid file for pid {} (process is gone)", record.pid);
+    remove(data_root);
+    return;
+  };
+  // Only kill it if it is running our backend binary; the PID may have been reused.
+  if !same_file(&running, binary) {
+    info!(
+      "pid {} from stale backend pid file now runs {:?}; leaving it alone",
+      record.pid, running
+    );
+    remove(data_root);
+    return;
+  }
+
+  warn!(
+    "terminating orphaned backend pid {} left on port {} by a previous session",
+    record.pid, record.port
+  );
+  if process::terminate(record.pid, ORPHAN_TERMINATE_TIMEOUT) {
+    info!("orphaned backend pid {} terminated", record.pid);
+  } else {
+    warn!("orphaned backend pid {} is still running", record.pid);
+  }
+  remove(data_root);
+}
+
+pub(super) fn same_file(a: &Path, b: &Path) -> bool {
+  match (a.canonicalize(), b.canonicalize()) {
+    (Ok(a), Ok(b)) => a == b,
+    _ => a == b,
+  }
+}
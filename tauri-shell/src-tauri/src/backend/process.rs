@@ -0,0 +1,258 @@
+//! Helpers for processes we only know by PID (e.g. a backend left over from a crashed shell),
+//! as opposed to children we hold a `Child` handle for.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Path of the executable running as `pid`, or `None` if it can't be determined.
+#[cfg(target_os = "linux")]
+pub fn executable_path(pid: u32) -> Option<PathBuf> {
+  std::fs::read_link(format!("/proc/{pid}/exe")).ok()
+}
+
+#[cfg(target_os = "macos")]
+pub fn executable_path(pid: u32) -> Option<PathBuf> {
+  use std::ffi::OsStr;
+  use std::os::unix::ffi::OsStrExt;
+
+  let mut buffer = vec![0u8; libc::PROC_PIDPATHINFO_MAXSIZE as usize];
+  // SAFETY: the buffer is writable and its length is passed alongside it.
+  let len = unsafe {
+    libc::proc_pidpath(
+      pid as libc::c_int,
+      buffer.as_mut_ptr().cast(),
+      buffer.len() as u32,
+    )
+  };
+  if len <= 0 {
+    return None;
+  }
+  Some(PathBuf::from(OsStr::from_bytes(&buffer[..len as usize])))
+}
+
+#[cfg(windows)]
+pub fn executable_path(pid: u32) -> Option<PathBuf> {
+  use std::ffi::OsString;
+  use std::os::windows::ffi::OsStringExt;
+  use windows_sys::Win32::Foundation::CloseHandle;
+  use windows_sys::Win32::System::Threading::{
+    OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+  };
+
+  // SAFETY: the handle is checked for null and closed before returning; the buffer length
+  // is passed with the buffer.
+  unsafe {
+    let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+    if handle.is_null() {
+      return None;
+    }
+    let mut buffer = vec![0u16; 32 * 1024];
+    let mut len = buffer.len() as u32;
+    let ok = QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, buffer.as_mut_ptr(), &mut len);
+    CloseHandle(handle);
+    if ok == 0 {
+      return None;
+    }
+    Some(PathBuf::from(OsString::from_wide(&buffer[..len as usize])))
+  }
+}
+
+/// Sends `signal` to the process group led by `pid`, or to `pid` alone if it doesn't lead one.
+#[cfg(unix)]
+pub fn signal_tree(pid: u32, signal: libc::c_int) -> bool {
+  let pid = pid as libc::pid_t;
+  // SAFETY: getpgid/kill take plain integers; a stale PID just makes them fail.
+  unsafe {
+    let target = if libc::getpgid(pid) == pid { -pid } else { pid };
+    libc::kill(target, signal) == 0
+  }
+}
+
+/// SIGKILLs whatever is left in process group `pgid`, e.g. workers whose leader already exited.
+#[cfg(unix)]
+pub fn kill_group(pgid: u32) {
+  // SAFETY: kill(2) with a negative PID targets the group; a vanished group just fails.
+  unsafe {
+    libc::kill(-(pgid as libc::pid_t), libc::SIGKILL);
+  }
+}
+
+/// Whether `pid` is running, including as a process this user may not inspect.
+#[cfg(unix)]
+pub fn is_alive(pid: u32) -> bool {
+  // SAFETY: signal 0 performs the existence/permission check without sending anything.
+  let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+  result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Whether `pid` is running, including as a process this user may not inspect.
+#[cfg(windows)]
+pub fn is_alive(pid: u32) -> bool {
+  use windows_sys::Win32::Foundation::{
+    CloseHandle, GetLastError, ERROR_ACCESS_DENIED, STILL_ACTIVE,
+  };
+  use windows_sys::Win32::System::Threading::{
+    GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+  };
+
+  // SAFETY: the handle is checked for null and closed before returning; the exit code is
+  // written to a local.
+  unsafe {
+    let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+    if handle.is_null() {
+      // Someone else's process, or a protected one; either way it's there.
+      return GetLastError() == ERROR_ACCESS_DENIED;
+    }
+    let mut code = 0u32;
+    let ok = GetExitCodeProcess(handle, &mut code);
+    CloseHandle(handle);
+    ok == 0 || code == STILL_ACTIVE as u32
+  }
+}
+
+/// Sends SIGTERM to the process (and its group), waits up to `timeout`, then SIGKILLs.
+/// Returns `true` once the process is gone.
+#[cfg(unix)]
+pub fn terminate(pid: u32, timeout: Duration) -> bool {
+  use std::time::Instant;
+
+  signal_tree(pid, libc::SIGTERM);
+  let deadline = Instant::now() + timeout;
+  while Instant::now() < deadline {
+    if !is_alive(pid) {
+      // Workers may outlive the leader; don't leave them holding the port.
+      signal_tree(pid, libc::SIGKILL);
+      return true;
+    }
+    std::thread::sleep(Duration::from_millis(100));
+  }
+  signal_tree(pid, libc::SIGKILL);
+  std::thread::sleep(Duration::from_millis(100));
+  !is_alive(pid)
+}
+
+/// Terminates the process and waits up to `timeout` for it to exit.
+#[cfg(windows)]
+pub fn terminate(pid: u32, timeout: Duration) -> bool {
+  use windows_sys::Win32::Foundation::{CloseHandle, WAIT_OBJECT_0};
+  use windows_sys::Win32::System::Threading::{
+    OpenProcess, TerminateProcess, WaitForSingleObject, PROCESS_SYNCHRONIZE, PROCESS_TERMINATE,
+  };
+
+  // SAFETY: the handle is checked for null and closed before returning.
+  unsafe {
+    let handle = OpenProcess(PROCESS_TERMINATE | PROCESS_SYNCHRONIZE, 0, pid);
+    if handle.is_null() {
+      // Already gone (or not ours to touch).
+      return true;
+    }
+    TerminateProcess(handle, 1);
+    let waited = WaitForSingleObject(handle, timeout.as_millis() as u32);
+    CloseHandle(handle);
+    waited == WAIT_OBJECT_0
+  }
+}
+
+/// A Job Object that kills every process assigned to it once its last handle closes, so the
+/// backend's helper processes can't outlive it (or the shell, even if the shell crashes).
+#[cfg(windows)]
+pub struct Job(windows_sys::Win32::Foundation::HANDLE);
+
+// SAFETY: job handles may be used from any thread.
+#[cfg(windows)]
+unsafe impl Send for Job {}
+
+#[cfg(windows)]
+impl Job {
+  pub fn new() -> std::io::Result<Self> {
+    use windows_sys::Win32::System::JobObjects::{
+      CreateJobObjectW, JobObjectExtendedLimitInformation, SetInformationJobObject,
+      JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    // SAFETY: the info struct is plain data and lives for the duration of the call.
+    unsafe {
+      let handle = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+      if handle.is_null() {
+        return Err(std::io::Error::last_os_error());
+      }
+      let job = Self(handle);
+      let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+      info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+      let ok = SetInformationJobObject(
+        job.0,
+        JobObjectExtendedLimitInformation,
+        (&info as *const JOBOBJECT_EXTENDED_LIMIT_INFORMATION).cast(),
+        std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+      );
+      if ok == 0 {
+        return Err(std::io::Error::last_os_error());
+      }
+      Ok(job)
+    }
+  }
+
+  /// Adds the child (and, from then on, everything it spawns) to the job.
+  pub fn assign(&self, child: &std::process::Child) -> std::io::Result<()> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::System::JobObjects::AssignProcessToJobObject;
+
+    // SAFETY: both handles are valid for the duration of the call.
+    let ok = unsafe { AssignProcessToJobObject(self.0, child.as_raw_handle() as _) };
+    if ok == 0 {
+      return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+  }
+
+  /// Kills every process still in the job.
+  pub fn terminate(&self) {
+    use windows_sys::Win32::System::JobObjects::TerminateJobObject;
+
+    // SAFETY: the handle is owned by `self`.
+    unsafe {
+      TerminateJobObject(self.0, 1);
+    }
+  }
+}
+
+#[cfg(windows)]
+impl Drop for Job {
+  fn drop(&mut self) {
+    // SAFETY: the handle is owned by `self` and closed exactly once.
+    unsafe {
+      windows_sys::Win32::Foundation::CloseHandle(self.0);
+    }
+  }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+  use crate::backend::test_support;
+
+  #[test]
+  fn stopping_the_backend_kills_its_grandchildren() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut backend = test_support::backend(
+      dir.path(),
+      "sleep 300 &\necho $! > \"$PLUTODUCK_DATA_DIR__ROOT/worker.pid\"\nwait\n",
+    );
+    let pid = test_support::spawn(&mut backend);
+    let worker = test_support::read_pid(&dir.path().join("worker.pid"));
+
+    assert!(backend.stop().is_some());
+    assert!(test_support::gone(pid), "backend process {pid} is still running");
+    assert!(test_support::gone(worker), "worker process {worker} is still running");
+  }
+
+  #[test]
+  fn is_alive_tells_running_from_reaped() {
+    assert!(super::is_alive(std::process::id()));
+    // PID 1 is never ours to signal, which still counts as running.
+    assert!(super::is_alive(1));
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    let pid = child.id();
+    child.wait().unwrap();
+    assert!(!super::is_alive(pid));
+  }
+}
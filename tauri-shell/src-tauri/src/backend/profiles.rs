@@ -0,0 +1,300 @@
+//! Named profiles, each with its own data root, so e.g. work and personal data stay apart.
+//! `profiles.json` in the config directory lists them and which one is active, and
+//! `--profile <name>` (or `PLUTODUCK_PROFILE`) picks one for a single launch. The `default`
+//! profile is the data root from settings, so a shell without profiles behaves as before.
+//! Switching relaunches the app, like changing the data root; each profile has its own
+//! instance lock, so two profiles can be open side by side.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::data_root;
+use crate::{audit, config, portable};
+
+pub const DEFAULT_PROFILE: &str = "default";
+const PROFILES_FILE: &str = "profiles.json";
+/// Wins over `--profile`. Also set by `switch_profile`, because the relaunch repeats the
+/// original arguments.
+const PROFILE_ENV: &str = "PLUTODUCK_PROFILE";
+const MAX_NAME_LEN: usize = 64;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfilesFile {
+  active: Option<String>,
+  #[serde(default)]
+  profiles: Vec<SavedProfile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SavedProfile {
+  name: String,
+  data_root: PathBuf,
+}
+
+/// The profile this launch uses, picked once in `setup`.
+pub struct ActiveProfile {
+  name: String,
+  /// `None` for the default profile, whose root comes from settings.
+  data_root: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileInfo {
+  name: String,
+  data_root: PathBuf,
+  active: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfileChanged {
+  name: String,
+}
+
+/// Picks this launch's profile: `PLUTODUCK_PROFILE`, then `requested` (from `--profile`), then
+/// the saved active one. An unknown name falls back to the next choice.
+pub fn select(app: &AppHandle, requested: Option<String>) -> ActiveProfile {
+  let file = load(app).unwrap_or_else(|err| {
+    warn!("{err:#}; using the default profile");
+    ProfilesFile::default()
+  });
+  let env = std::env::var(PROFILE_ENV)
+    .ok()
+    .filter(|name| !name.trim().is_empty());
+  let candidates = [env, requested, file.active.clone()];
+  for name in candidates.into_iter().flatten() {
+    if is_default(&name) {
+      break;
+    }
+    match file.profiles.iter().find(|profile| same_name(&profile.name, &name)) {
+      Some(profile) => {
+        info!("using profile {:?} with data root {:?}", profile.name, profile.data_root);
+        return ActiveProfile {
+          name: profile.name.clone(),
+          data_root: Some(profile.data_root.clone()),
+        };
+      }
+      None => warn!("unknown profile {name:?}"),
+    }
+  }
+  ActiveProfile {
+    name: DEFAULT_PROFILE.to_string(),
+    data_root: None,
+  }
+}
+
+/// The active profile's own data root; `None` for the default profile.
+pub fn active_root(app: &AppHandle) -> Option<PathBuf> {
+  app.try_state::<ActiveProfile>()?.data_root.clone()
+}
+
+/// The active profile's name, unless it's the default one.
+pub fn active_name(app: &AppHandle) -> Option<String> {
+  let profile = app.try_state::<ActiveProfile>()?;
+  profile.data_root.is_some().then(|| profile.name.clone())
+}
+
+/// Points the active profile at `root` for the next launch. Returns `false` for the default
+/// profile, whose root lives in settings instead.
+pub fn set_active_root(app: &AppHandle, root: &Path) -> anyhow::Result<bool> {
+  let Some(name) = active_name(app) else {
+    return Ok(false);
+  };
+  update(app, |file| {
+    let profile = file
+      .profiles
+      .iter_mut()
+      .find(|profile| same_name(&profile.name, &name))
+      .with_context(|| format!("profile {name:?} no longer exists"))?;
+    profile.data_root = root.to_path_buf();
+    Ok(())
+  })?;
+  Ok(true)
+}
+
+/// Every profile, the default one first.
+#[tauri::command]
+pub fn list_profiles(app: AppHandle) -> Result<Vec<ProfileInfo>, String> {
+  let file = load(&app).map_err(|err| format!("{err:#}"))?;
+  let active = active_name(&app);
+  let mut profiles = vec![ProfileInfo {
+    name: DEFAULT_PROFILE.to_string(),
+    data_root: default_profile_root(&app),
+    active: active.is_none(),
+  }];
+  profiles.extend(file.profiles.into_iter().map(|profile| ProfileInfo {
+    active: active.as_deref().is_some_and(|name| same_name(name, &profile.name)),
+    name: profile.name,
+    data_root: profile.data_root,
+  }));
+  Ok(profiles)
+}
+
+/// Adds a profile, keeping its data in `path` or, without one, in a folder of its own next to
+/// the default data root. Doesn't switch to it.
+#[tauri::command]
+pub fn create_profile(
+  app: AppHandle,
+  name: String,
+  path: Option<PathBuf>,
+) -> Result<ProfileInfo, String> {
+  let result = add_profile(app.clone(), name.clone(), path.clone());
+  audit::record(&app, "create_profile", serde_json::json!({ "name": name, "path": path }), &result);
+  result
+}
+
+fn add_profile(app: AppHandle, name: String, path: Option<PathBuf>) -> Result<ProfileInfo, String> {
+  refuse_in_portable_mode()?;
+  let name = name.trim().to_string();
+  validate_name(&name)?;
+  let data_root = match path {
+    Some(path) if !path.is_absolute() => {
+      return Err(format!("{} is not an absolute path", path.display()));
+    }
+    Some(path) => path,
+    None => data_root::default_root(&app)
+      .with_file_name("profiles")
+      .join(&name),
+  };
+  data_root::check_writable(&data_root).map_err(|err| format!("{err:#}"))?;
+
+  let profile = SavedProfile {
+    name: name.clone(),
+    data_root: data_root.clone(),
+  };
+  update(&app, |file| {
+    if file.profiles.iter().any(|existing| same_name(&existing.name, &name)) {
+      anyhow::bail!("a profile named {name:?} already exists");
+    }
+    file.profiles.push(profile);
+    Ok(())
+  })
+  .map_err(|err| format!("{err:#}"))?;
+  info!("created profile {name:?} with data root {:?}", data_root);
+  Ok(ProfileInfo {
+    name,
+    data_root,
+    active: false,
+  })
+}
+
+/// Makes `name` the active profile and relaunches the app to use it. The backend is stopped
+/// gracefully on the way out.
+#[tauri::command]
+pub fn switch_profile(app: AppHandle, name: String) -> Result<(), String> {
+  let result = switch_to(app.clone(), name.clone());
+  audit::record(&app, "switch_profile", serde_json::json!({ "name": name }), &result);
+  result
+}
+
+fn switch_to(app: AppHandle, name: String) -> Result<(), String> {
+  refuse_in_portable_mode()?;
+  let name = name.trim().to_string();
+  let current = active_name(&app).unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+  if same_name(&current, &name) {
+    return Ok(());
+  }
+  let mut chosen = DEFAULT_PROFILE.to_string();
+  update(&app, |file| {
+    if !is_default(&name) {
+      let profile = file
+        .profiles
+        .iter()
+        .find(|profile| same_name(&profile.name, &name))
+        .with_context(|| format!("there is no profile named {name:?}"))?;
+      chosen = profile.name.clone();
+    }
+    file.active = Some(chosen.clone());
+    Ok(())
+  })
+  .map_err(|err| format!("{err:#}"))?;
+
+  info!("switching to profile {chosen:?}; relaunching");
+  std::env::set_var(PROFILE_ENV, &chosen);
+  let _ = app.emit("profile-changed", ProfileChanged { name: chosen });
+  app.request_restart();
+  Ok(())
+}
+
+/// Where the default profile keeps its data this launch or, in another profile, would.
+fn default_profile_root(app: &AppHandle) -> PathBuf {
+  app
+    .try_state::<config::ShellConfig>()
+    .and_then(|config| config.backend.data_root.clone())
+    .unwrap_or_else(|| data_root::default_root(app))
+}
+
+fn refuse_in_portable_mode() -> Result<(), String> {
+  match portable::root() {
+    Some(root) => Err(format!("portable mode keeps all data in {}", root.display())),
+    None => Ok(()),
+  }
+}
+
+/// Names double as folder names, so they're kept to characters every file system accepts.
+fn validate_name(name: &str) -> Result<(), String> {
+  if name.is_empty() {
+    return Err("profile name is empty".to_string());
+  }
+  if is_default(name) {
+    return Err(format!("{DEFAULT_PROFILE:?} is reserved"));
+  }
+  if name.len() > MAX_NAME_LEN {
+    return Err(format!("profile name is longer than {MAX_NAME_LEN} characters"));
+  }
+  let allowed = |c: char| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.');
+  if !name.chars().all(allowed) || name.starts_with('.') {
+    return Err(format!(
+      "{name:?} may only contain letters, digits, spaces, '-', '_' and '.' (not first)"
+    ));
+  }
+  Ok(())
+}
+
+fn is_default(name: &str) -> bool {
+  same_name(name, DEFAULT_PROFILE)
+}
+
+fn same_name(a: &str, b: &str) -> bool {
+  a.trim().to_lowercase() == b.trim().to_lowercase()
+}
+
+fn profiles_path(app: &AppHandle) -> anyhow::Result<PathBuf> {
+  config::config_dir(app)
+    .map(|dir| dir.join(PROFILES_FILE))
+    .context("app config directory unavailable")
+}
+
+/// The saved profiles; a missing file means none.
+fn load(app: &AppHandle) -> anyhow::Result<ProfilesFile> {
+  let path = profiles_path(app)?;
+  match std::fs::read(&path) {
+    Ok(raw) => serde_json::from_slice(&raw)
+      .with_context(|| format!("{} is invalid", path.display())),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(ProfilesFile::default()),
+    Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+  }
+}
+
+/// Applies `change` to the file on disk and writes it back, refusing to replace a file that
+/// doesn't parse.
+fn update(
+  app: &AppHandle,
+  change: impl FnOnce(&mut ProfilesFile) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+  let path = profiles_path(app)?;
+  let mut file = load(app).context("not overwriting it")?;
+  change(&mut file)?;
+  let bytes = serde_json::to_vec_pretty(&file)?;
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir)?;
+  }
+  std::fs::write(&path, bytes).with_context(|| format!("failed to write {}", path.display()))
+}
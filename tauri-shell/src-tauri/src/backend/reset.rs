@@ -0,0 +1,159 @@
+//! `reset_app_data`: a clean way out of a broken install, one scope at a time. The user is shown
+//! exactly which folders go before anything is deleted, and every deletion is logged.
+
+use std::path::{Path, PathBuf};
+
+use log::{error, info, warn};
+use serde::Deserialize;
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use super::{migrate, CACHE_DIRS};
+use crate::audit;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResetScope {
+  /// Downloaded artifacts and scratch files. The backend is restarted afterwards.
+  Cache,
+  /// Log sessions other than the current one.
+  Logs,
+  /// All data and old logs, with the backend stopped, and the frontend's stored state.
+  All,
+}
+
+/// Deletes the data in `scope` after the user confirms, then brings the backend back. Returns
+/// `false` if the user declined.
+#[tauri::command]
+pub async fn reset_app_data(app: AppHandle, scope: ResetScope) -> Result<bool, String> {
+  let result = reset(app.clone(), scope).await;
+  if !matches!(result, Ok(false)) {
+    let params = serde_json::json!({ "scope": format!("{scope:?}").to_lowercase() });
+    audit::record(&app, "reset_app_data", params, &result);
+  }
+  result
+}
+
+async fn reset(app: AppHandle, scope: ResetScope) -> Result<bool, String> {
+  let root = super::data_root(&app);
+  let targets = targets(&app, &root, scope).map_err(|err| format!("{err:#}"))?;
+
+  let dialog_app = app.clone();
+  let listing = targets
+    .iter()
+    .map(|path| format!("• {}", path.display()))
+    .collect::<Vec<_>>()
+    .join("\n");
+  let confirmed =
+    tauri::async_runtime::spawn_blocking(move || confirm(&dialog_app, scope, &listing))
+      .await
+      .map_err(|err| err.to_string())?;
+  if !confirmed {
+    info!("reset of {scope:?} data cancelled");
+    return Ok(false);
+  }
+
+  info!("resetting {scope:?} data in {:?}", root);
+  let managed = super::is_running(&app).is_some();
+  if scope == ResetScope::All && managed {
+    super::stop_backend(app.clone()).await?;
+  }
+  let deleted = tauri::async_runtime::spawn_blocking(move || delete(&targets))
+    .await
+    .map_err(|err| err.to_string())?;
+  // Recreates the skeleton the backend expects, as at launch.
+  super::data_root(&app);
+
+  let restarted = match (scope, managed) {
+    (ResetScope::All, true) => super::start_backend(app.clone()).await.map(|_| ()),
+    (ResetScope::Cache, true) => super::restart_backend(app.clone()).await.map(|_| ()),
+    _ => Ok(()),
+  };
+  if scope == ResetScope::All {
+    if let Err(err) = crate::windows::clear_frontend_storage(&app).await {
+      error!("{err}");
+    }
+  }
+  deleted?;
+  restarted.map_err(|err| format!("data was reset but the backend did not start again: {err}"))?;
+  Ok(true)
+}
+
+/// What `scope` deletes, top-level entries only.
+fn targets(app: &AppHandle, root: &Path, scope: ResetScope) -> anyhow::Result<Vec<PathBuf>> {
+  let targets = match scope {
+    ResetScope::Cache => CACHE_DIRS
+      .iter()
+      .map(|dir| root.join(dir))
+      .filter(|path| path.exists())
+      .collect(),
+    ResetScope::Logs => old_log_sessions(app, root)?,
+    // Everything but the files the running shell holds.
+    ResetScope::All => {
+      let mut targets = migrate::entries(root)?;
+      targets.extend(old_log_sessions(app, root)?);
+      targets
+    }
+  };
+  Ok(targets)
+}
+
+/// Entries of `logs/` except the current session's, whose files are still being written.
+fn old_log_sessions(app: &AppHandle, root: &Path) -> anyhow::Result<Vec<PathBuf>> {
+  let current = super::current_log_dir(app);
+  Ok(
+    migrate::entries(&root.join("logs"))?
+      .into_iter()
+      .filter(|path| current.as_deref() != Some(path.as_path()))
+      .collect(),
+  )
+}
+
+/// Asks for confirmation with the list of what will be deleted; blocks until answered.
+fn confirm(app: &AppHandle, scope: ResetScope, listing: &str) -> bool {
+  let (title, summary) = match scope {
+    ResetScope::Cache => ("Clear Cache?", "Downloaded files and temporary data will be deleted."),
+    ResetScope::Logs => ("Delete Logs?", "Logs from previous sessions will be deleted."),
+    ResetScope::All => (
+      "Reset All Data?",
+      "All of your Pluto Duck data — databases, saved analyses, settings stored by the \
+       backend — will be permanently deleted. The backend is stopped while this happens.",
+    ),
+  };
+  let listing = if listing.is_empty() {
+    "(nothing to delete)"
+  } else {
+    listing
+  };
+  app
+    .dialog()
+    .message(format!("{summary}\n\nThis deletes:\n{listing}"))
+    .title(title)
+    .kind(MessageDialogKind::Warning)
+    .buttons(MessageDialogButtons::OkCancelCustom(
+      "Delete".into(),
+      "Cancel".into(),
+    ))
+    .blocking_show()
+}
+
+fn delete(targets: &[PathBuf]) -> Result<(), String> {
+  let mut failed = Vec::new();
+  for path in targets {
+    info!("reset: deleting {:?}", path);
+    let result = if path.is_dir() {
+      std::fs::remove_dir_all(path)
+    } else {
+      std::fs::remove_file(path)
+    };
+    if let Err(err) = result {
+      warn!("reset: failed to delete {:?}: {err}", path);
+      failed.push(format!("{}: {err}", path.display()));
+    }
+  }
+  if failed.is_empty() {
+    Ok(())
+  } else {
+    Err(format!("some data could not be deleted:\n{}", failed.join("\n")))
+  }
+}
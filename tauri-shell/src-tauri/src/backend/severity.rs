@@ -0,0 +1,63 @@
+//! Spots error-level lines in backend output so the UI can flag problems the backend only
+//! logged. Patterns come from `logs.severity_patterns` in the shell config.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use log::warn;
+use regex::Regex;
+
+use crate::config::SeverityPattern;
+
+struct Rule {
+  severity: String,
+  pattern: Regex,
+  notify: bool,
+}
+
+pub struct Classifier {
+  rules: Vec<Rule>,
+  counts: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Classifier {
+  /// Invalid patterns are logged and skipped rather than failing the launch.
+  pub fn new(patterns: &[SeverityPattern]) -> Self {
+    let rules = patterns
+      .iter()
+      .filter_map(|entry| match Regex::new(&entry.pattern) {
+        Ok(pattern) => Some(Rule {
+          severity: entry.severity.clone(),
+          pattern,
+          notify: entry.notify,
+        }),
+        Err(err) => {
+          warn!("ignoring invalid severity pattern {:?}: {err}", entry.pattern);
+          None
+        }
+      })
+      .collect();
+    Self {
+      rules,
+      counts: Mutex::new(BTreeMap::new()),
+    }
+  }
+
+  /// Counts `line` under the first matching rule. Returns the severity when the rule asks for
+  /// the line to be surfaced.
+  pub fn classify(&self, line: &str) -> Option<&str> {
+    let rule = self.rules.iter().find(|rule| rule.pattern.is_match(line))?;
+    if let Ok(mut counts) = self.counts.lock() {
+      *counts.entry(rule.severity.clone()).or_default() += 1;
+    }
+    rule.notify.then_some(rule.severity.as_str())
+  }
+
+  pub fn counts(&self) -> BTreeMap<String, u64> {
+    self
+      .counts
+      .lock()
+      .map(|counts| counts.clone())
+      .unwrap_or_default()
+  }
+}
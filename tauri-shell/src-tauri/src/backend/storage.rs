@@ -0,0 +1,101 @@
+//! Disk space on the data root's volume: a warning at launch when it's nearly full (DuckDB
+//! fails in confusing ways once writes start failing) and the numbers for the settings page.
+
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+use crate::config::ShellConfig;
+use crate::{platform, walk};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageInfo {
+  data_root: PathBuf,
+  total_bytes: u64,
+  free_bytes: u64,
+  /// Everything under the data root, logs included.
+  data_root_bytes: u64,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LowDiskSpace {
+  data_root: PathBuf,
+  free_bytes: u64,
+  threshold_bytes: u64,
+}
+
+/// Warns (log, `low-disk-space` event, and a dialog that doesn't hold up launch) when the data
+/// root's volume is below the configured free-space threshold.
+pub fn check_free_space(app: &AppHandle, data_root: &Path) {
+  let threshold = app.state::<ShellConfig>().backend.min_free_bytes();
+  let space = match platform::disk_space(data_root) {
+    Ok(space) => space,
+    Err(err) => {
+      warn!("failed to check free space for {:?}: {err}", data_root);
+      return;
+    }
+  };
+  if space.available >= threshold {
+    return;
+  }
+  warn!(
+    "only {} free on the volume holding {:?} (warning below {})",
+    format_bytes(space.available),
+    data_root,
+    format_bytes(threshold)
+  );
+  let _ = app.emit(
+    "low-disk-space",
+    LowDiskSpace {
+      data_root: data_root.to_path_buf(),
+      free_bytes: space.available,
+      threshold_bytes: threshold,
+    },
+  );
+  app
+    .dialog()
+    .message(format!(
+      "Only {} is free on the disk that holds Pluto Duck's data ({}).\n\nPluto Duck may fail \
+       to save your work once it fills up. Free up some space, or move the data to a larger \
+       disk.",
+      format_bytes(space.available),
+      data_root.display()
+    ))
+    .title("Low disk space")
+    .kind(MessageDialogKind::Warning)
+    .show(|_| {});
+}
+
+/// Size and free space of the data root's volume, and how much the data root itself takes.
+#[tauri::command]
+pub async fn get_storage_info(app: AppHandle) -> Result<StorageInfo, String> {
+  let data_root = super::data_root(&app);
+  tauri::async_runtime::spawn_blocking(move || {
+    let space = platform::disk_space(&data_root)
+      .map_err(|err| format!("failed to check free space for {}: {err}", data_root.display()))?;
+    Ok(StorageInfo {
+      data_root_bytes: walk::dir_size(&data_root),
+      data_root,
+      total_bytes: space.total,
+      free_bytes: space.available,
+    })
+  })
+  .await
+  .map_err(|err| format!("storage check failed: {err}"))?
+}
+
+fn format_bytes(bytes: u64) -> String {
+  const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+  const MB: f64 = 1024.0 * 1024.0;
+  let bytes = bytes as f64;
+  if bytes >= GB {
+    format!("{:.1} GB", bytes / GB)
+  } else {
+    format!("{:.0} MB", bytes / MB)
+  }
+}
@@ -0,0 +1,99 @@
+use std::process::ExitStatus;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+/// Who ended the backend process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Cause {
+  /// It exited (or crashed) on its own.
+  Exited,
+  /// It exited after the shell asked it to stop.
+  Stopped,
+  /// The shell killed it, either directly or after it ignored a stop request.
+  Killed,
+}
+
+/// How the last backend process ended. Sent with `backend-exited` and kept for `backend_status`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Termination {
+  pub pid: u32,
+  pub cause: Cause,
+  pub code: Option<i32>,
+  /// Unix only: the signal that terminated the process.
+  pub signal: Option<i32>,
+  pub reason: String,
+  /// Milliseconds since the Unix epoch.
+  pub exited_at: Option<u64>,
+}
+
+impl Termination {
+  pub fn new(pid: u32, cause: Cause, status: Option<ExitStatus>) -> Self {
+    let code = status.and_then(|status| status.code());
+    let signal = status.and_then(exit_signal);
+    Self {
+      pid,
+      cause,
+      code,
+      signal,
+      reason: describe(cause, code, signal),
+      exited_at: SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|elapsed| elapsed.as_millis() as u64),
+    }
+  }
+}
+
+fn describe(cause: Cause, code: Option<i32>, signal: Option<i32>) -> String {
+  let status = match (code, signal) {
+    (Some(code), _) => format!("exit code {code}"),
+    (None, Some(signal)) => match signal_name(signal) {
+      Some(name) => format!("signal {signal} ({name})"),
+      None => format!("signal {signal}"),
+    },
+    (None, None) => "unknown status".to_string(),
+  };
+  match cause {
+    Cause::Exited => format!("exited on its own with {status}"),
+    Cause::Stopped => format!("stopped by shell ({status})"),
+    Cause::Killed => "killed by shell".to_string(),
+  }
+}
+
+#[cfg(unix)]
+fn exit_signal(status: ExitStatus) -> Option<i32> {
+  use std::os::unix::process::ExitStatusExt;
+  status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: ExitStatus) -> Option<i32> {
+  None
+}
+
+#[cfg(unix)]
+fn signal_name(signal: i32) -> Option<&'static str> {
+  let name = match signal {
+    libc::SIGHUP => "SIGHUP",
+    libc::SIGINT => "SIGINT",
+    libc::SIGQUIT => "SIGQUIT",
+    libc::SIGILL => "SIGILL",
+    libc::SIGABRT => "SIGABRT",
+    libc::SIGBUS => "SIGBUS",
+    libc::SIGFPE => "SIGFPE",
+    libc::SIGKILL => "SIGKILL",
+    libc::SIGSEGV => "SIGSEGV",
+    libc::SIGPIPE => "SIGPIPE",
+    libc::SIGTERM => "SIGTERM",
+    _ => return None,
+  };
+  Some(name)
+}
+
+#[cfg(not(unix))]
+fn signal_name(_signal: i32) -> Option<&'static str> {
+  None
+}
@@ -0,0 +1,136 @@
+//! A `Backend` around a shell script, for exercising the real spawn and stop paths without an
+//! app.
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::output::{self, Forwarder, LogRing};
+use super::severity::Classifier;
+use super::{Backend, LaunchSpec};
+use crate::logs;
+
+const WAIT: Duration = Duration::from_secs(10);
+
+/// A backend whose binary is `dir/backend`, a `/bin/sh` script running `body`, with its data
+/// root and logs in `dir`.
+pub fn backend(dir: &Path, body: &str) -> Backend {
+  let binary = dir.join("backend");
+  write_executable(&binary, &format!("#!/bin/sh\n{body}"));
+  let log_dir = dir.join("logs");
+  std::fs::create_dir_all(&log_dir).unwrap();
+  let spec = LaunchSpec {
+    binary,
+    data_root: dir.to_path_buf(),
+    log_dir,
+    log_rotation: logs::Rotation {
+      max_bytes: 1024 * 1024,
+      keep: 1,
+    },
+    output: Forwarder::detached(
+      Arc::new(LogRing::new(output::RING_CAPACITY)),
+      Arc::new(Classifier::new(&[])),
+    ),
+    port: 0,
+    proxy_env: Vec::new(),
+  };
+  Backend::new(spec, Duration::from_secs(2))
+}
+
+/// A copy of `sleep` in `dir`, for a script to `exec` into when a test needs the child's PID
+/// to run a binary of its own.
+pub fn sleeper(dir: &Path) -> PathBuf {
+  let path = dir.join("sleeper");
+  std::fs::copy("/bin/sleep", &path).unwrap();
+  path
+}
+
+pub fn write_executable(path: &Path, contents: &str) {
+  std::fs::write(path, contents).unwrap();
+  std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+/// Spawns the backend, retrying the "text file busy" a parallel test's fork can cause while
+/// a freshly written script is still open in it.
+pub fn spawn(backend: &mut Backend) -> u32 {
+  let deadline = Instant::now() + WAIT;
+  loop {
+    match backend.spawn_now() {
+      Ok(pid) => return pid,
+      Err(err) if Instant::now() < deadline => {
+        eprintln!("retrying backend spawn: {err:#}");
+        thread::sleep(Duration::from_millis(50));
+      }
+      Err(err) => panic!("failed to spawn the test backend: {err:#}"),
+    }
+  }
+}
+
+/// The PID a script wrote to `path`, once it has.
+pub fn read_pid(path: &Path) -> u32 {
+  let deadline = Instant::now() + WAIT;
+  loop {
+    if let Some(pid) = std::fs::read_to_string(path)
+      .ok()
+      .and_then(|text| text.trim().parse().ok())
+    {
+      return pid;
+    }
+    assert!(Instant::now() < deadline, "nothing wrote a PID to {}", path.display());
+    thread::sleep(Duration::from_millis(20));
+  }
+}
+
+/// Waits until `pid` runs `binary`, i.e. a script has `exec`ed into it.
+pub fn wait_for_exec(pid: u32, binary: &Path) {
+  let deadline = Instant::now() + WAIT;
+  while !super::process::executable_path(pid)
+    .is_some_and(|running| super::pidfile::same_file(&running, binary))
+  {
+    assert!(Instant::now() < deadline, "process {pid} never ran {}", binary.display());
+    thread::sleep(Duration::from_millis(20));
+  }
+}
+
+/// Whether `pid` is running right now.
+pub fn alive(pid: u32) -> bool {
+  // SAFETY: signal 0 only checks whether the process exists.
+  let exists = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0;
+  exists && !zombie(pid)
+}
+
+/// Whether `pid` is gone, waiting a while for it to go: `kill(pid, 0)` fails with `ESRCH`.
+pub fn gone(pid: u32) -> bool {
+  let deadline = Instant::now() + WAIT;
+  loop {
+    // SAFETY: signal 0 only checks whether the process exists.
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    let missing =
+      result != 0 && std::io::Error::last_os_error().raw_os_error() == Some(libc::ESRCH);
+    if missing || zombie(pid) {
+      return true;
+    }
+    if Instant::now() >= deadline {
+      return false;
+    }
+    thread::sleep(Duration::from_millis(20));
+  }
+}
+
+/// An orphan reparented to a PID 1 that doesn't reap (as in some containers) stays a zombie,
+/// which is dead all the same.
+#[cfg(target_os = "linux")]
+fn zombie(pid: u32) -> bool {
+  std::fs::read_to_string(format!("/proc/{pid}/stat")).is_ok_and(|stat| {
+    stat
+      .rsplit_once(')')
+      .is_some_and(|(_, rest)| rest.trim_start().starts_with('Z'))
+  })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn zombie(_pid: u32) -> bool {
+  false
+}
@@ -0,0 +1,162 @@
+//! What the data root's space goes to, for "why is Pluto Duck using 40 GB?". The walk runs on a
+//! blocking thread and streams running totals as `data-usage-progress`; a newer request or
+//! `cancel_data_dir_usage` stops it. A finished result is reused for a minute.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::{ARTIFACTS_DIR, RUNTIME_DIR};
+use crate::walk::{self, Cancelled};
+
+const CACHE_TTL: Duration = Duration::from_secs(60);
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+/// Categories by the top-level directory of the data root that holds them; anything else
+/// counts as `other`.
+const CATEGORIES: &[(&str, &str)] = &[
+  ("databases", "data"),
+  ("cache", ARTIFACTS_DIR),
+  ("logs", "logs"),
+  ("crashes", "crashes"),
+  ("temp", RUNTIME_DIR),
+];
+const OTHER: &str = "other";
+
+#[derive(Default)]
+pub struct DataUsageState(Mutex<Walks>);
+
+#[derive(Default)]
+struct Walks {
+  /// Cancels the walk in progress.
+  running: Option<Arc<AtomicBool>>,
+  last: Option<(Instant, DataUsage)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataUsage {
+  data_root: PathBuf,
+  categories: Vec<CategoryUsage>,
+  total_bytes: u64,
+  /// `false` in progress events sent while the walk is still going.
+  complete: bool,
+  computed_at: DateTime<Local>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryUsage {
+  name: &'static str,
+  bytes: u64,
+}
+
+/// Sizes of the data root's categories and their total. `refresh` skips the cached result.
+#[tauri::command]
+pub async fn get_data_dir_usage(
+  app: AppHandle,
+  refresh: Option<bool>,
+) -> Result<DataUsage, String> {
+  let state = app.state::<DataUsageState>();
+  let cancel = {
+    let mut walks = state
+      .0
+      .lock()
+      .map_err(|_| "data usage state is poisoned".to_string())?;
+    if let Some((at, last)) = &walks.last {
+      if !refresh.unwrap_or(false) && at.elapsed() < CACHE_TTL {
+        return Ok(last.clone());
+      }
+    }
+    if let Some(previous) = walks.running.take() {
+      previous.store(true, Ordering::Relaxed);
+    }
+    let cancel = Arc::new(AtomicBool::new(false));
+    walks.running = Some(cancel.clone());
+    cancel
+  };
+
+  let data_root = super::data_root(&app);
+  let walk_app = app.clone();
+  let walk_cancel = cancel.clone();
+  let measured =
+    tauri::async_runtime::spawn_blocking(move || measure(&walk_app, data_root, &walk_cancel))
+      .await
+      .map_err(|err| format!("data usage walk failed: {err}"))?;
+
+  let mut walks = state
+    .0
+    .lock()
+    .map_err(|_| "data usage state is poisoned".to_string())?;
+  // A newer walk may have taken over already.
+  if walks
+    .running
+    .as_ref()
+    .is_some_and(|running| Arc::ptr_eq(running, &cancel))
+  {
+    walks.running = None;
+  }
+  match measured {
+    Ok(usage) => {
+      walks.last = Some((Instant::now(), usage.clone()));
+      Ok(usage)
+    }
+    Err(Cancelled) => Err("data usage walk was cancelled".to_string()),
+  }
+}
+
+/// Stops the walk in progress, if any; its `get_data_dir_usage` call fails.
+#[tauri::command]
+pub fn cancel_data_dir_usage(app: AppHandle) {
+  let running = app
+    .state::<DataUsageState>()
+    .0
+    .lock()
+    .ok()
+    .and_then(|mut walks| walks.running.take());
+  if let Some(running) = running {
+    running.store(true, Ordering::Relaxed);
+  }
+}
+
+fn measure(
+  app: &AppHandle,
+  data_root: PathBuf,
+  cancel: &AtomicBool,
+) -> Result<DataUsage, Cancelled> {
+  let names = CATEGORIES.iter().map(|(name, _)| *name).chain(Some(OTHER));
+  let mut usage = DataUsage {
+    categories: names.map(|name| CategoryUsage { name, bytes: 0 }).collect(),
+    data_root,
+    total_bytes: 0,
+    complete: false,
+    computed_at: Local::now(),
+  };
+  let entries = std::fs::read_dir(&usage.data_root)
+    .map(|entries| entries.flatten().collect::<Vec<_>>())
+    .unwrap_or_default();
+  let mut reported = Instant::now();
+  for entry in entries {
+    let name = entry.file_name();
+    let index = CATEGORIES
+      .iter()
+      .position(|(_, dir)| name == *dir)
+      .unwrap_or(CATEGORIES.len());
+    walk::for_each_file(&entry.path(), cancel, |_, len| {
+      usage.categories[index].bytes += len;
+      usage.total_bytes += len;
+      if reported.elapsed() >= PROGRESS_INTERVAL {
+        reported = Instant::now();
+        let _ = app.emit("data-usage-progress", &usage);
+      }
+    })?;
+  }
+  usage.complete = true;
+  usage.computed_at = Local::now();
+  let _ = app.emit("data-usage-progress", &usage);
+  Ok(usage)
+}
@@ -0,0 +1,122 @@
+//! Copying query results, which can run to hundreds of thousands of rows: the webview's
+//! clipboard is slow at that size and sometimes cuts it short, so the frontend hands the shell
+//! the content instead. HTML tables go up with a plain-text flavor too, so either kind of
+//! paste target gets something. For the largest results the frontend passes a file the
+//! backend dumped them to, which saves sending the whole string over IPC.
+
+use std::path::PathBuf;
+
+use log::{info, warn};
+use serde::Deserialize;
+use tauri::AppHandle;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::backend;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardFormat {
+  Text,
+  Tsv,
+  Html,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ClipboardPayload {
+  Text { text: String },
+  /// Tab-separated rows, which spreadsheets paste as cells.
+  Tsv { text: String },
+  /// An HTML table; `text` is the plain-text flavor, derived from the table when missing.
+  Html { html: String, text: Option<String> },
+  /// Content in `format`, read from `path`, which must be in the temp directory or the data
+  /// root. With `delete_after`, the file is removed once it's on the clipboard.
+  #[serde(rename_all = "camelCase")]
+  File {
+    path: PathBuf,
+    format: ClipboardFormat,
+    #[serde(default)]
+    delete_after: bool,
+  },
+}
+
+#[tauri::command]
+pub async fn copy_to_clipboard(app: AppHandle, content: ClipboardPayload) -> Result<(), String> {
+  let read_app = app.clone();
+  let contents = tauri::async_runtime::spawn_blocking(move || read(&read_app, content))
+    .await
+    .map_err(|err| err.to_string())??;
+  let clipboard = app.clipboard();
+  let result = match contents {
+    Contents::Plain(text) => clipboard.write_text(text),
+    Contents::Html { html, text } => {
+      let text = text.unwrap_or_else(|| plain_text(&html));
+      clipboard.write_html(html, Some(text))
+    }
+  };
+  result.map_err(|err| format!("failed to copy to the clipboard: {err}"))
+}
+
+/// What goes on the clipboard: text alone, or HTML and its plain-text flavor if it has one.
+enum Contents {
+  Plain(String),
+  Html { html: String, text: Option<String> },
+}
+
+fn read(app: &AppHandle, content: ClipboardPayload) -> Result<Contents, String> {
+  match content {
+    ClipboardPayload::Text { text } | ClipboardPayload::Tsv { text } => Ok(Contents::Plain(text)),
+    ClipboardPayload::Html { html, text } => Ok(Contents::Html { html, text }),
+    ClipboardPayload::File { path, format, delete_after } => {
+      let path = backend::scratch_file(app, &path)?;
+      let text = std::fs::read_to_string(&path)
+        .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+      info!("copying {} bytes from {}", text.len(), path.display());
+      if delete_after {
+        if let Err(err) = std::fs::remove_file(&path) {
+          warn!("failed to remove {}: {err}", path.display());
+        }
+      }
+      Ok(match format {
+        ClipboardFormat::Text | ClipboardFormat::Tsv => Contents::Plain(text),
+        ClipboardFormat::Html => Contents::Html { html: text, text: None },
+      })
+    }
+  }
+}
+
+/// Tab-separated rows from an HTML table: cells end in tabs, rows in newlines, and the other tags
+/// go. Good enough for the tables the frontend builds; not a general HTML renderer.
+fn plain_text(html: &str) -> String {
+  let mut text = String::with_capacity(html.len() / 2);
+  let mut rest = html;
+  while let Some(start) = rest.find('<') {
+    text.push_str(&rest[..start]);
+    let Some(end) = rest[start..].find('>') else {
+      rest = "";
+      break;
+    };
+    let tag = rest[start + 1..start + end].trim().to_ascii_lowercase();
+    match tag.split_whitespace().next().unwrap_or_default() {
+      "/td" | "/th" => text.push('\t'),
+      "/tr" | "br" | "br/" => {
+        if text.ends_with('\t') {
+          text.pop();
+        }
+        text.push('\n');
+      }
+      _ => {}
+    }
+    rest = &rest[start + end + 1..];
+  }
+  text.push_str(rest);
+  text
+    .replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&quot;", "\"")
+    .replace("&#39;", "'")
+    .replace("&nbsp;", " ")
+    .replace("&amp;", "&")
+    .trim_matches('\n')
+    .to_string()
+}
@@ -0,0 +1,632 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{audit, autostart, logs, quit, shortcut, updates, windows};
+
+pub mod watch;
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// Settings that take effect as soon as they're saved; anything else waits for a restart.
+const LIVE_SETTINGS: &[&str] = &[
+  "autostart.enabled",
+  // Read at launch, from the saved config.
+  "autostart.start_hidden",
+  "data_folders.bookmarks",
+  "export.last_directories",
+  "backend.import_opened_files",
+  "logs.level",
+  "updates.channel",
+  // Read from the saved config whenever they're needed.
+  "backend.allow_remote_bind",
+  "updates.auto_check",
+  "updates.last_prompted_at",
+  "updates.notified_version",
+  "updates.remind_interval_hours",
+  "updates.skipped_version",
+  "updates.snoozed_until",
+  "window.close_behavior",
+  "window.confirm_quit",
+  "window.global_shortcut",
+  "window.navigation_allow_list",
+  "window.open_blocked_links",
+  "window.palette_shortcut",
+  "window.theme",
+  "window.titlebar_height",
+];
+
+/// Shell-side settings loaded from `config.toml` in the app config directory.
+///
+/// Every field has a default so a missing or partial file still yields a usable config. The
+/// managed copy is the config as loaded at launch; `LIVE_SETTINGS` changed since are tracked by
+/// the state that uses them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShellConfig {
+  pub autostart: AutostartConfig,
+  pub backend: BackendConfig,
+  pub data_folders: DataFolderConfig,
+  pub export: ExportConfig,
+  pub logs: LogConfig,
+  pub network: NetworkConfig,
+  pub updates: UpdateConfig,
+  pub window: WindowConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+  pub channel: UpdateChannel,
+  /// Update manifest URL, with `{channel}` standing for the channel's manifest name.
+  pub endpoint: String,
+  /// Markdown release notes for versions the manifest gives none for, with `{channel}`
+  /// (`stable` or `beta`) and `{version}` filled in.
+  pub notes_url: Option<String>,
+  /// Check for updates once the backend is up at launch (release builds only).
+  pub auto_check: bool,
+  /// The automatic check offers an update at most once per this many hours.
+  pub remind_interval_hours: u64,
+  /// A version the user chose to skip; only checking by hand still offers it.
+  pub skipped_version: Option<String>,
+  /// No automatic offers until then.
+  pub snoozed_until: Option<DateTime<Utc>>,
+  /// When the automatic check last offered an update.
+  pub last_prompted_at: Option<DateTime<Utc>>,
+  /// The last version announced with a system notification, so none is announced twice.
+  pub notified_version: Option<String>,
+}
+
+impl Default for UpdateConfig {
+  fn default() -> Self {
+    Self {
+      channel: UpdateChannel::default(),
+      endpoint: updates::DEFAULT_ENDPOINT.to_string(),
+      notes_url: None,
+      auto_check: true,
+      remind_interval_hours: 24,
+      skipped_version: None,
+      snoozed_until: None,
+      last_prompted_at: None,
+      notified_version: None,
+    }
+  }
+}
+
+/// Launching at login (see `autostart`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutostartConfig {
+  pub enabled: bool,
+  /// Launches at login show only the tray icon.
+  pub start_hidden: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DataFolderConfig {
+  /// Security-scoped bookmarks (base64) of picked folders by path, kept only when the app runs
+  /// in the macOS App Sandbox (see `data_folder`).
+  pub bookmarks: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportConfig {
+  /// Where the last export of each format (`csv`, `parquet`, …) was saved; the save dialog
+  /// opens there next time.
+  pub last_directories: BTreeMap<String, PathBuf>,
+}
+
+/// Proxies for the shell's and the backend's HTTP requests; unset ones fall back to the
+/// usual environment variables (see `proxy`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+  /// e.g. `http://proxy.example.com:8080`, for plain HTTP requests.
+  pub http_proxy: Option<String>,
+  /// For HTTPS requests, update checks included.
+  pub https_proxy: Option<String>,
+  /// Comma-separated hosts or domains reached directly; loopback always is.
+  pub no_proxy: Option<String>,
+}
+
+/// Which releases the updater offers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+  #[default]
+  Stable,
+  Beta,
+}
+
+/// The config as last read from or written to disk, which is what the live settings follow.
+/// Lets the watcher tell edits made elsewhere from the app's own writes.
+pub struct SavedConfig(Mutex<ShellConfig>);
+
+impl SavedConfig {
+  pub fn new(config: ShellConfig) -> Self {
+    Self(Mutex::new(config))
+  }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ShellConfigChanged {
+  config: ShellConfig,
+  /// Dotted names (`window.theme`) of the settings that changed.
+  changed: Vec<String>,
+  /// The changed settings that only take effect after a restart.
+  restart_required: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+  pub close_behavior: CloseBehavior,
+  /// macOS only: height in points of the area the frontend draws under the traffic lights.
+  pub titlebar_height: f64,
+  pub theme: ThemePreference,
+  /// Windows 11 only: titlebar color as `#rrggbb`; unset keeps the system accent.
+  pub caption_color: Option<String>,
+  /// Windows/Linux only: drop the native frame so the frontend's header (with
+  /// `data-tauri-drag-region` and its own caption buttons) acts as the titlebar.
+  pub custom_titlebar: bool,
+  /// System-wide shortcut that shows or hides the main window; empty disables it.
+  pub global_shortcut: String,
+  /// System-wide shortcut that opens the quick-query palette; empty disables it.
+  pub palette_shortcut: String,
+  /// Origins (`https://docs.example.com`) windows may navigate to besides the app's and the
+  /// backend's.
+  pub navigation_allow_list: Vec<String>,
+  /// Open web links that windows aren't allowed to navigate to in the default browser.
+  pub open_blocked_links: bool,
+  /// Ask "Quit Pluto Duck?" before every quit, not only while backend jobs are running.
+  pub confirm_quit: bool,
+}
+
+impl Default for WindowConfig {
+  fn default() -> Self {
+    Self {
+      close_behavior: CloseBehavior::default(),
+      titlebar_height: 40.0,
+      theme: ThemePreference::default(),
+      caption_color: None,
+      custom_titlebar: false,
+      global_shortcut: "CmdOrCtrl+Shift+D".to_string(),
+      palette_shortcut: "CmdOrCtrl+Shift+Space".to_string(),
+      navigation_allow_list: Vec::new(),
+      open_blocked_links: true,
+      confirm_quit: false,
+    }
+  }
+}
+
+/// Which theme windows use; anything but `System` overrides the OS setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemePreference {
+  #[default]
+  System,
+  Light,
+  Dark,
+}
+
+/// What the window close button does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloseBehavior {
+  /// Hide the window and keep running (reopened from the Dock on macOS).
+  Hide,
+  /// Close the window, and quit once no visible window is left.
+  Quit,
+}
+
+impl Default for CloseBehavior {
+  fn default() -> Self {
+    // Only macOS has a standard way (the Dock) to bring a hidden window back.
+    if cfg!(target_os = "macos") {
+      Self::Hide
+    } else {
+      Self::Quit
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackendConfig {
+  /// Port the backend listens on, on localhost.
+  pub port: u16,
+  /// Seconds to wait for the backend to exit after asking it to stop before killing it.
+  pub shutdown_timeout_secs: u64,
+  /// Seconds the backend has to pass its first health check before the user is told.
+  pub startup_timeout_secs: u64,
+  /// Where the backend keeps its data; unset uses the app data directory.
+  pub data_root: Option<PathBuf>,
+  /// Launch warns when the data root's volume has less free space than this.
+  pub min_free_space_mb: u64,
+  /// Launch warns, once, when the data root is inside a Dropbox, iCloud Drive, or OneDrive
+  /// folder; cleared after the warning is shown.
+  pub warn_sync_folder: bool,
+  /// Files opened with the app before the backend was ready are imported by the shell rather
+  /// than left to the frontend.
+  pub import_opened_files: bool,
+  /// Lets the backend listen on more than loopback, making it (and the user's data) reachable
+  /// from the network; otherwise a backend found doing so is stopped.
+  pub allow_remote_bind: bool,
+}
+
+impl BackendConfig {
+  pub fn min_free_bytes(&self) -> u64 {
+    self.min_free_space_mb.saturating_mul(1024 * 1024)
+  }
+}
+
+impl Default for BackendConfig {
+  fn default() -> Self {
+    Self {
+      port: 8123,
+      shutdown_timeout_secs: 5,
+      startup_timeout_secs: 30,
+      data_root: None,
+      min_free_space_mb: 1024,
+      warn_sync_folder: true,
+      import_opened_files: false,
+      allow_remote_bind: false,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+  /// Log files (backend output and the shell's own log) are rotated once they grow past
+  /// this many megabytes.
+  pub max_file_size_mb: u64,
+  /// Rotated files kept per log (`backend-stderr.log.1` …).
+  pub rotated_files: usize,
+  /// Shell log level (`error` … `trace`). `PLUTODUCK_LOG` takes precedence; when neither is
+  /// set, debug builds log at `debug` and release builds at `info`.
+  pub level: Option<String>,
+  /// Per-run log directories kept under `logs/`; older ones are deleted at startup.
+  pub kept_sessions: usize,
+  /// Cap on the whole `logs/` directory; the oldest sessions are deleted at startup to stay
+  /// under it.
+  pub max_total_size_mb: u64,
+  /// Also write shell records and backend output, interleaved, to each session's
+  /// `combined.log`.
+  pub combined: bool,
+  /// Regexes that classify backend output lines, checked in order; the first match wins.
+  pub severity_patterns: Vec<SeverityPattern>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeverityPattern {
+  pub severity: String,
+  pub pattern: String,
+  /// Whether matching lines are pushed to the frontend as `backend-error-logged`.
+  #[serde(default)]
+  pub notify: bool,
+}
+
+impl LogConfig {
+  /// At least a megabyte, whatever a hand-edited config says: at zero every line would rotate.
+  pub fn max_file_bytes(&self) -> u64 {
+    self.max_file_size_mb.max(1).saturating_mul(1024 * 1024)
+  }
+
+  pub fn max_total_bytes(&self) -> u64 {
+    self.max_total_size_mb.saturating_mul(1024 * 1024)
+  }
+}
+
+impl SeverityPattern {
+  fn new(severity: &str, pattern: &str, notify: bool) -> Self {
+    Self {
+      severity: severity.to_string(),
+      pattern: pattern.to_string(),
+      notify,
+    }
+  }
+}
+
+impl Default for LogConfig {
+  fn default() -> Self {
+    Self {
+      max_file_size_mb: 10,
+      rotated_files: 5,
+      kept_sessions: 10,
+      max_total_size_mb: 200,
+      combined: false,
+      level: None,
+      severity_patterns: vec![
+        SeverityPattern::new("fatal", r"\b(FATAL|CRITICAL)\b", true),
+        SeverityPattern::new("error", r"\bERROR\b", true),
+        SeverityPattern::new("warning", r"\bWARN(ING)?\b", false),
+      ],
+    }
+  }
+}
+
+pub fn config_path(app: &AppHandle) -> Option<PathBuf> {
+  config_dir(app).map(|dir| dir.join(CONFIG_FILE))
+}
+
+/// Where the shell keeps its settings and window state: the app config directory, or the
+/// portable data folder.
+pub fn config_dir(app: &AppHandle) -> Option<PathBuf> {
+  match crate::portable::root() {
+    Some(root) => Some(root.join("config")),
+    None => app.path().app_config_dir().ok(),
+  }
+}
+
+/// Applies `change` to the config on disk and writes it back. Reads the file fresh so settings
+/// saved since launch are kept, and refuses to replace a file that doesn't parse.
+pub fn update(app: &AppHandle, change: impl FnOnce(&mut ShellConfig)) -> anyhow::Result<()> {
+  let path = config_path(app).ok_or_else(|| anyhow::anyhow!("app config directory unavailable"))?;
+  let mut config = read(&path).context("not overwriting it")?;
+  change(&mut config);
+  write(&path, &config)?;
+  // Callers put their own change into effect.
+  if let Some(saved) = app.try_state::<SavedConfig>() {
+    if let Ok(mut saved) = saved.0.lock() {
+      *saved = config;
+    }
+  }
+  Ok(())
+}
+
+/// The config as last saved, with the changes made since launch.
+pub fn saved(app: &AppHandle) -> ShellConfig {
+  app
+    .try_state::<SavedConfig>()
+    .and_then(|saved| saved.0.lock().ok().map(|config| config.clone()))
+    .unwrap_or_default()
+}
+
+/// The config saved on disk; a missing file means the defaults.
+fn read(path: &Path) -> anyhow::Result<ShellConfig> {
+  match std::fs::read_to_string(path) {
+    Ok(raw) => toml::from_str(&raw).with_context(|| format!("{} is invalid", path.display())),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(ShellConfig::default()),
+    Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+  }
+}
+
+/// Writes through a temporary file and a rename, so a crash mid-write can't leave a torn file.
+fn write(path: &Path, config: &ShellConfig) -> anyhow::Result<()> {
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir)?;
+  }
+  let temp = path.with_extension("toml.tmp");
+  std::fs::write(&temp, toml::to_string_pretty(config)?)
+    .with_context(|| format!("failed to write {}", temp.display()))?;
+  std::fs::rename(&temp, path).with_context(|| format!("failed to replace {}", path.display()))
+}
+
+/// The saved config, which is what the next launch uses.
+#[tauri::command]
+pub fn get_shell_config(app: AppHandle) -> Result<ShellConfig, String> {
+  let path = config_path(&app).ok_or("app config directory unavailable")?;
+  read(&path).map_err(|err| format!("{err:#}"))
+}
+
+/// Merges `patch` (a partial config, e.g. `{"window": {"theme": "dark"}}`) into the saved
+/// config, applies the settings that can change while running, and emits
+/// `shell-config-changed`. Returns the new config.
+#[tauri::command]
+pub fn set_shell_config(
+  app: AppHandle,
+  patch: serde_json::Value,
+) -> Result<ShellConfig, String> {
+  let result = save_patch(app.clone(), patch.clone());
+  audit::record(&app, "set_shell_config", serde_json::json!({ "patch": patch }), &result);
+  result
+}
+
+fn save_patch(app: AppHandle, patch: serde_json::Value) -> Result<ShellConfig, String> {
+  let path = config_path(&app).ok_or("app config directory unavailable")?;
+  let old = read(&path).map_err(|err| format!("{err:#}; not overwriting it"))?;
+  let mut merged = serde_json::to_value(&old).map_err(|err| err.to_string())?;
+  merge(&mut merged, patch);
+  let new: ShellConfig =
+    serde_json::from_value(merged).map_err(|err| format!("invalid settings: {err}"))?;
+  if new.logs.max_file_size_mb == 0 {
+    return Err("invalid settings: logs.max_file_size_mb must be at least 1".to_string());
+  }
+  write(&path, &new).map_err(|err| format!("settings could not be saved: {err:#}"))?;
+  reconcile(&app, new.clone());
+  Ok(new)
+}
+
+/// Makes `new` the saved config, putting what changed since the last one into effect. That
+/// touches windows, menus and shortcuts, so it happens on the main thread; the file watcher
+/// calls this from its own.
+fn reconcile(app: &AppHandle, new: ShellConfig) {
+  let Some(saved) = app.try_state::<SavedConfig>() else {
+    return;
+  };
+  let Ok(mut saved) = saved.0.lock() else {
+    return;
+  };
+  let old = std::mem::replace(&mut *saved, new.clone());
+  drop(saved);
+  let main_app = app.clone();
+  if let Err(err) = app.run_on_main_thread(move || apply(&main_app, &old, &new)) {
+    warn!("failed to apply the changed settings: {err}");
+  }
+}
+
+/// Puts the `LIVE_SETTINGS` that differ between `old` and `new` into effect and emits
+/// `shell-config-changed`, naming separately the changes that need a restart.
+fn apply(app: &AppHandle, old: &ShellConfig, new: &ShellConfig) {
+  let changed = changed_settings(old, new);
+  if changed.is_empty() {
+    return;
+  }
+  let mut restart_required = Vec::new();
+  for setting in &changed {
+    if !LIVE_SETTINGS.contains(&setting.as_str()) {
+      restart_required.push(setting.clone());
+      continue;
+    }
+    match setting.as_str() {
+      "autostart.enabled" => autostart::apply(app, new.autostart.enabled),
+      "logs.level" => {
+        if let Some(warning) = logs::set_level(new.logs.level.as_deref()) {
+          warn!("{warning}");
+        }
+      }
+      "updates.channel" => updates::set_channel(app, new.updates.channel),
+      "window.close_behavior" => windows::apply_close_behavior(app, new.window.close_behavior),
+      "window.confirm_quit" => quit::set_confirm(app, new.window.confirm_quit),
+      "window.global_shortcut" => {
+        if let Err(err) = shortcut::replace(app, &new.window.global_shortcut) {
+          warn!("{err}");
+        }
+      }
+      "window.palette_shortcut" => {
+        if let Err(err) = shortcut::replace_palette(app, &new.window.palette_shortcut) {
+          warn!("{err}");
+        }
+      }
+      "window.theme" => windows::theme::apply_preference(app, new.window.theme),
+      "window.titlebar_height" => {
+        let height = new.window.titlebar_height;
+        if let Err(err) = windows::titlebar::set_titlebar_height(app.clone(), height) {
+          warn!("{err}");
+        }
+      }
+      _ => {}
+    }
+  }
+  info!("settings changed: {changed:?} (restart required for {restart_required:?})");
+  let _ = app.emit(
+    "shell-config-changed",
+    ShellConfigChanged {
+      config: new.clone(),
+      changed,
+      restart_required,
+    },
+  );
+}
+
+/// Dotted names of the settings whose values differ.
+fn changed_settings(old: &ShellConfig, new: &ShellConfig) -> Vec<String> {
+  let (old, new) = (settings(old), settings(new));
+  new
+    .iter()
+    .filter(|(name, value)| old.get(*name) != Some(*value))
+    .map(|(name, _)| name.clone())
+    .collect()
+}
+
+/// Every setting by its dotted name, `section.field`.
+fn settings(config: &ShellConfig) -> BTreeMap<String, serde_json::Value> {
+  let mut settings = BTreeMap::new();
+  if let Ok(serde_json::Value::Object(sections)) = serde_json::to_value(config) {
+    for (section, fields) in sections {
+      match fields {
+        serde_json::Value::Object(fields) => {
+          for (field, value) in fields {
+            settings.insert(format!("{section}.{field}"), value);
+          }
+        }
+        value => {
+          settings.insert(section, value);
+        }
+      }
+    }
+  }
+  settings
+}
+
+/// Recursively overlays `patch` onto `base`; objects merge, anything else replaces.
+fn merge(base: &mut serde_json::Value, patch: serde_json::Value) {
+  match (base, patch) {
+    (serde_json::Value::Object(base), serde_json::Value::Object(patch)) => {
+      for (key, value) in patch {
+        merge(base.entry(key).or_insert(serde_json::Value::Null), value);
+      }
+    }
+    (base, patch) => *base = patch,
+  }
+}
+
+pub fn load(app: &AppHandle) -> ShellConfig {
+  let Some(path) = config_path(app) else {
+    warn!("app config directory unavailable; using default shell config");
+    return ShellConfig::default();
+  };
+  read(&path).unwrap_or_else(|err| {
+    warn!("ignoring shell config: {err:#}");
+    ShellConfig::default()
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn merge_overlays_objects_and_replaces_everything_else() {
+    let mut base = serde_json::json!({
+      "logs": { "level": "info", "max_file_size_mb": 10 },
+      "network": { "http_proxy": "http://old:3128" },
+    });
+    merge(
+      &mut base,
+      serde_json::json!({
+        "logs": { "level": "debug" },
+        "network": { "http_proxy": null },
+        "window": { "confirm_quit": false },
+      }),
+    );
+    assert_eq!(
+      base,
+      serde_json::json!({
+        "logs": { "level": "debug", "max_file_size_mb": 10 },
+        "network": { "http_proxy": null },
+        "window": { "confirm_quit": false },
+      })
+    );
+  }
+
+  #[test]
+  fn changed_settings_names_exactly_what_differs() {
+    let old = ShellConfig::default();
+    assert!(changed_settings(&old, &old.clone()).is_empty());
+
+    let mut new = old.clone();
+    new.logs.level = Some("trace".to_string());
+    new.window.confirm_quit = !old.window.confirm_quit;
+    new.updates.channel = UpdateChannel::Beta;
+    let mut changed = changed_settings(&old, &new);
+    changed.sort();
+    assert_eq!(changed, ["logs.level", "updates.channel", "window.confirm_quit"]);
+  }
+
+  #[test]
+  fn read_fills_in_defaults_and_rejects_a_broken_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shell.toml");
+    let missing = read(&path).unwrap();
+    assert_eq!(missing.logs.max_file_size_mb, ShellConfig::default().logs.max_file_size_mb);
+
+    std::fs::write(&path, "[logs]\nlevel = \"warn\"\n").unwrap();
+    let partial = read(&path).unwrap();
+    assert_eq!(partial.logs.level.as_deref(), Some("warn"));
+    assert_eq!(partial.window.confirm_quit, ShellConfig::default().window.confirm_quit);
+
+    std::fs::write(&path, "[logs\n").unwrap();
+    assert!(read(&path).is_err());
+  }
+}
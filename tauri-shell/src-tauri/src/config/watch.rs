@@ -0,0 +1,106 @@
+//! Picks up hand edits to `config.toml` while the app runs. The directory is watched rather than
+//! the file, because editors save by replacing the file, and only events naming `config.toml`
+//! itself count, so swap and backup files are ignored. Editors also save in several steps, so a
+//! change is read once writes have settled, and a file that doesn't parse yet is retried
+//! before the edit is given up on.
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use log::{debug, warn};
+use notify::{EventKind, RecursiveMode, Watcher};
+use tauri::AppHandle;
+
+use super::CONFIG_FILE;
+
+/// Quiet time after the last write before the file is read.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+/// Reads of a file that doesn't parse, `DEBOUNCE` apart, before keeping the current settings.
+const PARSE_ATTEMPTS: u32 = 3;
+
+/// Starts watching the config file for the rest of the run.
+pub fn start(app: &AppHandle) {
+  let Some(path) = super::config_path(app) else {
+    return;
+  };
+  let Some(dir) = path.parent().map(Path::to_path_buf) else {
+    return;
+  };
+  if let Err(err) = std::fs::create_dir_all(&dir) {
+    warn!("not watching {:?} for changes: {err}", dir);
+    return;
+  }
+  let (tx, rx) = mpsc::channel();
+  let watcher = notify::recommended_watcher(move |event| {
+    let _ = tx.send(event);
+  });
+  let mut watcher = match watcher {
+    Ok(watcher) => watcher,
+    Err(err) => {
+      warn!("not watching {:?} for changes: {err}", path);
+      return;
+    }
+  };
+  if let Err(err) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+    warn!("not watching {:?} for changes: {err}", path);
+    return;
+  }
+
+  let app = app.clone();
+  let spawned = std::thread::Builder::new()
+    .name("config-watcher".into())
+    .spawn(move || {
+      // Dropping the watcher would end the events.
+      let _watcher = watcher;
+      watch(&app, &path, &rx);
+    });
+  if let Err(err) = spawned {
+    warn!("not watching {:?} for changes: {err}", dir);
+  }
+}
+
+fn watch(app: &AppHandle, path: &Path, rx: &Receiver<notify::Result<notify::Event>>) {
+  while let Ok(event) = rx.recv() {
+    let event = match event {
+      Ok(event) => event,
+      Err(err) => {
+        warn!("config watcher: {err}");
+        continue;
+      }
+    };
+    if matches!(event.kind, EventKind::Access(_)) || !names_config(&event.paths) {
+      continue;
+    }
+    while rx.recv_timeout(DEBOUNCE).is_ok() {}
+    reload(app, path);
+  }
+}
+
+fn names_config(paths: &[PathBuf]) -> bool {
+  paths
+    .iter()
+    .any(|path| path.file_name() == Some(OsStr::new(CONFIG_FILE)))
+}
+
+fn reload(app: &AppHandle, path: &Path) {
+  // Some editors remove the file for a moment while saving; a deletion that sticks takes
+  // effect at the next launch.
+  if !path.exists() {
+    return;
+  }
+  for attempt in 1..=PARSE_ATTEMPTS {
+    match super::read(path) {
+      Ok(config) => {
+        debug!("{:?} changed; reloading settings", path);
+        super::reconcile(app, config);
+        return;
+      }
+      Err(err) if attempt == PARSE_ATTEMPTS => {
+        warn!("{err:#}; keeping the current settings");
+      }
+      Err(_) => std::thread::sleep(DEBOUNCE),
+    }
+  }
+}
@@ -0,0 +1,274 @@
+//! Folders of data files (Parquet, usually) registered as data sources. The webview can't
+//! browse the filesystem, so the frontend picks the folder with `pick_directory`, shows the
+//! user what `validate_data_folder` found in it, and only then has the backend register it.
+//!
+//! Under the macOS App Sandbox, access to a picked folder ends with the process unless it's
+//! kept as a security-scoped bookmark, so picks are bookmarked there and reopened at launch.
+//! The app isn't sandboxed today, and without a sandbox none of that happens.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::walk;
+
+/// Extensions counted as data files.
+const DATA_EXTENSIONS: &[&str] = &["parquet", "csv", "tsv", "json", "jsonl", "ndjson"];
+/// Files looked at before the count gives up, for folders that turn out to be a whole disk.
+const MAX_FILES: usize = 100_000;
+
+/// Why a folder can't be used, tagged so the frontend can word each case.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum FolderError {
+  NotFound { path: PathBuf },
+  NotADirectory { path: PathBuf },
+  Unreadable { path: PathBuf, message: String },
+  /// The dialog itself failed.
+  Failed { message: String },
+}
+
+impl fmt::Display for FolderError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::NotFound { path } => write!(f, "{} doesn't exist", path.display()),
+      Self::NotADirectory { path } => write!(f, "{} isn't a folder", path.display()),
+      Self::Unreadable { path, message } => {
+        write!(f, "{} can't be read: {message}", path.display())
+      }
+      Self::Failed { message } => write!(f, "{message}"),
+    }
+  }
+}
+
+/// What's in a data folder, counting subfolders (Hive-style partitions, say).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSummary {
+  path: PathBuf,
+  /// Data files by extension, e.g. `{"parquet": 12}`.
+  data_files: BTreeMap<String, usize>,
+  data_bytes: u64,
+  /// Files that aren't data files.
+  other_files: usize,
+  /// The count stopped at `MAX_FILES`.
+  truncated: bool,
+}
+
+/// Asks for a folder, returning its absolute path, or `None` if the dialog was cancelled.
+#[tauri::command]
+pub async fn pick_directory(
+  app: AppHandle,
+  title: String,
+  default_path: Option<PathBuf>,
+) -> Result<Option<PathBuf>, FolderError> {
+  tauri::async_runtime::spawn_blocking(move || {
+    let mut dialog = app.dialog().file().set_title(title);
+    let start = default_path
+      .filter(|path| path.is_dir())
+      .or_else(|| app.path().document_dir().ok());
+    if let Some(start) = start {
+      dialog = dialog.set_directory(start);
+    }
+    let Some(picked) = dialog.blocking_pick_folder() else {
+      return Ok(None);
+    };
+    let path = picked.into_path().map_err(|err| FolderError::Failed {
+      message: format!("the folder dialog returned an unusable path: {err}"),
+    })?;
+    let path = check(&path)?;
+    #[cfg(target_os = "macos")]
+    bookmark::keep(&app, &path);
+    Ok(Some(path))
+  })
+  .await
+  .map_err(|err| FolderError::Failed { message: err.to_string() })?
+}
+
+/// Counts the data files in `path`, after checking it's a folder that can be read.
+#[tauri::command]
+pub async fn validate_data_folder(path: PathBuf) -> Result<FolderSummary, FolderError> {
+  tauri::async_runtime::spawn_blocking(move || Ok(summarize(&check(&path)?)))
+    .await
+    .map_err(|err| FolderError::Failed { message: err.to_string() })?
+}
+
+/// Reopens the folders bookmarked in earlier runs.
+pub fn restore_access(app: &AppHandle) {
+  #[cfg(target_os = "macos")]
+  bookmark::restore(app);
+  #[cfg(not(target_os = "macos"))]
+  let _ = app;
+}
+
+/// `path`, absolute, if it is a folder whose entries can be listed.
+fn check(path: &Path) -> Result<PathBuf, FolderError> {
+  let metadata = match std::fs::metadata(path) {
+    Ok(metadata) => metadata,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+      return Err(FolderError::NotFound { path: path.to_path_buf() });
+    }
+    Err(err) => {
+      return Err(FolderError::Unreadable {
+        path: path.to_path_buf(),
+        message: err.to_string(),
+      });
+    }
+  };
+  if !metadata.is_dir() {
+    return Err(FolderError::NotADirectory { path: path.to_path_buf() });
+  }
+  let unreadable = |err: std::io::Error| FolderError::Unreadable {
+    path: path.to_path_buf(),
+    message: err.to_string(),
+  };
+  let path = path.canonicalize().map_err(unreadable)?;
+  std::fs::read_dir(&path).map_err(unreadable)?;
+  Ok(path)
+}
+
+fn summarize(path: &Path) -> FolderSummary {
+  let mut summary = FolderSummary {
+    path: path.to_path_buf(),
+    data_files: BTreeMap::new(),
+    data_bytes: 0,
+    other_files: 0,
+    truncated: false,
+  };
+  let mut seen = 0;
+  let stop = AtomicBool::new(false);
+  let _ = walk::for_each_file(path, &stop, |file, len| {
+    seen += 1;
+    if seen >= MAX_FILES {
+      stop.store(true, Ordering::Relaxed);
+    }
+    let extension = file
+      .extension()
+      .and_then(|extension| extension.to_str())
+      .map(str::to_ascii_lowercase)
+      .filter(|extension| DATA_EXTENSIONS.contains(&extension.as_str()));
+    match extension {
+      Some(extension) => {
+        *summary.data_files.entry(extension).or_default() += 1;
+        summary.data_bytes += len;
+      }
+      None => summary.other_files += 1,
+    }
+  });
+  summary.truncated = stop.load(Ordering::Relaxed);
+  summary
+}
+
+#[cfg(target_os = "macos")]
+mod bookmark {
+  use std::path::Path;
+
+  use base64::Engine;
+  use cocoa::base::{id, nil, BOOL, NO};
+  use cocoa::foundation::NSString;
+  use log::{info, warn};
+  use objc::{class, msg_send, sel, sel_impl};
+  use tauri::AppHandle;
+
+  use crate::config;
+
+  const WITH_SECURITY_SCOPE_CREATION: u64 = 1 << 11;
+  const WITH_SECURITY_SCOPE_RESOLUTION: u64 = 1 << 10;
+
+  fn sandboxed() -> bool {
+    std::env::var_os("APP_SANDBOX_CONTAINER_ID").is_some()
+  }
+
+  /// Saves a bookmark for `path` so later runs can reach it too.
+  pub fn keep(app: &AppHandle, path: &Path) {
+    if !sandboxed() {
+      return;
+    }
+    let Some(data) = create(path) else {
+      warn!("failed to bookmark {:?}; access to it ends when the app quits", path);
+      return;
+    };
+    let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+    let key = path.to_string_lossy().into_owned();
+    if let Err(err) = config::update(app, |config| {
+      config.data_folders.bookmarks.insert(key, encoded);
+    }) {
+      warn!("failed to save the bookmark for {:?}: {err:#}", path);
+    }
+  }
+
+  /// Starts access to every bookmarked folder.
+  pub fn restore(app: &AppHandle) {
+    if !sandboxed() {
+      return;
+    }
+    for (path, encoded) in config::saved(app).data_folders.bookmarks {
+      let Ok(data) = base64::engine::general_purpose::STANDARD.decode(encoded) else {
+        warn!("ignoring the unreadable bookmark for {path}");
+        continue;
+      };
+      if open(&data) {
+        info!("reopened bookmarked folder {path}");
+      } else {
+        warn!("bookmarked folder {path} is no longer reachable");
+      }
+    }
+  }
+
+  fn create(path: &Path) -> Option<Vec<u8>> {
+    // SAFETY: plain Foundation calls on objects created here; the autoreleased results are
+    // copied out before returning.
+    unsafe {
+      let string = NSString::alloc(nil).init_str(&path.to_string_lossy());
+      let url: id = msg_send![class!(NSURL), fileURLWithPath: string];
+      let _: () = msg_send![string, release];
+      let mut error: id = nil;
+      let data: id = msg_send![
+        url,
+        bookmarkDataWithOptions: WITH_SECURITY_SCOPE_CREATION
+        includingResourceValuesForKeys: nil
+        relativeToURL: nil
+        error: &mut error
+      ];
+      if data == nil {
+        return None;
+      }
+      let length: usize = msg_send![data, length];
+      let bytes: *const u8 = msg_send![data, bytes];
+      Some(std::slice::from_raw_parts(bytes, length).to_vec())
+    }
+  }
+
+  /// Resolves a bookmark and starts access to it, which stays open for the rest of the run.
+  fn open(data: &[u8]) -> bool {
+    // SAFETY: as in `create`; the NSData only borrows `data` for the duration of the call.
+    unsafe {
+      let data: id = msg_send![
+        class!(NSData),
+        dataWithBytesNoCopy: data.as_ptr()
+        length: data.len()
+        freeWhenDone: NO
+      ];
+      let mut stale: BOOL = NO;
+      let mut error: id = nil;
+      let url: id = msg_send![
+        class!(NSURL),
+        URLByResolvingBookmarkData: data
+        options: WITH_SECURITY_SCOPE_RESOLUTION
+        relativeToURL: nil
+        bookmarkDataIsStale: &mut stale
+        error: &mut error
+      ];
+      if url == nil {
+        return false;
+      }
+      let started: BOOL = msg_send![url, startAccessingSecurityScopedResource];
+      started != NO
+    }
+  }
+}
@@ -0,0 +1,170 @@
+//! `pluto-duck://` links, e.g. `pluto-duck://query?id=abc`. The scheme is registered through
+//! the deep-link plugin (in the bundle on install, and at runtime for AppImages and
+//! development builds), and a link brings the main window forward and reaches the frontend
+//! as `deep-link`.
+//!
+//! Links come in three ways: as the launch argument on Windows and Linux, as an Apple Event on
+//! macOS, and forwarded by a second instance, which a click on Windows or Linux starts while
+//! the app is already running. Links that arrive before the backend is ready, cold starts
+//! included, are held until then, since the frontend can't act on them before.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use log::{error, info, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Listener, Manager, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use crate::{backend, recents, windows};
+
+pub const SCHEME: &str = "pluto-duck";
+
+/// A link, parsed for the frontend: `pluto-duck://query?id=abc` is action `query` with
+/// `params` `{"id": "abc"}`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeepLink {
+  url: String,
+  action: String,
+  /// What follows the action, without the leading `/`.
+  path: String,
+  params: BTreeMap<String, String>,
+}
+
+impl DeepLink {
+  fn parse(url: &Url) -> Option<Self> {
+    if url.scheme() != SCHEME {
+      return None;
+    }
+    // `pluto-duck:query?id=abc` has no host; take the action from the path then.
+    let (action, path) = match url.host_str() {
+      Some(host) => (host.to_string(), url.path().trim_start_matches('/').to_string()),
+      None => {
+        let path = url.path().trim_start_matches('/');
+        let (action, rest) = path.split_once('/').unwrap_or((path, ""));
+        (action.to_string(), rest.to_string())
+      }
+    };
+    Some(Self {
+      url: url.to_string(),
+      action,
+      path,
+      params: url.query_pairs().into_owned().collect(),
+    })
+  }
+}
+
+#[derive(Default)]
+pub struct DeepLinkState(Mutex<Pending>);
+
+#[derive(Default)]
+struct Pending {
+  ready: bool,
+  links: Vec<DeepLink>,
+}
+
+/// Registers the scheme where the installer doesn't and starts taking links, including the one
+/// this launch was started with.
+pub fn install(app: &AppHandle) {
+  app.manage(DeepLinkState::default());
+  // AppImages and development builds aren't registered by any installer.
+  let unregistered = cfg!(debug_assertions)
+    || (cfg!(target_os = "linux") && std::env::var_os("APPIMAGE").is_some());
+  if unregistered && !cfg!(target_os = "macos") {
+    if let Err(err) = app.deep_link().register_all() {
+      warn!("failed to register the {SCHEME}:// scheme: {err}");
+    }
+  }
+
+  let open_app = app.clone();
+  app.deep_link().on_open_url(move |event| receive(&open_app, event.urls()));
+  match app.deep_link().get_current() {
+    Ok(Some(urls)) => receive(app, urls),
+    Ok(None) => {}
+    Err(err) => warn!("failed to read the launch deep link: {err}"),
+  }
+
+  // An external backend is up before the shell starts.
+  if app.try_state::<backend::ExternalBackend>().is_some() {
+    flush(app);
+  } else {
+    let ready_app = app.clone();
+    app.once_any("backend-ready", move |_| flush(&ready_app));
+  }
+}
+
+/// Takes the links in a second instance's arguments.
+pub fn handle_args(app: &AppHandle, args: &[String]) {
+  let exe = std::env::args().next().unwrap_or_default();
+  app
+    .deep_link()
+    .handle_cli_arguments(std::iter::once(exe).chain(args.iter().cloned()));
+}
+
+fn receive(app: &AppHandle, urls: Vec<Url>) {
+  let links: Vec<DeepLink> = urls
+    .iter()
+    // Opened files come this way too on macOS; open_files takes those.
+    .filter(|url| url.scheme() != "file")
+    .filter_map(|url| {
+      let link = DeepLink::parse(url);
+      if link.is_none() {
+        warn!("ignoring link {url}, which isn't a {SCHEME}:// link");
+      }
+      link
+    })
+    .collect();
+  if links.is_empty() {
+    return;
+  }
+  info!("opened with {:?}", links.iter().map(|link| &link.url).collect::<Vec<_>>());
+
+  let main_app = app.clone();
+  let _ = app.run_on_main_thread(move || {
+    if let Err(err) = windows::show_main_window(&main_app) {
+      error!("failed to show main window: {err}");
+    }
+  });
+  let Some(state) = app.try_state::<DeepLinkState>() else {
+    return;
+  };
+  let Ok(mut pending) = state.0.lock() else {
+    return;
+  };
+  if pending.ready {
+    drop(pending);
+    emit(app, links);
+  } else {
+    pending.links.extend(links);
+  }
+}
+
+/// Sends the held links, and from now on every link as it comes.
+fn flush(app: &AppHandle) {
+  let Some(state) = app.try_state::<DeepLinkState>() else {
+    return;
+  };
+  let links = match state.0.lock() {
+    Ok(mut pending) => {
+      pending.ready = true;
+      std::mem::take(&mut pending.links)
+    }
+    Err(_) => return,
+  };
+  emit(app, links);
+}
+
+/// Emits each link, except Jump List entries, which open their recent entry.
+fn emit(app: &AppHandle, links: Vec<DeepLink>) {
+  for link in links {
+    if link.action == recents::OPEN_ACTION {
+      match link.params.get("id") {
+        Some(id) => recents::open(app, id),
+        None => warn!("ignoring {}, which names no recent entry", link.url),
+      }
+      continue;
+    }
+    let _ = app.emit_to(windows::MAIN_WINDOW, "deep-link", link);
+  }
+}
@@ -0,0 +1,134 @@
+//! Progress and a running-jobs badge on the Dock icon (macOS) and taskbar button (Windows,
+//! and Unity launchers on Linux). The frontend reports job progress with `set_progress`; the
+//! backend has no progress stream of its own yet, so there is nothing for the shell to follow
+//! while windows are hidden. The badge it can keep up itself, by asking the backend.
+
+use std::time::Duration;
+
+use log::{debug, warn};
+use serde::Deserialize;
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{AppHandle, Listener, Manager};
+
+use crate::{backend, windows};
+
+/// How often running jobs are counted for the badge while every main window is hidden.
+const BADGE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Backend events after which any progress shown is stale.
+const RESET_EVENTS: &[&str] = &["backend-exited", "backend-stopped"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressState {
+  None,
+  Normal,
+  Indeterminate,
+}
+
+/// Clears the indicators whenever the backend goes away, since its jobs went with it, and
+/// keeps the badge up to date while no main window is visible.
+pub fn install(app: &AppHandle) {
+  for event in RESET_EVENTS {
+    let app_handle = app.clone();
+    app.listen_any(*event, move |_| {
+      if let Err(err) = show_progress(&app_handle, ProgressState::None, None) {
+        warn!("failed to clear progress: {err}");
+      }
+      if let Err(err) = show_badge(&app_handle, 0) {
+        warn!("failed to clear badge: {err}");
+      }
+    });
+  }
+  tauri::async_runtime::spawn(poll_badge(app.clone()));
+}
+
+/// Shows `state` on the main window's Dock icon or taskbar button. `value` is the fraction
+/// done, 0.0 to 1.0, and only matters for `normal`.
+#[tauri::command]
+pub fn set_progress(
+  app: AppHandle,
+  state: ProgressState,
+  value: Option<f64>,
+) -> Result<(), String> {
+  show_progress(&app, state, value).map_err(|err| format!("failed to set progress: {err}"))
+}
+
+fn show_progress(app: &AppHandle, state: ProgressState, value: Option<f64>) -> tauri::Result<()> {
+  let Some(window) = app.get_webview_window(windows::MAIN_WINDOW) else {
+    return Ok(());
+  };
+  let status = match state {
+    ProgressState::None => ProgressBarStatus::None,
+    ProgressState::Normal => ProgressBarStatus::Normal,
+    ProgressState::Indeterminate => ProgressBarStatus::Indeterminate,
+  };
+  let progress = match state {
+    ProgressState::Normal => value
+      .filter(|value| value.is_finite())
+      .map(|value| (value.clamp(0.0, 1.0) * 100.0).round() as u64),
+    _ => None,
+  };
+  window.set_progress_bar(ProgressBarState {
+    status: Some(status),
+    progress,
+  })
+}
+
+/// Shows `count` on the main window's Dock icon, or clears it at zero. Windows has no badge
+/// count, so there this does nothing.
+#[tauri::command]
+pub fn set_badge_count(app: AppHandle, count: u32) -> Result<(), String> {
+  show_badge(&app, count).map_err(|err| format!("failed to set badge: {err}"))
+}
+
+fn show_badge(app: &AppHandle, count: u32) -> tauri::Result<()> {
+  if cfg!(windows) {
+    return Ok(());
+  }
+  let Some(window) = app.get_webview_window(windows::MAIN_WINDOW) else {
+    return Ok(());
+  };
+  window.set_badge_count((count > 0).then_some(i64::from(count)))
+}
+
+/// While every main window is hidden, shows the backend's running job count on the badge.
+/// Polling stops as soon as one is visible again, and the badge it set is cleared, since the
+/// frontend shows the jobs itself from then on.
+async fn poll_badge(app: AppHandle) {
+  let mut polled = false;
+  let mut failing = false;
+  loop {
+    tokio::time::sleep(BADGE_POLL_INTERVAL).await;
+    // The log viewer alone doesn't show jobs, so only main windows count here.
+    let visible = windows::visibility::is_visible(&app)
+      && windows::main_style_windows(&app)
+        .iter()
+        .any(|window| window.is_visible().unwrap_or(false));
+    if visible {
+      if polled {
+        polled = false;
+        let _ = show_badge(&app, 0);
+      }
+      continue;
+    }
+    let count = match backend::fetch_active_jobs(&app).await {
+      Ok(count) => {
+        failing = false;
+        count
+      }
+      Err(err) => {
+        // A backend without the endpoint would otherwise log this every few seconds.
+        if !failing {
+          debug!("badge job count unavailable: {err}");
+          failing = true;
+        }
+        0
+      }
+    };
+    polled = true;
+    if let Err(err) = show_badge(&app, u32::try_from(count).unwrap_or(u32::MAX)) {
+      warn!("failed to set badge: {err}");
+    }
+  }
+}
@@ -0,0 +1,201 @@
+//! Saving exported results where the user chooses. Downloads in the webview land in a temp
+//! directory nobody can find, so the frontend asks the shell instead: a save dialog filtered to
+//! the export's formats, opening where the last export of that format went, and then either a
+//! file the backend produced is copied there or the frontend's bytes are written. Completion is
+//! announced as `export-complete`, with `reveal_exported_file` for the follow-up.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use base64::Engine;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::{backend, config, platform};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+  Csv,
+  Parquet,
+  Xlsx,
+  Json,
+}
+
+impl ExportFormat {
+  fn name(self) -> &'static str {
+    match self {
+      Self::Csv => "CSV",
+      Self::Parquet => "Parquet",
+      Self::Xlsx => "Excel workbook",
+      Self::Json => "JSON",
+    }
+  }
+
+  fn extension(self) -> &'static str {
+    match self {
+      Self::Csv => "csv",
+      Self::Parquet => "parquet",
+      Self::Xlsx => "xlsx",
+      Self::Json => "json",
+    }
+  }
+
+  /// The key its last directory is kept under in `export.last_directories`.
+  fn key(self) -> String {
+    self.extension().to_string()
+  }
+
+  fn of(path: &Path) -> Option<Self> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    [Self::Csv, Self::Parquet, Self::Xlsx, Self::Json]
+      .into_iter()
+      .find(|format| format.extension() == extension)
+  }
+}
+
+/// What to save.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ExportSource {
+  /// A file the backend wrote, in the temp directory or the data root. It's copied, and with
+  /// `delete_after` removed once it has been.
+  #[serde(rename_all = "camelCase")]
+  File {
+    path: PathBuf,
+    #[serde(default)]
+    delete_after: bool,
+  },
+  /// Text as is, e.g. CSV or JSON the frontend built.
+  Text { text: String },
+  /// Base64-encoded bytes.
+  Bytes { base64: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportComplete {
+  path: PathBuf,
+  format: Option<ExportFormat>,
+  size_bytes: u64,
+}
+
+/// Asks where to save the export and saves it there, returning the path it went to, or `None`
+/// if the dialog was cancelled. The first of `filters` is the one the dialog starts with.
+#[tauri::command]
+pub async fn export_file(
+  app: AppHandle,
+  suggested_name: String,
+  filters: Vec<ExportFormat>,
+  source: ExportSource,
+) -> Result<Option<PathBuf>, String> {
+  tauri::async_runtime::spawn_blocking(move || {
+    let Some(target) = ask_for_target(&app, &suggested_name, &filters)? else {
+      return Ok(None);
+    };
+    let size_bytes = write(&app, source, &target)?;
+    let format = ExportFormat::of(&target);
+    info!("exported {size_bytes} bytes to {:?}", target);
+    if let Some(format) = format {
+      remember_directory(&app, format, &target);
+    }
+    let _ = app.emit(
+      "export-complete",
+      ExportComplete {
+        path: target.clone(),
+        format,
+        size_bytes,
+      },
+    );
+    Ok(Some(target))
+  })
+  .await
+  .map_err(|err| err.to_string())?
+  .map_err(|err: anyhow::Error| format!("{err:#}"))
+}
+
+/// Shows an exported file in Finder or Explorer.
+#[tauri::command]
+pub fn reveal_exported_file(path: PathBuf) -> Result<(), String> {
+  if !path.exists() {
+    return Err(format!("{} no longer exists", path.display()));
+  }
+  platform::reveal(&path).map_err(|err| format!("failed to reveal {}: {err}", path.display()))
+}
+
+fn ask_for_target(
+  app: &AppHandle,
+  suggested_name: &str,
+  filters: &[ExportFormat],
+) -> anyhow::Result<Option<PathBuf>> {
+  let mut dialog = app
+    .dialog()
+    .file()
+    .set_title("Export")
+    .set_file_name(suggested_name);
+  for format in filters {
+    dialog = dialog.add_filter(format.name(), &[format.extension()]);
+  }
+  let remembered = filters
+    .first()
+    .and_then(|format| config::saved(app).export.last_directories.remove(&format.key()))
+    .filter(|dir| dir.is_dir());
+  if let Some(dir) = remembered.or_else(|| app.path().download_dir().ok()) {
+    dialog = dialog.set_directory(dir);
+  }
+  let Some(path) = dialog.blocking_save_file() else {
+    return Ok(None);
+  };
+  let mut path = path
+    .into_path()
+    .context("save dialog returned an unusable path")?;
+  // Not every platform's dialog adds the filter's extension.
+  if path.extension().is_none() {
+    if let Some(format) = filters.first() {
+      path.set_extension(format.extension());
+    }
+  }
+  Ok(Some(path))
+}
+
+/// Saves `source` at `target`, returning its size.
+fn write(app: &AppHandle, source: ExportSource, target: &Path) -> anyhow::Result<u64> {
+  let failed = || format!("failed to write {}", target.display());
+  match source {
+    ExportSource::File { path, delete_after } => {
+      let path = backend::scratch_file(app, &path).map_err(anyhow::Error::msg)?;
+      let size = std::fs::copy(&path, target).with_context(failed)?;
+      if delete_after {
+        if let Err(err) = std::fs::remove_file(&path) {
+          warn!("failed to remove {}: {err}", path.display());
+        }
+      }
+      Ok(size)
+    }
+    ExportSource::Text { text } => {
+      std::fs::write(target, &text).with_context(failed)?;
+      Ok(text.len() as u64)
+    }
+    ExportSource::Bytes { base64 } => {
+      let bytes = base64::engine::general_purpose::STANDARD
+        .decode(base64)
+        .context("export data isn't valid base64")?;
+      std::fs::write(target, &bytes).with_context(failed)?;
+      Ok(bytes.len() as u64)
+    }
+  }
+}
+
+fn remember_directory(app: &AppHandle, format: ExportFormat, target: &Path) {
+  let Some(dir) = target.parent() else {
+    return;
+  };
+  let saved = config::update(app, |config| {
+    config.export.last_directories.insert(format.key(), dir.to_path_buf());
+  });
+  if let Err(err) = saved {
+    warn!("failed to remember the export directory: {err:#}");
+  }
+}
@@ -1,84 +1,233 @@
-use tauri::{Manager, WebviewUrl, WebviewWindowBuilder};
-#[cfg(target_os = "macos")]
-use tauri::TitleBarStyle;
+use tauri::Manager;
+
+mod audit;
+mod autostart;
+mod backend;
+mod clipboard;
+mod config;
+mod data_folder;
+mod deep_link;
+mod diagnostics;
+mod dock;
+mod export;
+mod logs;
+mod menu;
+mod notifications;
+mod open_files;
+mod panic_report;
+mod platform;
+mod portable;
+mod power;
+mod proxy;
+mod quit;
+mod recents;
+mod secrets;
+mod shortcut;
+mod tray;
+mod updates;
+mod walk;
+mod windows;
+
+/// Backend to attach to instead of spawning one, from `--backend-url` or `PLUTODUCK_BACKEND_URL`.
+fn external_backend_url() -> Option<String> {
+  arg_value("--backend-url").or_else(|| {
+    std::env::var("PLUTODUCK_BACKEND_URL")
+      .ok()
+      .filter(|url| !url.trim().is_empty())
+  })
+}
+
+/// The value of `--flag value` or `--flag=value` on the command line.
+fn arg_value(flag: &str) -> Option<String> {
+  let mut args = std::env::args().skip(1);
+  while let Some(arg) = args.next() {
+    if arg == flag {
+      return args.next();
+    }
+    if let Some(value) = arg.strip_prefix(flag).and_then(|rest| rest.strip_prefix('=')) {
+      return Some(value.to_string());
+    }
+  }
+  None
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+  panic_report::install();
+  let external_backend = external_backend_url();
+
   tauri::Builder::default()
+    .plugin(autostart::plugin())
+    .plugin(tauri_plugin_clipboard_manager::init())
+    .plugin(tauri_plugin_deep_link::init())
     .plugin(tauri_plugin_dialog::init())
     .plugin(tauri_plugin_process::init())
-    .plugin(tauri_plugin_updater::Builder::new().build())
-    .setup(|app| {
-      if let Err(err) = backend::launch(app) {
-        log::error!("backend launch failed: {err:?}");
-        eprintln!("backend launch failed: {err:?}");
-      }
-      if cfg!(debug_assertions) {
-        app.handle().plugin(
-          tauri_plugin_log::Builder::default()
-            .level(log::LevelFilter::Info)
-            .build(),
-        )?;
+    .plugin(
+      tauri_plugin_global_shortcut::Builder::new()
+        .with_handler(shortcut::handle)
+        .build(),
+    )
+    .invoke_handler(tauri::generate_handler![
+      audit::get_audit_log,
+      autostart::get_autostart,
+      autostart::set_autostart,
+      backend::backend_status,
+      backend::backup::backup_data,
+      backend::backup::restore_data,
+      backend::data_root::set_data_root,
+      backend::get_backend_error_counts,
+      backend::get_backend_url,
+      backend::get_recent_backend_output,
+      backend::list_crash_reports,
+      backend::profiles::create_profile,
+      backend::profiles::list_profiles,
+      backend::profiles::switch_profile,
+      backend::reset::reset_app_data,
+      backend::restart_backend,
+      backend::start_backend,
+      backend::stop_backend,
+      backend::storage::get_storage_info,
+      backend::usage::cancel_data_dir_usage,
+      backend::usage::get_data_dir_usage,
+      clipboard::copy_to_clipboard,
+      config::get_shell_config,
+      config::set_shell_config,
+      data_folder::pick_directory,
+      data_folder::validate_data_folder,
+      diagnostics::export_diagnostics,
+      dock::set_badge_count,
+      dock::set_progress,
+      export::export_file,
+      export::reveal_exported_file,
+      logs::get_backend_logs,
+      logs::get_log_sessions,
+      logs::get_logs_disk_usage,
+      logs::open_logs_folder,
+      notifications::notify,
+      power::allow_sleep,
+      power::get_power_state,
+      power::prevent_sleep,
+      proxy::test_proxy,
+      quit::quit_app,
+      recents::add_recent,
+      recents::clear_recents,
+      recents::get_recents,
+      secrets::secret_delete,
+      secrets::secret_get,
+      secrets::secret_set,
+      shortcut::set_global_shortcut,
+      shortcut::set_palette_shortcut,
+      updates::check_for_updates,
+      updates::get_update_channel,
+      updates::install_update,
+      updates::notes::get_release_notes,
+      updates::offline::install_update_from_file,
+      updates::set_update_channel,
+      updates::skip_update_version,
+      updates::snooze_update,
+      windows::force_reload_window,
+      windows::navigation::open_external,
+      windows::open_new_window,
+      windows::open_utility_window,
+      windows::palette::dismiss_palette,
+      windows::palette::submit_palette,
+      windows::reload_window,
+      windows::set_always_on_top,
+      windows::set_close_behavior,
+      windows::show_log_viewer,
+      windows::theme::set_theme_override,
+      windows::titlebar::set_titlebar_height,
+      windows::toggle_devtools,
+      windows::window_close,
+      windows::window_minimize,
+      windows::window_toggle_maximize,
+      windows::zoom_in,
+      windows::zoom_out,
+      windows::zoom_reset
+    ])
+    .register_uri_scheme_protocol(windows::splash::SCHEME, windows::splash::serve)
+    .register_uri_scheme_protocol(windows::fallback::SCHEME, windows::fallback::serve)
+    .menu(menu::build)
+    .on_menu_event(menu::handle_event)
+    .on_window_event(|window, event| {
+      menu::handle_window_event(window, event);
+      windows::visibility::handle_window_event(window, event);
+      windows::file_drop::handle_window_event(window, event);
+    })
+    .setup(move |app| {
+      let shell_config = config::load(app.handle());
+      // Install the logger first so a failed backend launch ends up in the log file.
+      app.handle().plugin(logs::shell_log_plugin(&shell_config.logs))?;
+      if let Some(warning) = logs::set_level(shell_config.logs.level.as_deref()) {
+        log::warn!("{warning}");
       }
-      
-      // Get or create main window
-      let window = if let Some(existing) = app.get_webview_window("main") {
-        existing
-      } else {
-        let mut window_builder = WebviewWindowBuilder::new(app, "main", WebviewUrl::default())
-          .title("Pluto Duck")
-          .inner_size(1400.0, 900.0)
-          .resizable(true);
-
-        #[cfg(target_os = "macos")]
-        {
-          window_builder = window_builder
-            .hidden_title(true)
-            .title_bar_style(TitleBarStyle::Overlay);
-        }
-
-        window_builder.build()?
-      };
-
-      // Apply macOS native titlebar customizations
-      #[cfg(target_os = "macos")]
-      {
-        use cocoa::appkit::{NSColor, NSWindow, NSWindowTitleVisibility};
-        use cocoa::base::{id, nil, NO, YES};
-
-        if let Ok(ns_window) = window.ns_window() {
-          let ns_window = ns_window as id;
-          unsafe {
-            ns_window.setTitlebarAppearsTransparent_(YES);
-            ns_window.setOpaque_(NO);
-            ns_window.setBackgroundColor_(NSColor::clearColor(nil));
-            ns_window.setTitleVisibility_(NSWindowTitleVisibility::NSWindowTitleHidden);
-          }
-        }
-
-        // Ensure the system knows our desired titlebar height without per-resize tweaking
-        #[allow(unused_must_use)]
-        {
-          apply_titlebar_accessory(&window, 40.0);
-          // apply_unified_toolbar(&window);  // 방법 2: Toolbar 제거로 separator 해결 시도
+      // Updates replace the installed app in place, which a portable copy isn't.
+      match portable::root() {
+        Some(root) => log::warn!("portable mode, data in {:?}; updates are disabled", root),
+        None => {
+          app.handle().plugin(tauri_plugin_updater::Builder::new().build())?;
         }
       }
+      app.manage(windows::CloseBehaviorState::new(
+        shell_config.window.close_behavior,
+      ));
+      app.manage(windows::theme::ThemeState::new(shell_config.window.theme));
+      app.manage(updates::UpdateChannelState::new(shell_config.updates.channel));
+      app.manage(updates::UpdateState::load(app.handle()));
+      app.manage(quit::QuitGuard::new(shell_config.window.confirm_quit));
+      app.manage(config::SavedConfig::new(shell_config.clone()));
+      app.manage(shell_config);
+      config::watch::start(app.handle());
+      data_folder::restore_access(app.handle());
+      app.manage(backend::profiles::select(app.handle(), arg_value("--profile")));
+      panic_report::set_data_root(
+        &backend::data_root::configured_root(app.handle()),
+        app.package_info().version.to_string(),
+      );
+      app.manage(windows::ZoomLevels::default());
+      app.manage(windows::visibility::AppVisibility::default());
+      app.manage(windows::bootstrap::BootstrapState::default());
+      app.manage(power::PowerState::default());
+      app.manage(notifications::NotificationState::default());
+      app.manage(backend::usage::DataUsageState::default());
+
+      // The first run picks a data root and relaunches with it.
+      if external_backend.is_none() && backend::ask_for_data_root(app.handle()) {
+        return Ok(());
+      }
 
-      // Suppress unused variable warning on non-macOS
-      let _ = &window;
+      // A second launch hands its arguments to the first and leaves before spawning a backend.
+      if !backend::claim_single_instance(app.handle()) {
+        std::process::exit(0);
+      }
 
-      // Handle window close event (hide instead of quit) for all windows
-      for (_, window) in app.webview_windows() {
-        let window_clone = window.clone();
-        window.on_window_event(move |event| {
-          if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-            // Hide window instead of closing the app
-            api.prevent_close();
-            let _ = window_clone.hide();
-          }
-        });
+      let hidden = autostart::install(app.handle());
+      // An external backend is already up, so there's nothing to wait for.
+      let splash = !hidden && external_backend.is_none() && windows::splash::open(app.handle());
+      windows::fallback::install(app.handle());
+      // Attaches to an external backend here; a managed one launches in the background.
+      let launched = backend::launch(app.handle(), external_backend);
+      updates::check_after_launch(app.handle());
+      deep_link::install(app.handle());
+      open_files::install(app.handle());
+      recents::install(app.handle());
+
+      // Built hidden; the splash reveals them once the backend is ready.
+      windows::session::restore(app.handle())?;
+      let tray = tray::install(app.handle());
+      // Starting hidden needs the tray to bring the windows back from.
+      if !splash && (!hidden || !tray) {
+        windows::session::show(app.handle())?;
       }
-      
+      // Reported once the windows exist, so a failure dialog has one to belong to.
+      if let Err(err) = launched {
+        backend::launch_failed(app.handle(), &err);
+      }
+      dock::install(app.handle());
+      notifications::jobs::install(app.handle());
+      power::install(app.handle());
+      shortcut::register_configured(app.handle());
+
       Ok(())
     })
     .build(tauri::generate_context!())
@@ -89,208 +238,52 @@ pub fn run() {
           log::info!("App is ready");
         }
         #[cfg(target_os = "macos")]
+        tauri::RunEvent::Opened { urls } => {
+          open_files::handle_urls(app_handle, &urls);
+        }
+        #[cfg(target_os = "macos")]
         tauri::RunEvent::Reopen { has_visible_windows, .. } => {
           log::info!("App reopen event - has_visible_windows: {}", has_visible_windows);
           if !has_visible_windows {
-            // Show all windows when app is activated from Dock
-            for (_, window) in app_handle.webview_windows() {
-              let _ = window.show();
-              let _ = window.set_focus();
+            // Bring back the main windows when app is activated from Dock; secondary windows
+            // like the log viewer stay hidden until asked for. A main window that was destroyed
+            // rather than hidden is rebuilt, with its titlebar and close handling.
+            if let Err(err) = windows::show_main_style_windows(app_handle) {
+              log::error!("failed to show main windows: {err}");
             }
+            windows::visibility::refresh(app_handle);
           }
         }
+        tauri::RunEvent::ExitRequested { code, api, .. } => {
+          quit::on_exit_requested(app_handle, code, &api);
+        }
         tauri::RunEvent::Exit => {
           log::info!("App is exiting - cleaning up backend");
-          if let Some(state) = app_handle.try_state::<backend::BackendState>() {
-            if let Ok(mut guard) = state.lock() {
-              if let Some(mut child) = guard.take() {
-                log::info!("Killing backend process on exit...");
-                let _ = child.kill();
-                let _ = child.wait();
-                log::info!("Backend process killed on exit");
-              }
-            }
-          }
+          clean_up_before_exit(app_handle);
         }
         _ => {}
       }
     });
 }
 
-#[cfg(target_os = "macos")]
-fn apply_titlebar_accessory(window: &tauri::WebviewWindow, height: f64) {
-  use cocoa::appkit::NSView;
-  use cocoa::base::{id, nil, YES};
-  use cocoa::foundation::{NSPoint, NSRect, NSSize};
-  use objc::{class, msg_send, sel, sel_impl};
-
-  if let Ok(ns_window) = window.ns_window() {
-    let ns_window = ns_window as id;
-    unsafe {
-      let accessory: id = msg_send![class!(NSTitlebarAccessoryViewController), new];
-      let view: id = NSView::alloc(nil).initWithFrame_(NSRect::new(
-        NSPoint::new(0.0, 0.0),
-        NSSize::new(1.0, height),
-      ));
-      let _: () = msg_send![view, setWantsLayer: YES];
-      // Transparent accessory; only height matters for layout
-      let _: () = msg_send![view, setAlphaValue: 0.0f64];
-
-      let _: () = msg_send![accessory, setView: view];
-      // Add accessory so AppKit derives titlebar height from its view
-      let _: () = msg_send![ns_window, addTitlebarAccessoryViewController: accessory];
+/// Saves window state and shuts the backend down gracefully. Runs at `RunEvent::Exit`, and
+/// before an update installer ends the process without one.
+fn clean_up_before_exit(app_handle: &tauri::AppHandle) {
+  windows::session::save(app_handle);
+  for (label, window) in app_handle.webview_windows() {
+    if label != windows::SPLASH_WINDOW && label != windows::PALETTE_WINDOW {
+      windows::geometry::save(&window);
     }
   }
-}
-
-#[cfg(target_os = "macos")]
-fn apply_unified_toolbar(window: &tauri::WebviewWindow) {
-  use cocoa::base::{id, nil, NO, YES, BOOL};
-  use cocoa::foundation::NSString;
-  use objc::{class, msg_send, sel, sel_impl};
-
-  if let Ok(ns_window) = window.ns_window() {
-    let ns_window = ns_window as id;
-    unsafe {
-      // Create NSToolbar with an identifier
-      let identifier = NSString::alloc(nil).init_str("PlutoDuckToolbar");
-      let toolbar: id = msg_send![class!(NSToolbar), alloc];
-      let toolbar: id = msg_send![toolbar, initWithIdentifier: identifier];
-
-      // Optional cosmetic adjustments
-      let _: () = msg_send![toolbar, setShowsBaselineSeparator: NO];
-      // Small size mode (1). Default is 0. This helps lower the baseline.
-      let _: () = msg_send![toolbar, setSizeMode: 1u64];
-
-      // Attach toolbar to window
-      let _: () = msg_send![ns_window, setToolbar: toolbar];
-
-      // Try to center/compact further by setting toolbar style when available.
-      // We avoid hardcoding NSWindowToolbarStyle enums to keep compatibility.
-      // If the selector exists, set to UnifiedCompact (commonly = 5) as a best-effort.
-      let sel_toolbarStyle = sel!(setToolbarStyle:);
-      let responds: BOOL = msg_send![ns_window, respondsToSelector: sel_toolbarStyle];
-      if responds == YES {
-        let unified_compact: u64 = 8; // NSWindowToolbarStyleUnifiedCompact (best-effort)
-        let _: () = msg_send![ns_window, setToolbarStyle: unified_compact];
-      }
+  shortcut::unregister_all(app_handle);
+  power::release_all(app_handle);
+  backend::cancel_launch(app_handle);
+  if let Some(process) = app_handle.try_state::<backend::BackendProcess>() {
+    if !process.shutdown() {
+      log::error!("the backend may still be running after exit cleanup");
     }
   }
-}
-
-mod backend {
-  use std::path::PathBuf;
-  use std::process::{Child, Command, Stdio};
-  use std::sync::{Arc, Mutex};
-
-  use anyhow::{Context, Result};
-  use log::{error, info};
-  use tauri::{App, AppHandle, Manager};
-
-  const BACKEND_BINARY_DEBUG: &str = "../../dist/pluto-duck-backend/pluto-duck-backend";
-  const BACKEND_RESOURCE_PATH: &str = "_up_/_up_/dist/pluto-duck-backend/pluto-duck-backend";
-  const BACKEND_PORT: u16 = 8123;
-
-  struct BackendProcess(Arc<Mutex<Option<Child>>>);
-
-  impl Drop for BackendProcess {
-    fn drop(&mut self) {
-      info!("BackendProcess dropping - killing backend");
-      if let Ok(mut guard) = self.0.lock() {
-        if let Some(mut child) = guard.take() {
-          info!("Killing backend process...");
-          let _ = child.kill();
-          let _ = child.wait();
-          info!("Backend process killed");
-        }
-      }
-    }
+  if let Some(lock) = app_handle.try_state::<backend::DataLock>() {
+    lock.release();
   }
-
-  pub type BackendState = Arc<Mutex<Option<Child>>>;
-
-  pub fn launch(app: &mut App) -> Result<()> {
-    let app_handle = app.handle();
-    let binary = backend_binary_path(app)?;
-    let data_root = resolve_data_root(&app_handle);
-
-    info!(
-      "launching backend binary {:?} with data root {:?}",
-      binary,
-      data_root
-    );
-
-    let log_dir = data_root.join("logs");
-    std::fs::create_dir_all(&log_dir).context("failed to create log directory")?;
-    let stdout_log = std::fs::File::create(log_dir.join("backend-stdout.log"))
-      .context("failed to create stdout log")?;
-    let stderr_log = std::fs::File::create(log_dir.join("backend-stderr.log"))
-      .context("failed to create stderr log")?;
-
-    let mut command = Command::new(&binary);
-    if let Some(parent) = binary.parent() {
-      command.current_dir(parent);
-    }
-    command
-      .env("PLUTODUCK_DATA_DIR__ROOT", &data_root)
-      .args([
-        "--port",
-        &BACKEND_PORT.to_string(),
-        "--data-root",
-        data_root.to_string_lossy().as_ref(),
-      ])
-      .stdout(Stdio::from(stdout_log))
-      .stderr(Stdio::from(stderr_log));
-
-    let child = command.spawn().context("failed to spawn backend process")?;
-    let state: BackendState = Arc::new(Mutex::new(Some(child)));
-    let process_wrapper = BackendProcess(state.clone());
-
-    app.manage(state);
-    app.manage(process_wrapper);
-
-    info!(
-      "backend process spawned on http://127.0.0.1:{BACKEND_PORT} with data root {:?}",
-      data_root
-    );
-    info!("backend health will be checked by frontend polling");
-
-    Ok(())
-  }
-
-
-  fn backend_binary_path(app: &App) -> Result<PathBuf> {
-    let path = if cfg!(debug_assertions) {
-      PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .join(BACKEND_BINARY_DEBUG)
-    } else {
-      app
-        .path()
-        .resource_dir()
-        .context("resource directory unavailable")?
-        .join(BACKEND_RESOURCE_PATH)
-    };
-    if !path.exists() {
-      anyhow::bail!("backend binary not found at {}", path.display());
-    }
-    Ok(path)
-  }
-
-  fn resolve_data_root(app: &AppHandle) -> PathBuf {
-    let base = if cfg!(debug_assertions) {
-      PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../.dev-data")
-    } else {
-      app
-        .path()
-        .app_data_dir()
-        .unwrap_or_else(|_| std::env::temp_dir().join("pluto_duck"))
-    };
-    let root = base.join("backend");
-    let logs = root.join("logs");
-    if let Err(err) = std::fs::create_dir_all(&logs) {
-      error!("failed to create backend data directories: {err}");
-    }
-    root
-  }
-
 }
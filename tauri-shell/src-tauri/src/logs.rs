@@ -0,0 +1,498 @@
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use log::{info, warn, LevelFilter};
+use serde::{Deserialize, Serialize};
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
+
+use crate::config::LogConfig;
+use crate::walk::dir_size;
+
+pub mod combined;
+
+pub const STDOUT_LOG: &str = "backend-stdout.log";
+pub const STDERR_LOG: &str = "backend-stderr.log";
+
+const TAIL_CHUNK: u64 = 8 * 1024;
+const MAX_TAIL_LINES: u32 = 5000;
+const SESSION_DIR_FORMAT: &str = "%Y-%m-%dT%H-%M-%S";
+const SESSION_FILE: &str = "session.json";
+pub const SHELL_LOG_NAME: &str = "pluto-duck-shell";
+const LOG_LEVEL_ENV: &str = "PLUTODUCK_LOG";
+
+/// Returns up to the last `count` lines of the file at `path`, oldest first.
+///
+/// Reads backwards from the end in fixed-size chunks so large logs are never loaded whole.
+pub fn tail_lines(path: &Path, count: usize) -> std::io::Result<Vec<String>> {
+  let mut file = File::open(path)?;
+  let len = file.metadata()?.len();
+  if count == 0 || len == 0 {
+    return Ok(Vec::new());
+  }
+
+  let mut buffer: Vec<u8> = Vec::new();
+  let mut offset = len;
+  // One extra newline is needed to be sure the earliest wanted line is complete.
+  while offset > 0 && buffer.iter().filter(|byte| **byte == b'\n').count() <= count {
+    let read = TAIL_CHUNK.min(offset);
+    offset -= read;
+    let mut chunk = vec![0; read as usize];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut chunk)?;
+    chunk.extend_from_slice(&buffer);
+    buffer = chunk;
+  }
+
+  let text = String::from_utf8_lossy(&buffer);
+  let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+  if offset > 0 && !lines.is_empty() {
+    // The first line is only a fragment when we stopped reading mid-file.
+    lines.remove(0);
+  }
+  let skip = lines.len().saturating_sub(count);
+  Ok(lines.split_off(skip))
+}
+
+/// Size- and count-based rotation limits for a log file.
+#[derive(Debug, Clone, Copy)]
+pub struct Rotation {
+  pub max_bytes: u64,
+  /// How many rotated files (`name.1` … `name.N`) to keep besides the current one.
+  pub keep: usize,
+}
+
+/// Shifts `name.1` … `name.N-1` up by one, moves the current contents of `path` to `name.1`,
+/// and empties `path`.
+///
+/// The current file is copied and truncated in place rather than renamed, so a process that
+/// has it open in append mode keeps writing to `path`.
+pub fn rotate(path: &Path, keep: usize) -> std::io::Result<()> {
+  if !path.exists() {
+    return Ok(());
+  }
+  if keep > 0 {
+    remove_if_exists(&rotated_path(path, keep))?;
+    for index in (1..keep).rev() {
+      let from = rotated_path(path, index);
+      if from.exists() {
+        std::fs::rename(&from, rotated_path(path, index + 1))?;
+      }
+    }
+    std::fs::copy(path, rotated_path(path, 1))?;
+  }
+  OpenOptions::new().write(true).open(path)?.set_len(0)
+}
+
+/// Rotates `path` if it has grown past `rotation.max_bytes`. Returns whether it rotated.
+pub fn rotate_if_oversized(path: &Path, rotation: Rotation) -> std::io::Result<bool> {
+  let len = match std::fs::metadata(path) {
+    Ok(metadata) => metadata.len(),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+    Err(err) => return Err(err),
+  };
+  if len <= rotation.max_bytes {
+    return Ok(false);
+  }
+  rotate(path, rotation.keep)?;
+  Ok(true)
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+  let mut name = OsString::from(path.as_os_str());
+  name.push(format!(".{index}"));
+  PathBuf::from(name)
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+  match std::fs::remove_file(path) {
+    Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+    _ => Ok(()),
+  }
+}
+
+/// Contents of `session.json`, written into each session's log directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+  pub started_at: DateTime<Local>,
+  pub app_version: String,
+  pub binary: PathBuf,
+  pub port: u16,
+}
+
+/// A session directory under the logs root, as listed by `get_log_sessions`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogSession {
+  /// Directory name, e.g. `2024-06-01T10-33-12`.
+  id: String,
+  path: PathBuf,
+  /// Milliseconds since the Unix epoch.
+  started_at: i64,
+  /// `None` if the session's `session.json` is missing or unreadable.
+  info: Option<SessionInfo>,
+}
+
+/// Creates `root/<local timestamp>` for this run of the app.
+pub fn create_session_dir(root: &Path, started_at: DateTime<Local>) -> std::io::Result<PathBuf> {
+  let dir = root.join(started_at.format(SESSION_DIR_FORMAT).to_string());
+  std::fs::create_dir_all(&dir)?;
+  Ok(dir)
+}
+
+pub fn write_session_info(dir: &Path, info: &SessionInfo) -> std::io::Result<()> {
+  let bytes = serde_json::to_vec_pretty(info).map_err(std::io::Error::other)?;
+  std::fs::write(dir.join(SESSION_FILE), bytes)
+}
+
+/// Session directories under `root`, newest first. Anything not named like a session is ignored.
+pub fn list_sessions(root: &Path) -> std::io::Result<Vec<LogSession>> {
+  let mut sessions = Vec::new();
+  for entry in std::fs::read_dir(root)? {
+    let entry = entry?;
+    if !entry.file_type()?.is_dir() {
+      continue;
+    }
+    let id = entry.file_name().to_string_lossy().into_owned();
+    let Ok(started) = NaiveDateTime::parse_from_str(&id, SESSION_DIR_FORMAT) else {
+      continue;
+    };
+    let path = entry.path();
+    let info = std::fs::read(path.join(SESSION_FILE))
+      .ok()
+      .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+    let started_at = started
+      .and_local_timezone(Local)
+      .earliest()
+      .map_or_else(|| started.and_utc().timestamp_millis(), |at| at.timestamp_millis());
+    sessions.push(LogSession {
+      id,
+      path,
+      started_at,
+      info,
+    });
+  }
+  // The directory names sort chronologically.
+  sessions.sort_by(|a, b| b.id.cmp(&a.id));
+  Ok(sessions)
+}
+
+/// Deletes the oldest entries (session directories or stray files) under `root` until its total
+/// size is at most `cap_bytes`. `keep` (the current session) is never removed.
+pub fn enforce_size_cap(root: &Path, cap_bytes: u64, keep: &Path) {
+  let entries = match std::fs::read_dir(root) {
+    Ok(entries) => entries,
+    Err(err) => {
+      warn!("failed to read logs directory {:?}: {err}", root);
+      return;
+    }
+  };
+  let mut candidates: Vec<(std::time::SystemTime, PathBuf, u64)> = Vec::new();
+  let mut total = 0;
+  for entry in entries.flatten() {
+    let path = entry.path();
+    let Ok(kind) = entry.file_type() else {
+      continue;
+    };
+    let size = if kind.is_dir() {
+      dir_size(&path)
+    } else {
+      entry.metadata().map_or(0, |metadata| metadata.len())
+    };
+    total += size;
+    if path != keep {
+      let modified = entry
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .unwrap_or(std::time::UNIX_EPOCH);
+      candidates.push((modified, path, size));
+    }
+  }
+  if total <= cap_bytes {
+    return;
+  }
+
+  candidates.sort_by_key(|(modified, _, _)| *modified);
+  for (_, path, size) in candidates {
+    if total <= cap_bytes {
+      break;
+    }
+    let removed = if path.is_dir() {
+      std::fs::remove_dir_all(&path)
+    } else {
+      std::fs::remove_file(&path)
+    };
+    match removed {
+      Ok(()) => {
+        total = total.saturating_sub(size);
+        info!("removed {:?} ({size} bytes) to keep logs under {cap_bytes} bytes", path);
+      }
+      Err(err) => warn!("failed to remove {:?}: {err}", path),
+    }
+  }
+  if total > cap_bytes {
+    warn!("logs directory is still {total} bytes, over the {cap_bytes} byte cap");
+  }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogsDiskUsage {
+  path: PathBuf,
+  total_bytes: u64,
+  cap_bytes: u64,
+}
+
+/// Sizes up the logs directory off the async runtime, since it walks every session.
+#[tauri::command]
+pub async fn get_logs_disk_usage(app: AppHandle) -> Result<LogsDiskUsage, String> {
+  tauri::async_runtime::spawn_blocking(move || {
+    let path = crate::backend::log_root(&app);
+    LogsDiskUsage {
+      total_bytes: dir_size(&path),
+      cap_bytes: app.state::<crate::config::ShellConfig>().logs.max_total_bytes(),
+      path,
+    }
+  })
+  .await
+  .map_err(|err| err.to_string())
+}
+
+/// Deletes all but the newest `keep` session directories under `root`.
+pub fn prune_sessions(root: &Path, keep: usize) {
+  let sessions = match list_sessions(root) {
+    Ok(sessions) => sessions,
+    Err(err) => {
+      warn!("failed to list log sessions in {:?}: {err}", root);
+      return;
+    }
+  };
+  for session in sessions.into_iter().skip(keep) {
+    if let Err(err) = std::fs::remove_dir_all(&session.path) {
+      warn!("failed to remove old log session {:?}: {err}", session.path);
+    }
+  }
+}
+
+#[tauri::command]
+pub fn get_log_sessions(app: AppHandle) -> Result<Vec<LogSession>, String> {
+  let root = crate::backend::log_root(&app);
+  list_sessions(&root).map_err(|err| format!("failed to list log sessions in {}: {err}", root.display()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+  Stdout,
+  Stderr,
+}
+
+impl LogStream {
+  pub fn name(self) -> &'static str {
+    match self {
+      Self::Stdout => "stdout",
+      Self::Stderr => "stderr",
+    }
+  }
+
+  fn file_name(self) -> &'static str {
+    match self {
+      Self::Stdout => STDOUT_LOG,
+      Self::Stderr => STDERR_LOG,
+    }
+  }
+}
+
+/// Error returned by `get_backend_logs`, tagged so the frontend can tell "nothing logged yet"
+/// apart from real failures.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum LogReadError {
+  /// The shell is attached to an external backend, so it has no logs of its own.
+  NotLaunched,
+  NotFound { path: PathBuf },
+  Io { path: PathBuf, message: String },
+}
+
+/// Returns up to the last `lines` lines (capped at 5000) of this session's backend log.
+#[tauri::command]
+pub fn get_backend_logs(
+  app: AppHandle,
+  stream: LogStream,
+  lines: u32,
+) -> Result<Vec<String>, LogReadError> {
+  let dir = crate::backend::current_log_dir(&app).ok_or(LogReadError::NotLaunched)?;
+  let path = dir.join(stream.file_name());
+  tail_lines(&path, lines.min(MAX_TAIL_LINES) as usize).map_err(|err| {
+    if err.kind() == std::io::ErrorKind::NotFound {
+      LogReadError::NotFound { path }
+    } else {
+      LogReadError::Io {
+        message: err.to_string(),
+        path,
+      }
+    }
+  })
+}
+
+/// Sets the shell's log level from `PLUTODUCK_LOG` or, without it, `configured`. Returns a
+/// warning to log if the level was not understood.
+pub fn set_level(configured: Option<&str>) -> Option<String> {
+  let default_level = if cfg!(debug_assertions) {
+    LevelFilter::Debug
+  } else {
+    LevelFilter::Info
+  };
+  let requested = std::env::var(LOG_LEVEL_ENV)
+    .ok()
+    .filter(|level| !level.trim().is_empty())
+    .or_else(|| configured.map(str::to_string));
+  let (level, warning) = match requested {
+    Some(name) => match name.trim().parse::<LevelFilter>() {
+      Ok(level) => (level, None),
+      Err(_) => (
+        default_level,
+        Some(format!("unknown log level {name:?}; using {default_level}")),
+      ),
+    },
+    None => (default_level, None),
+  };
+  log::set_max_level(level);
+  warning
+}
+
+/// The shell's own logger: a rotating file in the OS log directory, plus stdout in debug builds.
+///
+/// It passes every level through; `set_level`, called once it's installed, picks the one in
+/// effect, so the level can change while running.
+pub fn shell_log_plugin<R: Runtime>(config: &LogConfig) -> TauriPlugin<R> {
+  let file_name = Some(SHELL_LOG_NAME.to_string());
+  let file = match crate::portable::root() {
+    Some(root) => TargetKind::Folder {
+      path: root.join("logs"),
+      file_name,
+    },
+    None => TargetKind::LogDir { file_name },
+  };
+  let mut targets = vec![Target::new(file)];
+  if cfg!(debug_assertions) {
+    targets.push(Target::new(TargetKind::Stdout));
+  }
+  if config.combined {
+    combined::enable();
+    targets.push(Target::new(TargetKind::Dispatch(
+      tauri_plugin_log::fern::Dispatch::new().chain(tauri_plugin_log::fern::Output::call(
+        |record| combined::write(combined::Source::Shell, &record.args().to_string()),
+      )),
+    )));
+  }
+  tauri_plugin_log::Builder::new()
+    .clear_targets()
+    .targets(targets)
+    .level(LevelFilter::Trace)
+    // At lower levels these trace the D-Bus messages that carry secrets (see `secrets`).
+    .level_for("zbus", LevelFilter::Warn)
+    .level_for("secret_service", LevelFilter::Warn)
+    .max_file_size(u128::from(config.max_file_bytes()))
+    .rotation_strategy(RotationStrategy::KeepSome(config.rotated_files.max(1)))
+    .build()
+}
+
+/// Where the shell's own log files go: the app log directory, or the portable data folder.
+pub fn shell_log_dir(app: &AppHandle) -> Option<PathBuf> {
+  match crate::portable::root() {
+    Some(root) => Some(root.join("logs")),
+    None => app.path().app_log_dir().ok(),
+  }
+}
+
+/// Reveals the logs root (all sessions) in the platform file manager.
+pub fn reveal_logs_folder(app: &AppHandle) -> std::io::Result<PathBuf> {
+  let root = crate::backend::log_root(app);
+  std::fs::create_dir_all(&root)?;
+  crate::platform::open_path(&root)?;
+  Ok(root)
+}
+
+#[tauri::command]
+pub fn open_logs_folder(app: AppHandle) -> Result<PathBuf, String> {
+  reveal_logs_folder(&app).map_err(|err| format!("failed to open logs folder: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn session(root: &Path, id: &str) -> PathBuf {
+    let dir = root.join(id);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  #[test]
+  fn tail_lines_returns_the_last_lines_across_chunks() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("log");
+    let text: String = (0..5000).map(|line| format!("line {line}\n")).collect();
+    std::fs::write(&path, text).unwrap();
+
+    assert_eq!(tail_lines(&path, 3).unwrap(), ["line 4997", "line 4998", "line 4999"]);
+    assert_eq!(tail_lines(&path, 2000).unwrap().first().unwrap(), "line 3000");
+    assert!(tail_lines(&path, 0).unwrap().is_empty());
+    assert_eq!(tail_lines(&path, 10_000).unwrap().len(), 5000);
+  }
+
+  #[test]
+  fn rotate_if_oversized_shifts_the_old_files_and_empties_the_current_one() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("backend.log");
+    let rotation = Rotation {
+      max_bytes: 4,
+      keep: 2,
+    };
+    std::fs::write(&path, "abc").unwrap();
+    assert!(!rotate_if_oversized(&path, rotation).unwrap());
+    assert!(!rotate_if_oversized(&dir.path().join("missing.log"), rotation).unwrap());
+
+    for contents in ["first", "second", "third"] {
+      std::fs::write(&path, contents).unwrap();
+      assert!(rotate_if_oversized(&path, rotation).unwrap());
+    }
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+    assert_eq!(std::fs::read_to_string(rotated_path(&path, 1)).unwrap(), "third");
+    assert_eq!(std::fs::read_to_string(rotated_path(&path, 2)).unwrap(), "second");
+    assert!(!rotated_path(&path, 3).exists());
+  }
+
+  #[test]
+  fn prune_sessions_keeps_the_newest_and_leaves_other_entries_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    let oldest = session(dir.path(), "2024-06-01T10-00-00");
+    let middle = session(dir.path(), "2024-06-02T10-00-00");
+    let newest = session(dir.path(), "2024-06-03T10-00-00");
+    let other = session(dir.path(), "not-a-session");
+
+    prune_sessions(dir.path(), 2);
+    assert!(!oldest.exists());
+    assert!(middle.exists() && newest.exists() && other.exists());
+  }
+
+  #[test]
+  fn enforce_size_cap_removes_the_oldest_but_never_the_current_session() {
+    let dir = tempfile::tempdir().unwrap();
+    let old = session(dir.path(), "2024-06-01T10-00-00");
+    std::fs::write(old.join(STDOUT_LOG), vec![0; 600]).unwrap();
+    let current = session(dir.path(), "2024-06-02T10-00-00");
+    std::fs::write(current.join(STDOUT_LOG), vec![0; 600]).unwrap();
+
+    enforce_size_cap(dir.path(), 1000, &current);
+    assert!(!old.exists());
+    enforce_size_cap(dir.path(), 100, &current);
+    assert!(current.exists());
+  }
+}
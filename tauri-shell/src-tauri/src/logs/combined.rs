@@ -0,0 +1,99 @@
+//! Optional `combined.log`: shell log records and backend output interleaved in one file, each
+//! line prefixed with its source and the time since the shell started.
+//!
+//! Shell logging starts before the session directory exists, so lines are held in memory until
+//! [`open`] is called (or dropped if it never is).
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+pub const COMBINED_LOG: &str = "combined.log";
+/// Lines held before the file is opened; anything beyond this is dropped.
+const MAX_PENDING: usize = 1000;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Source {
+  Shell,
+  BackendOut,
+  BackendErr,
+}
+
+impl Source {
+  fn prefix(self) -> &'static str {
+    match self {
+      Self::Shell => "[shell]",
+      Self::BackendOut => "[be-out]",
+      Self::BackendErr => "[be-err]",
+    }
+  }
+}
+
+struct Writer {
+  started: Instant,
+  file: Option<File>,
+  pending: Vec<String>,
+}
+
+static WRITER: OnceLock<Mutex<Writer>> = OnceLock::new();
+
+/// Turns the combined log on. Until this is called, [`write`] does nothing.
+pub fn enable() {
+  WRITER.get_or_init(|| {
+    Mutex::new(Writer {
+      started: Instant::now(),
+      file: None,
+      pending: Vec::new(),
+    })
+  });
+}
+
+/// Starts writing to `dir/combined.log`, flushing anything logged so far.
+pub fn open(dir: &Path) {
+  let Some(writer) = WRITER.get() else {
+    return;
+  };
+  let path = dir.join(COMBINED_LOG);
+  // Don't use `log` here: its records come back through `write` and this lock.
+  let mut file = match OpenOptions::new().create(true).append(true).open(&path) {
+    Ok(file) => file,
+    Err(err) => {
+      eprintln!("failed to open {}: {err}", path.display());
+      return;
+    }
+  };
+  let Ok(mut writer) = writer.lock() else {
+    return;
+  };
+  for line in writer.pending.drain(..) {
+    let _ = file.write_all(line.as_bytes());
+  }
+  writer.file = Some(file);
+}
+
+/// Appends one line. Safe to call from any thread; a no-op unless the combined log is enabled.
+pub fn write(source: Source, line: &str) {
+  let Some(writer) = WRITER.get() else {
+    return;
+  };
+  let Ok(mut guard) = writer.lock() else {
+    return;
+  };
+  let writer = &mut *guard;
+  let elapsed = writer.started.elapsed();
+  let entry = format!(
+    "+{:>10.3}s {} {}\n",
+    elapsed.as_secs_f64(),
+    source.prefix(),
+    line.trim_end_matches(['\r', '\n'])
+  );
+  match writer.file.as_mut() {
+    Some(file) => {
+      let _ = file.write_all(entry.as_bytes());
+    }
+    None if writer.pending.len() < MAX_PENDING => writer.pending.push(entry),
+    None => {}
+  }
+}
@@ -0,0 +1,264 @@
+use log::{error, warn};
+use tauri::menu::{
+  CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu, HELP_SUBMENU_ID,
+  WINDOW_SUBMENU_ID,
+};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow, Window, WindowEvent, Wry};
+
+use crate::windows::{self, UtilityWindow, Zoom};
+use crate::{logs, platform, portable, updates};
+
+const ISSUES_URL: &str = "https://github.com/Fluxloop-AI/pluto-duck-oss/issues/new";
+
+const ABOUT: &str = "about";
+const CHECK_FOR_UPDATES: &str = "check-for-updates";
+const INSTALL_UPDATE_FROM_FILE: &str = "install-update-from-file";
+const PREFERENCES: &str = "preferences";
+const QUIT: &str = "quit";
+const RELOAD: &str = "reload";
+const FORCE_RELOAD: &str = "force-reload";
+const ACTUAL_SIZE: &str = "actual-size";
+const ZOOM_IN: &str = "zoom-in";
+const ZOOM_OUT: &str = "zoom-out";
+const TOGGLE_FULLSCREEN: &str = "toggle-fullscreen";
+const TOGGLE_DEVTOOLS: &str = "toggle-devtools";
+const ALWAYS_ON_TOP: &str = "always-on-top";
+const OPEN_LOGS_FOLDER: &str = "open-logs-folder";
+const SHOW_LOG_VIEWER: &str = "show-log-viewer";
+const REPORT_ISSUE: &str = "report-issue";
+
+/// Menu items the frontend handles: the event named in the second column is sent to the
+/// focused window.
+const FORWARDED: &[(&str, &str)] = &[(CHECK_FOR_UPDATES, "check-for-updates")];
+
+/// The application menu. The Edit menu matters even though the frontend never sees it: on
+/// macOS the clipboard shortcuts only work in the webview when the menu has those roles.
+pub fn build(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+  let about = item(app, ABOUT, "About Pluto Duck", None)?;
+  let check_for_updates = item(app, CHECK_FOR_UPDATES, "Check for Updates…", None)?;
+  // The updater isn't installed in portable mode.
+  check_for_updates.set_enabled(portable::root().is_none())?;
+  let install_from_file = item(app, INSTALL_UPDATE_FROM_FILE, "Install Update from File…", None)?;
+  install_from_file.set_enabled(portable::root().is_none())?;
+  let preferences = item(app, PREFERENCES, "Preferences…", Some("CmdOrCtrl+,"))?;
+  let quit = item(app, QUIT, "Quit Pluto Duck", Some("CmdOrCtrl+Q"))?;
+
+  #[cfg(target_os = "macos")]
+  let first = Submenu::with_items(
+    app,
+    "Pluto Duck",
+    true,
+    &[
+      &about,
+      &PredefinedMenuItem::separator(app)?,
+      &check_for_updates,
+      &install_from_file,
+      &preferences,
+      &PredefinedMenuItem::separator(app)?,
+      &PredefinedMenuItem::services(app, None)?,
+      &PredefinedMenuItem::separator(app)?,
+      &PredefinedMenuItem::hide(app, None)?,
+      &PredefinedMenuItem::hide_others(app, None)?,
+      &PredefinedMenuItem::show_all(app, None)?,
+      &PredefinedMenuItem::separator(app)?,
+      &quit,
+    ],
+  )?;
+  // Without an app menu, these live in File (and About in Help) as is usual on Windows/Linux.
+  #[cfg(not(target_os = "macos"))]
+  let first = Submenu::with_items(
+    app,
+    "File",
+    true,
+    &[
+      &preferences,
+      &check_for_updates,
+      &install_from_file,
+      &PredefinedMenuItem::separator(app)?,
+      &quit,
+    ],
+  )?;
+
+  let edit = Submenu::with_items(
+    app,
+    "Edit",
+    true,
+    &[
+      &PredefinedMenuItem::undo(app, None)?,
+      &PredefinedMenuItem::redo(app, None)?,
+      &PredefinedMenuItem::separator(app)?,
+      &PredefinedMenuItem::cut(app, None)?,
+      &PredefinedMenuItem::copy(app, None)?,
+      &PredefinedMenuItem::paste(app, None)?,
+      &PredefinedMenuItem::select_all(app, None)?,
+    ],
+  )?;
+
+  let fullscreen_shortcut = if cfg!(target_os = "macos") {
+    "Ctrl+Cmd+F"
+  } else {
+    "F11"
+  };
+  let view = Submenu::with_items(
+    app,
+    "View",
+    true,
+    &[
+      &item(app, RELOAD, "Reload", Some("CmdOrCtrl+R"))?,
+      &item(app, FORCE_RELOAD, "Force Reload", Some("CmdOrCtrl+Shift+R"))?,
+      &PredefinedMenuItem::separator(app)?,
+      &item(app, ACTUAL_SIZE, "Actual Size", Some("CmdOrCtrl+0"))?,
+      &item(app, ZOOM_IN, "Zoom In", Some("CmdOrCtrl+="))?,
+      &item(app, ZOOM_OUT, "Zoom Out", Some("CmdOrCtrl+-"))?,
+      &PredefinedMenuItem::separator(app)?,
+      &item(app, TOGGLE_FULLSCREEN, "Toggle Full Screen", Some(fullscreen_shortcut))?,
+      &PredefinedMenuItem::separator(app)?,
+      &item(app, TOGGLE_DEVTOOLS, "Toggle Developer Tools", Some("CmdOrCtrl+Alt+I"))?,
+    ],
+  )?;
+
+  let window = Submenu::with_id_and_items(
+    app,
+    WINDOW_SUBMENU_ID,
+    "Window",
+    true,
+    &[
+      &PredefinedMenuItem::minimize(app, None)?,
+      &PredefinedMenuItem::maximize(app, None)?,
+      &CheckMenuItem::with_id(app, ALWAYS_ON_TOP, "Keep on Top", true, false, None::<&str>)?,
+      &PredefinedMenuItem::separator(app)?,
+      &PredefinedMenuItem::close_window(app, None)?,
+    ],
+  )?;
+
+  let help = Submenu::with_id_and_items(
+    app,
+    HELP_SUBMENU_ID,
+    "Help",
+    true,
+    &[
+      &item(app, SHOW_LOG_VIEWER, "Show Log Viewer", None)?,
+      &item(app, OPEN_LOGS_FOLDER, "Open Logs Folder", None)?,
+      &PredefinedMenuItem::separator(app)?,
+      &item(app, REPORT_ISSUE, "Report an Issue…", None)?,
+    ],
+  )?;
+  #[cfg(not(target_os = "macos"))]
+  {
+    help.append(&PredefinedMenuItem::separator(app)?)?;
+    help.append(&about)?;
+  }
+
+  Menu::with_items(app, &[&first, &edit, &view, &window, &help])
+}
+
+fn item(
+  app: &AppHandle,
+  id: &str,
+  text: &str,
+  accelerator: Option<&str>,
+) -> tauri::Result<MenuItem<Wry>> {
+  MenuItem::with_id(app, id, text, true, accelerator)
+}
+
+/// Routes every menu item: shell actions run here, frontend ones go to the focused window.
+pub fn handle_event(app: &AppHandle, event: MenuEvent) {
+  let id = event.id().as_ref();
+  if let Some((_, name)) = FORWARDED.iter().find(|(item, _)| *item == id) {
+    match focused_window(app).filter(|window| window.is_visible().unwrap_or(false)) {
+      Some(window) => {
+        let _ = app.emit_to(window.label(), name, ());
+      }
+      // Without a window to show the frontend's update UI, native dialogs stand in.
+      None if id == CHECK_FOR_UPDATES => updates::check_with_dialogs(app),
+      None => warn!("no window to handle menu item {id}"),
+    }
+    return;
+  }
+
+  let result = match id {
+    QUIT => {
+      // RunEvent::Exit stops the backend.
+      app.exit(0);
+      Ok(())
+    }
+    INSTALL_UPDATE_FROM_FILE => {
+      updates::offline::install_with_dialogs(app);
+      Ok(())
+    }
+    OPEN_LOGS_FOLDER => logs::reveal_logs_folder(app).map(|_| ()).map_err(tauri::Error::from),
+    SHOW_LOG_VIEWER => windows::open_log_viewer(app),
+    ABOUT => windows::show_utility_window(app, UtilityWindow::About),
+    TOGGLE_DEVTOOLS => {
+      let Some(window) = focused_window(app) else {
+        return;
+      };
+      // Refusals are already logged.
+      let _ = windows::toggle_window_devtools(&window);
+      Ok(())
+    }
+    FORCE_RELOAD => {
+      let Some(window) = focused_window(app) else {
+        return;
+      };
+      tauri::async_runtime::spawn(async move {
+        if let Err(err) = windows::force_reload(&window).await {
+          error!("menu item {FORCE_RELOAD} failed: {err}");
+        }
+      });
+      Ok(())
+    }
+    PREFERENCES => windows::show_utility_window(app, UtilityWindow::Preferences),
+    REPORT_ISSUE => platform::open_url(ISSUES_URL).map_err(tauri::Error::from),
+    RELOAD | ACTUAL_SIZE | ZOOM_IN | ZOOM_OUT | TOGGLE_FULLSCREEN | ALWAYS_ON_TOP => {
+      let Some(window) = focused_window(app) else {
+        return;
+      };
+      match id {
+        RELOAD => window.reload(),
+        ACTUAL_SIZE => windows::zoom(&window, Zoom::Reset).map(|_| ()),
+        ZOOM_IN => windows::zoom(&window, Zoom::In).map(|_| ()),
+        ZOOM_OUT => windows::zoom(&window, Zoom::Out).map(|_| ()),
+        ALWAYS_ON_TOP => window
+          .is_always_on_top()
+          .and_then(|pinned| windows::pin(&window, !pinned)),
+        _ => window
+          .is_fullscreen()
+          .and_then(|fullscreen| window.set_fullscreen(!fullscreen)),
+      }
+    }
+    _ => return,
+  };
+  if let Err(err) = result {
+    error!("menu item {id} failed: {err}");
+  }
+}
+
+/// Shows whether the focused window is pinned in the Window menu.
+pub fn set_pinned_checked(app: &AppHandle, pinned: bool) {
+  let item = app
+    .menu()
+    .and_then(|menu| menu.get(WINDOW_SUBMENU_ID))
+    .and_then(|window| window.as_submenu().and_then(|submenu| submenu.get(ALWAYS_ON_TOP)))
+    .and_then(|item| item.as_check_menuitem().cloned());
+  if let Some(item) = item {
+    let _ = item.set_checked(pinned);
+  }
+}
+
+/// Keeps window-specific menu state in step with the focused window.
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+  if let WindowEvent::Focused(true) = event {
+    set_pinned_checked(window.app_handle(), window.is_always_on_top().unwrap_or(false));
+  }
+}
+
+/// The window with focus, falling back to the main window.
+fn focused_window(app: &AppHandle) -> Option<WebviewWindow> {
+  let open = app.webview_windows();
+  open
+    .values()
+    .find(|window| window.is_focused().unwrap_or(false))
+    .cloned()
+    .or_else(|| open.get(windows::MAIN_WINDOW).cloned())
+}
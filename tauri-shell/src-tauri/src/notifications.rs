@@ -0,0 +1,149 @@
+//! System notifications, for things worth telling the user while no window is on screen: the
+//! frontend's `notify`, backend jobs that finish (see `jobs`), and updates (see
+//! `updates::notification`). While a window is visible the in-app UI tells the user itself, so
+//! `notify` and jobs post nothing then, and a burst of them is cut short after a few.
+//!
+//! The notification plugin can't report clicks on desktop, so this goes through notify-rust,
+//! the library the plugin wraps, and waits for each click on a thread of its own.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::{debug, error, info, warn};
+use notify_rust::{Notification, NotificationResponse};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::windows;
+
+pub mod jobs;
+
+/// At most this many notifications per `BURST_WINDOW`; the rest are dropped.
+const BURST_LIMIT: usize = 3;
+const BURST_WINDOW: Duration = Duration::from_secs(60);
+
+/// When recent notifications went up, for the rate limit.
+#[derive(Default)]
+pub struct NotificationState(Mutex<VecDeque<Instant>>);
+
+/// Posts a notification; clicking it runs `on_click`. `action` names the click on desktops
+/// that show it as a button.
+pub fn show(
+  app: &AppHandle,
+  title: &str,
+  body: &str,
+  action: &str,
+  on_click: impl FnOnce(&AppHandle) + Send + 'static,
+) {
+  let mut notification = Notification::new();
+  notification.summary(title).body(body);
+  configure(app, &mut notification, action);
+  let app = app.clone();
+  let title = title.to_string();
+  let spawned = std::thread::Builder::new()
+    .name("notification".into())
+    .spawn(move || {
+      let handle = match notification.show() {
+        Ok(handle) => handle,
+        Err(err) => {
+          warn!("failed to show notification {title:?}: {err}");
+          return;
+        }
+      };
+      info!("posted notification {title:?}");
+      let mut on_click = Some(on_click);
+      let result = handle.wait_for_response(|response: &NotificationResponse| {
+        if matches!(response, NotificationResponse::Default | NotificationResponse::Action(_)) {
+          if let Some(on_click) = on_click.take() {
+            on_click(&app);
+          }
+        }
+      });
+      if let Err(err) = result {
+        debug!("stopped waiting for notification {title:?}: {err}");
+      }
+    });
+  if let Err(err) = spawned {
+    error!("failed to start the notification thread: {err}");
+  }
+}
+
+/// Posts a notification that, clicked, shows the main window and emits `navigate` there with
+/// `route`, unless a window is visible or too many went up just now. Says whether it was posted.
+pub fn post(app: &AppHandle, title: &str, body: &str, route: Option<String>) -> bool {
+  if windows::visibility::is_visible(app) {
+    debug!("not posting notification {title:?} while a window is visible");
+    return false;
+  }
+  if !within_limit(app) {
+    debug!("not posting notification {title:?}: too many just now");
+    return false;
+  }
+  show(app, title, body, "Show", move |app| {
+    info!("notification clicked");
+    let main_app = app.clone();
+    let _ = app.run_on_main_thread(move || {
+      if let Err(err) = windows::show_main_window(&main_app) {
+        error!("failed to show main window: {err}");
+      }
+      if let Some(route) = route {
+        let _ = main_app.emit_to(windows::MAIN_WINDOW, "navigate", route);
+      }
+    });
+  });
+  true
+}
+
+/// Counts a notification against the burst limit, if there's room for it.
+fn within_limit(app: &AppHandle) -> bool {
+  let Some(state) = app.try_state::<NotificationState>() else {
+    return true;
+  };
+  let Ok(mut recent) = state.0.lock() else {
+    return true;
+  };
+  let now = Instant::now();
+  while recent.front().is_some_and(|posted| now.duration_since(*posted) >= BURST_WINDOW) {
+    recent.pop_front();
+  }
+  if recent.len() >= BURST_LIMIT {
+    return false;
+  }
+  recent.push_back(now);
+  true
+}
+
+/// Posts a notification from the frontend; clicking it opens `route`. Nothing is posted while a
+/// window is visible; the result says whether it was.
+#[tauri::command]
+pub fn notify(app: AppHandle, title: String, body: String, route: Option<String>) -> bool {
+  post(&app, &title, &body, route)
+}
+
+/// XDG servers only report a click on a notification with a `default` action.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn configure(app: &AppHandle, notification: &mut Notification, action: &str) {
+  notification
+    .appname(&app.package_info().name)
+    .action("default", action);
+}
+
+/// Toasts carry the installed app's identity; a development build has none to give.
+#[cfg(windows)]
+fn configure(app: &AppHandle, notification: &mut Notification, _action: &str) {
+  if !cfg!(debug_assertions) {
+    notification.app_id(&app.config().identifier);
+  }
+}
+
+/// Notifications come from the app's bundle (only settable once per process, hence the ignored
+/// error after the first); a development build has none, so they come from Terminal.
+#[cfg(target_os = "macos")]
+fn configure(app: &AppHandle, _notification: &mut Notification, _action: &str) {
+  let bundle = if cfg!(debug_assertions) {
+    "com.apple.Terminal"
+  } else {
+    app.config().identifier.as_str()
+  };
+  let _ = notify_rust::set_application(bundle);
+}
@@ -0,0 +1,142 @@
+//! Notifications for backend jobs, such as long imports, that finish while no window is
+//! visible. The backend doesn't push job changes, so while the app is hidden the shell polls
+//! `GET /api/jobs/recent` and posts one notification per poll for the jobs that finished since
+//! the last: the job itself when it is alone, a count when there are several.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use log::debug;
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::{backend, windows};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A job that has ended, from the backend's recent jobs.
+#[derive(Debug)]
+struct FinishedJob {
+  id: String,
+  name: Option<String>,
+  failed: bool,
+  error: Option<String>,
+  route: Option<String>,
+}
+
+pub fn install(app: &AppHandle) {
+  tauri::async_runtime::spawn(poll(app.clone()));
+}
+
+/// Polls while the app is hidden. The first poll after it hides only notes what has finished
+/// already, which the in-app UI will have shown.
+async fn poll(app: AppHandle) {
+  let mut seen: Option<HashSet<String>> = None;
+  let mut failing = false;
+  loop {
+    tokio::time::sleep(POLL_INTERVAL).await;
+    if windows::visibility::is_visible(&app) {
+      seen = None;
+      continue;
+    }
+    let jobs = match fetch_finished(&app).await {
+      Ok(jobs) => {
+        failing = false;
+        jobs
+      }
+      Err(err) => {
+        // A backend without the endpoint would otherwise log this every few seconds.
+        if !failing {
+          debug!("recent jobs unavailable: {err}");
+          failing = true;
+        }
+        continue;
+      }
+    };
+    let Some(seen) = seen.as_mut() else {
+      seen = Some(jobs.into_iter().map(|job| job.id).collect());
+      continue;
+    };
+    let new = jobs.into_iter().filter(|job| seen.insert(job.id.clone())).collect();
+    announce(&app, new);
+  }
+}
+
+fn announce(app: &AppHandle, mut jobs: Vec<FinishedJob>) {
+  match jobs.len() {
+    0 => {}
+    1 => {
+      let job = jobs.remove(0);
+      let name = job.name.as_deref().unwrap_or("A task");
+      let (title, body) = if job.failed {
+        let body = job.error.unwrap_or_else(|| "Click to see what went wrong.".to_string());
+        (format!("{name} failed"), body)
+      } else {
+        (format!("{name} finished"), "Click to see the result.".to_string())
+      };
+      super::post(app, &title, &body, job.route);
+    }
+    count => {
+      let failed = jobs.iter().filter(|job| job.failed).count();
+      let body = match failed {
+        0 => "Click to see the results.".to_string(),
+        1 => "One of them failed.".to_string(),
+        _ => format!("{failed} of them failed."),
+      };
+      super::post(app, &format!("{count} tasks finished"), &body, None);
+    }
+  }
+}
+
+async fn fetch_finished(app: &AppHandle) -> Result<Vec<FinishedJob>, String> {
+  let base = backend::backend_url(app).ok_or_else(|| "backend address unknown".to_string())?;
+  // The backend is on loopback; a configured proxy would only get in the way.
+  let client = reqwest::Client::builder()
+    .no_proxy()
+    .timeout(REQUEST_TIMEOUT)
+    .build()
+    .map_err(|err| err.to_string())?;
+  let response = backend::authorize(app, client.get(format!("{base}/api/jobs/recent")))
+    .send()
+    .await
+    .map_err(|err| err.to_string())?;
+  if !response.status().is_success() {
+    return Err(format!("returned status {}", response.status()));
+  }
+  let bytes = response.bytes().await.map_err(|err| err.to_string())?;
+  let body: Value = serde_json::from_slice(&bytes).map_err(|err| err.to_string())?;
+  // Either a list of jobs or `{"jobs": [...]}`, as for the active jobs.
+  let jobs = body.get("jobs").unwrap_or(&body);
+  let jobs = jobs.as_array().ok_or_else(|| "no job list in the response".to_string())?;
+  Ok(jobs.iter().filter_map(finished_job).collect())
+}
+
+/// Reads a job that has ended; running or unrecognisable ones are skipped.
+fn finished_job(job: &Value) -> Option<FinishedJob> {
+  let id = match &job["id"] {
+    Value::String(id) => id.clone(),
+    Value::Number(id) => id.to_string(),
+    _ => return None,
+  };
+  let status = job["status"].as_str()?.to_ascii_lowercase();
+  let failed = match status.as_str() {
+    "completed" | "complete" | "succeeded" | "success" | "done" | "finished" => false,
+    "failed" | "failure" | "error" => true,
+    _ => return None,
+  };
+  let text = |keys: &[&str]| {
+    keys
+      .iter()
+      .find_map(|key| job[*key].as_str())
+      .filter(|text| !text.trim().is_empty())
+      .map(str::to_string)
+  };
+  Some(FinishedJob {
+    id,
+    name: text(&["name", "title", "kind"]),
+    failed,
+    error: text(&["error", "message"]),
+    route: text(&["route"]),
+  })
+}
@@ -0,0 +1,274 @@
+//! `.duckdb`, `.parquet` and `.csv` files opened with Pluto Duck, from Finder or Explorer. The
+//! associations are declared in the bundle config; the files come as an Apple Event on macOS,
+//! and as launch arguments on Windows and Linux, forwarded by a second instance when the app is
+//! already running. Either way they reach the main window as `open-files`.
+//!
+//! Files opened before the backend is ready are held until then. With
+//! `backend.import_opened_files` set, those are imported (or, for `.duckdb`, attached) by the
+//! shell itself once it is, since the frontend may not have loaded yet to do it.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::{info, warn};
+use reqwest::header::CONTENT_TYPE;
+use serde::Serialize;
+use serde_json::json;
+use tauri::{AppHandle, Emitter, Listener, Manager};
+
+use crate::{backend, config, windows};
+
+const IMPORT_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileKind {
+  Duckdb,
+  Parquet,
+  Csv,
+}
+
+impl FileKind {
+  fn of(path: &Path) -> Option<Self> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+      "duckdb" => Some(Self::Duckdb),
+      "parquet" => Some(Self::Parquet),
+      "csv" => Some(Self::Csv),
+      _ => None,
+    }
+  }
+}
+
+/// One entry of `open-files`. `imported` is set when the shell already imported the file, in
+/// which case the frontend only needs to show it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenedFile {
+  path: PathBuf,
+  kind: FileKind,
+  imported: bool,
+}
+
+#[derive(Default)]
+pub struct OpenFilesState(Mutex<Pending>);
+
+#[derive(Default)]
+struct Pending {
+  ready: bool,
+  files: Vec<OpenedFile>,
+}
+
+/// Starts taking opened files, including those this launch was started with.
+pub fn install(app: &AppHandle) {
+  app.manage(OpenFilesState::default());
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  handle_args(app, &args, None);
+
+  // An external backend is up before the shell starts.
+  if app.try_state::<backend::ExternalBackend>().is_some() {
+    flush(app, false);
+  } else {
+    let ready_app = app.clone();
+    app.once_any("backend-ready", move |_| flush(&ready_app, true));
+  }
+}
+
+/// Takes the files among launch arguments; relative paths are against `cwd`, the launching
+/// process's directory, or ours.
+pub fn handle_args(app: &AppHandle, args: &[String], cwd: Option<&Path>) {
+  let paths = args
+    .iter()
+    .filter(|arg| !arg.starts_with('-'))
+    .map(PathBuf::from)
+    .filter(|path| FileKind::of(path).is_some())
+    .map(|path| match cwd {
+      Some(cwd) if path.is_relative() => cwd.join(path),
+      _ => path,
+    })
+    .collect();
+  receive(app, paths);
+}
+
+/// Takes the files among the URLs of a macOS `RunEvent::Opened`.
+#[cfg(target_os = "macos")]
+pub fn handle_urls(app: &AppHandle, urls: &[tauri::Url]) {
+  let paths = urls
+    .iter()
+    .filter(|url| url.scheme() == "file")
+    .filter_map(|url| url.to_file_path().ok())
+    .collect();
+  receive(app, paths);
+}
+
+fn receive(app: &AppHandle, paths: Vec<PathBuf>) {
+  let files: Vec<OpenedFile> = paths.into_iter().filter_map(opened_file).collect();
+  if files.is_empty() {
+    return;
+  }
+  info!("opened files {:?}", files.iter().map(|file| &file.path).collect::<Vec<_>>());
+
+  let main_app = app.clone();
+  let _ = app.run_on_main_thread(move || {
+    if let Err(err) = windows::show_main_window(&main_app) {
+      log::error!("failed to show main window: {err}");
+    }
+  });
+  let Some(state) = app.try_state::<OpenFilesState>() else {
+    return;
+  };
+  let Ok(mut pending) = state.0.lock() else {
+    return;
+  };
+  if pending.ready {
+    drop(pending);
+    emit(app, files);
+  } else {
+    pending.files.extend(files);
+  }
+}
+
+/// An absolute, existing file of a kind we open; anything else is logged and dropped.
+fn opened_file(path: PathBuf) -> Option<OpenedFile> {
+  let kind = FileKind::of(&path)?;
+  let path = if path.is_absolute() {
+    path
+  } else {
+    match std::env::current_dir() {
+      Ok(cwd) => cwd.join(path),
+      Err(err) => {
+        warn!("ignoring opened file {path:?}: {err}");
+        return None;
+      }
+    }
+  };
+  if !path.is_file() {
+    warn!("ignoring opened file {path:?}, which doesn't exist");
+    return None;
+  }
+  Some(OpenedFile { path, kind, imported: false })
+}
+
+/// Sends the held files, imported first when the settings ask for it, and from now on every
+/// file as it comes. `import` is false when the frontend was up before any of them came.
+fn flush(app: &AppHandle, import: bool) {
+  let Some(state) = app.try_state::<OpenFilesState>() else {
+    return;
+  };
+  let files = match state.0.lock() {
+    Ok(mut pending) => {
+      pending.ready = true;
+      std::mem::take(&mut pending.files)
+    }
+    Err(_) => return,
+  };
+  if files.is_empty() {
+    return;
+  }
+  if !import || !config::saved(app).backend.import_opened_files {
+    emit(app, files);
+    return;
+  }
+  let app = app.clone();
+  tauri::async_runtime::spawn(async move {
+    let mut imported = Vec::with_capacity(files.len());
+    for mut file in files {
+      match import_file(&app, &file).await {
+        Ok(()) => file.imported = true,
+        Err(err) => warn!("failed to import {:?}, leaving it to the frontend: {err}", file.path),
+      }
+      imported.push(file);
+    }
+    emit(&app, imported);
+  });
+}
+
+fn emit(app: &AppHandle, files: Vec<OpenedFile>) {
+  let _ = app.emit_to(windows::MAIN_WINDOW, "open-files", files);
+}
+
+/// Imports a CSV or Parquet file as a file asset, or attaches a DuckDB file to the default
+/// project, through the backend's API.
+async fn import_file(app: &AppHandle, file: &OpenedFile) -> Result<(), String> {
+  let base = backend::backend_url(app).ok_or_else(|| "backend address unknown".to_string())?;
+  // The backend is on loopback; a configured proxy would only get in the way.
+  let client = reqwest::Client::builder()
+    .no_proxy()
+    .timeout(IMPORT_TIMEOUT)
+    .build()
+    .map_err(|err| err.to_string())?;
+  let name = table_name(&file.path);
+  let path = file.path.to_string_lossy();
+  let (request, body) = match file.kind {
+    FileKind::Duckdb => {
+      let project = default_project(app, &client, &base).await?;
+      let request = client
+        .post(format!("{base}/api/v1/source/attach/duckdb"))
+        .query(&[("project_id", project)]);
+      (request, json!({ "name": name, "path": path, "read_only": true }))
+    }
+    FileKind::Parquet | FileKind::Csv => {
+      let file_type = if file.kind == FileKind::Csv { "csv" } else { "parquet" };
+      let display_name = file.path.file_name().map(|name| name.to_string_lossy());
+      let body = json!({
+        "file_path": path,
+        "file_type": file_type,
+        "table_name": name,
+        "name": display_name,
+      });
+      (client.post(format!("{base}/api/v1/asset/files")), body)
+    }
+  };
+  let response = backend::authorize(app, request)
+    .header(CONTENT_TYPE, "application/json")
+    .body(body.to_string())
+    .send()
+    .await
+    .map_err(|err| err.to_string())?;
+  if !response.status().is_success() {
+    return Err(format!("returned status {}", response.status()));
+  }
+  Ok(())
+}
+
+/// The id of the project marked default, from `GET /api/v1/projects`.
+async fn default_project(
+  app: &AppHandle,
+  client: &reqwest::Client,
+  base: &str,
+) -> Result<String, String> {
+  let response = backend::authorize(app, client.get(format!("{base}/api/v1/projects")))
+    .send()
+    .await
+    .map_err(|err| err.to_string())?;
+  if !response.status().is_success() {
+    return Err(format!("listing projects returned status {}", response.status()));
+  }
+  let bytes = response.bytes().await.map_err(|err| err.to_string())?;
+  let projects: serde_json::Value =
+    serde_json::from_slice(&bytes).map_err(|err| err.to_string())?;
+  projects
+    .as_array()
+    .and_then(|projects| {
+      projects
+        .iter()
+        .find(|project| project["is_default"].as_bool() == Some(true))
+    })
+    .and_then(|project| project["id"].as_str())
+    .map(str::to_string)
+    .ok_or_else(|| "no default project".to_string())
+}
+
+/// A DuckDB identifier from the file's name: `Sales 2024.csv` becomes `sales_2024`.
+fn table_name(path: &Path) -> String {
+  let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+  let mut name: String = stem
+    .chars()
+    .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+    .collect();
+  if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+    name.insert_str(0, "t_");
+  }
+  name
+}
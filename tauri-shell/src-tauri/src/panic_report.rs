@@ -0,0 +1,152 @@
+//! A panic in the shell otherwise ends the app without a word, so the hook installed first
+//! thing in `run` writes `crashes/shell-panic-<timestamp>.txt` and points the user at it
+//! before the default hook (and the abort or unwind) carries on. Until setup knows the data
+//! root, reports go to the temp directory. Nothing in here may panic: every failure to write
+//! or to show the message is ignored.
+
+use std::any::Any;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::panic::Location;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+const CRASH_DIR: &str = "crashes";
+const TITLE: &str = "Pluto Duck ran into a problem";
+
+/// Where reports go and which version to put in them, once the app knows.
+struct Destination {
+  dir: PathBuf,
+  app_version: String,
+}
+
+static DESTINATION: OnceLock<Destination> = OnceLock::new();
+/// Only the first panic is reported; later ones (say, in threads torn down with it) would only
+/// stack more dialogs.
+static REPORTED: AtomicBool = AtomicBool::new(false);
+
+pub fn install() {
+  let default = std::panic::take_hook();
+  std::panic::set_hook(Box::new(move |info| {
+    if !REPORTED.swap(true, Ordering::SeqCst) {
+      let path = write_report(info.payload(), info.location());
+      show_message(path.as_deref());
+    }
+    default(info);
+  }));
+}
+
+/// Sends later reports to the data root's `crashes/`, with the app's version in them.
+pub fn set_data_root(data_root: &Path, app_version: String) {
+  let _ = DESTINATION.set(Destination {
+    dir: data_root.join(CRASH_DIR),
+    app_version,
+  });
+}
+
+fn write_report(payload: &(dyn Any + Send), location: Option<&Location>) -> Option<PathBuf> {
+  let now = chrono::Local::now();
+  let name = format!("shell-panic-{}.txt", now.format("%Y-%m-%dT%H-%M-%S%.3f"));
+  let message = match payload.downcast_ref::<&str>() {
+    Some(message) => (*message).to_string(),
+    None => match payload.downcast_ref::<String>() {
+      Some(message) => message.clone(),
+      None => "(no message)".to_string(),
+    },
+  };
+  let app_version = DESTINATION
+    .get()
+    .map_or("unknown (before setup)", |destination| destination.app_version.as_str());
+
+  let mut report = String::new();
+  let _ = writeln!(report, "Pluto Duck shell panic report");
+  let _ = writeln!(report, "time: {}", now.to_rfc3339());
+  let _ = writeln!(report, "app version: {app_version}");
+  let _ = writeln!(report, "os: {} {}", std::env::consts::OS, std::env::consts::ARCH);
+  let _ = writeln!(report, "pid: {}", std::process::id());
+  match location {
+    Some(location) => {
+      let _ = writeln!(report, "location: {location}");
+    }
+    None => {
+      let _ = writeln!(report, "location: unknown");
+    }
+  }
+  let _ = writeln!(report, "message: {message}");
+  let _ = writeln!(report, "\n--- backtrace ---");
+  let _ = writeln!(report, "{}", std::backtrace::Backtrace::force_capture());
+
+  let configured = DESTINATION.get().map(|destination| destination.dir.clone());
+  let temp = std::env::temp_dir().join("pluto_duck").join(CRASH_DIR);
+  for dir in configured.into_iter().chain(Some(temp)) {
+    let path = dir.join(&name);
+    if std::fs::create_dir_all(&dir).is_ok() && std::fs::write(&path, &report).is_ok() {
+      let _ = writeln!(std::io::stderr(), "shell panic report written to {}", path.display());
+      return Some(path);
+    }
+  }
+  None
+}
+
+fn show_message(path: Option<&Path>) {
+  let text = match path {
+    Some(path) => format!(
+      "Pluto Duck hit an unexpected error and may close. A report was saved to:\n\n{}\n\n\
+       Please attach it if you report the problem.",
+      path.display()
+    ),
+    None => "Pluto Duck hit an unexpected error and may close. No report could be saved."
+      .to_string(),
+  };
+  message_box(&text);
+}
+
+/// A blocking system message box that's safe from any thread, since the panic may not be on
+/// the main one.
+#[cfg(windows)]
+fn message_box(text: &str) {
+  use windows_sys::Win32::UI::WindowsAndMessaging::{
+    MessageBoxW, MB_ICONERROR, MB_OK, MB_SETFOREGROUND, MB_TOPMOST,
+  };
+
+  let wide = |text: &str| text.encode_utf16().chain(Some(0)).collect::<Vec<u16>>();
+  let (text, title) = (wide(text), wide(TITLE));
+  // SAFETY: both strings are NUL-terminated and outlive the call; no owner window is passed.
+  unsafe {
+    MessageBoxW(
+      std::ptr::null_mut(),
+      text.as_ptr(),
+      title.as_ptr(),
+      MB_OK | MB_ICONERROR | MB_SETFOREGROUND | MB_TOPMOST,
+    );
+  }
+}
+
+/// Runs `osascript` rather than AppKit, which would have to be reached on the main thread.
+#[cfg(target_os = "macos")]
+fn message_box(text: &str) {
+  let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+  let script = format!(
+    "display alert {} message {} as critical",
+    quote(TITLE),
+    quote(text)
+  );
+  let _ = std::process::Command::new("/usr/bin/osascript")
+    .args(["-e", &script])
+    .status();
+}
+
+/// Whichever of zenity and kdialog is installed; without either, the report path is only on
+/// stderr.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn message_box(text: &str) {
+  let zenity = std::process::Command::new("zenity")
+    .args(["--error", "--no-markup", "--title", TITLE, "--text", text])
+    .status();
+  if zenity.is_err() {
+    let _ = std::process::Command::new("kdialog")
+      .args(["--title", TITLE, "--error", text])
+      .status();
+  }
+}
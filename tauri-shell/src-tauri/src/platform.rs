@@ -0,0 +1,182 @@
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+
+/// Opens `path` with the platform file manager (Finder, Explorer, or the XDG default).
+pub fn open_path(path: &Path) -> std::io::Result<()> {
+  launch(path.as_os_str())
+}
+
+/// Shows `path` selected in Finder or Explorer; other file managers just open its folder.
+pub fn reveal(path: &Path) -> std::io::Result<()> {
+  #[cfg(target_os = "macos")]
+  let mut child = Command::new("open").arg("-R").arg(path).spawn()?;
+  #[cfg(windows)]
+  let mut child = {
+    let mut select = std::ffi::OsString::from("/select,");
+    select.push(path);
+    Command::new("explorer").arg(select).spawn()?
+  };
+  #[cfg(all(unix, not(target_os = "macos")))]
+  let mut child = Command::new("xdg-open")
+    .arg(path.parent().unwrap_or(path))
+    .spawn()?;
+  thread::spawn(move || {
+    let _ = child.wait();
+  });
+  Ok(())
+}
+
+/// Opens `url` in the default browser.
+pub fn open_url(url: &str) -> std::io::Result<()> {
+  launch(OsStr::new(url))
+}
+
+fn launch(target: &OsStr) -> std::io::Result<()> {
+  #[cfg(target_os = "macos")]
+  let mut command = Command::new("open");
+  #[cfg(windows)]
+  let mut command = Command::new("explorer");
+  #[cfg(all(unix, not(target_os = "macos")))]
+  let mut command = Command::new("xdg-open");
+
+  let mut child = command.arg(target).spawn()?;
+  // Reap the launcher in the background so it doesn't linger as a zombie.
+  thread::spawn(move || {
+    let _ = child.wait();
+  });
+  Ok(())
+}
+
+/// Size and free space, in bytes, of the volume holding `path`.
+#[derive(Debug, Clone, Copy)]
+pub struct DiskSpace {
+  pub total: u64,
+  /// What this user can still write, i.e. without blocks reserved for root.
+  pub available: u64,
+}
+
+#[cfg(unix)]
+pub fn disk_space(path: &Path) -> std::io::Result<DiskSpace> {
+  use std::os::unix::ffi::OsStrExt;
+
+  let path = std::ffi::CString::new(path.as_os_str().as_bytes())
+    .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+  let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+  if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+  let stat = unsafe { stat.assume_init() };
+  // The field types vary by platform (u32 block counts on macOS).
+  #[allow(clippy::unnecessary_cast)]
+  let (block, blocks, available) = (
+    stat.f_frsize as u64,
+    stat.f_blocks as u64,
+    stat.f_bavail as u64,
+  );
+  Ok(DiskSpace {
+    total: blocks.saturating_mul(block),
+    available: available.saturating_mul(block),
+  })
+}
+
+#[cfg(windows)]
+pub fn disk_space(path: &Path) -> std::io::Result<DiskSpace> {
+  use std::os::windows::ffi::OsStrExt;
+  use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+  let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+  let (mut available, mut total) = (0u64, 0u64);
+  let ok = unsafe {
+    GetDiskFreeSpaceExW(wide.as_ptr(), &mut available, &mut total, std::ptr::null_mut())
+  };
+  if ok == 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+  Ok(DiskSpace { total, available })
+}
+
+/// Asks the OS to leave the directory `path` out of backups and indexing: the
+/// `NSURLIsExcludedFromBackupKey` resource value on macOS, and on Windows the not-indexed
+/// attribute plus a `desktop.ini` tip explaining why. Nothing to do elsewhere.
+#[cfg(target_os = "macos")]
+pub fn exclude_from_backup(path: &Path) -> std::io::Result<()> {
+  use cocoa::base::{id, nil, BOOL, NO, YES};
+  use cocoa::foundation::{NSAutoreleasePool, NSString};
+  use objc::{class, msg_send, sel, sel_impl};
+
+  #[link(name = "Foundation", kind = "framework")]
+  extern "C" {
+    static NSURLIsExcludedFromBackupKey: id;
+  }
+
+  // SAFETY: plain Foundation calls on objects created here; the pool releases the autoreleased
+  // ones, and the string allocated here is released explicitly.
+  unsafe {
+    let pool = NSAutoreleasePool::new(nil);
+    let string = NSString::alloc(nil).init_str(&path.to_string_lossy());
+    let url: id = msg_send![class!(NSURL), fileURLWithPath: string isDirectory: YES];
+    let value: id = msg_send![class!(NSNumber), numberWithBool: YES];
+    let mut error: id = nil;
+    let ok: BOOL = msg_send![
+      url,
+      setResourceValue: value
+      forKey: NSURLIsExcludedFromBackupKey
+      error: &mut error
+    ];
+    let _: () = msg_send![string, release];
+    pool.drain();
+    if ok == NO {
+      return Err(std::io::Error::other("NSURLIsExcludedFromBackupKey was not set"));
+    }
+  }
+  Ok(())
+}
+
+#[cfg(windows)]
+pub fn exclude_from_backup(path: &Path) -> std::io::Result<()> {
+  use windows_sys::Win32::Storage::FileSystem::{
+    FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_NOT_CONTENT_INDEXED, FILE_ATTRIBUTE_READONLY,
+    FILE_ATTRIBUTE_SYSTEM,
+  };
+
+  let desktop_ini = path.join("desktop.ini");
+  if !desktop_ini.exists() {
+    std::fs::write(
+      &desktop_ini,
+      "[.ShellClassInfo]\r\nInfoTip=Pluto Duck's databases. Keep this folder out of OneDrive, \
+       Dropbox, and other sync or backup tools; copying open databases can damage them.\r\n",
+    )?;
+    add_attributes(&desktop_ini, FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM)?;
+  }
+  // On a folder, read-only only tells Explorer to read its desktop.ini.
+  add_attributes(path, FILE_ATTRIBUTE_NOT_CONTENT_INDEXED | FILE_ATTRIBUTE_READONLY)
+}
+
+#[cfg(windows)]
+fn add_attributes(path: &Path, attributes: u32) -> std::io::Result<()> {
+  use std::os::windows::ffi::OsStrExt;
+  use windows_sys::Win32::Storage::FileSystem::{
+    GetFileAttributesW, SetFileAttributesW, INVALID_FILE_ATTRIBUTES,
+  };
+
+  let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+  // SAFETY: `wide` is a NUL-terminated UTF-16 path that outlives both calls.
+  unsafe {
+    let current = GetFileAttributesW(wide.as_ptr());
+    if current == INVALID_FILE_ATTRIBUTES {
+      return Err(std::io::Error::last_os_error());
+    }
+    let missing = current & attributes != attributes;
+    if missing && SetFileAttributesW(wide.as_ptr(), current | attributes) == 0 {
+      return Err(std::io::Error::last_os_error());
+    }
+  }
+  Ok(())
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+pub fn exclude_from_backup(_path: &Path) -> std::io::Result<()> {
+  Ok(())
+}
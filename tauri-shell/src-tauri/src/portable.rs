@@ -0,0 +1,70 @@
+//! Portable mode keeps everything — backend data, shell settings, and logs — in a `data`
+//! folder next to the executable, for running from a USB stick or a project folder. It's on
+//! when `PLUTODUCK_PORTABLE=1` is set or a `portable.marker` file sits next to the executable;
+//! `PLUTODUCK_PORTABLE=0` turns it off even with a marker. A portable root wins over the
+//! `backend.data_root` setting, which wins over the default location.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const PORTABLE_ENV: &str = "PLUTODUCK_PORTABLE";
+const MARKER_FILE: &str = "portable.marker";
+
+/// The portable data folder, or `None` outside portable mode. Decided once per launch.
+pub fn root() -> Option<&'static Path> {
+  static ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+  ROOT
+    .get_or_init(|| {
+      let exe = std::env::current_exe().ok()?;
+      let exe_dir = exe.parent()?;
+      let env = std::env::var(PORTABLE_ENV).ok();
+      enabled(env.as_deref(), exe_dir).then(|| exe_dir.join("data"))
+    })
+    .as_deref()
+}
+
+/// The env var decides when set to a recognizable value; the marker file otherwise.
+fn enabled(env: Option<&str>, exe_dir: &Path) -> bool {
+  match env.map(|value| value.trim().to_ascii_lowercase()).as_deref() {
+    Some("1" | "true") => true,
+    Some("0" | "false") => false,
+    _ => exe_dir.join(MARKER_FILE).is_file(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn env_var_decides_when_recognizable() {
+    let dir = tempfile::tempdir().unwrap();
+    let marked = tempfile::tempdir().unwrap();
+    std::fs::write(marked.path().join(MARKER_FILE), "").unwrap();
+
+    for exe_dir in [dir.path(), marked.path()] {
+      assert!(enabled(Some("1"), exe_dir));
+      assert!(enabled(Some(" TRUE "), exe_dir));
+      assert!(!enabled(Some("0"), exe_dir));
+      assert!(!enabled(Some("false"), exe_dir));
+    }
+  }
+
+  #[test]
+  fn marker_decides_otherwise() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(!enabled(None, dir.path()));
+    assert!(!enabled(Some("bogus"), dir.path()));
+
+    std::fs::write(dir.path().join(MARKER_FILE), "").unwrap();
+    assert!(enabled(None, dir.path()));
+    assert!(enabled(Some("bogus"), dir.path()));
+  }
+
+  #[test]
+  fn marker_must_be_a_file() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join(MARKER_FILE)).unwrap();
+    assert!(!enabled(None, dir.path()));
+  }
+}
@@ -0,0 +1,241 @@
+//! Keeps the machine awake while the backend runs long tasks (overnight imports die when a
+//! laptop sleeps). Holds are counted per reason, and the OS-level inhibition lasts while any
+//! is held. Everything is released on exit and whenever the backend exits, so a forgotten
+//! hold can't keep a machine up forever.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use log::info;
+use serde::Serialize;
+use tauri::{AppHandle, Listener, Manager};
+
+#[derive(Default)]
+struct Holds {
+  /// Outstanding `prevent_sleep` calls by reason.
+  reasons: BTreeMap<String, usize>,
+  inhibitor: Option<native::Inhibitor>,
+}
+
+#[derive(Default)]
+pub struct PowerState(Mutex<Holds>);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerStatus {
+  /// Whether the OS has actually been asked to stay awake.
+  inhibited: bool,
+  reasons: Vec<String>,
+}
+
+/// Releases every hold when the backend exits, since whatever it was doing has stopped.
+pub fn install(app: &AppHandle) {
+  let app_handle = app.clone();
+  app.listen_any("backend-exited", move |_| release_all(&app_handle));
+}
+
+/// Keeps the system from sleeping until a matching `allow_sleep`. Calls stack: the same reason
+/// given twice needs two releases.
+#[tauri::command]
+pub fn prevent_sleep(app: AppHandle, reason: String) -> Result<(), String> {
+  let state = app.state::<PowerState>();
+  let mut holds = state.0.lock().map_err(|_| "power state is poisoned".to_string())?;
+  if holds.inhibitor.is_none() {
+    let inhibitor = native::Inhibitor::acquire(&reason)
+      .map_err(|err| format!("failed to prevent sleep: {err}"))?;
+    info!("preventing sleep: {reason}");
+    holds.inhibitor = Some(inhibitor);
+  }
+  *holds.reasons.entry(reason).or_default() += 1;
+  Ok(())
+}
+
+/// Releases one hold for `reason`, or every hold without one. Sleep is allowed again once none
+/// is left.
+#[tauri::command]
+pub fn allow_sleep(app: AppHandle, reason: Option<String>) -> Result<(), String> {
+  let state = app.state::<PowerState>();
+  let mut holds = state.0.lock().map_err(|_| "power state is poisoned".to_string())?;
+  match reason {
+    Some(reason) => {
+      let Some(count) = holds.reasons.get_mut(&reason) else {
+        return Err(format!("sleep isn't being prevented for {reason:?}"));
+      };
+      *count -= 1;
+      if *count == 0 {
+        holds.reasons.remove(&reason);
+      }
+    }
+    None => holds.reasons.clear(),
+  }
+  if holds.reasons.is_empty() && holds.inhibitor.take().is_some() {
+    info!("allowing sleep again");
+  }
+  Ok(())
+}
+
+#[tauri::command]
+pub fn get_power_state(app: AppHandle) -> Result<PowerStatus, String> {
+  let state = app.state::<PowerState>();
+  let holds = state.0.lock().map_err(|_| "power state is poisoned".to_string())?;
+  Ok(PowerStatus {
+    inhibited: holds.inhibitor.is_some(),
+    reasons: holds.reasons.keys().cloned().collect(),
+  })
+}
+
+/// Drops every hold, e.g. on exit.
+pub fn release_all(app: &AppHandle) {
+  let Some(state) = app.try_state::<PowerState>() else {
+    return;
+  };
+  // Whatever panicked holding the lock, the holds still have to go.
+  let mut holds = state.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+  holds.reasons.clear();
+  if holds.inhibitor.take().is_some() {
+    info!("released sleep prevention");
+  }
+}
+
+/// An IOKit power assertion, released on drop.
+#[cfg(target_os = "macos")]
+mod native {
+  use core_foundation::base::TCFType;
+  use core_foundation::string::{CFString, CFStringRef};
+
+  const ASSERTION_LEVEL_ON: u32 = 255;
+
+  #[link(name = "IOKit", kind = "framework")]
+  extern "C" {
+    fn IOPMAssertionCreateWithName(
+      assertion_type: CFStringRef,
+      level: u32,
+      name: CFStringRef,
+      assertion_id: *mut u32,
+    ) -> i32;
+    fn IOPMAssertionRelease(assertion_id: u32) -> i32;
+  }
+
+  pub struct Inhibitor(u32);
+
+  impl Inhibitor {
+    pub fn acquire(reason: &str) -> Result<Self, String> {
+      let assertion_type = CFString::new("PreventUserIdleSystemSleep");
+      let name = CFString::new(&format!("Pluto Duck: {reason}"));
+      let mut id = 0;
+      // SAFETY: both strings outlive the call and `id` is a valid out pointer.
+      let status = unsafe {
+        IOPMAssertionCreateWithName(
+          assertion_type.as_concrete_TypeRef(),
+          ASSERTION_LEVEL_ON,
+          name.as_concrete_TypeRef(),
+          &mut id,
+        )
+      };
+      if status == 0 {
+        Ok(Self(id))
+      } else {
+        Err(format!("IOPMAssertionCreateWithName returned {status:#x}"))
+      }
+    }
+  }
+
+  impl Drop for Inhibitor {
+    fn drop(&mut self) {
+      // SAFETY: the assertion was created by us and is released exactly once.
+      unsafe {
+        IOPMAssertionRelease(self.0);
+      }
+    }
+  }
+}
+
+/// Execution state belongs to a thread, so a dedicated one sets it and clears it when told to.
+#[cfg(windows)]
+mod native {
+  use std::sync::mpsc;
+  use std::thread::JoinHandle;
+
+  use windows_sys::Win32::System::Power::{
+    SetThreadExecutionState, ES_CONTINUOUS, ES_SYSTEM_REQUIRED,
+  };
+
+  pub struct Inhibitor {
+    release: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+  }
+
+  impl Inhibitor {
+    pub fn acquire(_reason: &str) -> Result<Self, String> {
+      let (release, released) = mpsc::channel();
+      let (acquired, result) = mpsc::channel();
+      let thread = std::thread::Builder::new()
+        .name("sleep-inhibitor".into())
+        .spawn(move || {
+          // SAFETY: plain Win32 call with valid flags.
+          let previous = unsafe { SetThreadExecutionState(ES_CONTINUOUS | ES_SYSTEM_REQUIRED) };
+          let _ = acquired.send(previous != 0);
+          if previous == 0 {
+            return;
+          }
+          // Blocks until the sender is dropped or used.
+          let _ = released.recv();
+          // SAFETY: as above.
+          unsafe {
+            SetThreadExecutionState(ES_CONTINUOUS);
+          }
+        })
+        .map_err(|err| err.to_string())?;
+      match result.recv() {
+        Ok(true) => Ok(Self {
+          release: Some(release),
+          thread: Some(thread),
+        }),
+        _ => Err("SetThreadExecutionState failed".to_string()),
+      }
+    }
+  }
+
+  impl Drop for Inhibitor {
+    fn drop(&mut self) {
+      drop(self.release.take());
+      if let Some(thread) = self.thread.take() {
+        let _ = thread.join();
+      }
+    }
+  }
+}
+
+/// A `systemd-inhibit` child holding a logind sleep lock for as long as it runs.
+#[cfg(all(unix, not(target_os = "macos")))]
+mod native {
+  use std::process::{Child, Command, Stdio};
+
+  use log::warn;
+
+  pub struct Inhibitor(Child);
+
+  impl Inhibitor {
+    pub fn acquire(reason: &str) -> Result<Self, String> {
+      Command::new("systemd-inhibit")
+        .args(["--what=sleep:idle", "--who=Pluto Duck", "--mode=block"])
+        .arg(format!("--why={reason}"))
+        .args(["sleep", "infinity"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(Self)
+        .map_err(|err| format!("could not run systemd-inhibit: {err}"))
+    }
+  }
+
+  impl Drop for Inhibitor {
+    fn drop(&mut self) {
+      if let Err(err) = self.0.kill() {
+        warn!("failed to stop systemd-inhibit: {err}");
+      }
+      let _ = self.0.wait();
+    }
+  }
+}
@@ -0,0 +1,111 @@
+//! Every quit (the Quit menu item, Cmd+Q, the tray, `quit_app`) goes through
+//! `RunEvent::ExitRequested`, where it is held until confirmed: always when `confirm_quit` is
+//! set, and otherwise only while the backend is in the middle of a job, since exit cleanup
+//! stops (and if needed kills) it. `RunEvent::Exit` then shuts the backend down gracefully.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::info;
+use tauri::{AppHandle, ExitRequestApi, Manager};
+
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::backend;
+
+pub struct QuitGuard {
+  /// `confirm_quit`, which can change while running.
+  confirm: AtomicBool,
+  /// Set once the exit has been checked (or confirmed), so the next request goes through.
+  confirmed: AtomicBool,
+  /// Set while a check or dialog is pending, so repeated quit requests don't stack dialogs.
+  pending: AtomicBool,
+}
+
+impl QuitGuard {
+  pub fn new(confirm_quit: bool) -> Self {
+    Self {
+      confirm: AtomicBool::new(confirm_quit),
+      confirmed: AtomicBool::new(false),
+      pending: AtomicBool::new(false),
+    }
+  }
+}
+
+/// Turns the "Quit Pluto Duck?" question on or off for this run.
+pub fn set_confirm(app: &AppHandle, confirm_quit: bool) {
+  if let Some(guard) = app.try_state::<QuitGuard>() {
+    guard.confirm.store(confirm_quit, Ordering::SeqCst);
+  }
+}
+
+/// Handles `RunEvent::ExitRequested`: holds the exit until the backend has been asked about
+/// running jobs (and, with `confirm_quit`, the user about quitting at all), then exits again
+/// with the same code unless the user chooses to keep going.
+pub fn on_exit_requested(app: &AppHandle, code: Option<i32>, api: &ExitRequestApi) {
+  let Some(guard) = app.try_state::<QuitGuard>() else {
+    return;
+  };
+  // A relaunch (e.g. after moving the data root) can't be held anyway.
+  if guard.confirmed.load(Ordering::SeqCst) || code == Some(tauri::RESTART_EXIT_CODE) {
+    return;
+  }
+  api.prevent_exit();
+  if guard.pending.swap(true, Ordering::SeqCst) {
+    return;
+  }
+
+  let app = app.clone();
+  tauri::async_runtime::spawn(async move {
+    let jobs = backend::active_jobs(&app).await.unwrap_or(0);
+    let confirm_quit = app
+      .try_state::<QuitGuard>()
+      .is_some_and(|guard| guard.confirm.load(Ordering::SeqCst));
+    let message = match jobs {
+      0 if !confirm_quit => {
+        proceed(&app, code);
+        return;
+      }
+      0 => "Quit Pluto Duck?".to_string(),
+      1 => "A task is still running — quit anyway?".to_string(),
+      _ => format!("{jobs} tasks are still running — quit anyway?"),
+    };
+    info!("quit requested with {jobs} backend job(s) running; asking for confirmation");
+    let dialog_app = app.clone();
+    app
+      .dialog()
+      .message(message)
+      .title("Quit Pluto Duck")
+      .kind(MessageDialogKind::Warning)
+      .buttons(MessageDialogButtons::OkCancelCustom(
+        "Quit".into(),
+        "Cancel".into(),
+      ))
+      .show(move |quit| {
+        if quit {
+          proceed(&dialog_app, code);
+        } else if let Some(guard) = dialog_app.try_state::<QuitGuard>() {
+          info!("quit cancelled");
+          guard.pending.store(false, Ordering::SeqCst);
+        }
+      });
+  });
+}
+
+fn proceed(app: &AppHandle, code: Option<i32>) {
+  if let Some(guard) = app.try_state::<QuitGuard>() {
+    guard.confirmed.store(true, Ordering::SeqCst);
+  }
+  app.exit(code.unwrap_or(0));
+}
+
+/// Quits the way the Quit menu item does. `force` skips the confirmation, for when the
+/// frontend has already asked.
+#[tauri::command]
+pub fn quit_app(app: AppHandle, force: bool) {
+  if force {
+    info!("forced quit requested by the frontend");
+    proceed(&app, None);
+  } else {
+    app.exit(0);
+  }
+}
@@ -0,0 +1,188 @@
+//! Recently opened profiles, files and the like, which the frontend records with `add_recent`,
+//! offered from the OS launcher: the Dock menu on macOS and the taskbar Jump List on Windows.
+//! Choosing one shows the app and emits `open-recent` with the entry. The list is kept in
+//! `recents.json` next to the config, and entries whose path is gone are dropped whenever the
+//! launcher menus are rebuilt.
+//!
+//! A Jump List entry starts the app with a `pluto-duck://open-recent?id=…` link, so it takes
+//! the deep-link path: forwarded by a second instance, and held until the backend is ready.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{config, windows};
+
+#[cfg(target_os = "macos")]
+mod dock;
+#[cfg(windows)]
+mod jump_list;
+
+const RECENTS_FILE: &str = "recents.json";
+/// Entries kept in the file.
+const MAX_RECENTS: usize = 20;
+/// Entries shown in the Dock menu or Jump List.
+pub const LAUNCHER_ENTRIES: usize = 10;
+/// The deep-link action a Jump List entry opens with.
+pub const OPEN_ACTION: &str = "open-recent";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recent {
+  pub id: String,
+  /// What the frontend opens it as, e.g. `profile` or `file`.
+  pub kind: String,
+  pub label: String,
+  pub path: PathBuf,
+  pub opened_at: DateTime<Utc>,
+}
+
+/// The list, most recent first.
+#[derive(Default)]
+pub struct RecentsState(Mutex<Vec<Recent>>);
+
+pub fn install(app: &AppHandle) {
+  app.manage(RecentsState(Mutex::new(load(app))));
+  #[cfg(target_os = "macos")]
+  dock::install(app);
+  rebuild(app);
+}
+
+/// Records `path` as just opened, or moves it to the top if it's already listed.
+#[tauri::command]
+pub fn add_recent(
+  app: AppHandle,
+  kind: String,
+  label: String,
+  path: PathBuf,
+) -> Result<Vec<Recent>, String> {
+  let recents = change(&app, |recents| {
+    let existing = recents
+      .iter()
+      .position(|recent| recent.kind == kind && recent.path == path);
+    let id = match existing {
+      Some(index) => recents.remove(index).id,
+      None => new_id(),
+    };
+    recents.insert(
+      0,
+      Recent {
+        id,
+        kind,
+        label,
+        path,
+        opened_at: Utc::now(),
+      },
+    );
+    recents.truncate(MAX_RECENTS);
+  })?;
+  rebuild(&app);
+  Ok(recents)
+}
+
+#[tauri::command]
+pub fn get_recents(app: AppHandle) -> Vec<Recent> {
+  current(&app)
+}
+
+#[tauri::command]
+pub fn clear_recents(app: AppHandle) -> Result<(), String> {
+  change(&app, Vec::clear)?;
+  rebuild(&app);
+  Ok(())
+}
+
+/// Shows the app and hands the entry to the frontend.
+pub fn open(app: &AppHandle, id: &str) {
+  let Some(recent) = current(app).into_iter().find(|recent| recent.id == id) else {
+    warn!("no recent entry {id} to open");
+    return;
+  };
+  info!("opening recent {} {:?}", recent.kind, recent.path);
+  let main_app = app.clone();
+  let _ = app.run_on_main_thread(move || {
+    if let Err(err) = windows::show_main_window(&main_app) {
+      error!("failed to show main window: {err}");
+    }
+    let _ = main_app.emit_to(windows::MAIN_WINDOW, "open-recent", recent);
+  });
+}
+
+fn current(app: &AppHandle) -> Vec<Recent> {
+  app
+    .try_state::<RecentsState>()
+    .and_then(|state| state.0.lock().ok().map(|recents| recents.clone()))
+    .unwrap_or_default()
+}
+
+/// Applies `edit` to the list and saves it, returning the result.
+fn change(app: &AppHandle, edit: impl FnOnce(&mut Vec<Recent>)) -> Result<Vec<Recent>, String> {
+  let state = app
+    .try_state::<RecentsState>()
+    .ok_or_else(|| "recents aren't loaded".to_string())?;
+  let mut recents = state.0.lock().map_err(|err| err.to_string())?;
+  edit(&mut recents);
+  save(app, &recents);
+  Ok(recents.clone())
+}
+
+/// Drops entries whose path is gone, then rebuilds the Dock menu or Jump List.
+fn rebuild(app: &AppHandle) {
+  let recents = match change(app, |recents| recents.retain(|recent| recent.path.exists())) {
+    Ok(recents) => recents,
+    Err(err) => {
+      warn!("failed to prune recents: {err}");
+      return;
+    }
+  };
+  let shown: Vec<Recent> = recents.into_iter().take(LAUNCHER_ENTRIES).collect();
+  #[cfg(target_os = "macos")]
+  dock::set_entries(shown);
+  #[cfg(windows)]
+  jump_list::set_entries(shown);
+  #[cfg(not(any(target_os = "macos", windows)))]
+  let _ = shown;
+}
+
+fn new_id() -> String {
+  format!("{:x}", Utc::now().timestamp_micros())
+}
+
+fn recents_path(app: &AppHandle) -> Option<PathBuf> {
+  config::config_dir(app).map(|dir| dir.join(RECENTS_FILE))
+}
+
+/// The saved list; a missing or corrupted file means an empty one.
+fn load(app: &AppHandle) -> Vec<Recent> {
+  let Some(path) = recents_path(app) else {
+    return Vec::new();
+  };
+  let Ok(raw) = std::fs::read(&path) else {
+    return Vec::new();
+  };
+  serde_json::from_slice(&raw).unwrap_or_else(|err| {
+    warn!("ignoring unreadable recents {:?}: {err}", path);
+    Vec::new()
+  })
+}
+
+fn save(app: &AppHandle, recents: &[Recent]) {
+  let Some(path) = recents_path(app) else {
+    return;
+  };
+  let result = serde_json::to_vec_pretty(recents)
+    .map_err(std::io::Error::other)
+    .and_then(|bytes| {
+      if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+      }
+      std::fs::write(&path, bytes)
+    });
+  if let Err(err) = result {
+    warn!("failed to save recents {:?}: {err}", path);
+  }
+}
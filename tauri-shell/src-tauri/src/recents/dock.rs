@@ -0,0 +1,125 @@
+//! The recents in the Dock icon's menu. Tauri has no Dock menu, so the app delegate is taught
+//! `applicationDockMenu:`, which AppKit asks each time the menu opens; it builds the menu from
+//! the entries last set, with items that call back into `open`.
+
+use std::sync::{Mutex, OnceLock};
+
+use cocoa::base::{id, nil};
+use cocoa::foundation::NSString;
+use log::warn;
+use objc::declare::ClassDecl;
+use objc::runtime::{class_addMethod, Class, Object, Sel, NO};
+use objc::{class, msg_send, sel, sel_impl};
+use tauri::AppHandle;
+
+use super::Recent;
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+/// The ids and labels of the entries shown, in order; an item's tag is its index here.
+static ENTRIES: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+/// The items' target, retained for the life of the process.
+static TARGET: OnceLock<usize> = OnceLock::new();
+
+pub fn install(app: &AppHandle) {
+  if APP.set(app.clone()).is_err() {
+    return;
+  }
+  let result = app.run_on_main_thread(|| {
+    // SAFETY: on the main thread, after the app delegate is in place. Adding a method the
+    // delegate's class doesn't have changes nothing else about it.
+    unsafe {
+      let Some(target) = target() else {
+        warn!("failed to create the Dock menu target");
+        return;
+      };
+      let _ = TARGET.set(target as usize);
+      let ns_app: id = msg_send![class!(NSApplication), sharedApplication];
+      let delegate: id = msg_send![ns_app, delegate];
+      if delegate == nil {
+        warn!("no app delegate for the Dock menu");
+        return;
+      }
+      let class: *mut Class = msg_send![delegate, class];
+      let imp: extern "C" fn(&Object, Sel, id) -> id = dock_menu;
+      let added = class_addMethod(
+        class,
+        sel!(applicationDockMenu:),
+        std::mem::transmute::<extern "C" fn(&Object, Sel, id) -> id, objc::runtime::Imp>(imp),
+        c"@@:@".as_ptr(),
+      );
+      if added == NO {
+        warn!("the app delegate already has a Dock menu");
+      }
+    }
+  });
+  if let Err(err) = result {
+    warn!("failed to install the Dock menu: {err}");
+  }
+}
+
+pub fn set_entries(recents: Vec<Recent>) {
+  if let Ok(mut entries) = ENTRIES.lock() {
+    *entries = recents.into_iter().map(|recent| (recent.id, recent.label)).collect();
+  }
+}
+
+/// An instance of a small class whose `openRecent:` opens the sender's entry.
+unsafe fn target() -> Option<id> {
+  let class = match ClassDecl::new("PlutoDuckRecentsTarget", class!(NSObject)) {
+    Some(mut decl) => {
+      decl.add_method(
+        sel!(openRecent:),
+        open_recent as extern "C" fn(&Object, Sel, id),
+      );
+      decl.register()
+    }
+    None => Class::get("PlutoDuckRecentsTarget")?,
+  };
+  let target: id = msg_send![class, new];
+  (target != nil).then_some(target)
+}
+
+extern "C" fn dock_menu(_this: &Object, _sel: Sel, _app: id) -> id {
+  let entries = ENTRIES.lock().map(|entries| entries.clone()).unwrap_or_default();
+  let Some(target) = TARGET.get().map(|target| *target as id) else {
+    return nil;
+  };
+  if entries.is_empty() {
+    return nil;
+  }
+  // SAFETY: called by AppKit on the main thread; the menu is autoreleased for AppKit to
+  // retain, and everything else created here is released once the menu holds it.
+  unsafe {
+    let menu: id = msg_send![class!(NSMenu), new];
+    let empty = NSString::alloc(nil).init_str("");
+    for (index, (_, label)) in entries.iter().enumerate() {
+      let title = NSString::alloc(nil).init_str(label);
+      let item: id = msg_send![class!(NSMenuItem), alloc];
+      let item: id = msg_send![
+        item,
+        initWithTitle: title
+        action: sel!(openRecent:)
+        keyEquivalent: empty
+      ];
+      let _: () = msg_send![item, setTarget: target];
+      let _: () = msg_send![item, setTag: index as isize];
+      let _: () = msg_send![menu, addItem: item];
+      let _: () = msg_send![item, release];
+      let _: () = msg_send![title, release];
+    }
+    let _: () = msg_send![empty, release];
+    msg_send![menu, autorelease]
+  }
+}
+
+extern "C" fn open_recent(_this: &Object, _sel: Sel, sender: id) {
+  // SAFETY: `sender` is the NSMenuItem that was chosen.
+  let tag: isize = unsafe { msg_send![sender, tag] };
+  let id = ENTRIES
+    .lock()
+    .ok()
+    .and_then(|entries| entries.get(tag as usize).map(|(id, _)| id.clone()));
+  if let (Some(app), Some(id)) = (APP.get(), id) {
+    super::open(app, &id);
+  }
+}
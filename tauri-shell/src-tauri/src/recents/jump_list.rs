@@ -0,0 +1,75 @@
+//! The recents in the taskbar button's Jump List, as a "Recent" category of shortcuts that start
+//! the app with an `open-recent` link.
+
+use log::{debug, warn};
+use windows::core::{Interface, HSTRING, PCWSTR};
+use windows::Win32::Foundation::E_FAIL;
+use windows::Win32::Storage::EnhancedStorage::PKEY_Title;
+use windows::Win32::System::Com::StructuredStorage::PROPVARIANT;
+use windows::Win32::System::Com::{
+  CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_INPROC_SERVER,
+  COINIT_APARTMENTTHREADED,
+};
+use windows::Win32::UI::Shell::Common::{IObjectArray, IObjectCollection};
+use windows::Win32::UI::Shell::PropertiesSystem::IPropertyStore;
+use windows::Win32::UI::Shell::{
+  DestinationList, EnumerableObjectCollection, ICustomDestinationList, IShellLinkW, ShellLink,
+};
+
+use super::{Recent, OPEN_ACTION};
+use crate::deep_link;
+
+/// Replaces the Jump List's recents with `recents`. COM wants a thread of its own.
+pub fn set_entries(recents: Vec<Recent>) {
+  let spawned = std::thread::Builder::new()
+    .name("jump-list".into())
+    .spawn(move || {
+      // SAFETY: COM is initialized for this thread for as long as the list is built on it.
+      let result = unsafe {
+        let initialized = CoInitializeEx(None, COINIT_APARTMENTTHREADED).is_ok();
+        let result = build(&recents);
+        if initialized {
+          CoUninitialize();
+        }
+        result
+      };
+      match result {
+        Ok(()) => debug!("jump list updated with {} entries", recents.len()),
+        Err(err) => warn!("failed to update the jump list: {err}"),
+      }
+    });
+  if let Err(err) = spawned {
+    warn!("failed to start the jump list thread: {err}");
+  }
+}
+
+unsafe fn build(recents: &[Recent]) -> windows::core::Result<()> {
+  let exe = std::env::current_exe()
+    .map_err(|err| windows::core::Error::new(E_FAIL, err.to_string()))?;
+  let exe = HSTRING::from(exe.as_os_str());
+  let list: ICustomDestinationList =
+    CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)?;
+  if recents.is_empty() {
+    return list.DeleteList(PCWSTR::null());
+  }
+  let mut max_slots = 0u32;
+  let _removed: IObjectArray = list.BeginList(&mut max_slots)?;
+  let collection: IObjectCollection =
+    CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+  for recent in recents.iter().take(max_slots as usize) {
+    let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+    link.SetPath(&exe)?;
+    let url = format!("{}://{OPEN_ACTION}?id={}", deep_link::SCHEME, recent.id);
+    link.SetArguments(&HSTRING::from(format!("\"{url}\"")))?;
+    link.SetDescription(&HSTRING::from(recent.path.as_os_str()))?;
+    link.SetIconLocation(&exe, 0)?;
+    // The title is what the list shows.
+    let store: IPropertyStore = link.cast()?;
+    store.SetValue(&PKEY_Title, &PROPVARIANT::from(recent.label.as_str()))?;
+    store.Commit()?;
+    collection.AddObject(&link)?;
+  }
+  let array: IObjectArray = collection.cast()?;
+  list.AppendCategory(&HSTRING::from("Recent"), &array)?;
+  list.CommitList()
+}
@@ -0,0 +1,119 @@
+//! Credentials for the backend's remote connectors (Postgres passwords, S3 keys), which the
+//! frontend can't keep anywhere safe itself. They go to the OS store: the Keychain on macOS,
+//! Credential Manager on Windows, and the Secret Service (GNOME Keyring, KWallet) through
+//! libsecret's D-Bus API on Linux. Entries are filed under the app's identifier, with the
+//! active profile's name in the account, so each profile sees only its own.
+//!
+//! A Linux session without a Secret Service gets an encrypted file instead; see `file`.
+//!
+//! Values are never logged, and nothing here is part of backups or the diagnostics bundle.
+
+use log::info;
+use tauri::AppHandle;
+
+use crate::audit;
+use crate::backend::profiles;
+
+#[cfg(target_os = "linux")]
+mod file;
+
+/// Longest key accepted; Credential Manager caps the whole target name at 32 KiB, but nothing
+/// needs keys anywhere near that.
+const MAX_KEY_LEN: usize = 256;
+
+/// Only the key goes to the audit log, never the value.
+#[tauri::command]
+pub async fn secret_set(app: AppHandle, key: String, value: String) -> Result<(), String> {
+  let result = set(app.clone(), key.clone(), value).await;
+  audit::record(&app, "secret_set", serde_json::json!({ "key": key }), &result);
+  result
+}
+
+async fn set(app: AppHandle, key: String, value: String) -> Result<(), String> {
+  let account = account(&app, &key)?;
+  blocking(move || {
+    match entry(&app, &account)?.set_password(&value) {
+      Ok(()) => {}
+      #[cfg(target_os = "linux")]
+      Err(err) if unavailable(&err) => {
+        log::warn!("no secret service ({err}); saving {account} to the encrypted file");
+        return file::set(&app, &account, &value);
+      }
+      Err(err) => return Err(format!("failed to save secret {key}: {err}")),
+    }
+    info!("saved secret {account}");
+    Ok(())
+  })
+  .await
+}
+
+#[tauri::command]
+pub async fn secret_get(app: AppHandle, key: String) -> Result<Option<String>, String> {
+  let account = account(&app, &key)?;
+  blocking(move || match entry(&app, &account)?.get_password() {
+    Ok(value) => Ok(Some(value)),
+    // Saved while the secret service was away, perhaps.
+    #[cfg(target_os = "linux")]
+    Err(keyring::Error::NoEntry) => file::get(&app, &account),
+    #[cfg(not(target_os = "linux"))]
+    Err(keyring::Error::NoEntry) => Ok(None),
+    #[cfg(target_os = "linux")]
+    Err(err) if unavailable(&err) => file::get(&app, &account),
+    Err(err) => Err(format!("failed to read secret {key}: {err}")),
+  })
+  .await
+}
+
+#[tauri::command]
+pub async fn secret_delete(app: AppHandle, key: String) -> Result<(), String> {
+  let result = delete(app.clone(), key.clone()).await;
+  audit::record(&app, "secret_delete", serde_json::json!({ "key": key }), &result);
+  result
+}
+
+async fn delete(app: AppHandle, key: String) -> Result<(), String> {
+  let account = account(&app, &key)?;
+  blocking(move || {
+    match entry(&app, &account)?.delete_credential() {
+      Ok(()) | Err(keyring::Error::NoEntry) => {}
+      #[cfg(target_os = "linux")]
+      Err(err) if unavailable(&err) => {}
+      Err(err) => return Err(format!("failed to delete secret {key}: {err}")),
+    }
+    // A value saved while the secret service was away may still be in the file.
+    #[cfg(target_os = "linux")]
+    file::delete(&app, &account)?;
+    info!("deleted secret {account}");
+    Ok(())
+  })
+  .await
+}
+
+/// `{profile}/{key}`, once the key is checked.
+fn account(app: &AppHandle, key: &str) -> Result<String, String> {
+  if key.trim().is_empty() || key.len() > MAX_KEY_LEN || key.chars().any(char::is_control) {
+    return Err(format!("invalid secret key {key:?}"));
+  }
+  let profile = profiles::active_name(app).unwrap_or_else(|| profiles::DEFAULT_PROFILE.into());
+  Ok(format!("{profile}/{key}"))
+}
+
+fn entry(app: &AppHandle, account: &str) -> Result<keyring::Entry, String> {
+  keyring::Entry::new(&app.config().identifier, account)
+    .map_err(|err| format!("invalid secret key {account}: {err}"))
+}
+
+/// The OS stores can block on a prompt or D-Bus, so they're used off the async runtime.
+async fn blocking<T: Send + 'static>(
+  work: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+  tauri::async_runtime::spawn_blocking(work)
+    .await
+    .map_err(|err| err.to_string())?
+}
+
+/// Whether `err` means there's no secret service to talk to, rather than a failure of one.
+#[cfg(target_os = "linux")]
+fn unavailable(err: &keyring::Error) -> bool {
+  matches!(err, keyring::Error::NoStorageAccess(_) | keyring::Error::PlatformFailure(_))
+}
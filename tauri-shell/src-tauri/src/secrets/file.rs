@@ -0,0 +1,119 @@
+//! Where secrets go on a Linux session with no Secret Service (a bare window manager, a
+//! container, SSH with X forwarding): `secrets.json` in the config directory, each value
+//! encrypted with ChaCha20-Poly1305 under a random key kept beside it in `secrets.key`. Both
+//! files are readable only by the user. This keeps values out of plain sight, and out of
+//! backups, which only take the data root; it is no defense against someone who can read the
+//! user's files, which is what the Secret Service is for.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use tauri::AppHandle;
+
+use crate::config;
+
+const SECRETS_FILE: &str = "secrets.json";
+const KEY_FILE: &str = "secrets.key";
+const NONCE_LEN: usize = 12;
+
+pub fn get(app: &AppHandle, account: &str) -> Result<Option<String>, String> {
+  let dir = dir(app)?;
+  let Some(encoded) = read(&dir)?.remove(account) else {
+    return Ok(None);
+  };
+  let sealed = base64::engine::general_purpose::STANDARD
+    .decode(encoded)
+    .map_err(|_| format!("the saved secret {account} is corrupted"))?;
+  if sealed.len() < NONCE_LEN {
+    return Err(format!("the saved secret {account} is corrupted"));
+  }
+  let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+  let plaintext = cipher(&dir)?
+    .decrypt(Nonce::from_slice(nonce), ciphertext)
+    .map_err(|_| format!("the saved secret {account} can't be decrypted"))?;
+  String::from_utf8(plaintext)
+    .map(Some)
+    .map_err(|_| format!("the saved secret {account} is corrupted"))
+}
+
+pub fn set(app: &AppHandle, account: &str, value: &str) -> Result<(), String> {
+  let dir = dir(app)?;
+  let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+  let ciphertext = cipher(&dir)?
+    .encrypt(&nonce, value.as_bytes())
+    .map_err(|_| format!("failed to encrypt secret {account}"))?;
+  let mut sealed = nonce.to_vec();
+  sealed.extend_from_slice(&ciphertext);
+  let mut secrets = read(&dir)?;
+  secrets.insert(
+    account.to_string(),
+    base64::engine::general_purpose::STANDARD.encode(sealed),
+  );
+  write(&dir, &secrets)
+}
+
+pub fn delete(app: &AppHandle, account: &str) -> Result<(), String> {
+  let dir = dir(app)?;
+  let mut secrets = read(&dir)?;
+  if secrets.remove(account).is_some() {
+    write(&dir, &secrets)?;
+  }
+  Ok(())
+}
+
+fn dir(app: &AppHandle) -> Result<PathBuf, String> {
+  config::config_dir(app).ok_or_else(|| "app config directory unavailable".to_string())
+}
+
+/// The cipher for `dir`'s key file, made on first use.
+fn cipher(dir: &Path) -> Result<ChaCha20Poly1305, String> {
+  let path = dir.join(KEY_FILE);
+  match std::fs::read(&path) {
+    Ok(key) if key.len() == 32 => return Ok(ChaCha20Poly1305::new(Key::from_slice(&key))),
+    Ok(_) => return Err(format!("{} is corrupted", path.display())),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+    Err(err) => return Err(format!("failed to read {}: {err}", path.display())),
+  }
+  let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+  write_private(&path, &key).map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+  Ok(ChaCha20Poly1305::new(&key))
+}
+
+fn read(dir: &Path) -> Result<BTreeMap<String, String>, String> {
+  let path = dir.join(SECRETS_FILE);
+  match std::fs::read(&path) {
+    Ok(raw) => serde_json::from_slice(&raw)
+      .map_err(|err| format!("{} is corrupted: {err}", path.display())),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+    Err(err) => Err(format!("failed to read {}: {err}", path.display())),
+  }
+}
+
+/// Replaces the file through a temporary one, so a crash leaves the old secrets intact.
+fn write(dir: &Path, secrets: &BTreeMap<String, String>) -> Result<(), String> {
+  let path = dir.join(SECRETS_FILE);
+  let staged = dir.join(format!(".{SECRETS_FILE}.tmp"));
+  let bytes = serde_json::to_vec_pretty(secrets).map_err(|err| err.to_string())?;
+  write_private(&staged, &bytes)
+    .and_then(|()| std::fs::rename(&staged, &path))
+    .map_err(|err| format!("failed to write {}: {err}", path.display()))
+}
+
+fn write_private(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir)?;
+  }
+  let mut file = std::fs::OpenOptions::new()
+    .write(true)
+    .create(true)
+    .truncate(true)
+    .mode(0o600)
+    .open(path)?;
+  file.write_all(bytes)?;
+  file.sync_all()
+}
@@ -0,0 +1,163 @@
+//! The system-wide shortcuts: one brings the main window back (or tucks it away), since
+//! closing it only hides it, and one opens the quick-query palette.
+
+use std::sync::Mutex;
+
+use log::{error, info, warn};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+use crate::config::{self, ShellConfig};
+use crate::{audit, windows};
+
+/// The shortcuts currently registered, if any.
+#[derive(Default)]
+pub struct GlobalShortcut {
+  main: Mutex<Option<Shortcut>>,
+  palette: Mutex<Option<Shortcut>>,
+}
+
+/// Registers the configured shortcuts. Failure (e.g. another app owns one) is logged and the
+/// app carries on without it.
+pub fn register_configured(app: &AppHandle) {
+  app.manage(GlobalShortcut::default());
+  let (main, palette) = app
+    .try_state::<ShellConfig>()
+    .map(|config| {
+      (
+        config.window.global_shortcut.clone(),
+        config.window.palette_shortcut.clone(),
+      )
+    })
+    .unwrap_or_default();
+  if let Err(err) = replace(app, &main) {
+    warn!("global shortcut unavailable: {err}");
+  }
+  if let Err(err) = replace_palette(app, &palette) {
+    warn!("palette shortcut unavailable: {err}");
+  }
+}
+
+/// Plugin handler for every registered shortcut.
+pub fn handle(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+  if event.state != ShortcutState::Pressed {
+    return;
+  }
+  let Some(state) = app.try_state::<GlobalShortcut>() else {
+    return;
+  };
+  let is = |slot: &Mutex<Option<Shortcut>>| {
+    slot.lock().ok().and_then(|current| *current).as_ref() == Some(shortcut)
+  };
+  if is(&state.main) {
+    toggle_main_window(app);
+  } else if is(&state.palette) {
+    windows::palette::toggle(app);
+  }
+}
+
+/// Hidden (or in the background) → shown and focused; focused → hidden.
+fn toggle_main_window(app: &AppHandle) {
+  let focused = app
+    .get_webview_window(windows::MAIN_WINDOW)
+    .is_some_and(|window| {
+      window.is_visible().unwrap_or(false) && window.is_focused().unwrap_or(false)
+    });
+  let result = if focused {
+    windows::main_window(app).and_then(|window| window.hide())
+  } else {
+    windows::show_main_window(app)
+  };
+  if let Err(err) = result {
+    error!("failed to toggle main window: {err}");
+  }
+}
+
+/// Swaps the registered show/hide shortcut for `accelerator` (empty unregisters it). If the new
+/// one can't be registered, the old one is kept.
+pub fn replace(app: &AppHandle, accelerator: &str) -> Result<(), String> {
+  let state = app
+    .try_state::<GlobalShortcut>()
+    .ok_or_else(|| "global shortcuts are not set up".to_string())?;
+  swap(app, &state.main, accelerator)
+}
+
+/// Like `replace`, for the palette's shortcut.
+pub fn replace_palette(app: &AppHandle, accelerator: &str) -> Result<(), String> {
+  let state = app
+    .try_state::<GlobalShortcut>()
+    .ok_or_else(|| "global shortcuts are not set up".to_string())?;
+  swap(app, &state.palette, accelerator)
+}
+
+fn swap(
+  app: &AppHandle,
+  slot: &Mutex<Option<Shortcut>>,
+  accelerator: &str,
+) -> Result<(), String> {
+  let accelerator = accelerator.trim();
+  let new = if accelerator.is_empty() {
+    None
+  } else {
+    Some(
+      accelerator
+        .parse::<Shortcut>()
+        .map_err(|err| format!("invalid shortcut {accelerator:?}: {err}"))?,
+    )
+  };
+  let mut current = slot
+    .lock()
+    .map_err(|_| "global shortcut state is poisoned".to_string())?;
+  if *current == new {
+    return Ok(());
+  }
+
+  let shortcuts = app.global_shortcut();
+  if let Some(old) = *current {
+    if let Err(err) = shortcuts.unregister(old) {
+      warn!("failed to unregister global shortcut: {err}");
+    }
+  }
+  if let Some(shortcut) = new {
+    if let Err(err) = shortcuts.register(shortcut) {
+      if let Some(old) = *current {
+        let _ = shortcuts.register(old);
+      }
+      return Err(format!(
+        "could not register {accelerator:?} (another app may be using it): {err}"
+      ));
+    }
+    info!("global shortcut {accelerator} registered");
+  }
+  *current = new;
+  Ok(())
+}
+
+/// Registers `accel` as the show/hide shortcut (empty disables it) and saves it.
+#[tauri::command]
+pub fn set_global_shortcut(app: AppHandle, accel: String) -> Result<(), String> {
+  let result = replace(&app, &accel).inspect_err(|err| warn!("{err}")).and_then(|()| {
+    config::update(&app, |config| config.window.global_shortcut = accel.trim().to_string())
+      .map_err(|err| format!("shortcut changed but could not be saved: {err:#}"))
+  });
+  audit::record(&app, "set_global_shortcut", serde_json::json!({ "accel": accel }), &result);
+  result
+}
+
+/// Registers `accel` as the palette's shortcut (empty disables it) and saves it.
+#[tauri::command]
+pub fn set_palette_shortcut(app: AppHandle, accel: String) -> Result<(), String> {
+  let result = replace_palette(&app, &accel).inspect_err(|err| warn!("{err}")).and_then(|()| {
+    config::update(&app, |config| config.window.palette_shortcut = accel.trim().to_string())
+      .map_err(|err| format!("shortcut changed but could not be saved: {err:#}"))
+  });
+  audit::record(&app, "set_palette_shortcut", serde_json::json!({ "accel": accel }), &result);
+  result
+}
+
+/// Releases every shortcut; called on exit.
+pub fn unregister_all(app: &AppHandle) {
+  if let Err(err) = app.global_shortcut().unregister_all() {
+    warn!("failed to unregister global shortcuts: {err}");
+  }
+}
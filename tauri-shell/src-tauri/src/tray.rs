@@ -0,0 +1,145 @@
+//! Tray icon: the way back to a hidden window on platforms without a Dock, plus backend status
+//! and quick actions.
+
+use log::{error, info, warn};
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Listener, Manager, Wry};
+
+use crate::{backend, windows};
+
+const TRAY_ID: &str = "main";
+const SHOW_MAIN: &str = "tray-show-main";
+const BACKEND_STATUS: &str = "tray-backend-status";
+const RESTART_BACKEND: &str = "tray-restart-backend";
+const QUIT: &str = "tray-quit";
+
+/// Backend events after which the status line is re-read from the backend state.
+const STATUS_EVENTS: &[&str] = &[
+  "backend-spawned",
+  "backend-restarted",
+  "backend-ready",
+  "backend-exited",
+  "backend-stopped",
+];
+
+/// The tray's status line, kept so it can be updated as the backend starts and stops.
+struct TrayStatus(MenuItem<Wry>);
+
+/// Adds the tray icon, saying whether there is one. Failure (e.g. no tray host on a Linux
+/// desktop) is logged, and the app carries on without one.
+pub fn install(app: &AppHandle) -> bool {
+  match build(app) {
+    Ok(Ok(_)) => {
+      info!("tray icon installed");
+      return true;
+    }
+    Ok(Err(err)) => warn!("tray icon unavailable: {err}"),
+    Err(_) => warn!("tray icon unavailable: the tray library panicked while loading"),
+  }
+  false
+}
+
+fn build(app: &AppHandle) -> std::thread::Result<tauri::Result<TrayIcon>> {
+  // libappindicator is loaded at runtime on Linux and panics if it's missing, so contain that.
+  std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+    let status = MenuItem::with_id(app, BACKEND_STATUS, status_text(app), false, None::<&str>)?;
+    let menu = Menu::with_items(
+      app,
+      &[
+        &MenuItem::with_id(app, SHOW_MAIN, "Show Pluto Duck", true, None::<&str>)?,
+        &PredefinedMenuItem::separator(app)?,
+        &status,
+        &MenuItem::with_id(
+          app,
+          RESTART_BACKEND,
+          "Restart backend",
+          backend::is_running(app).is_some(),
+          None::<&str>,
+        )?,
+        &PredefinedMenuItem::separator(app)?,
+        &MenuItem::with_id(app, QUIT, "Quit", true, None::<&str>)?,
+      ],
+    )?;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+      .tooltip("Pluto Duck")
+      .menu(&menu)
+      .show_menu_on_left_click(false)
+      .on_menu_event(handle_menu_event)
+      .on_tray_icon_event(|tray, event| {
+        if let TrayIconEvent::Click {
+          button: MouseButton::Left,
+          button_state: MouseButtonState::Up,
+          ..
+        } = event
+        {
+          toggle_main_window(tray.app_handle());
+        }
+      });
+    if let Some(icon) = app.default_window_icon() {
+      builder = builder.icon(icon.clone());
+    }
+    let tray = builder.build(app)?;
+
+    app.manage(TrayStatus(status));
+    for event in STATUS_EVENTS {
+      let app_handle = app.clone();
+      app.listen_any(*event, move |_| {
+        // Backend events can be sent with the backend lock held; read the state elsewhere.
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn_blocking(move || refresh_status(&app_handle));
+      });
+    }
+    Ok(tray)
+  }))
+}
+
+fn status_text(app: &AppHandle) -> &'static str {
+  match backend::is_running(app) {
+    Some(true) => "Backend: running",
+    Some(false) => "Backend: stopped",
+    None => "Backend: external",
+  }
+}
+
+fn refresh_status(app: &AppHandle) {
+  if let Some(status) = app.try_state::<TrayStatus>() {
+    if let Err(err) = status.0.set_text(status_text(app)) {
+      warn!("failed to update tray status: {err}");
+    }
+  }
+}
+
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+  if event.id() == SHOW_MAIN {
+    if let Err(err) = windows::show_main_style_windows(app) {
+      error!("failed to show main windows: {err}");
+    }
+  } else if event.id() == RESTART_BACKEND {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+      if let Err(err) = backend::restart_backend(app).await {
+        error!("backend restart from tray failed: {err}");
+      }
+    });
+  } else if event.id() == QUIT {
+    // RunEvent::Exit stops the backend.
+    app.exit(0);
+  }
+}
+
+fn toggle_main_window(app: &AppHandle) {
+  let visible = app
+    .get_webview_window(windows::MAIN_WINDOW)
+    .and_then(|window| window.is_visible().ok())
+    .unwrap_or(false);
+  let result = if visible {
+    windows::main_window(app).and_then(|window| window.hide())
+  } else {
+    windows::show_main_window(app)
+  };
+  if let Err(err) = result {
+    error!("failed to toggle main window: {err}");
+  }
+}
@@ -0,0 +1,676 @@
+//! Update checks against the release channel picked in settings. Each channel has its own
+//! manifest, found by filling `{channel}` into the `updates.endpoint` template, and switching
+//! channels checks the new one right away. A channel whose newest release is older than the
+//! running build (a beta build on the stable channel) is reported as such, not offered as a
+//! downgrade.
+//!
+//! The frontend drives the update UI with `check_for_updates` and `install_update`; the menu
+//! item falls back to native dialogs when there is no window to show it. Machines that can't
+//! reach the update server can install a downloaded bundle instead (see `offline`).
+//!
+//! Installing never cuts the backend off mid-write: where the installer ends the process itself
+//! (Windows), the usual exit cleanup, graceful backend shutdown included, runs first, and
+//! elsewhere the restart goes through `RunEvent::Exit` as usual. The new version's first launch
+//! is watched, so a backend broken by the update is reported as such.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Listener, Manager, Url};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::config::{self, ShellConfig, UpdateChannel};
+use crate::{audit, portable, proxy, windows};
+
+mod notification;
+pub mod notes;
+pub mod offline;
+
+/// `{channel}` is `latest` for stable, which is the manifest builds before channels read, and
+/// `beta` for beta. The updater's own `{{current_version}}`, `{{target}}`, and `{{arch}}` work
+/// too.
+pub const DEFAULT_ENDPOINT: &str = "https://fluxloop-ai.github.io/pluto-duck-oss/{channel}.json";
+/// How often the automatic check runs again while the app stays open; its own settings decide
+/// whether it asks the server or offers anything.
+const RECHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+/// Written just before an update is installed, and removed once the new version's backend has
+/// come up.
+const INSTALL_RECORD: &str = "installed-update.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct InstallRecord {
+  from: String,
+  to: String,
+}
+
+/// The channel in effect; starts from the config and changes with `set_update_channel`.
+pub struct UpdateChannelState(Mutex<UpdateChannel>);
+
+impl UpdateChannelState {
+  pub fn new(channel: UpdateChannel) -> Self {
+    Self(Mutex::new(channel))
+  }
+}
+
+pub struct UpdateState {
+  /// The update found by the last check, kept for `install_update`.
+  pending: Mutex<Option<Update>>,
+  installing: AtomicBool,
+  /// The version this one replaced, on its first launch after the update.
+  updated_from: Option<String>,
+}
+
+impl UpdateState {
+  /// Picks up the record of an update installed by the previous run, if this is its first
+  /// launch, and clears it once the backend is ready.
+  pub fn load(app: &AppHandle) -> Self {
+    let mut updated_from = None;
+    if let Some(record) = install_record(app).and_then(|path| read_record(&path)) {
+      let current = app.package_info().version.to_string();
+      if record.to == current {
+        info!("first launch after updating from {}", record.from);
+        updated_from = Some(record.from);
+        let ready_app = app.clone();
+        app.once_any("backend-ready", move |_| remove_record(&ready_app));
+      } else if record.from != current {
+        // Neither the old version still waiting for a restart nor the new one.
+        remove_record(app);
+      }
+    }
+    Self {
+      pending: Mutex::new(None),
+      installing: AtomicBool::new(false),
+      updated_from,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UpdateStatus {
+  UpToDate,
+  Available,
+  /// The channel's newest release is older than this build.
+  AheadOfChannel,
+}
+
+/// The outcome of a check, also emitted as `update-status`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCheck {
+  channel: UpdateChannel,
+  status: UpdateStatus,
+  available: bool,
+  current_version: String,
+  /// The channel's newest release, when the manifest named one.
+  version: Option<String>,
+  /// Markdown release notes for an available `version`, when there are any.
+  notes: Option<String>,
+  /// Release date of `version`, RFC 3339.
+  date: Option<String>,
+}
+
+/// Why a check or install failed, tagged so the frontend can word each case.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum UpdateError {
+  /// Portable copies don't update in place.
+  Disabled,
+  /// The update server couldn't be reached.
+  Offline { message: String },
+  /// The server answered, but not with a manifest (e.g. a 404).
+  EndpointNotFound { url: String },
+  InvalidEndpoint { message: String },
+  /// The manifest didn't parse or has no build for this platform.
+  InvalidManifest { message: String },
+  /// The download (or bundle file) didn't match its signature.
+  Signature { message: String },
+  /// A bundle file for another OS or architecture, or not a bundle at all. `platform` is the
+  /// OS it is for, when it's recognisably an update; `expected` lists the name endings this
+  /// platform takes.
+  WrongPlatform {
+    file: String,
+    platform: Option<String>,
+    expected: Vec<String>,
+  },
+  /// A bundle file whose contents don't match its name.
+  InvalidBundle { message: String },
+  /// A bundle file for an older version than the running one.
+  Downgrade { version: String, current: String },
+  /// `install_update` found nothing newer to install.
+  NoUpdate,
+  /// An install is already running.
+  InProgress,
+  Failed { message: String },
+}
+
+impl UpdateError {
+  fn new(err: tauri_plugin_updater::Error, url: &Url) -> Self {
+    use tauri_plugin_updater::Error;
+
+    let message = err.to_string();
+    match err {
+      Error::ReleaseNotFound => Self::EndpointNotFound {
+        url: url.to_string(),
+      },
+      Error::Reqwest(_) | Error::Network(_) => Self::Offline { message },
+      Error::EmptyEndpoints | Error::UrlParse(_) | Error::InsecureTransportProtocol => {
+        Self::InvalidEndpoint { message }
+      }
+      Error::Serialization(_) | Error::Semver(_) | Error::TargetNotFound(_) => {
+        Self::InvalidManifest { message }
+      }
+      Error::Minisign(_) | Error::Base64(_) | Error::SignatureUtf8(_) => {
+        Self::Signature { message }
+      }
+      _ => Self::Failed { message },
+    }
+  }
+}
+
+impl fmt::Display for UpdateError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Disabled => write!(f, "updates are disabled in portable mode"),
+      Self::Offline { message } => write!(f, "the update server couldn't be reached: {message}"),
+      Self::EndpointNotFound { url } => write!(f, "no update manifest at {url}"),
+      Self::InvalidEndpoint { message } => write!(f, "invalid update endpoint: {message}"),
+      Self::InvalidManifest { message } => write!(f, "invalid update manifest: {message}"),
+      Self::Signature { message } => write!(f, "the update's signature didn't match: {message}"),
+      Self::WrongPlatform {
+        file,
+        platform,
+        expected,
+      } => {
+        match platform.as_deref() {
+          Some("macos") => write!(f, "{file} is an update for macOS")?,
+          Some("windows") => write!(f, "{file} is an update for Windows")?,
+          Some("linux") => write!(f, "{file} is an update for Linux")?,
+          _ => write!(f, "{file} isn't an update for this computer")?,
+        }
+        write!(f, "; use the {} file", expected.join(" or "))
+      }
+      Self::InvalidBundle { message } => write!(f, "{message}"),
+      Self::Downgrade { version, current } => {
+        write!(f, "the file is for version {version}, older than the installed {current}")
+      }
+      Self::NoUpdate => write!(f, "there is no update to install"),
+      Self::InProgress => write!(f, "an update is already being installed"),
+      Self::Failed { message } => write!(f, "{message}"),
+    }
+  }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadProgress {
+  downloaded: u64,
+  /// `None` when the server didn't say.
+  total: Option<u64>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateReady {
+  /// `None` for a bundle file that didn't say.
+  version: Option<String>,
+}
+
+pub fn channel(app: &AppHandle) -> UpdateChannel {
+  app
+    .try_state::<UpdateChannelState>()
+    .and_then(|state| state.0.lock().ok().map(|channel| *channel))
+    .unwrap_or_default()
+}
+
+/// Switches to `channel` for this run, without saving or checking.
+pub fn set_channel(app: &AppHandle, channel: UpdateChannel) {
+  if let Some(state) = app.try_state::<UpdateChannelState>() {
+    if let Ok(mut current) = state.0.lock() {
+      *current = channel;
+    }
+  }
+}
+
+/// Asks the current channel's manifest for a newer release, and emits the result as
+/// `update-status`.
+pub async fn check(app: &AppHandle) -> Result<UpdateCheck, UpdateError> {
+  if portable::root().is_some() {
+    return Err(UpdateError::Disabled);
+  }
+  let channel = channel(app);
+  let endpoint = endpoint(app, channel)?;
+  // The comparator sees every release the manifest names, newer or not.
+  let latest: Arc<Mutex<Option<(String, bool)>>> = Arc::default();
+  let seen = latest.clone();
+  let exit_app = app.clone();
+  let proxies = proxy::settings(app);
+  let updater = app
+    .updater_builder()
+    .endpoints(vec![endpoint.clone()])
+    .map_err(|err| UpdateError::new(err, &endpoint))?
+    .version_comparator(move |current, remote| {
+      if let Ok(mut seen) = seen.lock() {
+        *seen = Some((remote.version.to_string(), remote.version < current));
+      }
+      remote.version > current
+    })
+    .configure_client(move |builder| proxies.apply(builder))
+    .on_before_exit(move || {
+      info!("update installer is taking over; cleaning up first");
+      crate::clean_up_before_exit(&exit_app);
+      exit_app.cleanup_before_exit();
+    })
+    .build()
+    .map_err(|err| UpdateError::new(err, &endpoint))?;
+  let update = updater.check().await.map_err(|err| {
+    warn!("update check against {endpoint} failed: {err}");
+    UpdateError::new(err, &endpoint)
+  })?;
+
+  let mut result = UpdateCheck {
+    channel,
+    status: UpdateStatus::UpToDate,
+    available: false,
+    current_version: app.package_info().version.to_string(),
+    version: None,
+    notes: None,
+    date: None,
+  };
+  let latest = latest.lock().ok().and_then(|mut latest| latest.take());
+  match (&update, latest) {
+    (Some(update), _) => {
+      result.status = UpdateStatus::Available;
+      result.available = true;
+      result.version = Some(update.version.clone());
+      result.notes = notes::resolve(app, channel, &update.version, update.body.as_deref()).await;
+      result.date = update
+        .date
+        .and_then(|date| chrono::DateTime::from_timestamp(date.unix_timestamp(), 0))
+        .map(|date| date.to_rfc3339());
+    }
+    (None, Some((version, older))) => {
+      if older {
+        result.status = UpdateStatus::AheadOfChannel;
+      }
+      result.version = Some(version);
+    }
+    (None, None) => {}
+  }
+  if let Some(state) = app.try_state::<UpdateState>() {
+    if let Ok(mut pending) = state.pending.lock() {
+      *pending = update;
+    }
+  }
+  info!(
+    "update check on the {:?} channel: {:?} (running {}, channel has {:?})",
+    channel, result.status, result.current_version, result.version
+  );
+  let _ = app.emit("update-status", result.clone());
+  Ok(result)
+}
+
+/// Downloads and installs the update found by the last check (checking first if there was
+/// none), emitting `update-download-progress` and then `update-ready`. Returns the installed
+/// version; it runs from the next launch.
+pub async fn install(app: &AppHandle) -> Result<String, UpdateError> {
+  let state = app.state::<UpdateState>();
+  if state.installing.swap(true, Ordering::SeqCst) {
+    return Err(UpdateError::InProgress);
+  }
+  let result = download_and_install(app, &state).await;
+  state.installing.store(false, Ordering::SeqCst);
+  result
+}
+
+async fn download_and_install(app: &AppHandle, state: &UpdateState) -> Result<String, UpdateError> {
+  let pending = state.pending.lock().ok().and_then(|mut pending| pending.take());
+  let update = match pending {
+    Some(update) => update,
+    None => {
+      check(app).await?;
+      let found = state.pending.lock().ok().and_then(|mut pending| pending.take());
+      found.ok_or(UpdateError::NoUpdate)?
+    }
+  };
+  info!("downloading update {} from {}", update.version, update.download_url);
+
+  let progress_app = app.clone();
+  let mut downloaded = 0u64;
+  let mut reported = None;
+  let bytes = update
+    .download(
+      move |chunk, total| {
+        downloaded += chunk as u64;
+        // A step per percent, or per MiB when the size is unknown.
+        let step = match total {
+          Some(total) => downloaded.saturating_mul(100) / total.max(1),
+          None => downloaded / (1024 * 1024),
+        };
+        if reported != Some(step) {
+          reported = Some(step);
+          let _ = progress_app.emit(
+            "update-download-progress",
+            DownloadProgress { downloaded, total },
+          );
+        }
+      },
+      || info!("update downloaded"),
+    )
+    .await
+    .map_err(|err| {
+      error!("failed to download update {}: {err}", update.version);
+      UpdateError::new(err, &update.download_url)
+    })?;
+
+  crate::backend::preserve_binary(app);
+  // On Windows, install doesn't return: the installer ends this process.
+  write_record(app, &update.version);
+  info!("installing update {}", update.version);
+  update.install(bytes).map_err(|err| {
+    error!("failed to install update {}: {err}", update.version);
+    remove_record(app);
+    UpdateError::new(err, &update.download_url)
+  })?;
+
+  info!("update {} installed; it takes effect after a restart", update.version);
+  let _ = app.emit(
+    "update-ready",
+    UpdateReady {
+      version: Some(update.version.clone()),
+    },
+  );
+  Ok(update.version)
+}
+
+/// Checks for updates once the backend is ready and hourly after that, unless
+/// `updates.auto_check` is off, and offers a newer version with `update-available`, or with a
+/// system notification while no window is visible. A skipped version isn't offered, and
+/// neither is anything while snoozed or within `remind_interval_hours` of the last offer.
+pub fn check_after_launch(app: &AppHandle) {
+  if cfg!(debug_assertions) || portable::root().is_some() {
+    return;
+  }
+  let check_app = app.clone();
+  app.once_any("backend-ready", move |_| {
+    tauri::async_runtime::spawn(async move {
+      loop {
+        automatic_check(&check_app).await;
+        tokio::time::sleep(RECHECK_INTERVAL).await;
+      }
+    });
+  });
+}
+
+async fn automatic_check(app: &AppHandle) {
+  let settings = config::saved(app).updates;
+  if !settings.auto_check {
+    return;
+  }
+  let now = chrono::Utc::now();
+  if let Some(until) = settings.snoozed_until.filter(|until| now < *until) {
+    info!("update reminders are snoozed until {until}");
+    return;
+  }
+  let interval = chrono::Duration::hours(settings.remind_interval_hours.min(24 * 365) as i64);
+  // A last offer in the future means the clock moved; don't wait for it.
+  if let Some(last) = settings
+    .last_prompted_at
+    .filter(|last| *last <= now && now - *last < interval)
+  {
+    info!("last offered an update at {last}; not checking again yet");
+    return;
+  }
+
+  let result = match check(app).await {
+    Ok(result) => result,
+    Err(err) => {
+      warn!("automatic update check failed: {err}");
+      return;
+    }
+  };
+  if result.status != UpdateStatus::Available {
+    return;
+  }
+  if result.version.is_some() && result.version == settings.skipped_version {
+    info!("not offering skipped version {:?}", result.version);
+    return;
+  }
+  if let Err(err) = config::update(app, |config| config.updates.last_prompted_at = Some(now)) {
+    warn!("failed to save when the update was offered: {err:#}");
+  }
+  if !windows::visibility::is_visible(app) {
+    notification::announce(app, &result);
+  }
+  let _ = app.emit("update-available", result);
+}
+
+/// The update flow for the menu item when no window can show the frontend's: a check, then
+/// native dialogs to install and restart.
+pub fn check_with_dialogs(app: &AppHandle) {
+  let app = app.clone();
+  tauri::async_runtime::spawn(async move {
+    let result = match check(&app).await {
+      Ok(result) => result,
+      Err(err) => {
+        show(&app, MessageDialogKind::Error, format!("Couldn't check for updates: {err}."));
+        return;
+      }
+    };
+    let version = result.version.clone().unwrap_or_default();
+    match result.status {
+      UpdateStatus::UpToDate => show(
+        &app,
+        MessageDialogKind::Info,
+        format!("Pluto Duck {} is the newest version.", result.current_version),
+      ),
+      UpdateStatus::AheadOfChannel => show(
+        &app,
+        MessageDialogKind::Info,
+        format!(
+          "This build ({}) is newer than the newest {} release ({version}), so there is \
+           nothing to update to.",
+          result.current_version,
+          channel_name(result.channel)
+        ),
+      ),
+      UpdateStatus::Available => {
+        let install_app = app.clone();
+        app
+          .dialog()
+          .message(format!(
+            "Pluto Duck {version} is available (you have {}). Install it now?",
+            result.current_version
+          ))
+          .title("Software Update")
+          .kind(MessageDialogKind::Info)
+          .buttons(MessageDialogButtons::OkCancelCustom(
+            "Install".into(),
+            "Later".into(),
+          ))
+          .show(move |install_now| {
+            if install_now {
+              tauri::async_runtime::spawn(async move { install_with_dialogs(&install_app).await });
+            }
+          });
+      }
+    }
+  });
+}
+
+async fn install_with_dialogs(app: &AppHandle) {
+  let version = match install(app).await {
+    Ok(version) => version,
+    Err(err) => {
+      show(app, MessageDialogKind::Error, format!("The update failed: {err}."));
+      return;
+    }
+  };
+  offer_restart(app, Some(&version));
+}
+
+fn offer_restart(app: &AppHandle, version: Option<&str>) {
+  let installed = match version {
+    Some(version) => format!("Pluto Duck {version} is installed."),
+    None => "The update is installed.".to_string(),
+  };
+  let restart_app = app.clone();
+  app
+    .dialog()
+    .message(format!("{installed} Restart now to use it?"))
+    .title("Software Update")
+    .kind(MessageDialogKind::Info)
+    .buttons(MessageDialogButtons::OkCancelCustom(
+      "Restart".into(),
+      "Later".into(),
+    ))
+    .show(move |restart| {
+      if restart {
+        restart_app.request_restart();
+      }
+    });
+}
+
+/// The version this one replaced, on its first launch after an update.
+pub fn updated_from(app: &AppHandle) -> Option<String> {
+  app.try_state::<UpdateState>()?.updated_from.clone()
+}
+
+fn install_record(app: &AppHandle) -> Option<std::path::PathBuf> {
+  config::config_dir(app).map(|dir| dir.join(INSTALL_RECORD))
+}
+
+fn read_record(path: &std::path::Path) -> Option<InstallRecord> {
+  let raw = std::fs::read(path).ok()?;
+  serde_json::from_slice(&raw)
+    .map_err(|err| warn!("ignoring invalid {:?}: {err}", path))
+    .ok()
+}
+
+fn write_record(app: &AppHandle, to: &str) {
+  let Some(path) = install_record(app) else {
+    return;
+  };
+  let record = InstallRecord {
+    from: app.package_info().version.to_string(),
+    to: to.to_string(),
+  };
+  let result = serde_json::to_vec_pretty(&record)
+    .map_err(std::io::Error::other)
+    .and_then(|bytes| {
+      if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+      }
+      std::fs::write(&path, bytes)
+    });
+  if let Err(err) = result {
+    warn!("failed to write {:?}: {err}", path);
+  }
+}
+
+fn remove_record(app: &AppHandle) {
+  let Some(path) = install_record(app) else {
+    return;
+  };
+  match std::fs::remove_file(&path) {
+    Ok(()) => {}
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+    Err(err) => warn!("failed to remove {:?}: {err}", path),
+  }
+}
+
+fn show(app: &AppHandle, kind: MessageDialogKind, message: String) {
+  app
+    .dialog()
+    .message(message)
+    .title("Software Update")
+    .kind(kind)
+    .show(|_| {});
+}
+
+fn channel_name(channel: UpdateChannel) -> &'static str {
+  match channel {
+    UpdateChannel::Stable => "stable",
+    UpdateChannel::Beta => "beta",
+  }
+}
+
+/// The manifest URL for `channel`, from the `updates.endpoint` template.
+pub fn endpoint(app: &AppHandle, channel: UpdateChannel) -> Result<Url, UpdateError> {
+  let template = app.state::<ShellConfig>().updates.endpoint.clone();
+  let name = match channel {
+    UpdateChannel::Stable => "latest",
+    UpdateChannel::Beta => "beta",
+  };
+  let url = template.replace("{channel}", name);
+  url
+    .parse()
+    .map_err(|err| UpdateError::InvalidEndpoint {
+      message: format!("{url:?}: {err}"),
+    })
+}
+
+/// Checks the current channel for a newer release.
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle) -> Result<UpdateCheck, UpdateError> {
+  check(&app).await
+}
+
+/// Installs the update `check_for_updates` found. Restarting afterwards is up to the caller.
+#[tauri::command]
+pub async fn install_update(app: AppHandle) -> Result<String, UpdateError> {
+  let result = install(&app).await;
+  let params = serde_json::json!({ "version": result.as_ref().ok() });
+  audit::record(&app, "install_update", params, &result);
+  result
+}
+
+/// Stops the automatic check from offering `version`.
+#[tauri::command]
+pub fn skip_update_version(app: AppHandle, version: String) -> Result<(), String> {
+  let version = version.trim().to_string();
+  if version.is_empty() {
+    return Err("no version to skip".to_string());
+  }
+  info!("skipping update {version}");
+  config::update(&app, |config| config.updates.skipped_version = Some(version))
+    .map_err(|err| format!("skipped version could not be saved: {err:#}"))
+}
+
+/// Holds off automatic update offers for `hours`; 0 lifts a snooze.
+#[tauri::command]
+pub fn snooze_update(app: AppHandle, hours: u32) -> Result<(), String> {
+  let until = (hours > 0).then(|| chrono::Utc::now() + chrono::Duration::hours(i64::from(hours)));
+  info!("update reminders snoozed until {until:?}");
+  config::update(&app, |config| config.updates.snoozed_until = until)
+    .map_err(|err| format!("snooze could not be saved: {err:#}"))
+}
+
+#[tauri::command]
+pub fn get_update_channel(app: AppHandle) -> UpdateChannel {
+  channel(&app)
+}
+
+/// Switches to `channel`, saves it, and checks it for updates right away.
+#[tauri::command]
+pub async fn set_update_channel(
+  app: AppHandle,
+  channel: UpdateChannel,
+) -> Result<UpdateCheck, UpdateError> {
+  set_channel(&app, channel);
+  // Only the switch is audited; the check after it is no change to anything.
+  let saved = config::update(&app, |config| config.updates.channel = channel).map_err(|err| {
+    warn!("failed to save update channel: {err:#}");
+    UpdateError::Failed {
+      message: format!("update channel could not be saved: {err:#}"),
+    }
+  });
+  audit::record(&app, "set_update_channel", serde_json::json!({ "channel": channel }), &saved);
+  saved?;
+  info!("update channel set to {channel:?}");
+  check(&app).await
+}
@@ -0,0 +1,113 @@
+//! Release notes for the update prompt: the manifest's `notes` for the version, or else the
+//! markdown at `updates.notes_url`. Either is cached under the data root's `runtime/`, so
+//! showing a version's notes again needs no request, and `get_release_notes` can look up
+//! versions other than the one on offer.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use log::{debug, warn};
+use tauri::{AppHandle, Manager};
+
+use super::channel_name;
+use crate::config::{ShellConfig, UpdateChannel};
+use crate::{backend, proxy};
+
+const CACHE_DIR: &str = "runtime/release-notes";
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The notes for `version`: `manifest` if it has any, else the cached or fetched ones. `None`
+/// when there are none to be had, which the prompt shows as no notes rather than an error.
+pub async fn resolve(
+  app: &AppHandle,
+  channel: UpdateChannel,
+  version: &str,
+  manifest: Option<&str>,
+) -> Option<String> {
+  if let Some(notes) = manifest.map(str::trim).filter(|notes| !notes.is_empty()) {
+    store(app, version, notes);
+    return Some(notes.to_string());
+  }
+  if let Some(notes) = cached(app, version) {
+    return Some(notes);
+  }
+  let notes = fetch(app, channel, version).await?;
+  store(app, version, &notes);
+  Some(notes)
+}
+
+/// Where `version`'s notes are cached; `None` for a version that isn't a plain file name.
+fn cache_path(app: &AppHandle, version: &str) -> Option<PathBuf> {
+  let plain = !version.is_empty()
+    && !version.starts_with('.')
+    && version
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'));
+  plain.then(|| backend::data_root(app).join(CACHE_DIR).join(format!("{version}.md")))
+}
+
+fn cached(app: &AppHandle, version: &str) -> Option<String> {
+  let notes = std::fs::read_to_string(cache_path(app, version)?).ok()?;
+  Some(notes).filter(|notes| !notes.trim().is_empty())
+}
+
+fn store(app: &AppHandle, version: &str, notes: &str) {
+  let Some(path) = cache_path(app, version) else {
+    return;
+  };
+  if std::fs::read_to_string(&path).is_ok_and(|cached| cached == notes) {
+    return;
+  }
+  let result = path
+    .parent()
+    .map_or(Ok(()), std::fs::create_dir_all)
+    .and_then(|()| std::fs::write(&path, notes));
+  if let Err(err) = result {
+    warn!("failed to cache release notes in {:?}: {err}", path);
+  }
+}
+
+/// `updates.notes_url` for `version`, with `{channel}` as `stable` or `beta`.
+async fn fetch(app: &AppHandle, channel: UpdateChannel, version: &str) -> Option<String> {
+  let template = app.state::<ShellConfig>().updates.notes_url.clone()?;
+  let url = template
+    .replace("{channel}", channel_name(channel))
+    .replace("{version}", version);
+  let client = proxy::settings(app)
+    .apply(reqwest::Client::builder().timeout(FETCH_TIMEOUT))
+    .build()
+    .map_err(|err| warn!("failed to build release notes client: {err}"))
+    .ok()?;
+  let response = match client.get(&url).send().await {
+    Ok(response) => response,
+    Err(err) => {
+      warn!("failed to fetch release notes from {url}: {err}");
+      return None;
+    }
+  };
+  if response.status() == reqwest::StatusCode::NOT_FOUND {
+    debug!("no release notes for {version} at {url}");
+    return None;
+  }
+  if !response.status().is_success() {
+    warn!("release notes at {url} returned status {}", response.status());
+    return None;
+  }
+  let notes = response
+    .text()
+    .await
+    .map_err(|err| warn!("failed to read release notes from {url}: {err}"))
+    .ok()?;
+  let notes = notes.trim();
+  (!notes.is_empty()).then(|| notes.to_string())
+}
+
+/// The notes for any release on the current channel; `None` when it has none.
+#[tauri::command]
+pub async fn get_release_notes(app: AppHandle, version: String) -> Result<Option<String>, String> {
+  let version = version.trim().trim_start_matches('v');
+  if version.is_empty() {
+    return Err("no version given".to_string());
+  }
+  Ok(resolve(&app, super::channel(&app), version, None).await)
+}
@@ -0,0 +1,48 @@
+//! System notifications for updates found while no window is on screen. Closing the window
+//! only hides it, so the frontend's update banner can go unseen for weeks; a notification
+//! reaches the user anyway, once per version. Clicking it shows the main window and emits
+//! `open-update-dialog` there.
+
+use log::{debug, error, info, warn};
+use tauri::{AppHandle, Emitter};
+
+use super::UpdateCheck;
+use crate::{config, notifications, windows};
+
+/// Announces `update` unless this version was announced before.
+pub fn announce(app: &AppHandle, update: &UpdateCheck) {
+  let Some(version) = update.version.clone() else {
+    return;
+  };
+  if config::saved(app).updates.notified_version.as_deref() == Some(version.as_str()) {
+    debug!("already notified about update {version}");
+    return;
+  }
+  // Saved first, so even a crash right after can't announce it twice.
+  let saved = config::update(app, |config| {
+    config.updates.notified_version = Some(version.clone());
+  });
+  if let Err(err) = saved {
+    warn!("failed to save the notified update version: {err:#}");
+  }
+
+  let update = update.clone();
+  notifications::show(
+    app,
+    &format!("Pluto Duck {version} is available"),
+    "Click to update.",
+    "Update",
+    move |app| open_update_dialog(app, update),
+  );
+}
+
+fn open_update_dialog(app: &AppHandle, update: UpdateCheck) {
+  info!("update notification clicked");
+  let main_app = app.clone();
+  let _ = app.run_on_main_thread(move || {
+    if let Err(err) = windows::show_main_window(&main_app) {
+      error!("failed to show main window: {err}");
+    }
+    let _ = main_app.emit_to(windows::MAIN_WINDOW, "open-update-dialog", update);
+  });
+}
@@ -0,0 +1,556 @@
+//! Installing an update from a bundle file, for machines that can't reach the update server.
+//! The bundle is the one a release publishes for the updater (`.app.tar.gz` on macOS, the
+//! `-setup.exe` or `.msi` on Windows, `.AppImage` on Linux, or their zipped forms), and its
+//! `.sig` has to sit next to it: nothing is replaced until the bundle checks out against the
+//! updater's own public key. A bundle named for an older version than the running one is
+//! refused, the way a check never offers one.
+//!
+//! From there it goes like a downloaded update: the backend binary is kept for rollback, the
+//! install is recorded for the next launch, and `update-ready` asks for a restart. On Windows
+//! the installer takes over the way the updater's does, after the usual exit cleanup.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+
+use anyhow::Context;
+use base64::Engine;
+use log::{error, info};
+use minisign_verify::{PublicKey, Signature};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+use super::{UpdateError, UpdateReady, UpdateState};
+use crate::{audit, portable};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+  TarGz,
+  AppImage,
+  Nsis,
+  Msi,
+  Zip,
+}
+
+/// The updater bundles a release publishes, by how their names end, and the OS each is for.
+const BUNDLES: &[(&str, &str, Format)] = &[
+  (".app.tar.gz", "macos", Format::TarGz),
+  ("-setup.exe", "windows", Format::Nsis),
+  (".msi", "windows", Format::Msi),
+  ("-setup.exe.zip", "windows", Format::Zip),
+  (".nsis.zip", "windows", Format::Zip),
+  (".msi.zip", "windows", Format::Zip),
+  (".AppImage", "linux", Format::AppImage),
+  (".AppImage.tar.gz", "linux", Format::TarGz),
+];
+
+/// For the file dialog, which only filters on the last part of an extension.
+const EXTENSIONS: &[&str] = if cfg!(target_os = "macos") {
+  &["gz"]
+} else if cfg!(windows) {
+  &["exe", "msi", "zip"]
+} else {
+  &["AppImage", "gz"]
+};
+
+/// Installs the bundle at `path`, emitting `update-ready`. Returns the installed version when
+/// the bundle names one; it runs from the next launch.
+pub async fn install(app: &AppHandle, path: PathBuf) -> Result<Option<String>, UpdateError> {
+  if portable::root().is_some() {
+    return Err(UpdateError::Disabled);
+  }
+  let state = app.state::<UpdateState>();
+  if state.installing.swap(true, Ordering::SeqCst) {
+    return Err(UpdateError::InProgress);
+  }
+  let install_app = app.clone();
+  let result = tauri::async_runtime::spawn_blocking(move || install_bundle(&install_app, &path))
+    .await
+    .unwrap_or_else(|err| {
+      Err(UpdateError::Failed {
+        message: err.to_string(),
+      })
+    });
+  state.installing.store(false, Ordering::SeqCst);
+  result
+}
+
+fn install_bundle(app: &AppHandle, path: &Path) -> Result<Option<String>, UpdateError> {
+  let name = path
+    .file_name()
+    .map(|name| name.to_string_lossy().into_owned())
+    .unwrap_or_default();
+  let format = format(&name)?;
+  let bytes = fs::read(path).map_err(|err| UpdateError::Failed {
+    message: format!("{} couldn't be read: {err}", path.display()),
+  })?;
+  if !looks_like(format, &bytes) {
+    return Err(UpdateError::InvalidBundle {
+      message: format!("{name} is damaged or not an update bundle"),
+    });
+  }
+  verify(app, path, &bytes)?;
+
+  let named = version_in(&name);
+  let current = &app.package_info().version;
+  if let Some(version) = named.as_deref().filter(|version| older(version, current)) {
+    info!("refusing to install {version} from {:?} over {current}", path);
+    return Err(UpdateError::Downgrade {
+      version: version.to_string(),
+      current: current.to_string(),
+    });
+  }
+  crate::backend::preserve_binary(app);
+  if let Some(version) = &named {
+    super::write_record(app, version);
+  }
+  info!("installing update from {:?} (version {:?})", path, named);
+  let version = install_inner(app, format, &name, &bytes)
+    .map_err(|err| {
+      error!("failed to install update from {:?}: {err:#}", path);
+      super::remove_record(app);
+      UpdateError::Failed {
+        message: format!("{err:#}"),
+      }
+    })?
+    .or(named.clone());
+  // A macOS bundle's version is only known from the app inside it.
+  if let (None, Some(version)) = (&named, &version) {
+    super::write_record(app, version);
+  }
+
+  info!("update {:?} installed; it takes effect after a restart", version);
+  let _ = app.emit(
+    "update-ready",
+    UpdateReady {
+      version: version.clone(),
+    },
+  );
+  Ok(version)
+}
+
+/// The menu item: picks a bundle, installs it, and offers to restart.
+pub fn install_with_dialogs(app: &AppHandle) {
+  let mut dialog = app
+    .dialog()
+    .file()
+    .set_title("Install Update from File")
+    .add_filter("Pluto Duck update", EXTENSIONS);
+  if let Ok(downloads) = app.path().download_dir() {
+    dialog = dialog.set_directory(downloads);
+  }
+  let install_app = app.clone();
+  dialog.pick_file(move |file| {
+    let Some(path) = file.and_then(|file| file.into_path().ok()) else {
+      return;
+    };
+    tauri::async_runtime::spawn(async move {
+      match install(&install_app, path).await {
+        Ok(version) => super::offer_restart(&install_app, version.as_deref()),
+        Err(err) => super::show(
+          &install_app,
+          MessageDialogKind::Error,
+          format!("The update failed: {err}."),
+        ),
+      }
+    });
+  });
+}
+
+/// The bundle's format, if it is one for this platform and architecture.
+fn format(name: &str) -> Result<Format, UpdateError> {
+  let found = BUNDLES.iter().find(|(suffix, _, _)| name.ends_with(suffix));
+  // Bundler names carry the architecture (`_x64-setup.exe`, `_aarch64.app.tar.gz`), if any.
+  let arch = name
+    .split(['_', '-', '.', ' '])
+    .find_map(|part| match part {
+      "x64" | "x86_64" | "amd64" => Some("x86_64"),
+      "aarch64" | "arm64" => Some("aarch64"),
+      "x86" | "i386" | "i686" => Some("x86"),
+      _ => None,
+    });
+  match found {
+    Some((_, os, format))
+      if *os == std::env::consts::OS && arch.map_or(true, |arch| arch == std::env::consts::ARCH) =>
+    {
+      Ok(*format)
+    }
+    _ => Err(UpdateError::WrongPlatform {
+      file: name.to_string(),
+      platform: found.map(|(_, os, _)| os.to_string()),
+      expected: BUNDLES
+        .iter()
+        .filter(|(_, os, _)| *os == std::env::consts::OS)
+        .map(|(suffix, _, _)| suffix.to_string())
+        .collect(),
+    }),
+  }
+}
+
+/// Checks the file's magic number against its name, so a renamed file is caught before the
+/// signature check.
+fn looks_like(format: Format, bytes: &[u8]) -> bool {
+  let magic: &[u8] = match format {
+    Format::TarGz => &[0x1f, 0x8b],
+    Format::AppImage => b"\x7fELF",
+    Format::Nsis => b"MZ",
+    Format::Msi => &[0xd0, 0xcf, 0x11, 0xe0],
+    Format::Zip => b"PK\x03\x04",
+  };
+  bytes.starts_with(magic)
+}
+
+/// The version in a bundler file name such as `Pluto Duck_0.2.4_x64-setup.exe`.
+fn version_in(name: &str) -> Option<String> {
+  name
+    .split('_')
+    .skip(1)
+    .find(|part| {
+      let mut numbers = part.split(['-', '+']).next().unwrap_or("").split('.');
+      numbers.clone().count() == 3 && numbers.all(|n| !n.is_empty() && n.parse::<u64>().is_ok())
+    })
+    .map(str::to_string)
+}
+
+/// Whether `version` is older than `current`. One that doesn't parse isn't held against the
+/// bundle; its signature still has to check out.
+fn older(version: &str, current: &semver::Version) -> bool {
+  semver::Version::parse(version).is_ok_and(|version| version < *current)
+}
+
+/// Checks `bytes` against the `.sig` next to `path` with the updater's public key, the way the
+/// updater checks a download.
+fn verify(app: &AppHandle, path: &Path, bytes: &[u8]) -> Result<(), UpdateError> {
+  let signature_error = |message: String| UpdateError::Signature { message };
+  let pubkey = app
+    .config()
+    .plugins
+    .0
+    .get("updater")
+    .and_then(|updater| updater.get("pubkey"))
+    .and_then(|pubkey| pubkey.as_str())
+    .ok_or_else(|| signature_error("no updater public key is configured".to_string()))?;
+  let mut signature_path = OsString::from(path.as_os_str());
+  signature_path.push(".sig");
+  let signature_path = PathBuf::from(signature_path);
+  let signature = fs::read_to_string(&signature_path).map_err(|err| {
+    signature_error(format!(
+      "{} couldn't be read ({err}); keep the .sig file next to the bundle",
+      signature_path.display()
+    ))
+  })?;
+
+  let public_key = decode(pubkey)
+    .and_then(|key| PublicKey::decode(&key).map_err(|err| err.to_string()))
+    .map_err(|err| signature_error(format!("the updater public key is invalid: {err}")))?;
+  let signature = decode(signature.trim())
+    .and_then(|signature| Signature::decode(&signature).map_err(|err| err.to_string()))
+    .map_err(|err| {
+      signature_error(format!("{} is not a signature: {err}", signature_path.display()))
+    })?;
+  public_key.verify(bytes, &signature, true).map_err(|err| {
+    error!("update bundle {:?} failed its signature check: {err}", path);
+    signature_error(format!("{err}; the bundle may be damaged or not from Pluto Duck"))
+  })
+}
+
+/// Tauri keeps minisign keys and signatures base64-encoded.
+fn decode(encoded: &str) -> Result<String, String> {
+  let decoded = base64::engine::general_purpose::STANDARD
+    .decode(encoded)
+    .map_err(|err| err.to_string())?;
+  String::from_utf8(decoded).map_err(|err| err.to_string())
+}
+
+/// Runs the installer the way the updater does: passively, after the usual exit cleanup, and
+/// with this process ending so the installer can replace the app. Only returns if the
+/// installer couldn't be started.
+#[cfg(windows)]
+fn install_inner(
+  app: &AppHandle,
+  format: Format,
+  name: &str,
+  bytes: &[u8],
+) -> anyhow::Result<Option<String>> {
+  use std::ffi::OsStr;
+  use std::os::windows::ffi::OsStrExt;
+  use windows_sys::w;
+  use windows_sys::Win32::UI::Shell::ShellExecuteW;
+  use windows_sys::Win32::UI::WindowsAndMessaging::SW_SHOW;
+
+  let dir = std::env::temp_dir().join(format!("pluto-duck-update-{}", std::process::id()));
+  fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+  let (installer, format) = match format {
+    Format::Zip => extract_installer(bytes, &dir)?,
+    _ => {
+      let path = dir.join(name);
+      fs::write(&path, bytes).with_context(|| format!("failed to write {}", path.display()))?;
+      (path, format)
+    }
+  };
+  let (file, parameters) = match format {
+    Format::Msi => {
+      let msiexec = std::env::var("SYSTEMROOT").map_or_else(
+        |_| "msiexec.exe".to_string(),
+        |root| format!("{root}\\System32\\msiexec.exe"),
+      );
+      let parameters = format!(
+        "/i \"{}\" /passive /promptrestart AUTOLAUNCHAPP=True",
+        installer.display()
+      );
+      (OsString::from(msiexec), OsString::from(parameters))
+    }
+    // `/R` relaunches the app once it's installed.
+    _ => (installer.clone().into_os_string(), OsString::from("/P /R /UPDATE")),
+  };
+  let wide = |value: &OsStr| -> Vec<u16> { value.encode_wide().chain(Some(0)).collect() };
+  let (file, parameters) = (wide(&file), wide(&parameters));
+
+  info!("starting installer {:?}; Pluto Duck exits for it", installer);
+  crate::clean_up_before_exit(app);
+  app.cleanup_before_exit();
+  // SAFETY: both strings are NUL-terminated UTF-16 that outlive the call.
+  let started = unsafe {
+    ShellExecuteW(
+      std::ptr::null_mut(),
+      w!("open"),
+      file.as_ptr(),
+      parameters.as_ptr(),
+      std::ptr::null(),
+      SW_SHOW,
+    )
+  };
+  // Anything up to 32 is an error code.
+  if started as isize <= 32 {
+    anyhow::bail!(
+      "the installer couldn't be started (error {}); run {} yourself after quitting Pluto Duck",
+      started as isize,
+      installer.display()
+    );
+  }
+  std::process::exit(0);
+}
+
+/// The installer inside a zipped Windows bundle.
+#[cfg(windows)]
+fn extract_installer(bytes: &[u8], dir: &Path) -> anyhow::Result<(PathBuf, Format)> {
+  let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))
+    .context("the update archive couldn't be read")?;
+  for index in 0..archive.len() {
+    let mut entry = archive.by_index(index).context("the update archive is damaged")?;
+    let Some(name) = entry.enclosed_name() else {
+      continue;
+    };
+    let format = match name.extension().and_then(|ext| ext.to_str()) {
+      Some("exe") => Format::Nsis,
+      Some("msi") => Format::Msi,
+      _ => continue,
+    };
+    let path = dir.join(name.file_name().unwrap_or_default());
+    let mut file =
+      fs::File::create(&path).with_context(|| format!("failed to create {}", path.display()))?;
+    std::io::copy(&mut entry, &mut file)
+      .with_context(|| format!("failed to write {}", path.display()))?;
+    return Ok((path, format));
+  }
+  anyhow::bail!("the archive has no installer in it")
+}
+
+/// Replaces the running `.app` with the bundle's, staging it next to the old one so the swap
+/// is a rename on the same volume. Returns the version from the new `Info.plist`.
+#[cfg(target_os = "macos")]
+fn install_inner(
+  _app: &AppHandle,
+  _format: Format,
+  _name: &str,
+  bytes: &[u8],
+) -> anyhow::Result<Option<String>> {
+  let exe = std::env::current_exe().context("failed to find the running app")?;
+  // `<name>.app/Contents/MacOS/<exe>`
+  let bundle = exe
+    .ancestors()
+    .nth(3)
+    .filter(|bundle| bundle.extension().is_some_and(|ext| ext == "app"))
+    .context("Pluto Duck isn't running from an app bundle")?
+    .to_path_buf();
+  let parent = bundle.parent().context("the app bundle has no parent folder")?;
+  let staged = parent.join(".pluto-duck-update.app");
+  let previous = parent.join(".pluto-duck-previous.app");
+  for stale in [&staged, &previous] {
+    if stale.exists() {
+      fs::remove_dir_all(stale).with_context(|| format!("failed to remove {}", stale.display()))?;
+    }
+  }
+
+  // The archive holds `<name>.app/...`; its contents go straight into `staged`.
+  let placed = unpack(bytes, |path| {
+    Some(staged.join(path.components().skip(1).collect::<PathBuf>()))
+  });
+  if let Err(err) = placed {
+    let _ = fs::remove_dir_all(&staged);
+    return Err(err);
+  }
+  let plist = fs::read_to_string(staged.join("Contents/Info.plist")).unwrap_or_default();
+  let version = regex::Regex::new(r"<key>CFBundleShortVersionString</key>\s*<string>([^<]+)<")
+    .ok()
+    .and_then(|re| re.captures(&plist).map(|found| found[1].trim().to_string()));
+
+  if let Err(err) = fs::rename(&bundle, &previous) {
+    let _ = fs::remove_dir_all(&staged);
+    if err.kind() == std::io::ErrorKind::PermissionDenied {
+      anyhow::bail!(
+        "{} can't be replaced without administrator rights; install the update by hand",
+        bundle.display()
+      );
+    }
+    return Err(err).with_context(|| format!("failed to move {} aside", bundle.display()));
+  }
+  if let Err(err) = fs::rename(&staged, &bundle) {
+    let _ = fs::rename(&previous, &bundle);
+    let _ = fs::remove_dir_all(&staged);
+    return Err(err).with_context(|| format!("failed to move the update into {}", bundle.display()));
+  }
+  if let Err(err) = fs::remove_dir_all(&previous) {
+    log::warn!("failed to remove the replaced app {:?}: {err}", previous);
+  }
+  // Lets Launch Services notice the new bundle.
+  let _ = std::process::Command::new("touch").arg(&bundle).status();
+  Ok(version)
+}
+
+/// Replaces the running AppImage; other Linux packages belong to the package manager.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn install_inner(
+  _app: &AppHandle,
+  format: Format,
+  _name: &str,
+  bytes: &[u8],
+) -> anyhow::Result<Option<String>> {
+  use std::os::unix::fs::PermissionsExt;
+
+  let target = std::env::var_os("APPIMAGE")
+    .map(PathBuf::from)
+    .context("only the AppImage can update itself; install the new package with your package \
+              manager")?;
+  let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+  let staged = target.with_file_name(format!(".{file_name}.update"));
+  let result = match format {
+    // Only the AppImage itself, written as `staged`.
+    Format::TarGz => unpack(bytes, |path| {
+      path
+        .extension()
+        .is_some_and(|ext| ext == "AppImage")
+        .then(|| staged.clone())
+    }),
+    _ => fs::write(&staged, bytes).with_context(|| format!("failed to write {}", staged.display())),
+  };
+  let result = result
+    .and_then(|()| {
+      anyhow::ensure!(staged.is_file(), "the archive has no AppImage in it");
+      fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("failed to make {} executable", staged.display()))
+    })
+    .and_then(|()| {
+      fs::rename(&staged, &target)
+        .with_context(|| format!("failed to replace {}", target.display()))
+    });
+  if result.is_err() {
+    let _ = fs::remove_file(&staged);
+  }
+  result.map(|()| None)
+}
+
+/// Unpacks a `.tar.gz`, writing each entry where `place` says, or skipping it for `None`.
+/// Entries with `..` or absolute paths are refused rather than placed.
+#[cfg(unix)]
+fn unpack(bytes: &[u8], place: impl Fn(&Path) -> Option<PathBuf>) -> anyhow::Result<()> {
+  let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(bytes));
+  for entry in archive.entries().context("the update archive couldn't be read")? {
+    let mut entry = entry.context("the update archive is damaged")?;
+    let path = entry.path().context("the update archive is damaged")?.into_owned();
+    anyhow::ensure!(
+      path.components().all(|part| {
+        matches!(part, std::path::Component::Normal(_) | std::path::Component::CurDir)
+      }),
+      "the update archive has an unsafe path: {}",
+      path.display()
+    );
+    let Some(target) = place(&path) else {
+      continue;
+    };
+    if let Some(parent) = target.parent() {
+      fs::create_dir_all(parent).with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    entry
+      .unpack(&target)
+      .with_context(|| format!("failed to unpack {}", path.display()))?;
+  }
+  Ok(())
+}
+
+/// Installs the update bundle at `path` once it matches the `.sig` beside it. Restarting
+/// afterwards is up to the caller.
+#[tauri::command]
+pub async fn install_update_from_file(
+  app: AppHandle,
+  path: PathBuf,
+) -> Result<Option<String>, UpdateError> {
+  let result = install(&app, path.clone()).await;
+  let version = result.as_ref().ok().and_then(Option::as_ref);
+  let params = serde_json::json!({ "path": path, "version": version });
+  audit::record(&app, "install_update_from_file", params, &result);
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn older_compares_with_the_running_version() {
+    let current = semver::Version::parse("0.3.0").unwrap();
+    assert!(older("0.2.9", &current));
+    assert!(older("0.3.0-beta.1", &current));
+    assert!(!older("0.3.0", &current));
+    assert!(!older("0.3.1", &current));
+    assert!(!older("not-a-version", &current));
+  }
+
+  #[test]
+  fn version_in_reads_bundler_file_names() {
+    assert_eq!(version_in("Pluto Duck_0.2.4_x64-setup.exe").as_deref(), Some("0.2.4"));
+    assert_eq!(
+      version_in("Pluto Duck_0.3.0-beta.1_aarch64.app.tar.gz").as_deref(),
+      Some("0.3.0-beta.1")
+    );
+    assert_eq!(version_in("pluto-duck_1.10.0_amd64.AppImage").as_deref(), Some("1.10.0"));
+    assert_eq!(version_in("Pluto Duck.app.tar.gz"), None);
+    assert_eq!(version_in("Pluto Duck_0.2_x64-setup.exe"), None);
+    assert_eq!(version_in("0.2.4_x64-setup.exe"), None);
+  }
+
+  #[test]
+  fn format_goes_by_the_name_ending_and_architecture() {
+    let arch = match std::env::consts::ARCH {
+      "x86_64" => "x64",
+      "aarch64" => "aarch64",
+      other => other,
+    };
+    let (ours, theirs) = match std::env::consts::OS {
+      "macos" => (format!("Pluto Duck_{arch}.app.tar.gz"), "Pluto Duck_x64-setup.exe"),
+      "windows" => (format!("Pluto Duck_{arch}-setup.exe"), "Pluto Duck.app.tar.gz"),
+      _ => (format!("pluto-duck_{arch}.AppImage"), "Pluto Duck.app.tar.gz"),
+    };
+    assert!(format(&ours).is_ok(), "{ours} was refused");
+    assert!(matches!(format(theirs), Err(UpdateError::WrongPlatform { .. })));
+    assert!(format("notes.txt").is_err());
+  }
+
+  #[test]
+  fn looks_like_checks_the_magic_number() {
+    assert!(looks_like(Format::Zip, b"PK\x03\x04rest"));
+    assert!(looks_like(Format::TarGz, &[0x1f, 0x8b, 0x08]));
+    assert!(!looks_like(Format::Msi, b"MZ\x90\x00"));
+  }
+}
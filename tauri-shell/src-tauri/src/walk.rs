@@ -0,0 +1,111 @@
+//! Directory walking shared by everything that sizes up or cleans out the data root.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+
+/// The walk was stopped through its cancellation flag.
+#[derive(Debug)]
+pub struct Cancelled;
+
+/// Calls `visit` with every regular file under `root` (or `root` itself, if it's a file) and
+/// its size. Symlinks aren't followed and unreadable entries are skipped. Checks `cancel`
+/// between directories and stops once it's set.
+pub fn for_each_file(
+  root: &Path,
+  cancel: &AtomicBool,
+  mut visit: impl FnMut(&Path, u64),
+) -> Result<(), Cancelled> {
+  match std::fs::symlink_metadata(root) {
+    Ok(metadata) if metadata.is_file() => {
+      visit(root, metadata.len());
+      return Ok(());
+    }
+    Ok(metadata) if metadata.is_dir() => {}
+    _ => return Ok(()),
+  }
+  let mut pending = vec![root.to_path_buf()];
+  while let Some(dir) = pending.pop() {
+    if cancel.load(Ordering::Relaxed) {
+      return Err(Cancelled);
+    }
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+      continue;
+    };
+    for entry in entries.flatten() {
+      let Ok(kind) = entry.file_type() else {
+        continue;
+      };
+      if kind.is_dir() {
+        pending.push(entry.path());
+      } else if kind.is_file() {
+        visit(&entry.path(), entry.metadata().map_or(0, |metadata| metadata.len()));
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Every regular file under `root` (or `root` itself, if it's a file) with its size, for
+/// callers that mustn't miss one: unlike `for_each_file`, an entry that can't be read fails
+/// the walk. Symlinks aren't followed.
+pub fn all_files(root: &Path) -> Result<Vec<(PathBuf, u64)>> {
+  let metadata =
+    std::fs::symlink_metadata(root).with_context(|| format!("failed to read {}", root.display()))?;
+  if metadata.is_file() {
+    return Ok(vec![(root.to_path_buf(), metadata.len())]);
+  }
+  let mut files = Vec::new();
+  if !metadata.is_dir() {
+    return Ok(files);
+  }
+  let mut pending = vec![root.to_path_buf()];
+  while let Some(dir) = pending.pop() {
+    let entries =
+      std::fs::read_dir(&dir).with_context(|| format!("failed to read {}", dir.display()))?;
+    for entry in entries {
+      let entry = entry.with_context(|| format!("failed to read {}", dir.display()))?;
+      let path = entry.path();
+      let metadata = entry
+        .metadata()
+        .with_context(|| format!("failed to read {}", path.display()))?;
+      if metadata.is_dir() {
+        pending.push(path);
+      } else if metadata.is_file() {
+        files.push((path, metadata.len()));
+      }
+    }
+  }
+  Ok(files)
+}
+
+/// Total size of the regular files under `root`, not following symlinks.
+pub fn dir_size(root: &Path) -> u64 {
+  let mut total = 0;
+  let _ = for_each_file(root, &AtomicBool::new(false), |_, len| total += len);
+  total
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn all_files_lists_nested_files_and_fails_on_a_missing_root() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("a/b")).unwrap();
+    std::fs::write(dir.path().join("top"), "12").unwrap();
+    std::fs::write(dir.path().join("a/b/deep"), "1234").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(dir.path().join("top"), dir.path().join("a/link")).unwrap();
+
+    let mut files = all_files(dir.path()).unwrap();
+    files.sort();
+    assert_eq!(
+      files,
+      vec![(dir.path().join("a/b/deep"), 4), (dir.path().join("top"), 2)]
+    );
+    assert!(all_files(&dir.path().join("missing")).is_err());
+  }
+}
@@ -0,0 +1,625 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+#[cfg(target_os = "macos")]
+use tauri::TitleBarStyle;
+use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+
+use crate::audit;
+use crate::config::{self, CloseBehavior};
+
+pub mod bootstrap;
+mod cache;
+#[cfg(windows)]
+mod dwm;
+pub mod fallback;
+pub mod file_drop;
+pub mod geometry;
+pub mod navigation;
+pub mod palette;
+pub mod session;
+pub mod splash;
+pub mod theme;
+pub mod titlebar;
+pub mod visibility;
+
+pub const MAIN_WINDOW: &str = "main";
+pub const LOG_VIEWER_WINDOW: &str = "logs";
+pub const SPLASH_WINDOW: &str = "splash";
+pub const PREFERENCES_WINDOW: &str = "preferences";
+pub const ABOUT_WINDOW: &str = "about";
+pub const PALETTE_WINDOW: &str = "palette";
+
+/// A window's opening size and the smallest it can be resized to, in logical pixels. Opening
+/// sizes are shrunk to fit small screens; see `geometry::restore`.
+#[derive(Debug, Clone, Copy)]
+pub struct WindowSize {
+  pub width: f64,
+  pub height: f64,
+  pub min_width: f64,
+  pub min_height: f64,
+}
+
+pub const MAIN_SIZE: WindowSize = WindowSize {
+  width: 1400.0,
+  height: 900.0,
+  min_width: 900.0,
+  min_height: 600.0,
+};
+pub const LOG_VIEWER_SIZE: WindowSize = WindowSize {
+  width: 900.0,
+  height: 600.0,
+  min_width: 480.0,
+  min_height: 320.0,
+};
+pub const SPLASH_SIZE: WindowSize = WindowSize {
+  width: 420.0,
+  height: 260.0,
+  min_width: 420.0,
+  min_height: 260.0,
+};
+pub const PREFERENCES_SIZE: WindowSize = WindowSize {
+  width: 720.0,
+  height: 520.0,
+  min_width: 720.0,
+  min_height: 520.0,
+};
+pub const ABOUT_SIZE: WindowSize = WindowSize {
+  width: 360.0,
+  height: 300.0,
+  min_width: 360.0,
+  min_height: 300.0,
+};
+pub const PALETTE_SIZE: WindowSize = WindowSize {
+  width: 640.0,
+  height: 88.0,
+  min_width: 640.0,
+  min_height: 88.0,
+};
+
+/// The close behavior currently in effect; starts from the config and can be changed at runtime.
+pub struct CloseBehaviorState(Mutex<CloseBehavior>);
+
+impl CloseBehaviorState {
+  pub fn new(behavior: CloseBehavior) -> Self {
+    Self(Mutex::new(behavior))
+  }
+
+  fn get(&self) -> CloseBehavior {
+    self.0.lock().map(|behavior| *behavior).unwrap_or_default()
+  }
+}
+
+/// Applies the current close behavior: either hide the window and keep running (and the
+/// backend with it), or let it close and quit once no visible window is left.
+pub fn handle_close(window: &WebviewWindow) {
+  let window_clone = window.clone();
+  window.on_window_event(move |event| {
+    let tauri::WindowEvent::CloseRequested { api, .. } = event else {
+      return;
+    };
+    let app = window_clone.app_handle();
+    let behavior = app
+      .try_state::<CloseBehaviorState>()
+      .map(|state| state.get())
+      .unwrap_or_default();
+    match behavior {
+      CloseBehavior::Hide => {
+        api.prevent_close();
+        let _ = window_clone.hide();
+      }
+      CloseBehavior::Quit => {
+        // A leftover About, Preferences or palette window shouldn't keep the app alive.
+        let others_visible = app.webview_windows().values().any(|other| {
+          other.label() != window_clone.label()
+            && !UtilityWindow::is_utility(other.label())
+            && other.label() != PALETTE_WINDOW
+            && other.is_visible().unwrap_or(false)
+        });
+        if !others_visible {
+          // Goes through RunEvent::Exit, which stops the backend.
+          api.prevent_close();
+          app.exit(0);
+        }
+      }
+    }
+  });
+}
+
+/// Switches between hiding and quitting on close for this run.
+pub fn apply_close_behavior(app: &AppHandle, behavior: CloseBehavior) {
+  if let Some(state) = app.try_state::<CloseBehaviorState>() {
+    if let Ok(mut current) = state.0.lock() {
+      *current = behavior;
+    }
+  }
+  info!("close behavior set to {behavior:?}");
+}
+
+/// Switches between hiding and quitting on close without a restart, and saves the choice.
+#[tauri::command]
+pub fn set_close_behavior(app: AppHandle, behavior: CloseBehavior) -> Result<(), String> {
+  apply_close_behavior(&app, behavior);
+  let result =
+    config::update(&app, |config| config.window.close_behavior = behavior).map_err(|err| {
+      warn!("failed to save close behavior: {err:#}");
+      format!("close behavior changed but could not be saved: {err:#}")
+    });
+  audit::record(&app, "set_close_behavior", serde_json::json!({ "behavior": behavior }), &result);
+  result
+}
+
+const ZOOM_STEP: f64 = 0.1;
+const MIN_ZOOM: f64 = 0.5;
+const MAX_ZOOM: f64 = 2.0;
+
+/// A change to a webview's zoom level.
+#[derive(Debug, Clone, Copy)]
+pub enum Zoom {
+  In,
+  Out,
+  Reset,
+}
+
+/// The zoom level of each window, by label; windows not listed are at 100%.
+#[derive(Default)]
+pub struct ZoomLevels(Mutex<HashMap<String, f64>>);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ZoomChanged {
+  factor: f64,
+}
+
+/// `window`'s current zoom factor.
+pub fn zoom_level(window: &WebviewWindow) -> f64 {
+  window
+    .try_state::<ZoomLevels>()
+    .and_then(|levels| levels.0.lock().ok()?.get(window.label()).copied())
+    .unwrap_or(1.0)
+}
+
+/// Zooms `window`'s webview one step in or out, or back to 100%, saves the level with the
+/// window's geometry, and tells the window (`zoom-changed`) so it can show the new level.
+pub fn zoom(window: &WebviewWindow, change: Zoom) -> tauri::Result<f64> {
+  let current = zoom_level(window);
+  let level = match change {
+    Zoom::In => (current + ZOOM_STEP).min(MAX_ZOOM),
+    Zoom::Out => (current - ZOOM_STEP).max(MIN_ZOOM),
+    Zoom::Reset => 1.0,
+  };
+  // Round away the float drift from repeated steps.
+  let level = (level * 10.0).round() / 10.0;
+  set_zoom(window, level)?;
+  geometry::save(window);
+  let _ = window.emit_to(window.label(), "zoom-changed", ZoomChanged { factor: level });
+  Ok(level)
+}
+
+fn set_zoom(window: &WebviewWindow, level: f64) -> tauri::Result<()> {
+  if let Some(levels) = window.try_state::<ZoomLevels>() {
+    if let Ok(mut levels) = levels.0.lock() {
+      levels.insert(window.label().to_string(), level);
+    }
+  }
+  window.set_zoom(level)
+}
+
+/// Puts a newly built window back at the zoom level it was saved with.
+fn restore_zoom(window: &WebviewWindow) {
+  let Some(geometry) = geometry::load(window.app_handle(), window.label()) else {
+    return;
+  };
+  let level = geometry.zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+  if level != 1.0 {
+    if let Err(err) = set_zoom(window, level) {
+      warn!("failed to restore zoom of {}: {err}", window.label());
+    }
+  }
+}
+
+#[tauri::command]
+pub fn zoom_in(window: WebviewWindow) -> Result<f64, String> {
+  zoom(&window, Zoom::In).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub fn zoom_out(window: WebviewWindow) -> Result<f64, String> {
+  zoom(&window, Zoom::Out).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub fn zoom_reset(window: WebviewWindow) -> Result<f64, String> {
+  zoom(&window, Zoom::Reset).map_err(|err| err.to_string())
+}
+
+/// Returns the main window, creating it (with last session's geometry) if it doesn't exist yet
+/// or was destroyed, e.g. closed for real from devtools. Setup, Reopen, the tray, and a second
+/// launch all come through here, so a rebuilt window gets the same styling and close handling
+/// as the first. A new window starts hidden; see `show_main_window`.
+pub fn main_window(app: &AppHandle) -> tauri::Result<WebviewWindow> {
+  if let Some(existing) = app.get_webview_window(MAIN_WINDOW) {
+    return Ok(existing);
+  }
+  build_main_style(app, MAIN_WINDOW, WebviewUrl::default())
+}
+
+/// Whether `label` is the main window or one opened with `open_new_window`.
+pub fn is_main_style(label: &str) -> bool {
+  label == MAIN_WINDOW
+    || label
+      .strip_prefix(MAIN_WINDOW)
+      .and_then(|rest| rest.strip_prefix('-'))
+      .is_some_and(|number| number.parse::<u32>().is_ok())
+}
+
+/// The main window and any extra ones, main first.
+pub fn main_style_windows(app: &AppHandle) -> Vec<WebviewWindow> {
+  let mut windows: Vec<_> = app
+    .webview_windows()
+    .into_values()
+    .filter(|window| is_main_style(window.label()))
+    .collect();
+  windows.sort_by_key(|window| window.label() != MAIN_WINDOW);
+  windows
+}
+
+/// `base` with the active profile's name, so it's always clear whose data a window shows.
+fn title(app: &AppHandle, base: &str) -> String {
+  match crate::backend::profiles::active_name(app) {
+    Some(profile) => format!("{base} — {profile}"),
+    None => base.to_string(),
+  }
+}
+
+/// Builds a hidden window that looks and behaves like the main one: same size rules, titlebar,
+/// close behavior, and backend URL, with its own saved geometry.
+fn build_main_style(
+  app: &AppHandle,
+  label: &str,
+  url: WebviewUrl,
+) -> tauri::Result<WebviewWindow> {
+  let mut window_builder = WebviewWindowBuilder::new(app, label, url)
+    .title(title(app, "Pluto Duck"))
+    .resizable(true)
+    .visible(false)
+    .theme(theme::forced(app))
+    .devtools(devtools_allowed());
+  if let Some(forced) = theme::forced(app) {
+    window_builder = window_builder.background_color(theme::background(forced));
+  }
+
+  window_builder = geometry::restore(app, label, MAIN_SIZE, window_builder);
+  window_builder = navigation::guard(app, window_builder);
+  window_builder = bootstrap::inject(app, window_builder);
+
+  #[cfg(target_os = "macos")]
+  {
+    window_builder = window_builder
+      .hidden_title(true)
+      .title_bar_style(TitleBarStyle::Overlay);
+  }
+  // The webview handles `data-tauri-drag-region` itself: dragging goes through the native
+  // move loop (so snapping and shake still work) and a double-click toggles maximize.
+  #[cfg(not(target_os = "macos"))]
+  {
+    let custom_titlebar = app
+      .try_state::<config::ShellConfig>()
+      .is_some_and(|config| config.window.custom_titlebar);
+    if custom_titlebar {
+      window_builder = window_builder.decorations(false).shadow(true);
+    }
+  }
+
+  let window = window_builder.build()?;
+  geometry::track(&window);
+  restore_zoom(&window);
+  handle_close(&window);
+  theme::track(&window);
+
+  #[cfg(target_os = "macos")]
+  titlebar::install(&window);
+
+  Ok(window)
+}
+
+/// Shows and focuses the main window, recreating it if needed.
+pub fn show_main_window(app: &AppHandle) -> tauri::Result<()> {
+  crate::autostart::reveal(app);
+  let window = main_window(app)?;
+  window.show()?;
+  window.unminimize()?;
+  window.set_focus()
+}
+
+/// Brings back every main-style window, e.g. when the app is reopened from the Dock. If none
+/// is left (they were destroyed, not hidden), the main window is rebuilt.
+pub fn show_main_style_windows(app: &AppHandle) -> tauri::Result<()> {
+  let windows = main_style_windows(app);
+  if windows.is_empty() {
+    return show_main_window(app);
+  }
+  crate::autostart::reveal(app);
+  for window in &windows {
+    window.show()?;
+    window.unminimize()?;
+  }
+  windows[0].set_focus()
+}
+
+/// Opens another main-style window (`main-2`, `main-3`, …) at `route`, a frontend route such
+/// as `#/dashboards/42`, or the start page.
+// Async so window creation doesn't run on (and deadlock) the main thread on Windows.
+#[tauri::command]
+pub async fn open_new_window(app: AppHandle, route: Option<String>) -> Result<String, String> {
+  let label = (2..)
+    .map(|number| format!("{MAIN_WINDOW}-{number}"))
+    .find(|label| app.get_webview_window(label).is_none())
+    .expect("an unused window label");
+  let url = match route {
+    Some(route) => WebviewUrl::App(route.into()),
+    None => WebviewUrl::default(),
+  };
+  let window = build_main_style(&app, &label, url)
+    .map_err(|err| format!("failed to open window: {err}"))?;
+  window
+    .show()
+    .and_then(|_| window.set_focus())
+    .map_err(|err| format!("failed to show window: {err}"))?;
+  info!("opened window {label}");
+  Ok(label)
+}
+
+// Caption buttons for the frontend's own titlebar (see `custom_titlebar` in the config).
+
+#[tauri::command]
+pub fn window_minimize(window: WebviewWindow) -> Result<(), String> {
+  window.minimize().map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub fn window_toggle_maximize(window: WebviewWindow) -> Result<(), String> {
+  let maximized = window.is_maximized().map_err(|err| err.to_string())?;
+  if maximized {
+    window.unmaximize()
+  } else {
+    window.maximize()
+  }
+  .map_err(|err| err.to_string())
+}
+
+/// Goes through the configured close behavior, like the native close button.
+#[tauri::command]
+pub fn window_close(window: WebviewWindow) -> Result<(), String> {
+  window.close().map_err(|err| err.to_string())
+}
+
+/// Pins window `label` above other apps' windows, or unpins it. Saved with its geometry.
+#[tauri::command]
+pub fn set_always_on_top(app: AppHandle, label: String, pinned: bool) -> Result<(), String> {
+  let window = app
+    .get_webview_window(&label)
+    .ok_or_else(|| format!("no window labelled {label:?}"))?;
+  pin(&window, pinned).map_err(|err| format!("failed to pin {label:?}: {err}"))
+}
+
+/// Sets always-on-top for `window`, saves it, and updates the Window menu's check mark.
+pub fn pin(window: &WebviewWindow, pinned: bool) -> tauri::Result<()> {
+  window.set_always_on_top(pinned)?;
+  geometry::save(window);
+  if window.is_focused().unwrap_or(false) {
+    crate::menu::set_pinned_checked(window.app_handle(), pinned);
+  }
+  Ok(())
+}
+
+/// Set to `1` to allow devtools in a release build, e.g. during a support session.
+const DEVTOOLS_ENV: &str = "PLUTODUCK_ENABLE_DEVTOOLS";
+
+/// Whether webviews get devtools: always in debug builds, and in release builds only when
+/// `PLUTODUCK_ENABLE_DEVTOOLS=1`. The `devtools` feature would otherwise enable them for
+/// everyone, so every window builder passes this along.
+pub fn devtools_allowed() -> bool {
+  cfg!(debug_assertions) || std::env::var(DEVTOOLS_ENV).is_ok_and(|value| value == "1")
+}
+
+/// Opens or closes window `label`'s devtools, if they're allowed (see `devtools_allowed`).
+#[tauri::command]
+pub fn toggle_devtools(app: AppHandle, label: String) -> Result<(), String> {
+  let window = app
+    .get_webview_window(&label)
+    .ok_or_else(|| format!("no window labelled {label:?}"))?;
+  toggle_window_devtools(&window)
+}
+
+pub fn toggle_window_devtools(window: &WebviewWindow) -> Result<(), String> {
+  if !devtools_allowed() {
+    warn!("devtools requested for {} but {DEVTOOLS_ENV} is not set", window.label());
+    return Err(format!("devtools are disabled; launch with {DEVTOOLS_ENV}=1 to enable them"));
+  }
+  if window.is_devtools_open() {
+    window.close_devtools();
+  } else {
+    window.open_devtools();
+  }
+  Ok(())
+}
+
+/// Reloads window `label`'s page from the shell side, which works even when the page's own
+/// scripts are stuck.
+#[tauri::command]
+pub fn reload_window(app: AppHandle, label: String) -> Result<(), String> {
+  let window = app
+    .get_webview_window(&label)
+    .ok_or_else(|| format!("no window labelled {label:?}"))?;
+  window
+    .reload()
+    .map_err(|err| format!("failed to reload {label:?}: {err}"))
+}
+
+/// Like `reload_window`, but clears the webview's HTTP caches first. The backend URL global
+/// is an initialization script, so the reloaded page gets it again.
+#[tauri::command]
+pub async fn force_reload_window(app: AppHandle, label: String) -> Result<(), String> {
+  let window = app
+    .get_webview_window(&label)
+    .ok_or_else(|| format!("no window labelled {label:?}"))?;
+  force_reload(&window).await
+}
+
+pub async fn force_reload(window: &WebviewWindow) -> Result<(), String> {
+  info!("force-reloading {}", window.label());
+  cache::clear(window)
+    .await
+    .map_err(|err| format!("failed to clear the cache of {:?}: {err}", window.label()))?;
+  window
+    .reload()
+    .map_err(|err| format!("failed to reload {:?}: {err}", window.label()))
+}
+
+/// Clears the frontend's saved state (local storage and IndexedDB) and reloads the main
+/// windows, e.g. after the data it refers to was deleted.
+pub async fn clear_frontend_storage(app: &AppHandle) -> Result<(), String> {
+  let windows = main_style_windows(app);
+  // All windows share one data store.
+  let Some(first) = windows.first() else {
+    return Ok(());
+  };
+  cache::clear_storage(first)
+    .await
+    .map_err(|err| format!("failed to clear the frontend's stored data: {err}"))?;
+  info!("cleared the frontend's local storage and IndexedDB");
+  for window in &windows {
+    if let Err(err) = window.reload() {
+      warn!("failed to reload {:?}: {err}", window.label());
+    }
+  }
+  Ok(())
+}
+
+/// Shows the log viewer, creating it on first use. It has its own size and position (kept
+/// across launches), separate from the main window.
+pub fn open_log_viewer(app: &AppHandle) -> tauri::Result<()> {
+  let window = log_viewer(app)?;
+  window.show()?;
+  window.unminimize()?;
+  window.set_focus()
+}
+
+/// Returns the log viewer, creating it hidden if it isn't open.
+fn log_viewer(app: &AppHandle) -> tauri::Result<WebviewWindow> {
+  if let Some(existing) = app.get_webview_window(LOG_VIEWER_WINDOW) {
+    return Ok(existing);
+  }
+  let url = WebviewUrl::App("#/logs".into());
+  let window_builder = WebviewWindowBuilder::new(app, LOG_VIEWER_WINDOW, url)
+    .title(title(app, "Pluto Duck Logs"))
+    .resizable(true)
+    .visible(false)
+    .theme(theme::forced(app))
+    .devtools(devtools_allowed());
+  let window_builder = navigation::guard(app, window_builder);
+  let window_builder = bootstrap::inject(app, window_builder);
+  let window =
+    geometry::restore(app, LOG_VIEWER_WINDOW, LOG_VIEWER_SIZE, window_builder).build()?;
+  geometry::track(&window);
+  restore_zoom(&window);
+  handle_close(&window);
+  theme::track(&window);
+  Ok(window)
+}
+
+// Async so window creation doesn't run on (and deadlock) the main thread on Windows.
+#[tauri::command]
+pub async fn show_log_viewer(app: AppHandle) -> Result<(), String> {
+  open_log_viewer(&app).map_err(|err| format!("failed to open log viewer: {err}"))
+}
+
+/// Small fixed-size windows for pages that would otherwise be in-page modals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UtilityWindow {
+  Preferences,
+  About,
+}
+
+impl UtilityWindow {
+  const ALL: [Self; 2] = [Self::Preferences, Self::About];
+
+  fn label(self) -> &'static str {
+    match self {
+      Self::Preferences => PREFERENCES_WINDOW,
+      Self::About => ABOUT_WINDOW,
+    }
+  }
+
+  fn title(self) -> &'static str {
+    match self {
+      Self::Preferences => "Preferences",
+      Self::About => "About Pluto Duck",
+    }
+  }
+
+  fn route(self) -> &'static str {
+    match self {
+      Self::Preferences => "#/preferences",
+      Self::About => "#/about",
+    }
+  }
+
+  fn size(self) -> WindowSize {
+    match self {
+      Self::Preferences => PREFERENCES_SIZE,
+      Self::About => ABOUT_SIZE,
+    }
+  }
+
+  fn is_utility(label: &str) -> bool {
+    Self::ALL.iter().any(|kind| kind.label() == label)
+  }
+}
+
+/// Shows the `kind` window, creating it if it isn't open. These really close when closed
+/// (they're cheap to rebuild), so they skip the close behavior and saved geometry.
+pub fn show_utility_window(app: &AppHandle, kind: UtilityWindow) -> tauri::Result<()> {
+  let window = match app.get_webview_window(kind.label()) {
+    Some(window) => window,
+    None => {
+      let size = kind.size();
+      let url = WebviewUrl::App(kind.route().into());
+      let mut window_builder = WebviewWindowBuilder::new(app, kind.label(), url)
+        .title(kind.title())
+        .inner_size(size.width, size.height)
+        .resizable(false)
+        .maximizable(false)
+        .minimizable(false)
+        .center()
+        .theme(theme::forced(app))
+        .devtools(devtools_allowed());
+      #[cfg(target_os = "macos")]
+      {
+        window_builder = window_builder
+          .hidden_title(true)
+          .title_bar_style(TitleBarStyle::Overlay);
+      }
+      if let Some(forced) = theme::forced(app) {
+        window_builder = window_builder.background_color(theme::background(forced));
+      }
+      window_builder = navigation::guard(app, window_builder);
+      let window = bootstrap::inject(app, window_builder).build()?;
+      theme::track(&window);
+      window
+    }
+  };
+  window.show()?;
+  window.unminimize()?;
+  window.set_focus()
+}
+
+// Async so window creation doesn't run on (and deadlock) the main thread on Windows.
+#[tauri::command]
+pub async fn open_utility_window(app: AppHandle, kind: UtilityWindow) -> Result<(), String> {
+  show_utility_window(&app, kind).map_err(|err| format!("failed to open {kind:?} window: {err}"))
+}
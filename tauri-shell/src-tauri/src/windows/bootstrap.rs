@@ -0,0 +1,114 @@
+//! `window.__PLUTODUCK__`: where the backend listens and how to authenticate to it, defined
+//! before any page script runs so the frontend never has to guess a port. This is the only
+//! way the auth token reaches the webview. Every app window gets it when built (the splash is
+//! the shell's own page and doesn't need it), but only the frontend's own pages see it, not
+//! the backend's or an allow-listed site's. When the backend comes back on another port or
+//! with a new token, open windows get the new values in place and as a
+//! `backend-config-changed` event, and keep them across reloads.
+
+use std::sync::Mutex;
+
+use log::info;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, WebviewWindowBuilder, Wry};
+
+use super::{fallback, navigation};
+use crate::backend;
+
+/// Where open windows keep the latest values; an initialization script can't be changed once
+/// the window is built, so after a change it prefers these.
+const STORAGE_KEY: &str = "__PLUTODUCK__";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendConfig {
+  /// `None` until the backend has been launched or attached to.
+  backend_url: Option<String>,
+  token: Option<String>,
+  version: String,
+  platform: &'static str,
+}
+
+/// What windows were last given.
+#[derive(Default)]
+pub struct BootstrapState(Mutex<Option<BackendConfig>>);
+
+fn current(app: &AppHandle) -> BackendConfig {
+  BackendConfig {
+    backend_url: backend::backend_url(app),
+    token: backend::auth_token(app),
+    version: app.package_info().version.to_string(),
+    platform: std::env::consts::OS,
+  }
+}
+
+fn remember(app: &AppHandle, config: &BackendConfig) -> bool {
+  let Some(state) = app.try_state::<BootstrapState>() else {
+    return true;
+  };
+  let Ok(mut last) = state.0.lock() else {
+    return true;
+  };
+  let changed = last.as_ref() != Some(config);
+  *last = Some(config.clone());
+  changed
+}
+
+/// Adds the script defining `window.__PLUTODUCK__` to a window about to be built.
+pub fn inject<'a>(
+  app: &AppHandle,
+  builder: WebviewWindowBuilder<'a, Wry, AppHandle>,
+) -> WebviewWindowBuilder<'a, Wry, AppHandle> {
+  let config = current(app);
+  remember(app, &config);
+  let json = serde_json::to_string(&config).unwrap_or_else(|_| "{}".to_string());
+  let origins = origins(app);
+  builder.initialization_script(format!(
+    r#"(() => {{
+  if (!{origins}.includes(location.origin)) return;
+  let config = {json};
+  try {{
+    const latest = sessionStorage.getItem("{STORAGE_KEY}");
+    if (latest) config = JSON.parse(latest);
+  }} catch (_) {{}}
+  window.__PLUTODUCK__ = Object.freeze(config);
+}})();"#
+  ))
+}
+
+/// The origins allowed to see the values, as a JS array.
+fn origins(app: &AppHandle) -> String {
+  serde_json::to_string(&navigation::frontend_origins(app)).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Tells open windows about backend values that differ from what they were given; called
+/// whenever the backend becomes ready, since a restart may have moved it to another port.
+pub fn announce(app: &AppHandle) {
+  let config = current(app);
+  if !remember(app, &config) {
+    return;
+  }
+  info!("backend config changed; now at {:?}", config.backend_url);
+  let json = serde_json::to_string(&config).unwrap_or_else(|_| "{}".to_string());
+  let origins = origins(app);
+  let script = format!(
+    r#"(() => {{
+  if (!{origins}.includes(location.origin)) return;
+  const config = {json};
+  try {{
+    sessionStorage.setItem("{STORAGE_KEY}", JSON.stringify(config));
+  }} catch (_) {{}}
+  window.__PLUTODUCK__ = Object.freeze(config);
+}})();"#
+  );
+  for window in app.webview_windows().values() {
+    // Whatever else a window is showing mustn't get the token.
+    let ours = window
+      .url()
+      .is_ok_and(|url| navigation::is_frontend(app, &url) || fallback::is_page(&url));
+    if window.label() != super::SPLASH_WINDOW && ours {
+      let _ = window.eval(&script);
+    }
+  }
+  let _ = app.emit("backend-config-changed", &config);
+}
@@ -0,0 +1,181 @@
+//! Clears a webview's HTTP caches or, when app data is reset, its local storage and IndexedDB,
+//! through each platform webview's own API, since Tauri only offers clearing everything
+//! (cookies included).
+
+use tauri::WebviewWindow;
+use tokio::sync::oneshot;
+
+#[derive(Debug, Clone, Copy)]
+enum Data {
+  /// Disk and memory caches.
+  Cache,
+  /// Local storage and IndexedDB, i.e. the frontend's own saved state.
+  Storage,
+}
+
+/// Clears `window`'s disk and memory caches and waits for the webview to finish.
+pub async fn clear(window: &WebviewWindow) -> Result<(), String> {
+  clear_data(window, Data::Cache).await
+}
+
+/// Clears the local storage and IndexedDB of `window`'s data store, shared by all windows.
+pub async fn clear_storage(window: &WebviewWindow) -> Result<(), String> {
+  clear_data(window, Data::Storage).await
+}
+
+async fn clear_data(window: &WebviewWindow, data: Data) -> Result<(), String> {
+  let (done, finished) = oneshot::channel::<Result<(), String>>();
+  window
+    .with_webview(move |webview| native::clear(webview, data, done))
+    .map_err(|err| err.to_string())?;
+  finished
+    .await
+    .map_err(|_| "the webview dropped the request".to_string())?
+}
+
+type Done = oneshot::Sender<Result<(), String>>;
+
+#[cfg(target_os = "linux")]
+mod native {
+  use tauri::webview::PlatformWebview;
+  use webkit2gtk::glib::TimeSpan;
+  use webkit2gtk::{
+    gio, WebContextExt, WebViewExt, WebsiteDataManagerExtManual, WebsiteDataTypes,
+  };
+
+  use super::{Data, Done};
+
+  pub fn clear(webview: PlatformWebview, data: Data, done: Done) {
+    let Some(context) = webview.inner().context() else {
+      let _ = done.send(Err("the webview has no web context".to_string()));
+      return;
+    };
+    match data {
+      Data::Cache => {
+        context.clear_cache();
+        let _ = done.send(Ok(()));
+      }
+      Data::Storage => {
+        let Some(manager) = context.website_data_manager() else {
+          let _ = done.send(Err("the webview has no website data manager".to_string()));
+          return;
+        };
+        // A zero timespan clears data of any age.
+        manager.clear(
+          WebsiteDataTypes::LOCAL_STORAGE | WebsiteDataTypes::INDEXEDDB_DATABASES,
+          TimeSpan(0),
+          None::<&gio::Cancellable>,
+          move |result| {
+            let _ = done.send(result.map_err(|err| err.to_string()));
+          },
+        );
+      }
+    }
+  }
+}
+
+#[cfg(target_os = "macos")]
+mod native {
+  use std::sync::Mutex;
+
+  use block::ConcreteBlock;
+  use cocoa::base::{id, nil};
+  use cocoa::foundation::NSArray;
+  use objc::{class, msg_send, sel, sel_impl};
+  use tauri::webview::PlatformWebview;
+
+  use super::{Data, Done};
+
+  #[link(name = "WebKit", kind = "framework")]
+  extern "C" {
+    static WKWebsiteDataTypeDiskCache: id;
+    static WKWebsiteDataTypeMemoryCache: id;
+    static WKWebsiteDataTypeLocalStorage: id;
+    static WKWebsiteDataTypeIndexedDBDatabases: id;
+  }
+
+  pub fn clear(webview: PlatformWebview, data: Data, done: Done) {
+    // The block may in principle be called more than once; only the first call counts.
+    let done = Mutex::new(Some(done));
+    let completion = ConcreteBlock::new(move || {
+      if let Some(done) = done.lock().ok().and_then(|mut done| done.take()) {
+        let _ = done.send(Ok(()));
+      }
+    })
+    .copy();
+    // SAFETY: runs on the main thread with the live WKWebView; the block is copied to the heap
+    // and retained by WebKit until it has been called.
+    unsafe {
+      let web_view = webview.inner() as id;
+      let configuration: id = msg_send![web_view, configuration];
+      let store: id = msg_send![configuration, websiteDataStore];
+      let kinds = match data {
+        Data::Cache => [WKWebsiteDataTypeDiskCache, WKWebsiteDataTypeMemoryCache],
+        Data::Storage => [WKWebsiteDataTypeLocalStorage, WKWebsiteDataTypeIndexedDBDatabases],
+      };
+      let kinds = NSArray::arrayWithObjects(nil, &kinds);
+      let kinds: id = msg_send![class!(NSSet), setWithArray: kinds];
+      let since: id = msg_send![class!(NSDate), distantPast];
+      let _: () = msg_send![
+        store,
+        removeDataOfTypes: kinds
+        modifiedSince: since
+        completionHandler: &*completion
+      ];
+    }
+  }
+}
+
+#[cfg(windows)]
+mod native {
+  use std::sync::Mutex;
+
+  use tauri::webview::PlatformWebview;
+  use webview2_com::ClearBrowsingDataCompletedHandler;
+  use webview2_com::Microsoft::Web::WebView2::Win32::{
+    ICoreWebView2Profile2, ICoreWebView2_13, COREWEBVIEW2_BROWSING_DATA_KINDS_CACHE_STORAGE,
+    COREWEBVIEW2_BROWSING_DATA_KINDS_DISK_CACHE, COREWEBVIEW2_BROWSING_DATA_KINDS_INDEXED_DB,
+    COREWEBVIEW2_BROWSING_DATA_KINDS_LOCAL_STORAGE,
+  };
+  use windows_core::Interface;
+
+  use super::{Data, Done};
+
+  pub fn clear(webview: PlatformWebview, data: Data, done: Done) {
+    let kinds = match data {
+      Data::Cache => {
+        COREWEBVIEW2_BROWSING_DATA_KINDS_DISK_CACHE | COREWEBVIEW2_BROWSING_DATA_KINDS_CACHE_STORAGE
+      }
+      Data::Storage => {
+        COREWEBVIEW2_BROWSING_DATA_KINDS_LOCAL_STORAGE | COREWEBVIEW2_BROWSING_DATA_KINDS_INDEXED_DB
+      }
+    };
+    // Shared with the completion handler, which WebView2 never calls if the request fails.
+    let done = std::sync::Arc::new(Mutex::new(Some(done)));
+    let finish = |done: &Mutex<Option<Done>>, result: Result<(), String>| {
+      if let Some(done) = done.lock().ok().and_then(|mut done| done.take()) {
+        let _ = done.send(result);
+      }
+    };
+    let handler_done = done.clone();
+    let handler = ClearBrowsingDataCompletedHandler::create(Box::new(move |result| {
+      finish(&handler_done, result.map_err(|err| err.to_string()));
+      Ok(())
+    }));
+    // SAFETY: runs on the WebView2 UI thread with the live controller.
+    let result = unsafe {
+      webview
+        .controller()
+        .CoreWebView2()
+        .and_then(|core| core.cast::<ICoreWebView2_13>())
+        .and_then(|core| core.Profile())
+        .and_then(|profile| profile.cast::<ICoreWebView2Profile2>())
+        .and_then(|profile| {
+          profile.ClearBrowsingData(kinds, &handler)
+        })
+    };
+    if let Err(err) = result {
+      finish(&done, Err(format!("WebView2 could not clear {data:?} data: {err}")));
+    }
+  }
+}
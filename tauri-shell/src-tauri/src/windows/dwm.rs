@@ -0,0 +1,68 @@
+//! Windows titlebar styling through DWM, the counterpart of the macOS titlebar code: a dark
+//! caption when the window theme is dark, and an optional caption color from the config.
+
+use std::ffi::c_void;
+
+use log::warn;
+use tauri::{Manager, Theme, WebviewWindow};
+use windows_sys::Win32::Graphics::Dwm::{
+  DwmSetWindowAttribute, DWMWA_CAPTION_COLOR, DWMWA_USE_IMMERSIVE_DARK_MODE,
+};
+
+use crate::config::ShellConfig;
+
+/// The value `DWMWA_USE_IMMERSIVE_DARK_MODE` had before Windows 10 20H1.
+const DWMWA_USE_IMMERSIVE_DARK_MODE_BEFORE_20H1: u32 = 19;
+
+/// Styles `window`'s caption for `theme`. Called on creation and whenever the theme changes.
+pub fn apply(window: &WebviewWindow, theme: Theme) {
+  let Ok(hwnd) = window.hwnd() else {
+    return;
+  };
+  let hwnd = hwnd.0;
+
+  let dark: i32 = matches!(theme, Theme::Dark).into();
+  // Older Windows 10 builds only know the attribute under its pre-release number.
+  let applied = set_attribute(hwnd, DWMWA_USE_IMMERSIVE_DARK_MODE as u32, &dark)
+    || set_attribute(hwnd, DWMWA_USE_IMMERSIVE_DARK_MODE_BEFORE_20H1, &dark);
+  if !applied {
+    warn!("this version of Windows doesn't support a dark titlebar");
+  }
+
+  let caption = window
+    .try_state::<ShellConfig>()
+    .and_then(|config| config.window.caption_color.clone());
+  if let Some(caption) = caption {
+    match parse_color(&caption) {
+      // Only Windows 11 has caption colors; failing elsewhere is expected.
+      Some(color) => {
+        set_attribute(hwnd, DWMWA_CAPTION_COLOR as u32, &color);
+      }
+      None => warn!("ignoring invalid caption color {caption:?}; expected #rrggbb"),
+    }
+  }
+}
+
+fn set_attribute<T>(hwnd: *mut c_void, attribute: u32, value: &T) -> bool {
+  // SAFETY: `value` points to a live `T` and its size is passed alongside it.
+  let result = unsafe {
+    DwmSetWindowAttribute(
+      hwnd,
+      attribute,
+      (value as *const T).cast(),
+      std::mem::size_of::<T>() as u32,
+    )
+  };
+  result == 0
+}
+
+/// Parses `#rrggbb` into a COLORREF (`0x00bbggrr`).
+fn parse_color(value: &str) -> Option<u32> {
+  let hex = value.strip_prefix('#')?;
+  if hex.len() != 6 {
+    return None;
+  }
+  let rgb = u32::from_str_radix(hex, 16).ok()?;
+  let (red, green, blue) = ((rgb >> 16) & 0xff, (rgb >> 8) & 0xff, rgb & 0xff);
+  Some((blue << 16) | (green << 8) | red)
+}
@@ -0,0 +1,162 @@
+//! A page for main windows whose backend never came up, which would otherwise sit on the
+//! frontend's spinner. It's served from the `fallback` URI scheme, which Tauri treats as local
+//! like the app itself, so its buttons call `restart_backend` and `open_logs_folder` over IPC.
+//! Once the backend is ready, each window goes back to the page it was on.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use log::{info, warn};
+use serde::Serialize;
+use tauri::http::{Method, Request, Response, StatusCode};
+use tauri::{AppHandle, Listener, Manager, UriSchemeContext, Url, WebviewWindow, Wry};
+
+use super::splash::{self, respond, scheme_url};
+use crate::backend;
+
+pub const SCHEME: &str = "fallback";
+const PAGE: &str = include_str!("fallback.html");
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FallbackStatus {
+  message: String,
+  detail: Option<String>,
+  log_path: String,
+}
+
+#[derive(Default)]
+struct Fallback {
+  status: Option<FallbackStatus>,
+  /// Where each window showing the page was before it.
+  returns: HashMap<String, Url>,
+}
+
+#[derive(Default)]
+struct FallbackState(Mutex<Fallback>);
+
+/// Sends main windows to the page when the backend misses its startup deadline or stops
+/// restarting, and back once it's ready.
+pub fn install(app: &AppHandle) {
+  app.manage(FallbackState::default());
+  follow(app, "backend-start-timeout", "The backend did not start in time.", "error");
+  follow(app, "backend-failed", "The backend keeps crashing.", "lastExit");
+  let ready_handle = app.clone();
+  app.listen_any("backend-ready", move |_| {
+    let app = ready_handle.clone();
+    let _ = ready_handle.run_on_main_thread(move || leave(&app));
+  });
+}
+
+/// Whether `url` is the fallback page.
+pub fn is_page(url: &Url) -> bool {
+  let page = scheme_url(SCHEME);
+  url.scheme() == page.scheme() && url.host_str() == page.host_str()
+}
+
+/// The app page `window` is on, or was on before the fallback page replaced it.
+pub fn app_url(window: &WebviewWindow) -> Option<Url> {
+  let url = window.url().ok()?;
+  if !is_page(&url) {
+    return Some(url);
+  }
+  let state = window.try_state::<FallbackState>()?;
+  let fallback = state.0.lock().ok()?;
+  fallback.returns.get(window.label()).cloned()
+}
+
+/// Serves the page and the failure it shows.
+pub fn serve(
+  ctx: UriSchemeContext<'_, Wry>,
+  request: Request<Vec<u8>>,
+) -> Response<Cow<'static, [u8]>> {
+  let app = ctx.app_handle();
+  match (request.method(), request.uri().path()) {
+    (&Method::GET, "/") => {
+      respond(StatusCode::OK, "text/html; charset=utf-8", PAGE.as_bytes().to_vec())
+    }
+    (&Method::GET, "/status") => {
+      let status = app
+        .try_state::<FallbackState>()
+        .and_then(|state| state.0.lock().ok().and_then(|fallback| fallback.status.clone()));
+      let body = serde_json::to_vec(&status).unwrap_or_default();
+      respond(StatusCode::OK, "application/json", body)
+    }
+    _ => respond(StatusCode::NOT_FOUND, "text/plain", Vec::new()),
+  }
+}
+
+/// Shows the page with `message` on `event`, its detail taken from the payload's `detail_key`.
+fn follow(app: &AppHandle, event: &str, message: &'static str, detail_key: &'static str) {
+  let app_handle = app.clone();
+  app.listen_any(event, move |event| {
+    // At launch the splash shows the failure instead.
+    if splash::is_open(&app_handle) {
+      return;
+    }
+    let payload = serde_json::from_str(event.payload()).unwrap_or(serde_json::Value::Null);
+    let log_dir =
+      backend::current_log_dir(&app_handle).unwrap_or_else(|| backend::log_root(&app_handle));
+    let status = FallbackStatus {
+      message: message.to_string(),
+      detail: payload
+        .get(detail_key)
+        .and_then(|detail| detail.as_str())
+        .map(str::to_string),
+      log_path: log_dir.display().to_string(),
+    };
+    let app = app_handle.clone();
+    let _ = app_handle.run_on_main_thread(move || enter(&app, status));
+  });
+}
+
+fn enter(app: &AppHandle, status: FallbackStatus) {
+  let Some(state) = app.try_state::<FallbackState>() else {
+    return;
+  };
+  let mut leaving = Vec::new();
+  if let Ok(mut fallback) = state.0.lock() {
+    fallback.status = Some(status);
+    for window in super::main_style_windows(app) {
+      // A window already on the page picks the new status up when it next polls.
+      let Some(url) = window.url().ok().filter(|url| !is_page(url)) else {
+        continue;
+      };
+      fallback.returns.insert(window.label().to_string(), url);
+      leaving.push(window);
+    }
+  }
+  for window in leaving {
+    info!("showing the backend-unreachable page in window {}", window.label());
+    if let Err(err) = window.navigate(scheme_url(SCHEME)) {
+      warn!("failed to show the backend-unreachable page in {}: {err}", window.label());
+    }
+  }
+}
+
+fn leave(app: &AppHandle) {
+  let Some(state) = app.try_state::<FallbackState>() else {
+    return;
+  };
+  let returns = match state.0.lock() {
+    Ok(mut fallback) => {
+      fallback.status = None;
+      std::mem::take(&mut fallback.returns)
+    }
+    Err(_) => return,
+  };
+  for (label, url) in returns {
+    let Some(window) = app.get_webview_window(&label) else {
+      continue;
+    };
+    // Someone may have navigated it off the page already.
+    if !window.url().is_ok_and(|current| is_page(&current)) {
+      continue;
+    }
+    info!("backend is ready; returning window {label} to {url}");
+    if let Err(err) = window.navigate(url) {
+      warn!("failed to return window {label} to the app: {err}");
+    }
+  }
+}
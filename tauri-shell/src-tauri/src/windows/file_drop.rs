@@ -0,0 +1,189 @@
+//! Files dropped onto a main window, handed to its webview as `files-dropped` with what the
+//! frontend needs to route them: size, extension, and a sniff of the first bytes, so it
+//! doesn't have to read the files itself. A dropped directory stands for the supported files
+//! directly inside it.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use serde::Serialize;
+use tauri::{DragDropEvent, Emitter, Window, WindowEvent};
+
+/// Extensions a dropped directory is searched for.
+const SUPPORTED: &[&str] = &["csv", "tsv", "parquet", "json", "jsonl", "ndjson"];
+/// Files listed for one drop, across every directory in it.
+const MAX_FILES: usize = 500;
+const SNIFF_LEN: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Sniffed {
+  Csv,
+  Parquet,
+  Json,
+  Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DroppedFile {
+  path: PathBuf,
+  size: u64,
+  extension: Option<String>,
+  content: Sniffed,
+  /// The dropped directory this file was found in, if it wasn't dropped itself.
+  directory: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FilesDropped {
+  files: Vec<DroppedFile>,
+  /// Some directory's files were left out for `MAX_FILES`.
+  truncated: bool,
+}
+
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+  let WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) = event else {
+    return;
+  };
+  if !super::is_main_style(window.label()) || paths.is_empty() {
+    return;
+  }
+  let window = window.clone();
+  let paths = paths.clone();
+  tauri::async_runtime::spawn(async move {
+    match tauri::async_runtime::spawn_blocking(move || describe(&paths)).await {
+      Ok(dropped) => {
+        let _ = window.emit_to(window.label(), "files-dropped", dropped);
+      }
+      Err(err) => warn!("failed to read dropped files: {err}"),
+    }
+  });
+}
+
+fn describe(paths: &[PathBuf]) -> FilesDropped {
+  let mut dropped = FilesDropped { files: Vec::new(), truncated: false };
+  for path in paths {
+    let path = absolute(path);
+    if path.is_dir() {
+      list_directory(&path, &mut dropped);
+    } else if let Some(file) = describe_file(&path, None) {
+      dropped.files.push(file);
+    }
+  }
+  dropped
+}
+
+/// The supported files directly inside `directory`, in name order, up to the cap.
+fn list_directory(directory: &Path, dropped: &mut FilesDropped) {
+  let entries = match std::fs::read_dir(directory) {
+    Ok(entries) => entries,
+    Err(err) => {
+      warn!("failed to list dropped directory {directory:?}: {err}");
+      return;
+    }
+  };
+  let mut paths: Vec<PathBuf> = entries
+    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+    .filter(|path| path.is_file() && extension(path).is_some_and(|ext| SUPPORTED.contains(&&*ext)))
+    .collect();
+  paths.sort();
+  for path in paths {
+    if dropped.files.len() >= MAX_FILES {
+      dropped.truncated = true;
+      return;
+    }
+    if let Some(file) = describe_file(&path, Some(directory)) {
+      dropped.files.push(file);
+    }
+  }
+}
+
+fn describe_file(path: &Path, directory: Option<&Path>) -> Option<DroppedFile> {
+  let mut file = match File::open(path) {
+    Ok(file) => file,
+    Err(err) => {
+      warn!("failed to open dropped file {path:?}: {err}");
+      return None;
+    }
+  };
+  let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+  let mut head = Vec::with_capacity(SNIFF_LEN);
+  if let Err(err) = (&mut file).take(SNIFF_LEN as u64).read_to_end(&mut head) {
+    warn!("failed to read dropped file {path:?}: {err}");
+  }
+  Some(DroppedFile {
+    path: path.to_path_buf(),
+    size,
+    extension: extension(path),
+    content: sniff(&head),
+    directory: directory.map(Path::to_path_buf),
+  })
+}
+
+fn extension(path: &Path) -> Option<String> {
+  Some(path.extension()?.to_str()?.to_ascii_lowercase())
+}
+
+fn absolute(path: &Path) -> PathBuf {
+  if path.is_absolute() {
+    return path.to_path_buf();
+  }
+  std::env::current_dir().map_or_else(|_| path.to_path_buf(), |cwd| cwd.join(path))
+}
+
+/// Parquet by its magic number, JSON by its first character, and CSV as text with a delimiter
+/// on its first line.
+fn sniff(head: &[u8]) -> Sniffed {
+  if head.starts_with(b"PAR1") {
+    return Sniffed::Parquet;
+  }
+  let head = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+  if head.contains(&0) {
+    return Sniffed::Unknown;
+  }
+  // The sample may end partway through a character.
+  let text = match std::str::from_utf8(head) {
+    Ok(text) => text,
+    Err(err) if err.error_len().is_none() => {
+      std::str::from_utf8(&head[..err.valid_up_to()]).unwrap_or_default()
+    }
+    Err(_) => return Sniffed::Unknown,
+  };
+  let text = text.trim_start();
+  if text.starts_with('{') || text.starts_with('[') {
+    return Sniffed::Json;
+  }
+  let first_line = text.lines().next().unwrap_or_default();
+  if first_line.contains([',', ';', '\t', '|']) {
+    return Sniffed::Csv;
+  }
+  Sniffed::Unknown
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn sniff_tells_formats_by_their_first_bytes() {
+    assert_eq!(sniff(b"PAR1\x15\x04"), Sniffed::Parquet);
+    assert_eq!(sniff(b"  [{\"a\": 1}]"), Sniffed::Json);
+    assert_eq!(sniff(b"\xEF\xBB\xBF{\"a\": 1}"), Sniffed::Json);
+    assert_eq!(sniff(b"name;age\nada;36\n"), Sniffed::Csv);
+    assert_eq!(sniff(b"a\tb\n"), Sniffed::Csv);
+    assert_eq!(sniff(b"just some words\n"), Sniffed::Unknown);
+    assert_eq!(sniff(b"a,b\0c"), Sniffed::Unknown);
+    assert_eq!(sniff(b""), Sniffed::Unknown);
+  }
+
+  #[test]
+  fn sniff_allows_a_sample_cut_mid_character() {
+    // "é" is two bytes; the sample ends after the first.
+    assert_eq!(sniff(b"caf\xC3\xA9,prix\ncaf\xC3"), Sniffed::Csv);
+    assert_eq!(sniff(b"a,b\n\xFF\xFE rest"), Sniffed::Unknown);
+  }
+}
@@ -0,0 +1,376 @@
+//! Remembers each window's size, position, maximized/fullscreen state, whether it is pinned on
+//! top, and its zoom level, across launches. `window-state.json` maps window labels to their
+//! geometry.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tauri::window::Monitor;
+use tauri::{AppHandle, Manager, WebviewWindow, WebviewWindowBuilder, WindowEvent, Wry};
+
+use super::WindowSize;
+
+const GEOMETRY_FILE: &str = "window-state.json";
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+/// How much of the title bar area must land on a monitor for a saved position to be kept.
+const MIN_VISIBLE_WIDTH: f64 = 100.0;
+const MIN_VISIBLE_HEIGHT: f64 = 40.0;
+
+/// Logical coordinates, so a restore on a monitor with a different scale factor still fits.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Geometry {
+  pub width: f64,
+  pub height: f64,
+  pub x: Option<f64>,
+  pub y: Option<f64>,
+  pub maximized: bool,
+  pub fullscreen: bool,
+  /// Kept above other apps' windows.
+  #[serde(default)]
+  pub pinned: bool,
+  /// Webview zoom factor; 1.0 is 100%.
+  #[serde(default = "default_zoom")]
+  pub zoom: f64,
+}
+
+fn default_zoom() -> f64 {
+  1.0
+}
+
+/// Saved geometry by window label. Files from before per-window state hold a single geometry,
+/// which was the main window's.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StateFile {
+  Windows(BTreeMap<String, Geometry>),
+  Main(Geometry),
+}
+
+fn geometry_path(app: &AppHandle) -> Option<PathBuf> {
+  crate::config::config_dir(app).map(|dir| dir.join(GEOMETRY_FILE))
+}
+
+fn load_all(app: &AppHandle) -> BTreeMap<String, Geometry> {
+  let Some(path) = geometry_path(app) else {
+    return BTreeMap::new();
+  };
+  let Ok(raw) = std::fs::read(&path) else {
+    return BTreeMap::new();
+  };
+  match serde_json::from_slice(&raw) {
+    Ok(StateFile::Windows(windows)) => windows,
+    Ok(StateFile::Main(geometry)) => BTreeMap::from([(super::MAIN_WINDOW.to_string(), geometry)]),
+    Err(err) => {
+      warn!("ignoring invalid window state {:?}: {err}", path);
+      BTreeMap::new()
+    }
+  }
+}
+
+/// A logical rectangle of a monitor: its bounds, or its work area (the bounds minus the
+/// taskbar, dock, and menu bar).
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Area {
+  x: f64,
+  y: f64,
+  width: f64,
+  height: f64,
+}
+
+impl Area {
+  fn work_area(monitor: &Monitor) -> Self {
+    let scale = monitor.scale_factor();
+    let area = monitor.work_area();
+    let position = area.position.to_logical::<f64>(scale);
+    let size = area.size.to_logical::<f64>(scale);
+    Self {
+      x: position.x,
+      y: position.y,
+      width: size.width,
+      height: size.height,
+    }
+  }
+
+  fn bounds(monitor: &Monitor) -> Self {
+    let scale = monitor.scale_factor();
+    let position = monitor.position().to_logical::<f64>(scale);
+    let size = monitor.size().to_logical::<f64>(scale);
+    Self {
+      x: position.x,
+      y: position.y,
+      width: size.width,
+      height: size.height,
+    }
+  }
+
+  /// `size`'s opening size, shrunk to fit.
+  fn fit(&self, size: WindowSize) -> (f64, f64) {
+    (size.width.min(self.width), size.height.min(self.height))
+  }
+
+  /// Whether enough of the title bar of a window `width` wide at `x`, `y` lands in here to
+  /// grab it.
+  fn shows_title_bar(&self, x: f64, y: f64, width: f64) -> bool {
+    let visible_width = (x + width).min(self.x + self.width) - x.max(self.x);
+    visible_width >= MIN_VISIBLE_WIDTH
+      && y >= self.y
+      && y + MIN_VISIBLE_HEIGHT <= self.y + self.height
+  }
+}
+
+/// The work area of the monitor under the cursor, which is where a new window is expected to
+/// appear, falling back to the primary monitor.
+fn cursor_work_area(app: &AppHandle) -> Option<Area> {
+  let under_cursor = app
+    .cursor_position()
+    .ok()
+    .and_then(|cursor| app.monitor_from_point(cursor.x, cursor.y).ok().flatten());
+  under_cursor
+    .or_else(|| app.primary_monitor().ok().flatten())
+    .map(|monitor| Area::work_area(&monitor))
+}
+
+/// The saved geometry of window `label`, fitted to the screen: the position is dropped if it
+/// no longer lands on any monitor, and the size is clamped to the work area of the monitor it
+/// lands on (or, without a position, the one under the cursor).
+pub fn load(app: &AppHandle, label: &str) -> Option<Geometry> {
+  let geometry = load_all(app).remove(label)?;
+  let screens: Vec<_> = app
+    .available_monitors()
+    .unwrap_or_default()
+    .iter()
+    .map(|monitor| (Area::bounds(monitor), Area::work_area(monitor)))
+    .collect();
+  Some(fitted(geometry, &screens, || cursor_work_area(app)))
+}
+
+/// `load`'s fitting, against `screens` (each monitor's bounds and work area), with `fallback`
+/// giving the work area for a window without a position.
+fn fitted(
+  mut geometry: Geometry,
+  screens: &[(Area, Area)],
+  fallback: impl FnOnce() -> Option<Area>,
+) -> Geometry {
+  let landed = match (geometry.x, geometry.y) {
+    (Some(x), Some(y)) => screens
+      .iter()
+      .find(|(bounds, _)| bounds.shows_title_bar(x, y, geometry.width))
+      .map(|(_, work_area)| *work_area),
+    _ => None,
+  };
+  let area = match landed {
+    Some(area) => Some(area),
+    None => {
+      geometry.x = None;
+      geometry.y = None;
+      fallback()
+    }
+  };
+  if let Some(area) = area {
+    geometry.width = geometry.width.min(area.width);
+    geometry.height = geometry.height.min(area.height);
+  }
+  geometry
+}
+
+/// Sizes `builder` for window `label`: its minimum from `size`, and either its saved geometry
+/// or, on first launch, `size` centered on the monitor under the cursor. Both are fitted to the
+/// screen, and applied before the window is built so it doesn't jump after it appears.
+pub fn restore<'a>(
+  app: &AppHandle,
+  label: &str,
+  size: WindowSize,
+  builder: WebviewWindowBuilder<'a, Wry, AppHandle>,
+) -> WebviewWindowBuilder<'a, Wry, AppHandle> {
+  let area = cursor_work_area(app);
+  // A screen smaller than the minimum still gets a window that fits on it.
+  let (min_width, min_height) = match area {
+    Some(area) => (size.min_width.min(area.width), size.min_height.min(area.height)),
+    None => (size.min_width, size.min_height),
+  };
+  let builder = builder.min_inner_size(min_width, min_height);
+
+  let Some(geometry) = load(app, label) else {
+    let Some(area) = area else {
+      return builder.inner_size(size.width, size.height);
+    };
+    let (width, height) = area.fit(size);
+    return builder
+      .inner_size(width, height)
+      .position(area.x + (area.width - width) / 2.0, area.y + (area.height - height) / 2.0);
+  };
+  let builder = builder
+    .inner_size(geometry.width, geometry.height)
+    .maximized(geometry.maximized)
+    .fullscreen(geometry.fullscreen)
+    .always_on_top(geometry.pinned);
+  match (geometry.x, geometry.y) {
+    (Some(x), Some(y)) => builder.position(x, y),
+    _ => builder,
+  }
+}
+
+/// Writes the window's current geometry. While maximized or fullscreen, the previously saved
+/// normal size and position are kept so un-maximizing after a restore still works.
+pub fn save(window: &WebviewWindow) {
+  let app = window.app_handle();
+  let Some(path) = geometry_path(app) else {
+    return;
+  };
+  let maximized = window.is_maximized().unwrap_or(false);
+  let fullscreen = window.is_fullscreen().unwrap_or(false);
+  let minimized = window.is_minimized().unwrap_or(false);
+  let pinned = window.is_always_on_top().unwrap_or(false);
+  let zoom = super::zoom_level(window);
+
+  let mut windows = load_all(app);
+  let geometry = if maximized || fullscreen || minimized {
+    let Some(previous) = windows.get(window.label()) else {
+      return;
+    };
+    Geometry {
+      maximized,
+      fullscreen,
+      pinned,
+      zoom,
+      ..*previous
+    }
+  } else {
+    let (Ok(scale), Ok(size), Ok(position)) =
+      (window.scale_factor(), window.inner_size(), window.outer_position())
+    else {
+      return;
+    };
+    let size = size.to_logical::<f64>(scale);
+    let position = position.to_logical::<f64>(scale);
+    Geometry {
+      width: size.width,
+      height: size.height,
+      x: Some(position.x),
+      y: Some(position.y),
+      maximized: false,
+      fullscreen: false,
+      pinned,
+      zoom,
+    }
+  };
+  windows.insert(window.label().to_string(), geometry);
+
+  let result = serde_json::to_vec_pretty(&windows)
+    .map_err(std::io::Error::other)
+    .and_then(|bytes| {
+      if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+      }
+      std::fs::write(&path, bytes)
+    });
+  if let Err(err) = result {
+    warn!("failed to save window state {:?}: {err}", path);
+  }
+}
+
+/// Saves on close and, debounced, after the window is moved or resized.
+pub fn track(window: &WebviewWindow) {
+  let generation = Arc::new(AtomicU64::new(0));
+  let tracked = window.clone();
+  window.on_window_event(move |event| match event {
+    WindowEvent::CloseRequested { .. } => save(&tracked),
+    WindowEvent::Moved(_) | WindowEvent::Resized(_) => {
+      let current = generation.fetch_add(1, Ordering::SeqCst) + 1;
+      let generation = generation.clone();
+      let window = tracked.clone();
+      tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(SAVE_DEBOUNCE).await;
+        if generation.load(Ordering::SeqCst) == current {
+          save(&window);
+        }
+      });
+    }
+    _ => {}
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SCREEN: Area = Area {
+    x: 0.0,
+    y: 0.0,
+    width: 1920.0,
+    height: 1080.0,
+  };
+  /// `SCREEN` less a 25-point menu bar.
+  const WORK_AREA: Area = Area {
+    x: 0.0,
+    y: 25.0,
+    width: 1920.0,
+    height: 1055.0,
+  };
+
+  fn geometry(x: f64, y: f64, width: f64, height: f64) -> Geometry {
+    Geometry {
+      width,
+      height,
+      x: Some(x),
+      y: Some(y),
+      maximized: false,
+      fullscreen: false,
+      pinned: false,
+      zoom: 1.0,
+    }
+  }
+
+  #[test]
+  fn a_window_on_screen_keeps_its_place_and_is_clamped_to_the_work_area() {
+    let fitted = fitted(geometry(100.0, 50.0, 2500.0, 1200.0), &[(SCREEN, WORK_AREA)], || None);
+    assert_eq!((fitted.x, fitted.y), (Some(100.0), Some(50.0)));
+    assert_eq!((fitted.width, fitted.height), (1920.0, 1055.0));
+  }
+
+  #[test]
+  fn a_window_off_every_screen_loses_its_position() {
+    let smaller = Area {
+      width: 1280.0,
+      height: 800.0,
+      ..WORK_AREA
+    };
+    // A monitor that was unplugged, and a title bar just past the bottom edge.
+    for (x, y) in [(2500.0, 100.0), (100.0, 1060.0), (1850.0, 100.0)] {
+      let fitted = fitted(geometry(x, y, 1400.0, 900.0), &[(SCREEN, WORK_AREA)], || {
+        Some(smaller)
+      });
+      assert_eq!((fitted.x, fitted.y), (None, None), "kept {x}, {y}");
+      assert_eq!((fitted.width, fitted.height), (1280.0, 800.0));
+    }
+  }
+
+  #[test]
+  fn the_title_bar_has_to_show_enough_to_grab() {
+    assert!(SCREEN.shows_title_bar(-1300.0, 0.0, 1400.0));
+    assert!(!SCREEN.shows_title_bar(-1350.0, 0.0, 1400.0));
+    assert!(!SCREEN.shows_title_bar(100.0, -10.0, 1400.0));
+    assert!(SCREEN.shows_title_bar(100.0, 1040.0, 1400.0));
+  }
+
+  #[test]
+  fn a_state_file_from_before_per_window_state_is_the_main_window() {
+    let raw = r#"{"width":800,"height":600,"x":10,"y":20,"maximized":false,"fullscreen":false}"#;
+    let Ok(StateFile::Main(geometry)) = serde_json::from_str(raw) else {
+      panic!("not read as a single geometry");
+    };
+    assert_eq!((geometry.zoom, geometry.pinned), (1.0, false));
+
+    let raw = format!(r#"{{"main":{raw},"logs":{raw}}}"#);
+    let Ok(StateFile::Windows(windows)) = serde_json::from_str(&raw) else {
+      panic!("not read per window");
+    };
+    assert_eq!(windows.keys().collect::<Vec<_>>(), ["logs", "main"]);
+  }
+}
@@ -0,0 +1,102 @@
+//! Keeps every window on the app. A link to anywhere else (documentation, say, or one crafted
+//! into user content) would otherwise navigate the webview away from the UI with no way back,
+//! so windows only navigate within the app's own origin, the backend's, and the origins in
+//! `window.navigation_allow_list`; other web links open in the default browser (unless
+//! `window.open_blocked_links` is off), and anything else (`file://` included) is refused.
+
+use log::{info, warn};
+use tauri::{AppHandle, Url, WebviewWindowBuilder, Wry};
+
+use crate::{backend, config, platform};
+
+/// Where the bundled frontend is served from on Windows and Android; elsewhere it's
+/// `tauri://localhost`.
+const APP_ORIGINS: &[&str] = &["http://tauri.localhost", "https://tauri.localhost"];
+
+/// What `open_external` opens.
+const EXTERNAL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+pub fn guard<'a>(
+  app: &AppHandle,
+  builder: WebviewWindowBuilder<'a, Wry, AppHandle>,
+) -> WebviewWindowBuilder<'a, Wry, AppHandle> {
+  let app = app.clone();
+  builder.on_navigation(move |url| allow(&app, url))
+}
+
+fn allow(app: &AppHandle, url: &Url) -> bool {
+  if is_app(app, url) {
+    return true;
+  }
+  // Blank pages come with iframes and `window.open`.
+  if url.scheme() == "about" && matches!(url.path(), "blank" | "srcdoc") {
+    return true;
+  }
+  let window = config::saved(app).window;
+  let origin = url.origin().ascii_serialization();
+  // Opaque origins (`file:`, `data:`) all serialize alike, so they can't be listed.
+  let listed = url.origin().is_tuple()
+    && window.navigation_allow_list.iter().any(|allowed| {
+      Url::parse(allowed).is_ok_and(|allowed| allowed.origin().ascii_serialization() == origin)
+    });
+  if listed {
+    return true;
+  }
+  if !matches!(url.scheme(), "http" | "https") {
+    warn!("blocked navigation to {url}");
+  } else if !window.open_blocked_links {
+    warn!("blocked navigation to {url}; not opening it in the browser");
+  } else {
+    info!("opening {url} in the browser instead of the app window");
+    if let Err(err) = platform::open_url(url.as_str()) {
+      warn!("failed to open {url} in the browser: {err}");
+    }
+  }
+  false
+}
+
+/// Whether `url` is the frontend's, the backend-unreachable page's, or the backend's, which
+/// serves pages of its own.
+fn is_app(app: &AppHandle, url: &Url) -> bool {
+  if is_frontend(app, url) || super::fallback::is_page(url) {
+    return true;
+  }
+  backend::backend_url(app)
+    .and_then(|backend| Url::parse(&backend).ok())
+    .is_some_and(|backend| backend.origin() == url.origin())
+}
+
+/// Whether `url` is one of the frontend's own pages.
+pub fn is_frontend(app: &AppHandle, url: &Url) -> bool {
+  frontend_origins(app).contains(&origin(url))
+}
+
+/// The frontend's origins as its pages see them in `location.origin`.
+pub fn frontend_origins(app: &AppHandle) -> Vec<String> {
+  let mut origins = vec!["tauri://localhost".to_string()];
+  origins.extend(APP_ORIGINS.iter().map(|origin| origin.to_string()));
+  if cfg!(debug_assertions) {
+    origins.extend(app.config().build.dev_url.as_ref().map(origin));
+  }
+  origins
+}
+
+/// A custom scheme's origin is opaque to `Url`, but not to the page, which sees
+/// `tauri://localhost`.
+fn origin(url: &Url) -> String {
+  match url.scheme() {
+    "tauri" => format!("tauri://{}", url.host_str().unwrap_or_default()),
+    _ => url.origin().ascii_serialization(),
+  }
+}
+
+/// Opens `url` in the default browser or, for `mailto:`, the mail app.
+#[tauri::command]
+pub fn open_external(url: String) -> Result<(), String> {
+  let parsed = Url::parse(&url).map_err(|err| format!("invalid URL {url:?}: {err}"))?;
+  if !EXTERNAL_SCHEMES.contains(&parsed.scheme()) {
+    warn!("refused to open {url}");
+    return Err(format!("only http, https and mailto links can be opened, not {url}"));
+  }
+  platform::open_url(parsed.as_str()).map_err(|err| format!("failed to open {url}: {err}"))
+}
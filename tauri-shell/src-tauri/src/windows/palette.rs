@@ -0,0 +1,113 @@
+//! The quick-query palette: a small frameless bar that the palette shortcut brings up over
+//! whatever the user is doing. Whatever is entered there goes to the main window as
+//! `palette-submit`, and the main window comes forward to show the result. The bar is built
+//! once and then only hidden, when it loses focus, on Escape, or on submit; it keeps no
+//! geometry and skips the close behavior.
+
+use log::{error, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+
+use super::{MAIN_WINDOW, PALETTE_SIZE, PALETTE_WINDOW};
+
+/// Escape never reaches the shell from inside the webview, so the page is given a handler.
+const ESCAPE_SCRIPT: &str = r#"
+window.addEventListener("keydown", (event) => {
+  if (event.key === "Escape") {
+    window.__TAURI_INTERNALS__.invoke("dismiss_palette");
+  }
+});
+"#;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PaletteSubmit {
+  query: String,
+}
+
+/// Shows the palette, or hides it if it's already up.
+pub fn toggle(app: &AppHandle) {
+  let open = app
+    .get_webview_window(PALETTE_WINDOW)
+    .is_some_and(|window| window.is_visible().unwrap_or(false));
+  let result = if open { hide(app) } else { show(app) };
+  if let Err(err) = result {
+    error!("failed to toggle the palette: {err}");
+  }
+}
+
+fn show(app: &AppHandle) -> tauri::Result<()> {
+  let window = match app.get_webview_window(PALETTE_WINDOW) {
+    Some(window) => window,
+    None => build(app)?,
+  };
+  window.center()?;
+  window.show()?;
+  window.set_focus()?;
+  // The page clears and focuses its input.
+  let _ = window.emit_to(PALETTE_WINDOW, "palette-shown", ());
+  Ok(())
+}
+
+fn hide(app: &AppHandle) -> tauri::Result<()> {
+  match app.get_webview_window(PALETTE_WINDOW) {
+    Some(window) => window.hide(),
+    None => Ok(()),
+  }
+}
+
+fn build(app: &AppHandle) -> tauri::Result<WebviewWindow> {
+  let url = WebviewUrl::App("#/palette".into());
+  let builder = WebviewWindowBuilder::new(app, PALETTE_WINDOW, url)
+    .title("Quick Query")
+    .inner_size(PALETTE_SIZE.width, PALETTE_SIZE.height)
+    .resizable(false)
+    .maximizable(false)
+    .minimizable(false)
+    .decorations(false)
+    .transparent(true)
+    .always_on_top(true)
+    .skip_taskbar(true)
+    .visible(false)
+    .center()
+    .theme(super::theme::forced(app))
+    .devtools(super::devtools_allowed())
+    .initialization_script(ESCAPE_SCRIPT);
+  let builder = super::navigation::guard(app, builder);
+  let window = super::bootstrap::inject(app, builder).build()?;
+  let window_clone = window.clone();
+  window.on_window_event(move |event| match event {
+    tauri::WindowEvent::Focused(false) => {
+      let _ = window_clone.hide();
+    }
+    // Closing it some other way (Alt+F4, say) only hides it too.
+    tauri::WindowEvent::CloseRequested { api, .. } => {
+      api.prevent_close();
+      let _ = window_clone.hide();
+    }
+    _ => {}
+  });
+  Ok(window)
+}
+
+/// Hands `query` to the main window and brings that forward in place of the palette.
+#[tauri::command]
+pub fn submit_palette(app: AppHandle, query: String) -> Result<(), String> {
+  if let Err(err) = hide(&app) {
+    warn!("failed to hide the palette: {err}");
+  }
+  let main_app = app.clone();
+  app
+    .run_on_main_thread(move || {
+      if let Err(err) = super::show_main_window(&main_app) {
+        error!("failed to show main window: {err}");
+      }
+      let _ = main_app.emit_to(MAIN_WINDOW, "palette-submit", PaletteSubmit { query });
+    })
+    .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub fn dismiss_palette(app: AppHandle) -> Result<(), String> {
+  hide(&app).map_err(|err| format!("failed to hide the palette: {err}"))
+}
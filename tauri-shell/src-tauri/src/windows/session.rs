@@ -0,0 +1,161 @@
+//! Brings back the windows that were open at the last quit: the main windows (at the routes
+//! they showed) and the log viewer. `windows.json` lists them; their geometry is kept with the
+//! rest in `window-state.json`. Windows are rebuilt hidden and shown together once the backend
+//! is ready, and a missing, corrupted, or incompatible file just means the main window alone.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, Url, WebviewUrl};
+
+use super::{LOG_VIEWER_WINDOW, MAIN_WINDOW};
+
+const SESSION_FILE: &str = "windows.json";
+/// Bumped when the file changes shape; other versions are ignored.
+const SESSION_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Session {
+  version: u32,
+  windows: Vec<SavedWindow>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedWindow {
+  label: String,
+  /// The page as an app-relative URL, e.g. `#/dashboards/42`.
+  route: Option<String>,
+  visible: bool,
+}
+
+/// Labels of the windows rebuilt at launch, shown together by `show`.
+#[derive(Default)]
+struct Restored(Mutex<Vec<String>>);
+
+fn session_path(app: &AppHandle) -> Option<PathBuf> {
+  crate::config::config_dir(app).map(|dir| dir.join(SESSION_FILE))
+}
+
+/// Records the open windows, for `restore` at the next launch.
+pub fn save(app: &AppHandle) {
+  let Some(path) = session_path(app) else {
+    return;
+  };
+  let mut windows: Vec<_> = app
+    .webview_windows()
+    .into_values()
+    .filter(|window| super::is_main_style(window.label()) || window.label() == LOG_VIEWER_WINDOW)
+    .map(|window| SavedWindow {
+      label: window.label().to_string(),
+      route: super::fallback::app_url(&window).map(|url| route(&url)),
+      visible: window.is_visible().unwrap_or(false),
+    })
+    .collect();
+  windows.sort_by(|a, b| a.label.cmp(&b.label));
+  let session = Session {
+    version: SESSION_VERSION,
+    windows,
+  };
+
+  let result = serde_json::to_vec_pretty(&session)
+    .map_err(std::io::Error::other)
+    .and_then(|bytes| {
+      if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+      }
+      std::fs::write(&path, bytes)
+    });
+  if let Err(err) = result {
+    warn!("failed to save open windows {:?}: {err}", path);
+  }
+}
+
+/// The part of `url` after the app's origin, which `WebviewUrl::App` resolves back to it.
+fn route(url: &Url) -> String {
+  let mut route = url.path().trim_start_matches('/').to_string();
+  if let Some(query) = url.query() {
+    route.push('?');
+    route.push_str(query);
+  }
+  if let Some(fragment) = url.fragment() {
+    route.push('#');
+    route.push_str(fragment);
+  }
+  route
+}
+
+fn url(window: &SavedWindow) -> WebviewUrl {
+  match &window.route {
+    Some(route) if !route.is_empty() => WebviewUrl::App(route.into()),
+    _ => WebviewUrl::default(),
+  }
+}
+
+fn load(app: &AppHandle) -> Vec<SavedWindow> {
+  let Some(path) = session_path(app) else {
+    return Vec::new();
+  };
+  let Ok(raw) = std::fs::read(&path) else {
+    return Vec::new();
+  };
+  match serde_json::from_slice::<Session>(&raw) {
+    Ok(session) if session.version == SESSION_VERSION => session.windows,
+    Ok(session) => {
+      warn!("ignoring open windows {:?} from version {}", path, session.version);
+      Vec::new()
+    }
+    Err(err) => {
+      warn!("ignoring invalid open windows {:?}: {err}", path);
+      Vec::new()
+    }
+  }
+}
+
+/// Builds, hidden, the main window and whichever other windows were visible at the last quit.
+/// A window that fails to come back is skipped; only the main window is required.
+pub fn restore(app: &AppHandle) -> tauri::Result<()> {
+  let saved = load(app);
+  let main = saved.iter().find(|window| window.label == MAIN_WINDOW);
+  let main_url = main.map_or_else(WebviewUrl::default, url);
+  if let Err(err) = super::build_main_style(app, MAIN_WINDOW, main_url) {
+    warn!("failed to restore the main window's page: {err}");
+    super::main_window(app)?;
+  }
+  let mut restored = vec![MAIN_WINDOW.to_string()];
+
+  for window in saved.iter().filter(|window| window.visible) {
+    let result = if window.label == LOG_VIEWER_WINDOW {
+      super::log_viewer(app)
+    } else if window.label != MAIN_WINDOW && super::is_main_style(&window.label) {
+      super::build_main_style(app, &window.label, url(window))
+    } else {
+      continue;
+    };
+    match result {
+      Ok(_) => restored.push(window.label.clone()),
+      Err(err) => warn!("failed to restore window {}: {err}", window.label),
+    }
+  }
+  if restored.len() > 1 {
+    info!("restored windows: {}", restored.join(", "));
+  }
+  app.manage(Restored(Mutex::new(restored)));
+  Ok(())
+}
+
+/// Shows the windows `restore` built, focusing the main one, or just the main window if there
+/// was nothing to restore. Later calls show only the main window.
+pub fn show(app: &AppHandle) -> tauri::Result<()> {
+  let labels = app
+    .try_state::<Restored>()
+    .and_then(|restored| restored.0.lock().ok().map(|mut labels| std::mem::take(&mut *labels)))
+    .unwrap_or_default();
+  for label in labels.iter().filter(|label| *label != MAIN_WINDOW) {
+    if let Some(window) = app.get_webview_window(label) {
+      window.show()?;
+    }
+  }
+  super::show_main_window(app)
+}
@@ -0,0 +1,258 @@
+//! A small frameless window shown while the backend boots, so the main window (built hidden)
+//! never shows a dead page. The page is served from the `splash` URI scheme and polls
+//! `status` for the current line; on failure it offers "Open logs" and "Retry", which it
+//! posts back to the same scheme, so it needs no IPC access.
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use log::{error, info, warn};
+use serde::Serialize;
+use tauri::http::{header, Method, Request, Response, StatusCode};
+use tauri::{
+  AppHandle, Listener, Manager, UriSchemeContext, Url, WebviewUrl, WebviewWindowBuilder,
+  WindowEvent, Wry,
+};
+
+use super::{SPLASH_SIZE, SPLASH_WINDOW};
+use crate::{backend, logs};
+
+pub const SCHEME: &str = "splash";
+const PAGE: &str = include_str!("splash.html");
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Phase {
+  Starting,
+  Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SplashStatus {
+  phase: Phase,
+  message: String,
+  detail: Option<String>,
+}
+
+struct SplashState(Mutex<SplashStatus>);
+
+/// Set once `finish` has revealed the windows, so the splash closing after it isn't taken for
+/// the user closing it early.
+static FINISHED: AtomicBool = AtomicBool::new(false);
+
+/// Opens the splash and follows backend startup events until the backend is ready. Returns
+/// `false` if the window couldn't be created, in which case the caller should show the main
+/// window right away.
+pub fn open(app: &AppHandle) -> bool {
+  app.manage(SplashState(Mutex::new(SplashStatus {
+    phase: Phase::Starting,
+    message: "Starting…".to_string(),
+    detail: None,
+  })));
+
+  let url = WebviewUrl::CustomProtocol(scheme_url(SCHEME));
+  let result = WebviewWindowBuilder::new(app, SPLASH_WINDOW, url)
+    .title("Pluto Duck")
+    .inner_size(SPLASH_SIZE.width, SPLASH_SIZE.height)
+    .resizable(false)
+    .maximizable(false)
+    .decorations(false)
+    .center()
+    .devtools(super::devtools_allowed())
+    .build();
+  let splash = match result {
+    Ok(splash) => splash,
+    Err(err) => {
+      warn!("failed to open splash window: {err}");
+      return false;
+    }
+  };
+  // Closed before the backend was ready (Alt+F4, say): the main window, built hidden, would
+  // otherwise stay hidden with nothing on screen to bring it back.
+  let closed_handle = app.clone();
+  splash.on_window_event(move |event| {
+    if !matches!(event, WindowEvent::Destroyed) || FINISHED.load(Ordering::SeqCst) {
+      return;
+    }
+    // Only while the hidden window is still there, not as exit tears everything down.
+    if closed_handle.get_webview_window(super::MAIN_WINDOW).is_some() {
+      info!("splash closed before the backend was ready; showing the main window");
+      if let Err(err) = super::show_main_window(&closed_handle) {
+        error!("failed to show main window: {err}");
+      }
+    }
+  });
+
+  follow(app, "data-migration-progress", |payload| {
+    let message = payload.get("message").and_then(|message| message.as_str());
+    let percent = payload.get("percent").and_then(|percent| percent.as_u64());
+    starting(&format!("{}… {}%", message.unwrap_or("Upgrading your data"), percent.unwrap_or(0)))
+  });
+  follow(app, "backend-launch-progress", |payload| {
+    starting(payload.get("message").and_then(|message| message.as_str()).unwrap_or("Starting…"))
+  });
+  follow(app, "backend-spawned", |_| starting("Starting backend…"));
+  follow(app, "backend-waiting-health", |_| {
+    starting("Waiting for the backend to respond…")
+  });
+  follow(app, "backend-restarting", |_| starting("Restarting backend…"));
+  follow(app, "backend-restarted", |_| starting("Restarting backend…"));
+  follow(app, "backend-start-timeout", |payload| {
+    failed(
+      "The backend did not start in time.",
+      payload.get("error").and_then(|error| error.as_str()),
+    )
+  });
+  follow(app, "backend-failed", |payload| {
+    failed(
+      "The backend keeps crashing.",
+      payload.get("lastExit").and_then(|exit| exit.as_str()),
+    )
+  });
+
+  let ready_handle = app.clone();
+  app.listen_any("backend-ready", move |_| {
+    if is_open(&ready_handle) {
+      let app = ready_handle.clone();
+      let _ = ready_handle.run_on_main_thread(move || finish(&app));
+    }
+  });
+  true
+}
+
+/// Whether the splash is still up (i.e. the backend hasn't become ready since launch).
+pub fn is_open(app: &AppHandle) -> bool {
+  app.get_webview_window(SPLASH_WINDOW).is_some()
+}
+
+/// Switches the splash to its error state, e.g. when the backend couldn't be launched at all.
+pub fn fail(app: &AppHandle, message: &str, detail: &str) {
+  set_status(app, failed(message, Some(detail)));
+}
+
+/// Serves the splash page, its status, and its button actions.
+pub fn serve(
+  ctx: UriSchemeContext<'_, Wry>,
+  request: Request<Vec<u8>>,
+) -> Response<Cow<'static, [u8]>> {
+  let app = ctx.app_handle();
+  // Other pages could otherwise post `/retry`, which may restart the app.
+  if ctx.webview_label() != SPLASH_WINDOW {
+    warn!("refusing splash request from webview {:?}", ctx.webview_label());
+    return respond(StatusCode::FORBIDDEN, "text/plain", Vec::new());
+  }
+  match (request.method(), request.uri().path()) {
+    (&Method::GET, "/") => {
+      respond(StatusCode::OK, "text/html; charset=utf-8", PAGE.as_bytes().to_vec())
+    }
+    (&Method::GET, "/status") => {
+      let status = app
+        .try_state::<SplashState>()
+        .and_then(|state| state.0.lock().ok().map(|status| status.clone()));
+      let body = serde_json::to_vec(&status).unwrap_or_default();
+      respond(StatusCode::OK, "application/json", body)
+    }
+    (&Method::POST, "/retry") => {
+      retry(app);
+      respond(StatusCode::NO_CONTENT, "text/plain", Vec::new())
+    }
+    (&Method::POST, "/open-logs") => {
+      if let Err(err) = logs::reveal_logs_folder(app) {
+        error!("failed to open logs folder: {err}");
+      }
+      respond(StatusCode::NO_CONTENT, "text/plain", Vec::new())
+    }
+    _ => respond(StatusCode::NOT_FOUND, "text/plain", Vec::new()),
+  }
+}
+
+pub(super) fn respond(
+  status: StatusCode,
+  content_type: &str,
+  body: Vec<u8>,
+) -> Response<Cow<'static, [u8]>> {
+  Response::builder()
+    .status(status)
+    .header(header::CONTENT_TYPE, content_type)
+    .body(Cow::Owned(body))
+    .unwrap_or_else(|_| Response::new(Cow::Borrowed(&[][..])))
+}
+
+/// Custom schemes are exposed as `http://<scheme>.localhost` on Windows and Android.
+pub(super) fn scheme_url(scheme: &str) -> Url {
+  let url = if cfg!(any(windows, target_os = "android")) {
+    format!("http://{scheme}.localhost/")
+  } else {
+    format!("{scheme}://localhost/")
+  };
+  url.parse().expect("scheme URL is valid")
+}
+
+fn starting(message: &str) -> SplashStatus {
+  SplashStatus {
+    phase: Phase::Starting,
+    message: message.to_string(),
+    detail: None,
+  }
+}
+
+fn failed(message: &str, detail: Option<&str>) -> SplashStatus {
+  SplashStatus {
+    phase: Phase::Error,
+    message: message.to_string(),
+    detail: detail.map(str::to_string),
+  }
+}
+
+/// Updates the splash from `event` while it is open.
+fn follow(
+  app: &AppHandle,
+  event: &str,
+  status: impl Fn(&serde_json::Value) -> SplashStatus + Send + 'static,
+) {
+  let app_handle = app.clone();
+  app.listen_any(event, move |event| {
+    if !is_open(&app_handle) {
+      return;
+    }
+    let payload = serde_json::from_str(event.payload()).unwrap_or(serde_json::Value::Null);
+    set_status(&app_handle, status(&payload));
+  });
+}
+
+fn set_status(app: &AppHandle, status: SplashStatus) {
+  if let Some(state) = app.try_state::<SplashState>() {
+    if let Ok(mut current) = state.0.lock() {
+      *current = status;
+    }
+  }
+}
+
+fn retry(app: &AppHandle) {
+  if backend::is_running(app).is_none() {
+    // The backend was never launched (e.g. its binary is missing); only a relaunch can help.
+    info!("relaunching after backend launch failure");
+    app.restart()
+  }
+  info!("user requested backend retry from splash");
+  set_status(app, starting("Restarting backend…"));
+  let app = app.clone();
+  tauri::async_runtime::spawn(async move {
+    if let Err(err) = backend::restart_backend(app).await {
+      error!("backend retry from splash failed: {err}");
+    }
+  });
+}
+
+/// Reveals the windows built at launch and closes the splash once the backend is ready.
+fn finish(app: &AppHandle) {
+  FINISHED.store(true, Ordering::SeqCst);
+  if let Err(err) = super::session::show(app) {
+    error!("failed to show windows: {err}");
+  }
+  if let Some(splash) = app.get_webview_window(SPLASH_WINDOW) {
+    let _ = splash.close();
+  }
+}
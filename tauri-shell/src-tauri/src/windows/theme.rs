@@ -0,0 +1,109 @@
+//! Keeps native window chrome in step with the light/dark theme: the OS setting by default, or
+//! the frontend's choice once it calls `set_theme_override`. Window backgrounds follow the
+//! theme so nothing flashes white before the page paints.
+
+use std::sync::Mutex;
+
+use log::{info, warn};
+use tauri::window::Color;
+use tauri::{AppHandle, Emitter, Manager, Theme, WebviewWindow, WindowEvent};
+
+use crate::audit;
+use crate::config::{self, ThemePreference};
+
+const LIGHT_BACKGROUND: Color = Color(255, 255, 255, 255);
+const DARK_BACKGROUND: Color = Color(28, 25, 23, 255);
+
+/// The theme preference in effect; starts from the config and can be changed at runtime.
+pub struct ThemeState(Mutex<ThemePreference>);
+
+impl ThemeState {
+  pub fn new(preference: ThemePreference) -> Self {
+    Self(Mutex::new(preference))
+  }
+}
+
+/// The theme to force on windows, or `None` to follow the OS.
+pub fn forced(app: &AppHandle) -> Option<Theme> {
+  let preference = app
+    .try_state::<ThemeState>()
+    .and_then(|state| state.0.lock().ok().map(|preference| *preference))
+    .unwrap_or_default();
+  match preference {
+    ThemePreference::System => None,
+    ThemePreference::Light => Some(Theme::Light),
+    ThemePreference::Dark => Some(Theme::Dark),
+  }
+}
+
+/// Background for windows and webviews under `theme`.
+pub fn background(theme: Theme) -> Color {
+  match theme {
+    Theme::Dark => DARK_BACKGROUND,
+    _ => LIGHT_BACKGROUND,
+  }
+}
+
+fn name(theme: Theme) -> &'static str {
+  match theme {
+    Theme::Dark => "dark",
+    _ => "light",
+  }
+}
+
+/// Colors `window` for its current theme and follows OS theme changes, announcing them to its
+/// webview as `system-theme-changed`.
+pub fn track(window: &WebviewWindow) {
+  apply(window);
+  let window_clone = window.clone();
+  window.on_window_event(move |event| {
+    if let WindowEvent::ThemeChanged(theme) = event {
+      info!("window {} theme changed to {}", window_clone.label(), name(*theme));
+      apply(&window_clone);
+      let _ = window_clone.emit_to(window_clone.label(), "system-theme-changed", name(*theme));
+    }
+  });
+}
+
+fn apply(window: &WebviewWindow) {
+  let theme = window.theme().unwrap_or(Theme::Light);
+  if let Err(err) = window.set_background_color(Some(background(theme))) {
+    warn!("failed to set {} background: {err}", window.label());
+  }
+  #[cfg(target_os = "macos")]
+  super::titlebar::set_background(window, background(theme));
+  #[cfg(windows)]
+  super::dwm::apply(window, theme);
+}
+
+/// Switches every window to `theme` for this run, without saving it.
+pub fn apply_preference(app: &AppHandle, theme: ThemePreference) {
+  if let Some(state) = app.try_state::<ThemeState>() {
+    if let Ok(mut current) = state.0.lock() {
+      *current = theme;
+    }
+  }
+  info!("theme override set to {theme:?}");
+
+  let forced = forced(app);
+  for window in app.webview_windows().values() {
+    if let Err(err) = window.set_theme(forced) {
+      warn!("failed to set {} theme: {err}", window.label());
+    }
+    apply(window);
+  }
+}
+
+/// Forces `theme` ("light" or "dark") on every window, or follows the OS again for "system".
+/// Sets the native appearance too (NSAppearance on macOS, the dark titlebar on Windows), and
+/// saves the choice.
+#[tauri::command]
+pub fn set_theme_override(app: AppHandle, theme: ThemePreference) -> Result<(), String> {
+  apply_preference(&app, theme);
+  let result = config::update(&app, |config| config.window.theme = theme).map_err(|err| {
+    warn!("failed to save theme override: {err:#}");
+    format!("theme changed but could not be saved: {err:#}")
+  });
+  audit::record(&app, "set_theme_override", serde_json::json!({ "theme": theme }), &result);
+  result
+}
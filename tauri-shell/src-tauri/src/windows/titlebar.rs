@@ -0,0 +1,255 @@
+//! macOS titlebar styling. The frontend draws its own header under the traffic lights, so the
+//! titlebar is made transparent and given a height via an invisible accessory view. AppKit
+//! drops that accessory when the window goes fullscreen, so it is removed on the way in and
+//! re-added on the way out.
+
+use tauri::AppHandle;
+
+#[cfg(target_os = "macos")]
+use std::collections::HashMap;
+#[cfg(target_os = "macos")]
+use std::sync::Mutex;
+
+#[cfg(target_os = "macos")]
+use log::warn;
+#[cfg(target_os = "macos")]
+use tauri::{Manager, WebviewWindow, WindowEvent};
+
+#[cfg(target_os = "macos")]
+use crate::config::{ShellConfig, WindowConfig};
+
+/// Tallest titlebar `set_titlebar_height` accepts, in points.
+const MAX_TITLEBAR_HEIGHT: f64 = 200.0;
+
+/// One window's accessory.
+#[cfg(target_os = "macos")]
+#[derive(Default)]
+struct Titlebar {
+  /// The accessory controller currently attached (retained by us), as a raw pointer.
+  controller: Option<usize>,
+  fullscreen: bool,
+}
+
+/// The height shared by every styled window, and each one's accessory by label.
+#[cfg(target_os = "macos")]
+struct Titlebars {
+  height: f64,
+  windows: HashMap<String, Titlebar>,
+}
+
+#[cfg(target_os = "macos")]
+struct TitlebarState(Mutex<Titlebars>);
+
+/// Styles a main window's titlebar and keeps the accessory in place across fullscreen.
+#[cfg(target_os = "macos")]
+pub fn install(window: &WebviewWindow) {
+  use cocoa::appkit::{NSWindow, NSWindowTitleVisibility};
+  use cocoa::base::{id, NO, YES};
+
+  let app = window.app_handle();
+  if app.try_state::<TitlebarState>().is_none() {
+    let height = app
+      .try_state::<ShellConfig>()
+      .map(|config| config.window.titlebar_height)
+      .unwrap_or_else(|| WindowConfig::default().titlebar_height);
+    app.manage(TitlebarState(Mutex::new(Titlebars {
+      height,
+      windows: HashMap::new(),
+    })));
+  }
+
+  if let Ok(ns_window) = window.ns_window() {
+    let ns_window = ns_window as id;
+    unsafe {
+      ns_window.setTitlebarAppearsTransparent_(YES);
+      ns_window.setOpaque_(NO);
+      ns_window.setTitleVisibility_(NSWindowTitleVisibility::NSWindowTitleHidden);
+    }
+  }
+  // The titlebar shows the window background until the page paints, so match the theme.
+  let theme = window.theme().unwrap_or(tauri::Theme::Light);
+  set_background(window, super::theme::background(theme));
+
+  // Ensure the system knows our desired titlebar height without per-resize tweaking
+  if let Some(state) = app.try_state::<TitlebarState>() {
+    if let Ok(mut titlebars) = state.0.lock() {
+      let height = titlebars.height;
+      let titlebar = titlebars.windows.entry(window.label().to_string()).or_default();
+      titlebar.fullscreen = window.is_fullscreen().unwrap_or(false);
+      apply(window, height, titlebar);
+    }
+  }
+  // apply_unified_toolbar(&window);  // 방법 2: Toolbar 제거로 separator 해결 시도
+
+  // There's no fullscreen event; a transition shows up as a resize.
+  let window_clone = window.clone();
+  window.on_window_event(move |event| {
+    let destroyed = matches!(event, WindowEvent::Destroyed);
+    if !destroyed && !matches!(event, WindowEvent::Resized(_)) {
+      return;
+    }
+    let Some(state) = window_clone.try_state::<TitlebarState>() else {
+      return;
+    };
+    let Ok(mut titlebars) = state.0.lock() else {
+      return;
+    };
+    if destroyed {
+      if let Some(controller) = titlebars
+        .windows
+        .remove(window_clone.label())
+        .and_then(|titlebar| titlebar.controller)
+      {
+        use objc::{msg_send, sel, sel_impl};
+        // SAFETY: retained when created; the window that held it is gone.
+        unsafe {
+          let _: () = msg_send![controller as cocoa::base::id, release];
+        }
+      }
+      return;
+    }
+    let fullscreen = window_clone.is_fullscreen().unwrap_or(false);
+    let height = titlebars.height;
+    let Some(titlebar) = titlebars.windows.get_mut(window_clone.label()) else {
+      return;
+    };
+    if titlebar.fullscreen != fullscreen {
+      titlebar.fullscreen = fullscreen;
+      apply(&window_clone, height, titlebar);
+    }
+  });
+}
+
+/// Sets the native window background, which shows through the transparent titlebar.
+#[cfg(target_os = "macos")]
+pub fn set_background(window: &WebviewWindow, color: tauri::window::Color) {
+  use cocoa::appkit::{NSColor, NSWindow};
+  use cocoa::base::{id, nil};
+
+  let Ok(ns_window) = window.ns_window() else {
+    return;
+  };
+  let tauri::window::Color(red, green, blue, alpha) = color;
+  let channel = |value: u8| f64::from(value) / 255.0;
+  unsafe {
+    let color = NSColor::colorWithSRGBRed_green_blue_alpha_(
+      nil,
+      channel(red),
+      channel(green),
+      channel(blue),
+      channel(alpha),
+    );
+    (ns_window as id).setBackgroundColor_(color);
+  }
+}
+
+/// Replaces the accessory (rather than stacking another) to match `height` and `titlebar`.
+/// Main thread only.
+#[cfg(target_os = "macos")]
+fn apply(window: &WebviewWindow, height: f64, titlebar: &mut Titlebar) {
+  use cocoa::appkit::NSView;
+  use cocoa::base::{id, nil, YES};
+  use cocoa::foundation::{NSPoint, NSRect, NSSize};
+  use objc::{class, msg_send, sel, sel_impl};
+
+  if let Some(controller) = titlebar.controller.take() {
+    let controller = controller as id;
+    // SAFETY: we retained the controller when creating it and release it exactly once here.
+    unsafe {
+      let _: () = msg_send![controller, removeFromParentViewController];
+      let _: () = msg_send![controller, release];
+    }
+  }
+  if titlebar.fullscreen || height <= 0.0 {
+    return;
+  }
+  let Ok(ns_window) = window.ns_window() else {
+    warn!("window {} has no native handle; titlebar accessory not applied", window.label());
+    return;
+  };
+  let ns_window = ns_window as id;
+  unsafe {
+    let accessory: id = msg_send![class!(NSTitlebarAccessoryViewController), new];
+    let view: id = NSView::alloc(nil).initWithFrame_(NSRect::new(
+      NSPoint::new(0.0, 0.0),
+      NSSize::new(1.0, height),
+    ));
+    let _: () = msg_send![view, setWantsLayer: YES];
+    // Transparent accessory; only height matters for layout
+    let _: () = msg_send![view, setAlphaValue: 0.0f64];
+
+    let _: () = msg_send![accessory, setView: view];
+    // The controller retains the view now.
+    let _: () = msg_send![view, release];
+    // Add accessory so AppKit derives titlebar height from its view
+    let _: () = msg_send![ns_window, addTitlebarAccessoryViewController: accessory];
+    titlebar.controller = Some(accessory as usize);
+  }
+}
+
+/// Changes the titlebar height of every main window for this run, replacing their accessory
+/// views. Does nothing off macOS.
+#[tauri::command]
+pub fn set_titlebar_height(app: AppHandle, px: f64) -> Result<(), String> {
+  if !px.is_finite() || !(0.0..=MAX_TITLEBAR_HEIGHT).contains(&px) {
+    return Err(format!("titlebar height must be between 0 and {MAX_TITLEBAR_HEIGHT}"));
+  }
+  #[cfg(target_os = "macos")]
+  {
+    let main_thread_app = app.clone();
+    app
+      .run_on_main_thread(move || {
+        let Some(state) = main_thread_app.try_state::<TitlebarState>() else {
+          return;
+        };
+        let Ok(mut titlebars) = state.0.lock() else {
+          return;
+        };
+        titlebars.height = px;
+        for (label, titlebar) in titlebars.windows.iter_mut() {
+          if let Some(window) = main_thread_app.get_webview_window(label) {
+            apply(&window, px, titlebar);
+          }
+        }
+      })
+      .map_err(|err| format!("failed to update titlebar: {err}"))?;
+  }
+  #[cfg(not(target_os = "macos"))]
+  let _ = (app, px);
+  Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn apply_unified_toolbar(window: &tauri::WebviewWindow) {
+  use cocoa::base::{id, nil, NO, YES, BOOL};
+  use cocoa::foundation::NSString;
+  use objc::{class, msg_send, sel, sel_impl};
+
+  if let Ok(ns_window) = window.ns_window() {
+    let ns_window = ns_window as id;
+    unsafe {
+      // Create NSToolbar with an identifier
+      let identifier = NSString::alloc(nil).init_str("PlutoDuckToolbar");
+      let toolbar: id = msg_send![class!(NSToolbar), alloc];
+      let toolbar: id = msg_send![toolbar, initWithIdentifier: identifier];
+
+      // Optional cosmetic adjustments
+      let _: () = msg_send![toolbar, setShowsBaselineSeparator: NO];
+      // Small size mode (1). Default is 0. This helps lower the baseline.
+      let _: () = msg_send![toolbar, setSizeMode: 1u64];
+
+      // Attach toolbar to window
+      let _: () = msg_send![ns_window, setToolbar: toolbar];
+
+      // Try to center/compact further by setting toolbar style when available.
+      // We avoid hardcoding NSWindowToolbarStyle enums to keep compatibility.
+      // If the selector exists, set to UnifiedCompact (commonly = 5) as a best-effort.
+      let sel_toolbarStyle = sel!(setToolbarStyle:);
+      let responds: BOOL = msg_send![ns_window, respondsToSelector: sel_toolbarStyle];
+      if responds == YES {
+        let unified_compact: u64 = 8; // NSWindowToolbarStyleUnifiedCompact (best-effort)
+        let _: () = msg_send![ns_window, setToolbarStyle: unified_compact];
+      }
+    }
+  }
+}
@@ -0,0 +1,57 @@
+//! Whether any window is on screen, so background work can slow down while the app is hidden.
+//! Changes are announced to the webviews as `app-hidden` / `app-visible`, which the frontend can
+//! use to pause its own polling.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use log::debug;
+use tauri::{AppHandle, Emitter, Manager, Window, WindowEvent};
+
+/// Showing and hiding don't raise window events of their own, but they move focus; this gives
+/// the platform time to finish the change before visibility is read back.
+const SETTLE_DELAY: Duration = Duration::from_millis(100);
+
+pub struct AppVisibility(AtomicBool);
+
+impl Default for AppVisibility {
+  fn default() -> Self {
+    Self(AtomicBool::new(true))
+  }
+}
+
+/// Whether any window is visible and not minimized. Assumed visible until first checked.
+pub fn is_visible(app: &AppHandle) -> bool {
+  app
+    .try_state::<AppVisibility>()
+    .map_or(true, |state| state.0.load(Ordering::SeqCst))
+}
+
+/// Re-checks visibility after any event that may come with a window appearing or going away.
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+  if matches!(
+    event,
+    WindowEvent::Focused(_) | WindowEvent::Resized(_) | WindowEvent::Destroyed
+  ) {
+    let app = window.app_handle().clone();
+    tauri::async_runtime::spawn(async move {
+      tokio::time::sleep(SETTLE_DELAY).await;
+      refresh(&app);
+    });
+  }
+}
+
+/// Reads every window's visibility and emits `app-hidden` or `app-visible` if the aggregate
+/// changed.
+pub fn refresh(app: &AppHandle) {
+  let Some(state) = app.try_state::<AppVisibility>() else {
+    return;
+  };
+  let visible = app.webview_windows().values().any(|window| {
+    window.is_visible().unwrap_or(false) && !window.is_minimized().unwrap_or(false)
+  });
+  if state.0.swap(visible, Ordering::SeqCst) != visible {
+    debug!("app is now {}", if visible { "visible" } else { "hidden" });
+    let _ = app.emit(if visible { "app-visible" } else { "app-hidden" }, ());
+  }
+}